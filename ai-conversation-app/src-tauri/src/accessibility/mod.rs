@@ -0,0 +1,104 @@
+use crate::config::{self, AccessibilityConfig};
+use crate::i18n;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncementKind {
+    ListeningStarted,
+    ListeningStopped,
+    Thinking,
+    ReplyReady,
+    SpeakingStopped,
+    ModeChanged,
+    Error,
+}
+
+/// Mirrors ARIA live-region politeness so the frontend can route announcements directly
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Politeness {
+    Polite,
+    Assertive,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Announcement {
+    pub kind: AnnouncementKind,
+    pub message: String,
+    pub politeness: Politeness,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessibilitySettings {
+    pub announcements: bool,
+    pub reduced_motion: bool,
+    pub high_contrast: bool,
+    pub verbose: bool,
+}
+
+impl From<&AccessibilityConfig> for AccessibilitySettings {
+    fn from(config: &AccessibilityConfig) -> Self {
+        Self {
+            announcements: config.announcements,
+            reduced_motion: config.reduced_motion,
+            high_contrast: config.high_contrast,
+            verbose: config.verbosity == "verbose",
+        }
+    }
+}
+
+pub fn current_settings() -> AccessibilitySettings {
+    let accessibility = config::try_get_config()
+        .map(|c| c.accessibility.clone())
+        .unwrap_or_default();
+    AccessibilitySettings::from(&accessibility)
+}
+
+fn default_message(kind: AnnouncementKind, verbose: bool) -> String {
+    let key = match (kind, verbose) {
+        (AnnouncementKind::ListeningStarted, false) => "a11y-listening-started",
+        (AnnouncementKind::ListeningStarted, true) => "a11y-listening-started-verbose",
+        (AnnouncementKind::ListeningStopped, false) => "a11y-listening-stopped",
+        (AnnouncementKind::ListeningStopped, true) => "a11y-listening-stopped-verbose",
+        (AnnouncementKind::Thinking, false) => "a11y-thinking",
+        (AnnouncementKind::Thinking, true) => "a11y-thinking-verbose",
+        (AnnouncementKind::ReplyReady, false) => "a11y-reply-ready",
+        (AnnouncementKind::ReplyReady, true) => "a11y-reply-ready-verbose",
+        (AnnouncementKind::SpeakingStopped, false) => "a11y-speaking-stopped",
+        (AnnouncementKind::SpeakingStopped, true) => "a11y-speaking-stopped-verbose",
+        (AnnouncementKind::ModeChanged, _) => "a11y-mode-changed",
+        (AnnouncementKind::Error, _) => "a11y-error",
+    };
+    i18n::tr(key)
+}
+
+/// Emit a screen-reader friendly `a11y-announcement` event; `detail` replaces the default wording.
+pub fn announce(app: &AppHandle, kind: AnnouncementKind, detail: Option<&str>) {
+    let settings = current_settings();
+    if !settings.announcements {
+        return;
+    }
+
+    let announcement = Announcement {
+        kind,
+        message: detail
+            .map(str::to_string)
+            .unwrap_or_else(|| default_message(kind, settings.verbose)),
+        politeness: if kind == AnnouncementKind::Error {
+            Politeness::Assertive
+        } else {
+            Politeness::Polite
+        },
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+    };
+
+    if let Err(e) = app.emit("a11y-announcement", announcement) {
+        log::error!("Failed to emit accessibility announcement: {}", e);
+    }
+}
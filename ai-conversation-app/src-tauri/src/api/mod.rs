@@ -0,0 +1,197 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+// Requests only carry a bit of text; anything bigger is refused unread
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// What a script asked the assistant to do.
+#[derive(Debug)]
+pub enum ApiRequest {
+    /// Say `text` aloud.
+    Speak { text: String },
+    /// Send `text` to the assistant as if the user had said it.
+    Ask { text: String },
+    StartListening,
+    StopListening,
+    /// What the assistant and its avatar are doing.
+    State,
+    Conversations { query: Option<String>, tag: Option<String> },
+}
+
+/// Carries API requests out; the app implements it.
+#[async_trait]
+pub trait ApiHandler: Send + Sync + 'static {
+    async fn handle(&self, request: ApiRequest) -> std::result::Result<serde_json::Value, String>;
+}
+
+#[derive(Deserialize)]
+struct TextBody {
+    text: String,
+}
+
+/// A REST API on localhost for scripts and other apps on the machine. Every request must
+/// carry `Authorization: Bearer <token>`.
+///
+/// - `POST /speak` `{"text": ...}`
+/// - `POST /ask` `{"text": ...}`
+/// - `POST /listen/start`, `POST /listen/stop`
+/// - `GET /state`
+/// - `GET /conversations?query=...&tag=...`
+pub struct ApiServer {
+    stop: Option<oneshot::Sender<()>>,
+    serve: JoinHandle<()>,
+}
+
+impl ApiServer {
+    /// Listen on 127.0.0.1:`port`; only local programs can connect.
+    pub fn start(port: u16, token: String, handler: Arc<dyn ApiHandler>) -> Result<Self> {
+        if token.is_empty() {
+            return Err(anyhow::anyhow!("The REST API needs a token"));
+        }
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let listener = TcpListener::bind(address).with_context(|| format!("Failed to listen on {}", address))?;
+        listener.set_nonblocking(true)?;
+
+        // Compare digests so how long a wrong token takes says nothing about the right one
+        let token = Arc::new(Sha256::digest(token.as_bytes()).to_vec());
+        let make_service = make_service_fn(move |_| {
+            let token = token.clone();
+            let handler = handler.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let token = token.clone();
+                    let handler = handler.clone();
+                    async move { Ok::<_, Infallible>(respond(request, &token, handler.as_ref()).await) }
+                }))
+            }
+        });
+        let server = hyper::Server::from_tcp(listener)?.serve(make_service);
+
+        let (stop, stopped) = oneshot::channel::<()>();
+        let serve = tokio::spawn(async move {
+            if let Err(e) = server
+                .with_graceful_shutdown(async {
+                    let _ = stopped.await;
+                })
+                .await
+            {
+                log::warn!("REST API stopped: {}", e);
+            }
+        });
+        log::info!("REST API listening on http://{}", address);
+        Ok(Self { stop: Some(stop), serve })
+    }
+
+    /// Finish the requests in flight and free the port.
+    pub async fn stop(mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        let _ = (&mut self.serve).await;
+    }
+}
+
+impl Drop for ApiServer {
+    fn drop(&mut self) {
+        self.serve.abort();
+    }
+}
+
+async fn respond(request: Request<Body>, token: &[u8], handler: &dyn ApiHandler) -> Response<Body> {
+    let authorized = request
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| Sha256::digest(given.trim().as_bytes()).as_slice() == token);
+    if !authorized {
+        return error(StatusCode::UNAUTHORIZED, "Missing or wrong API token");
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().trim_end_matches('/').to_string();
+    let query = request.uri().query().unwrap_or_default().to_string();
+    let api_request = match (&method, path.as_str()) {
+        (&Method::POST, "/speak") => match read_text(request).await {
+            Ok(text) => ApiRequest::Speak { text },
+            Err((status, message)) => return error(status, &message),
+        },
+        (&Method::POST, "/ask") => match read_text(request).await {
+            Ok(text) => ApiRequest::Ask { text },
+            Err((status, message)) => return error(status, &message),
+        },
+        (&Method::POST, "/listen/start") => ApiRequest::StartListening,
+        (&Method::POST, "/listen/stop") => ApiRequest::StopListening,
+        (&Method::GET, "/state") => ApiRequest::State,
+        (&Method::GET, "/conversations") => {
+            let mut query_value = None;
+            let mut tag = None;
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                match key.as_ref() {
+                    "query" => query_value = Some(value.into_owned()),
+                    "tag" => tag = Some(value.into_owned()),
+                    _ => {}
+                }
+            }
+            ApiRequest::Conversations { query: query_value, tag }
+        }
+        (_, "/speak" | "/ask" | "/listen/start" | "/listen/stop" | "/state" | "/conversations") => {
+            return error(StatusCode::METHOD_NOT_ALLOWED, &format!("{} isn't supported here", method));
+        }
+        _ => return error(StatusCode::NOT_FOUND, &format!("No such endpoint: {}", path)),
+    };
+
+    match handler.handle(api_request).await {
+        Ok(body) => json_response(StatusCode::OK, &body),
+        Err(message) => error(StatusCode::INTERNAL_SERVER_ERROR, &message),
+    }
+}
+
+/// The non-empty `text` of a JSON request body.
+async fn read_text(request: Request<Body>) -> std::result::Result<String, (StatusCode, String)> {
+    let mut body = request.into_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| (StatusCode::BAD_REQUEST, format!("Failed to read request: {}", e)))?;
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large".to_string()));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    let body: TextBody = serde_json::from_slice(&bytes)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Expected {{\"text\": ...}}: {}", e)))?;
+    if body.text.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "text must not be empty".to_string()));
+    }
+    Ok(body.text)
+}
+
+fn json_response(status: StatusCode, body: &serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap_or_default()
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &json!({ "error": message }))
+}
+
+/// A new random API token, as 64 hex digits.
+pub fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow::anyhow!("Failed to generate an API token: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
@@ -0,0 +1,192 @@
+use crate::audio::tts::WordTiming;
+
+// Analysis frame, in seconds
+const FRAME_SECONDS: f64 = 0.01;
+// Frames quieter than this fraction of the loud frames count as silence (about -20 dB)
+const SILENCE_RATIO: f32 = 0.1;
+// How far, in frames, a word may end from where the estimates put it
+const MAX_DRIFT_FRAMES: usize = 200;
+// A word may be spoken this much faster or slower than estimated
+const MIN_STRETCH: f64 = 0.33;
+const MAX_STRETCH: f64 = 3.0;
+// Weight of a word's length straying from its estimate against ending on a loud frame
+const DURATION_WEIGHT: f64 = 0.5;
+
+/// Move word and phoneme timings onto the synthesized audio. Word boundaries are aligned to
+/// the dips in loudness between words, found by dynamic programming against each word's
+/// estimated length; phonemes keep their estimated proportions within their word. When the
+/// audio gives nothing to align to, the estimates are stretched evenly over it.
+pub fn align_timings(phonemes: &mut Vec<(String, f64, f64)>, words: &mut [WordTiming], audio: &[f32], sample_rate: u32) {
+    let duration = audio.len() as f64 / sample_rate.max(1) as f64;
+    let frame_len = ((sample_rate as f64 * FRAME_SECONDS) as usize).max(1);
+    let loudness = frame_loudness(audio, frame_len);
+
+    let Some((first, last)) = speech_span(&loudness) else {
+        stretch_timings(phonemes, words, duration as f32);
+        return;
+    };
+    if words.is_empty() || last - first < words.len() {
+        stretch_timings(phonemes, words, duration as f32);
+        return;
+    }
+    let Some(boundaries) = word_boundaries(&loudness, words, first, last + 1) else {
+        stretch_timings(phonemes, words, duration as f32);
+        return;
+    };
+
+    let seconds = |frame: usize| (frame as f64 * frame_len as f64 / sample_rate as f64).min(duration);
+    let mut aligned = Vec::with_capacity(phonemes.len());
+    if first > 0 {
+        aligned.push(("sil".to_string(), 0.0, seconds(first)));
+    }
+    let spans: Vec<(usize, usize)> = boundaries
+        .windows(2)
+        .map(|pair| voiced_part(&loudness, pair[0], pair[1]))
+        .collect();
+    for (index, word) in words.iter_mut().enumerate() {
+        let (start, end) = spans[index];
+        let estimated_end = word.start + word.duration;
+        let own: Vec<(String, f64)> = phonemes
+            .iter()
+            .filter(|(phoneme, at, _)| phoneme != "sil" && *at >= word.start - 1e-9 && *at < estimated_end - 1e-9)
+            .map(|(phoneme, _, length)| (phoneme.clone(), *length))
+            .collect();
+
+        let (start, end) = (seconds(start), seconds(end));
+        word.start = start;
+        word.duration = end - start;
+        let total: f64 = own.iter().map(|(_, length)| length).sum();
+        let mut at = start;
+        for (phoneme, length) in own {
+            let length = if total > 0.0 { length / total * word.duration } else { 0.0 };
+            aligned.push((phoneme, at, length));
+            at += length;
+        }
+
+        // The pause after the word runs to the next one, or to the end of the audio
+        let pause_end = spans.get(index + 1).map_or(duration, |(next, _)| seconds(*next));
+        if pause_end > end {
+            aligned.push(("sil".to_string(), end, pause_end - end));
+        }
+    }
+    *phonemes = aligned;
+}
+
+/// Scale estimated timings evenly so they span `duration` seconds.
+fn stretch_timings(phonemes: &mut [(String, f64, f64)], words: &mut [WordTiming], duration: f32) {
+    let estimated = phonemes.last().map(|(_, start, length)| start + length).unwrap_or(0.0);
+    if estimated > 0.0 && duration > 0.0 {
+        let scale = duration as f64 / estimated;
+        for (_, start, length) in phonemes.iter_mut() {
+            *start *= scale;
+            *length *= scale;
+        }
+        for word in words.iter_mut() {
+            word.start *= scale;
+            word.duration *= scale;
+        }
+    }
+}
+
+/// RMS of each frame, relative to the loud frames so the threshold suits any voice volume.
+fn frame_loudness(audio: &[f32], frame_len: usize) -> Vec<f32> {
+    let rms: Vec<f32> = audio
+        .chunks(frame_len)
+        .map(|frame| (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt())
+        .collect();
+    let mut sorted = rms.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    // The 95th percentile rather than the peak, so a single click doesn't set the scale
+    let loud = sorted.get(sorted.len() * 95 / 100).copied().unwrap_or(0.0);
+    if loud <= f32::EPSILON {
+        return vec![0.0; rms.len()];
+    }
+    rms.into_iter().map(|level| (level / loud).min(1.0)).collect()
+}
+
+fn is_silent(level: f32) -> bool {
+    level < SILENCE_RATIO
+}
+
+/// The first and last frames with speech in them.
+fn speech_span(loudness: &[f32]) -> Option<(usize, usize)> {
+    let first = loudness.iter().position(|level| !is_silent(*level))?;
+    let last = loudness.iter().rposition(|level| !is_silent(*level))?;
+    Some((first, last))
+}
+
+/// The frames each word starts at, plus the end of the last one: the placement, between
+/// `start` and `end`, that best matches the estimated lengths while ending words on quiet frames.
+fn word_boundaries(loudness: &[f32], words: &[WordTiming], start: usize, end: usize) -> Option<Vec<usize>> {
+    let frames = end - start;
+    let estimated: f64 = words.iter().map(|word| word.duration.max(FRAME_SECONDS)).sum();
+    let targets: Vec<f64> = words
+        .iter()
+        .map(|word| word.duration.max(FRAME_SECONDS) / estimated * frames as f64)
+        .collect();
+
+    // cost[f] is the cheapest way to end the words so far at frame start + f
+    let mut cost = vec![f64::INFINITY; frames + 1];
+    cost[0] = 0.0;
+    let mut previous_end = vec![vec![0usize; frames + 1]; words.len()];
+    let mut expected_end = 0.0;
+    for (index, target) in targets.iter().enumerate() {
+        expected_end += target;
+        let shortest = ((target * MIN_STRETCH) as usize).max(1);
+        let longest = ((target * MAX_STRETCH).ceil() as usize).max(shortest);
+        let is_last = index + 1 == words.len();
+        let (low, high) = if is_last {
+            (frames, frames)
+        } else {
+            (
+                (expected_end as usize).saturating_sub(MAX_DRIFT_FRAMES).max(1),
+                ((expected_end as usize) + MAX_DRIFT_FRAMES).min(frames - 1),
+            )
+        };
+
+        let mut next = vec![f64::INFINITY; frames + 1];
+        for word_end in low..=high {
+            // Ending on a loud frame means cutting through the middle of a sound
+            let boundary_cost = if is_last { 0.0 } else { loudness[start + word_end] as f64 };
+            if word_end < shortest {
+                continue;
+            }
+            let earliest = word_end.saturating_sub(longest);
+            let latest = word_end - shortest;
+            for (word_start, start_cost) in cost.iter().enumerate().take(latest + 1).skip(earliest) {
+                if !start_cost.is_finite() {
+                    continue;
+                }
+                let stray = ((word_end - word_start) as f64 - target) / target;
+                let total = start_cost + DURATION_WEIGHT * stray * stray + boundary_cost;
+                if total < next[word_end] {
+                    next[word_end] = total;
+                    previous_end[index][word_end] = word_start;
+                }
+            }
+        }
+        cost = next;
+    }
+    if !cost[frames].is_finite() {
+        return None;
+    }
+
+    let mut boundaries = vec![start + frames];
+    let mut at = frames;
+    for index in (0..words.len()).rev() {
+        at = previous_end[index][at];
+        boundaries.push(start + at);
+    }
+    boundaries.reverse();
+    Some(boundaries)
+}
+
+/// A word's frames without the silence at either end, which belongs to the pauses around it.
+fn voiced_part(loudness: &[f32], start: usize, end: usize) -> (usize, usize) {
+    let voiced_start = (start..end).find(|&frame| !is_silent(loudness[frame])).unwrap_or(start);
+    let voiced_end = (voiced_start..end)
+        .rev()
+        .find(|&frame| !is_silent(loudness[frame]))
+        .map_or(end, |frame| frame + 1);
+    (voiced_start, voiced_end.max(voiced_start + 1))
+}
@@ -0,0 +1,774 @@
+use crate::audio::denoise::{NoiseSuppressor, RNNOISE_SAMPLE_RATE};
+use crate::audio::AudioFrame;
+use crate::config::AudioConfig;
+use crate::meeting::{downmix, resample_linear};
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender;
+
+/// A source of mono audio frames: a microphone, a file, or a test fixture.
+pub trait AudioInput: Send {
+    fn name(&self) -> String;
+    /// Begin delivering frames to `sender` until `stop` is called. Real devices drop frames
+    /// the receiver has no room for rather than stall capture.
+    fn start(&mut self, sender: Sender<AudioFrame>) -> Result<()>;
+    fn stop(&mut self) -> Result<()>;
+    fn is_active(&self) -> bool;
+    /// RMS of the most recent frame, 0.0..=1.0
+    fn level(&self) -> f32 {
+        0.0
+    }
+    /// RMS and peak of everything captured since the last call, or None if nothing was
+    fn take_level(&self) -> Option<SignalLevel> {
+        None
+    }
+    /// Switch noise suppression on or off without restarting capture, where supported
+    fn set_noise_suppression(&self, _enabled: bool) {}
+    /// The last few thousand captured samples and their rate, for visualizations
+    fn recent_samples(&self) -> (Vec<f32>, u32) {
+        (Vec::new(), 0)
+    }
+    /// Why the stream stopped working, once it has, e.g. because the device was unplugged
+    fn failure(&self) -> Option<String> {
+        None
+    }
+}
+
+/// A sink for mono audio: the speakers, or a buffer in tests.
+pub trait AudioOutput: Send {
+    fn name(&self) -> String;
+    /// Queue samples for playback; returns without waiting for them to finish.
+    /// The result is the `clock` time at which the first sample becomes audible.
+    fn play(&mut self, samples: &[f32], sample_rate: u32) -> Result<f64>;
+    /// Seconds of audio the device has consumed so far, silence included.
+    fn clock(&self) -> f64;
+    /// Drop anything still queued.
+    fn stop(&mut self) -> Result<()>;
+    fn is_playing(&self) -> bool;
+    /// RMS of the most recently played buffer, 0.0..=1.0
+    fn level(&self) -> f32 {
+        0.0
+    }
+    /// The last few thousand played samples and their rate, for visualizations
+    fn recent_samples(&self) -> (Vec<f32>, u32) {
+        (Vec::new(), 0)
+    }
+    /// Why the stream stopped working, once it has, e.g. because the device was unplugged
+    fn failure(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Pick the input backend from config: real devices, or a WAV file in simulation mode.
+pub fn create_input(config: &AudioConfig) -> Result<Box<dyn AudioInput>> {
+    match config.backend.as_str() {
+        "cpal" => Ok(Box::new(
+            CpalInput::new(&config.input.device).with_noise_suppression(config.input.noise_suppression, config.input.sample_rate),
+        )),
+        "file" => {
+            let path = config
+                .simulation_file
+                .as_ref()
+                .context("audio.simulation_file must be set when audio.backend is \"file\"")?;
+            Ok(Box::new(FileInput::from_wav(path, true)?))
+        }
+        other => Err(anyhow::anyhow!("Unknown audio backend: {}", other)),
+    }
+}
+
+pub fn create_output(config: &AudioConfig) -> Result<Box<dyn AudioOutput>> {
+    match config.backend.as_str() {
+        "cpal" => Ok(Box::new(CpalOutput::new(&config.output.device, config.output.volume))),
+        // Simulation mode has nobody to listen, so playback is only recorded
+        "file" => Ok(Box::new(MockOutput::new())),
+        other => Err(anyhow::anyhow!("Unknown audio backend: {}", other)),
+    }
+}
+
+// Enough recent audio for one spectrum window at 48 kHz
+const RECENT_CAPACITY: usize = 2048;
+
+/// Loudness over a stretch of audio, 0.0..=1.0
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SignalLevel {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+// Running totals for the audio recorded since the level was last taken
+#[derive(Default)]
+struct LevelWindow {
+    sum_squares: f64,
+    samples: usize,
+    peak: f32,
+}
+
+/// Level and most recent samples, shared between an audio callback and status or
+/// visualization queries.
+#[derive(Clone, Default)]
+struct SignalMeter {
+    level: Arc<AtomicU32>,
+    sample_rate: Arc<AtomicU32>,
+    recent: Arc<Mutex<VecDeque<f32>>>,
+    window: Arc<Mutex<LevelWindow>>,
+}
+
+impl SignalMeter {
+    fn record(&self, samples: &[f32], sample_rate: u32) {
+        if samples.is_empty() {
+            return;
+        }
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        self.level.store(rms.min(1.0).to_bits(), Ordering::Relaxed);
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        // Never block the audio callback on a reader
+        if let Ok(mut recent) = self.recent.try_lock() {
+            recent.extend(samples.iter().copied());
+            let excess = recent.len().saturating_sub(RECENT_CAPACITY);
+            recent.drain(..excess);
+        }
+        if let Ok(mut window) = self.window.try_lock() {
+            window.sum_squares += samples.iter().map(|&s| (s * s) as f64).sum::<f64>();
+            window.samples += samples.len();
+            window.peak = samples.iter().fold(window.peak, |peak, s| peak.max(s.abs()));
+        }
+    }
+
+    fn level(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    fn take_level(&self) -> Option<SignalLevel> {
+        let window = std::mem::take(&mut *self.window.lock().unwrap());
+        if window.samples == 0 {
+            return None;
+        }
+        Some(SignalLevel {
+            rms: ((window.sum_squares / window.samples as f64).sqrt() as f32).min(1.0),
+            peak: window.peak.min(1.0),
+        })
+    }
+
+    fn recent(&self) -> (Vec<f32>, u32) {
+        let recent = self.recent.lock().unwrap();
+        (recent.iter().copied().collect(), self.sample_rate.load(Ordering::Relaxed))
+    }
+
+    fn reset(&self) {
+        self.level.store(0.0f32.to_bits(), Ordering::Relaxed);
+        self.recent.lock().unwrap().clear();
+        *self.window.lock().unwrap() = LevelWindow::default();
+    }
+}
+
+fn find_device(host: &cpal::Host, name: &str, is_input: bool) -> Result<cpal::Device> {
+    if name == "default" {
+        return if is_input {
+            host.default_input_device().context("No default input device available")
+        } else {
+            host.default_output_device().context("No default output device available")
+        };
+    }
+    let mut devices = if is_input { host.input_devices()? } else { host.output_devices()? };
+    devices
+        .find(|device| device.name().map(|n| n.contains(name)).unwrap_or(false))
+        .with_context(|| format!("Audio device '{}' not found", name))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioDeviceKind {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioDeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    // Common rates the device can open at, ascending
+    pub sample_rates: Vec<u32>,
+}
+
+// Devices report rate ranges; these are the rates worth offering from them
+const COMMON_SAMPLE_RATES: [u32; 9] = [8000, 11025, 16000, 22050, 32000, 44100, 48000, 96000, 192000];
+
+/// Every input or output device cpal can see on the default host.
+pub fn list_devices(kind: AudioDeviceKind) -> Result<Vec<AudioDeviceInfo>> {
+    let host = cpal::default_host();
+    let (default, devices) = match kind {
+        AudioDeviceKind::Input => (host.default_input_device(), host.input_devices()?),
+        AudioDeviceKind::Output => (host.default_output_device(), host.output_devices()?),
+    };
+    let default_name = default.and_then(|device| device.name().ok());
+
+    let mut listed = Vec::new();
+    for device in devices {
+        let Ok(name) = device.name() else {
+            continue;
+        };
+        let ranges: Vec<(u32, u32)> = match kind {
+            AudioDeviceKind::Input => device
+                .supported_input_configs()
+                .map(|configs| configs.map(|c| (c.min_sample_rate().0, c.max_sample_rate().0)).collect()),
+            AudioDeviceKind::Output => device
+                .supported_output_configs()
+                .map(|configs| configs.map(|c| (c.min_sample_rate().0, c.max_sample_rate().0)).collect()),
+        }
+        // A device that can't describe itself is still selectable
+        .unwrap_or_default();
+        let sample_rates = COMMON_SAMPLE_RATES
+            .into_iter()
+            .filter(|rate| ranges.iter().any(|&(min, max)| (min..=max).contains(rate)))
+            .collect();
+        listed.push(AudioDeviceInfo {
+            is_default: default_name.as_ref() == Some(&name),
+            name,
+            sample_rates,
+        });
+    }
+    Ok(listed)
+}
+
+/// Microphone capture through cpal.
+pub struct CpalInput {
+    device_name: String,
+    meter: SignalMeter,
+    // Set by the stream's error callback; the stream keeps running but delivers nothing
+    failure: Arc<Mutex<Option<String>>>,
+    // Checked on every callback so suppression can be toggled while capturing
+    noise_suppression: Arc<AtomicBool>,
+    // Rate denoised frames are delivered at, so they match what STT is configured for
+    denoised_rate: u32,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CpalInput {
+    pub fn new(device_name: &str) -> Self {
+        Self {
+            device_name: device_name.to_string(),
+            meter: SignalMeter::default(),
+            failure: Arc::new(Mutex::new(None)),
+            noise_suppression: Arc::new(AtomicBool::new(false)),
+            denoised_rate: RNNOISE_SAMPLE_RATE,
+            running: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    /// Run captured audio through RNNoise, delivering the result at `output_rate`.
+    pub fn with_noise_suppression(mut self, enabled: bool, output_rate: u32) -> Self {
+        self.noise_suppression.store(enabled, Ordering::SeqCst);
+        self.denoised_rate = output_rate;
+        self
+    }
+}
+
+impl AudioInput for CpalInput {
+    fn name(&self) -> String {
+        format!("cpal:{}", self.device_name)
+    }
+
+    fn start(&mut self, sender: Sender<AudioFrame>) -> Result<()> {
+        if self.is_active() {
+            return Ok(());
+        }
+        self.running.store(true, Ordering::SeqCst);
+        *self.failure.lock().unwrap() = None;
+
+        // cpal streams are not Send, so the stream lives on its own thread
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<()>>();
+        let running = self.running.clone();
+        let device_name = self.device_name.clone();
+        let meter = self.meter.clone();
+        let failure = self.failure.clone();
+        let noise_suppression = self.noise_suppression.clone();
+        let denoised_rate = self.denoised_rate;
+        self.thread = Some(std::thread::spawn(move || {
+            let stream = (|| -> Result<cpal::Stream> {
+                let host = cpal::default_host();
+                let device = find_device(&host, &device_name, true)?;
+                let supported = device.default_input_config()?;
+                let sample_format = supported.sample_format();
+                let config: cpal::StreamConfig = supported.into();
+                let channels = config.channels as usize;
+                let sample_rate = config.sample_rate.0;
+
+                let mut suppressor = NoiseSuppressor::new(denoised_rate);
+                let mut suppressing = false;
+                let mut push = move |mono: Vec<f32>| {
+                    let enabled = noise_suppression.load(Ordering::Relaxed);
+                    if suppressing && !enabled {
+                        suppressor.reset();
+                    }
+                    suppressing = enabled;
+                    let (mono, sample_rate) = if enabled {
+                        (suppressor.process(&mono, sample_rate), suppressor.output_rate())
+                    } else {
+                        (mono, sample_rate)
+                    };
+                    if mono.is_empty() {
+                        return;
+                    }
+                    meter.record(&mono, sample_rate);
+                    let frame = AudioFrame {
+                        data: mono,
+                        sample_rate,
+                    };
+                    match sender.try_send(frame) {
+                        Ok(()) => {}
+                        // STT is behind; losing a frame beats stalling the audio callback
+                        Err(TrySendError::Full(_)) => log::debug!("Frame queue full, dropped an audio frame"),
+                        Err(e) => log::error!("Failed to send audio frame: {}", e),
+                    }
+                };
+                let on_error = move |err: cpal::StreamError| {
+                    log::error!("Audio input stream error: {}", err);
+                    *failure.lock().unwrap() = Some(err.to_string());
+                };
+
+                let stream = match sample_format {
+                    cpal::SampleFormat::F32 => device.build_input_stream(
+                        &config,
+                        move |data: &[f32], _: &cpal::InputCallbackInfo| push(downmix(data, channels)),
+                        on_error,
+                        None,
+                    )?,
+                    cpal::SampleFormat::I16 => device.build_input_stream(
+                        &config,
+                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                            let samples: Vec<f32> = data.iter().map(|&s| s as f32 / i16::MAX as f32).collect();
+                            push(downmix(&samples, channels))
+                        },
+                        on_error,
+                        None,
+                    )?,
+                    other => return Err(anyhow::anyhow!("Unsupported capture sample format: {:?}", other)),
+                };
+                stream.play()?;
+                Ok(stream)
+            })();
+
+            match stream {
+                Ok(stream) => {
+                    let _ = ready_tx.send(Ok(()));
+                    while running.load(Ordering::SeqCst) {
+                        std::thread::sleep(Duration::from_millis(20));
+                    }
+                    drop(stream);
+                }
+                Err(e) => {
+                    running.store(false, Ordering::SeqCst);
+                    let _ = ready_tx.send(Err(e));
+                }
+            }
+        }));
+
+        ready_rx
+            .recv()
+            .context("Audio capture thread exited unexpectedly")??;
+        log::info!("Audio capture started on {}", self.device_name);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.meter.reset();
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn level(&self) -> f32 {
+        self.meter.level()
+    }
+
+    fn take_level(&self) -> Option<SignalLevel> {
+        self.meter.take_level()
+    }
+
+    fn set_noise_suppression(&self, enabled: bool) {
+        self.noise_suppression.store(enabled, Ordering::SeqCst);
+    }
+
+    fn recent_samples(&self) -> (Vec<f32>, u32) {
+        self.meter.recent()
+    }
+
+    fn failure(&self) -> Option<String> {
+        self.failure.lock().unwrap().clone()
+    }
+}
+
+impl Drop for CpalInput {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// Speaker playback through cpal. The output stream is opened on first use and
+/// pulls from a shared queue, so `play` never blocks.
+pub struct CpalOutput {
+    device_name: String,
+    volume: f32,
+    queue: Arc<Mutex<VecDeque<f32>>>,
+    // Frames handed to the device since the stream opened, the basis of the playback clock
+    consumed: Arc<AtomicU64>,
+    // When the last queued sample handed to the device will have left the speakers
+    audible_until: Arc<Mutex<Option<Instant>>>,
+    meter: SignalMeter,
+    failure: Arc<Mutex<Option<String>>>,
+    device_rate: Option<u32>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CpalOutput {
+    pub fn new(device_name: &str, volume: f32) -> Self {
+        Self {
+            device_name: device_name.to_string(),
+            volume,
+            queue: Arc::new(Mutex::new(VecDeque::new())),
+            consumed: Arc::new(AtomicU64::new(0)),
+            audible_until: Arc::new(Mutex::new(None)),
+            meter: SignalMeter::default(),
+            failure: Arc::new(Mutex::new(None)),
+            device_rate: None,
+            running: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    fn open(&mut self) -> Result<u32> {
+        if let Some(rate) = self.device_rate {
+            return Ok(rate);
+        }
+        self.running.store(true, Ordering::SeqCst);
+
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<u32>>();
+        let running = self.running.clone();
+        let queue = self.queue.clone();
+        let consumed = self.consumed.clone();
+        let audible_until = self.audible_until.clone();
+        let meter = self.meter.clone();
+        let failure = self.failure.clone();
+        let device_name = self.device_name.clone();
+        let volume = self.volume;
+        self.thread = Some(std::thread::spawn(move || {
+            let stream = (|| -> Result<(cpal::Stream, u32)> {
+                let host = cpal::default_host();
+                let device = find_device(&host, &device_name, false)?;
+                let supported = device.default_output_config()?;
+                let sample_format = supported.sample_format();
+                let config: cpal::StreamConfig = supported.into();
+                let channels = config.channels as usize;
+                let device_rate = config.sample_rate.0;
+                let on_error = move |err: cpal::StreamError| {
+                    log::error!("Audio output stream error: {}", err);
+                    *failure.lock().unwrap() = Some(err.to_string());
+                };
+
+                // Pull one mono sample per output frame and remember the block's level
+                let next_block = move |frames: usize, info: &cpal::OutputCallbackInfo| -> Vec<f32> {
+                    let mut queue = queue.lock().unwrap();
+                    let queued = queue.len().min(frames);
+                    let block: Vec<f32> = (0..frames)
+                        .map(|_| (queue.pop_front().unwrap_or(0.0) * volume).clamp(-1.0, 1.0))
+                        .collect();
+                    consumed.fetch_add(frames as u64, Ordering::SeqCst);
+                    if queued > 0 {
+                        // The block is heard after the device's own buffering, and takes its length to play
+                        let timestamp = info.timestamp();
+                        let latency = timestamp.playback.duration_since(&timestamp.callback).unwrap_or_default();
+                        let length = Duration::from_secs_f64(queued as f64 / device_rate as f64);
+                        *audible_until.lock().unwrap() = Some(Instant::now() + latency + length);
+                    }
+                    meter.record(&block, device_rate);
+                    block
+                };
+
+                let stream = match sample_format {
+                    cpal::SampleFormat::F32 => device.build_output_stream(
+                        &config,
+                        move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                            let block = next_block(data.len() / channels, info);
+                            for (frame, sample) in data.chunks_mut(channels).zip(block) {
+                                frame.fill(sample);
+                            }
+                        },
+                        on_error,
+                        None,
+                    )?,
+                    cpal::SampleFormat::I16 => device.build_output_stream(
+                        &config,
+                        move |data: &mut [i16], info: &cpal::OutputCallbackInfo| {
+                            let block = next_block(data.len() / channels, info);
+                            for (frame, sample) in data.chunks_mut(channels).zip(block) {
+                                frame.fill((sample * i16::MAX as f32) as i16);
+                            }
+                        },
+                        on_error,
+                        None,
+                    )?,
+                    other => return Err(anyhow::anyhow!("Unsupported playback sample format: {:?}", other)),
+                };
+                stream.play()?;
+                Ok((stream, config.sample_rate.0))
+            })();
+
+            match stream {
+                Ok((stream, rate)) => {
+                    let _ = ready_tx.send(Ok(rate));
+                    while running.load(Ordering::SeqCst) {
+                        std::thread::sleep(Duration::from_millis(20));
+                    }
+                    drop(stream);
+                }
+                Err(e) => {
+                    running.store(false, Ordering::SeqCst);
+                    let _ = ready_tx.send(Err(e));
+                }
+            }
+        }));
+
+        let rate = ready_rx
+            .recv()
+            .context("Audio playback thread exited unexpectedly")??;
+        self.device_rate = Some(rate);
+        Ok(rate)
+    }
+}
+
+impl AudioOutput for CpalOutput {
+    fn name(&self) -> String {
+        format!("cpal:{}", self.device_name)
+    }
+
+    fn play(&mut self, samples: &[f32], sample_rate: u32) -> Result<f64> {
+        let device_rate = self.open()?;
+        let resampled = resample_linear(samples, sample_rate, device_rate);
+        let mut queue = self.queue.lock().unwrap();
+        // New audio is heard once everything already queued has been consumed
+        let starts_at = self.consumed.load(Ordering::SeqCst) + queue.len() as u64;
+        queue.extend(resampled);
+        Ok(starts_at as f64 / device_rate as f64)
+    }
+
+    fn clock(&self) -> f64 {
+        match self.device_rate {
+            Some(rate) => self.consumed.load(Ordering::SeqCst) as f64 / rate as f64,
+            None => 0.0,
+        }
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.queue.lock().unwrap().clear();
+        // What the device already holds is a few milliseconds; count playback as stopped now
+        *self.audible_until.lock().unwrap() = None;
+        Ok(())
+    }
+
+    /// True until the last queued sample has actually been heard, not just handed to the device.
+    fn is_playing(&self) -> bool {
+        !self.queue.lock().unwrap().is_empty()
+            || self.audible_until.lock().unwrap().is_some_and(|until| Instant::now() < until)
+    }
+
+    fn level(&self) -> f32 {
+        self.meter.level()
+    }
+
+    fn recent_samples(&self) -> (Vec<f32>, u32) {
+        self.meter.recent()
+    }
+
+    fn failure(&self) -> Option<String> {
+        self.failure.lock().unwrap().clone()
+    }
+}
+
+impl Drop for CpalOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Replays prerecorded audio as if it came from a microphone, for simulation mode and tests.
+pub struct FileInput {
+    label: String,
+    samples: Arc<Vec<f32>>,
+    sample_rate: u32,
+    // Pace frames at wall-clock speed rather than as fast as possible
+    realtime: bool,
+    meter: SignalMeter,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileInput {
+    const FRAME_MS: u32 = 20;
+
+    pub fn from_samples(samples: Vec<f32>, sample_rate: u32, realtime: bool) -> Self {
+        Self {
+            label: "memory".to_string(),
+            samples: Arc::new(samples),
+            sample_rate,
+            realtime,
+            meter: SignalMeter::default(),
+            running: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    pub fn from_wav(path: impl AsRef<Path>, realtime: bool) -> Result<Self> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        let mut reader = hound::WavReader::open(&path)
+            .with_context(|| format!("Failed to open WAV file {}", path.display()))?;
+        let spec = reader.spec();
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 / scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+        let samples = downmix(&interleaved, spec.channels as usize);
+
+        let mut input = Self::from_samples(samples, spec.sample_rate, realtime);
+        input.label = path.display().to_string();
+        Ok(input)
+    }
+}
+
+impl AudioInput for FileInput {
+    fn name(&self) -> String {
+        format!("file:{}", self.label)
+    }
+
+    fn start(&mut self, sender: Sender<AudioFrame>) -> Result<()> {
+        if self.is_active() {
+            return Ok(());
+        }
+        self.running.store(true, Ordering::SeqCst);
+
+        let running = self.running.clone();
+        let samples = self.samples.clone();
+        let sample_rate = self.sample_rate;
+        let realtime = self.realtime;
+        let meter = self.meter.clone();
+        self.thread = Some(std::thread::spawn(move || {
+            let frame_len = (sample_rate * Self::FRAME_MS / 1000).max(1) as usize;
+            // Trailing silence lets voice activity detection close the last utterance
+            let silence = vec![0.0; sample_rate as usize];
+            for chunk in samples.chunks(frame_len).chain(silence.chunks(frame_len)) {
+                if !running.load(Ordering::SeqCst) {
+                    break;
+                }
+                meter.record(chunk, sample_rate);
+                let frame = AudioFrame {
+                    data: chunk.to_vec(),
+                    sample_rate,
+                };
+                // Files aren't real time, so wait for room instead of losing audio
+                if sender.blocking_send(frame).is_err() {
+                    break;
+                }
+                if realtime {
+                    std::thread::sleep(Duration::from_millis(Self::FRAME_MS as u64));
+                }
+            }
+            meter.reset();
+            running.store(false, Ordering::SeqCst);
+        }));
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn level(&self) -> f32 {
+        self.meter.level()
+    }
+
+    fn take_level(&self) -> Option<SignalLevel> {
+        self.meter.take_level()
+    }
+
+    fn recent_samples(&self) -> (Vec<f32>, u32) {
+        self.meter.recent()
+    }
+}
+
+/// Collects everything played so tests can inspect it.
+#[derive(Default)]
+pub struct MockOutput {
+    played: Arc<Mutex<Vec<f32>>>,
+    // Mock playback is instantaneous, so the clock jumps to the end of each buffer
+    seconds_played: f64,
+}
+
+impl MockOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared handle to the recorded samples; stays valid after the output is boxed.
+    #[cfg(test)]
+    pub fn played(&self) -> Arc<Mutex<Vec<f32>>> {
+        self.played.clone()
+    }
+}
+
+impl AudioOutput for MockOutput {
+    fn name(&self) -> String {
+        "mock".to_string()
+    }
+
+    fn play(&mut self, samples: &[f32], sample_rate: u32) -> Result<f64> {
+        self.played.lock().unwrap().extend_from_slice(samples);
+        let starts_at = self.seconds_played;
+        self.seconds_played += samples.len() as f64 / sample_rate.max(1) as f64;
+        Ok(starts_at)
+    }
+
+    fn clock(&self) -> f64 {
+        self.seconds_played
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_playing(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,63 @@
+use crate::meeting::downmix;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Decode a WAV, MP3 or Ogg Vorbis file to mono samples at its own sample rate.
+pub fn decode_file(path: &Path) -> Result<(Vec<f32>, u32)> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .with_context(|| format!("Unsupported audio format: {}", path.display()))?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .context("File has no audio track")?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.context("Audio track has no sample rate")?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Unsupported audio codec")?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // Symphonia reports the end of the file as an unexpected EOF
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e).context("Failed to read audio file"),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt frame costs a few milliseconds of audio, not the whole file
+            Err(SymphoniaError::DecodeError(e)) => {
+                log::warn!("Skipping undecodable audio packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e).context("Failed to decode audio file"),
+        };
+        let channels = decoded.spec().channels.count();
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend(downmix(buffer.samples(), channels));
+    }
+    Ok((samples, sample_rate))
+}
@@ -0,0 +1,58 @@
+use crate::meeting::resample_linear;
+use nnnoiseless::DenoiseState;
+
+// RNNoise only works on 10ms frames of 48kHz audio
+pub const RNNOISE_SAMPLE_RATE: u32 = 48000;
+// It also expects samples at 16-bit scale rather than -1.0..=1.0
+const PCM_SCALE: f32 = 32768.0;
+
+/// RNNoise noise suppression for microphone audio, run before VAD and STT see it.
+pub struct NoiseSuppressor {
+    state: Box<DenoiseState<'static>>,
+    // Resampled audio not yet filling a whole frame
+    pending: Vec<f32>,
+    output_rate: u32,
+}
+
+impl NoiseSuppressor {
+    /// Denoised audio is delivered at `output_rate`, whatever rate the capture runs at.
+    pub fn new(output_rate: u32) -> Self {
+        Self {
+            state: DenoiseState::new(),
+            pending: Vec::new(),
+            output_rate,
+        }
+    }
+
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// Denoise mono samples at `sample_rate`. RNNoise works on whole frames, so output lags
+    /// input by up to 10ms and is empty when a short frame doesn't complete one.
+    pub fn process(&mut self, samples: &[f32], sample_rate: u32) -> Vec<f32> {
+        self.pending.extend(
+            resample_linear(samples, sample_rate, RNNOISE_SAMPLE_RATE)
+                .into_iter()
+                .map(|s| s * PCM_SCALE),
+        );
+        let whole = self.pending.len() / DenoiseState::FRAME_SIZE * DenoiseState::FRAME_SIZE;
+        let mut denoised = vec![0.0; whole];
+        for (input, output) in self.pending[..whole]
+            .chunks_exact(DenoiseState::FRAME_SIZE)
+            .zip(denoised.chunks_exact_mut(DenoiseState::FRAME_SIZE))
+        {
+            self.state.process_frame(output, input);
+        }
+        self.pending.drain(..whole);
+
+        let denoised: Vec<f32> = denoised.iter().map(|s| (s / PCM_SCALE).clamp(-1.0, 1.0)).collect();
+        resample_linear(&denoised, RNNOISE_SAMPLE_RATE, self.output_rate)
+    }
+
+    /// Forget the noise profile and any partial frame, e.g. after suppression was switched off.
+    pub fn reset(&mut self) {
+        self.state = DenoiseState::new();
+        self.pending.clear();
+    }
+}
@@ -0,0 +1,207 @@
+use crate::audio::backend::{AudioInput, AudioOutput, CpalInput, CpalOutput};
+use crate::config::AudioConfig;
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TryRecvError};
+
+const TONE_HZ: f32 = 440.0;
+const TONE_SECONDS: f32 = 1.0;
+const TONE_AMPLITUDE: f32 = 0.3;
+const TONE_SAMPLE_RATE: u32 = 48000;
+// Fade in and out so the tone doesn't click
+const TONE_FADE_SECONDS: f32 = 0.02;
+
+// Peak below this means the device delivered nothing but digital silence
+const SILENCE_PEAK: f32 = 1e-4;
+const LOW_LEVEL_DBFS: f32 = -50.0;
+const CLIP_THRESHOLD: f32 = 0.99;
+// Fraction of clipped samples tolerated before reporting clipping
+const CLIP_RATIO: f32 = 0.001;
+// Tone bin energy over broadband energy needed to call the tone audible
+const TONE_DETECTION_RATIO: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceIssue {
+    // "output_failed", "input_failed", "silence", "low_level", "clipping" or "sample_rate_mismatch"
+    pub kind: String,
+    pub severity: IssueSeverity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceTestReport {
+    pub input_device: String,
+    pub output_device: String,
+    pub input_sample_rate: Option<u32>,
+    pub expected_sample_rate: u32,
+    pub recorded_ms: u64,
+    pub peak: f32,
+    pub rms_dbfs: f32,
+    pub clipped_ratio: f32,
+    // The microphone heard the test tone, so speaker output reaches it (useful for echo setup)
+    pub tone_detected: bool,
+    pub issues: Vec<DeviceIssue>,
+}
+
+impl DeviceTestReport {
+    fn issue(&mut self, kind: &str, severity: IssueSeverity, message: String) {
+        self.issues.push(DeviceIssue {
+            kind: kind.to_string(),
+            severity,
+            message,
+        });
+    }
+}
+
+/// Play a test tone on `output_device` while recording `record_seconds` from `input_device`.
+///
+/// Blocks for the length of the recording; run it off the async runtime.
+pub fn run(config: &AudioConfig, input_device: &str, output_device: &str, record_seconds: f32) -> Result<DeviceTestReport> {
+    let record_for = Duration::from_secs_f32(record_seconds.clamp(TONE_SECONDS + 0.5, 10.0));
+    let mut report = DeviceTestReport {
+        input_device: input_device.to_string(),
+        output_device: output_device.to_string(),
+        input_sample_rate: None,
+        expected_sample_rate: config.input.sample_rate,
+        recorded_ms: 0,
+        peak: 0.0,
+        rms_dbfs: f32::NEG_INFINITY,
+        clipped_ratio: 0.0,
+        tone_detected: false,
+        issues: Vec::new(),
+    };
+
+    // Drained every 50ms, so this never fills and capture never drops a frame
+    let (sender, mut receiver) = mpsc::channel(512);
+    let mut input = CpalInput::new(input_device);
+    if let Err(e) = input.start(sender) {
+        report.issue("input_failed", IssueSeverity::Error, format!("Could not open the microphone: {:#}", e));
+    }
+
+    let mut output = CpalOutput::new(output_device, config.output.volume);
+    if let Err(e) = output.play(&test_tone(), TONE_SAMPLE_RATE) {
+        report.issue("output_failed", IssueSeverity::Error, format!("Could not play the test tone: {:#}", e));
+    }
+
+    let started = Instant::now();
+    let mut captured = Vec::new();
+    'recording: while started.elapsed() < record_for {
+        std::thread::sleep(Duration::from_millis(50));
+        loop {
+            match receiver.try_recv() {
+                Ok(frame) => {
+                    report.input_sample_rate = Some(frame.sample_rate);
+                    captured.extend(frame.data);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => break 'recording,
+            }
+        }
+    }
+    input.stop()?;
+    output.stop()?;
+
+    let Some(sample_rate) = report.input_sample_rate else {
+        if report.issues.iter().all(|issue| issue.kind != "input_failed") {
+            report.issue("silence", IssueSeverity::Error, "The microphone delivered no audio".to_string());
+        }
+        return Ok(report);
+    };
+    report.recorded_ms = captured.len() as u64 * 1000 / sample_rate.max(1) as u64;
+    analyze(&mut report, &captured, sample_rate);
+    Ok(report)
+}
+
+fn analyze(report: &mut DeviceTestReport, samples: &[f32], sample_rate: u32) {
+    if samples.is_empty() {
+        report.issue("silence", IssueSeverity::Error, "The microphone delivered no audio".to_string());
+        return;
+    }
+
+    report.peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+    report.rms_dbfs = 20.0 * rms.max(1e-9).log10();
+    report.clipped_ratio = samples.iter().filter(|s| s.abs() >= CLIP_THRESHOLD).count() as f32 / samples.len() as f32;
+
+    // The tone plays at the start of the recording
+    let tone_window = &samples[..samples.len().min((sample_rate as f32 * TONE_SECONDS) as usize)];
+    report.tone_detected = tone_energy_ratio(tone_window, sample_rate, TONE_HZ) >= TONE_DETECTION_RATIO;
+
+    if report.peak < SILENCE_PEAK {
+        report.issue(
+            "silence",
+            IssueSeverity::Error,
+            "The microphone is silent; it may be muted or blocked by OS privacy settings".to_string(),
+        );
+    } else if report.rms_dbfs < LOW_LEVEL_DBFS && !report.tone_detected {
+        report.issue(
+            "low_level",
+            IssueSeverity::Warning,
+            format!("The input level is very low ({:.0} dBFS); raise the microphone gain", report.rms_dbfs),
+        );
+    }
+    if report.clipped_ratio > CLIP_RATIO {
+        report.issue(
+            "clipping",
+            IssueSeverity::Warning,
+            format!("{:.1}% of samples are clipped; lower the microphone gain", report.clipped_ratio * 100.0),
+        );
+    }
+    if sample_rate != report.expected_sample_rate {
+        // Higher rates are resampled down; a lower rate loses detail speech recognition needs
+        let severity = if sample_rate < report.expected_sample_rate {
+            IssueSeverity::Error
+        } else {
+            IssueSeverity::Warning
+        };
+        report.issue(
+            "sample_rate_mismatch",
+            severity,
+            format!(
+                "The microphone runs at {} Hz but {} Hz is configured",
+                sample_rate, report.expected_sample_rate
+            ),
+        );
+    }
+}
+
+fn test_tone() -> Vec<f32> {
+    let total = (TONE_SAMPLE_RATE as f32 * TONE_SECONDS) as usize;
+    let fade = (TONE_SAMPLE_RATE as f32 * TONE_FADE_SECONDS) as usize;
+    (0..total)
+        .map(|i| {
+            let t = i as f32 / TONE_SAMPLE_RATE as f32;
+            let envelope = (i.min(total - 1 - i) as f32 / fade as f32).min(1.0);
+            TONE_AMPLITUDE * envelope * (2.0 * std::f32::consts::PI * TONE_HZ * t).sin()
+        })
+        .collect()
+}
+
+/// Energy at `frequency` (Goertzel) relative to the window's total energy.
+fn tone_energy_ratio(samples: &[f32], sample_rate: u32, frequency: f32) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let coefficient = 2.0 * (2.0 * std::f32::consts::PI * frequency / sample_rate as f32).cos();
+    let (mut previous, mut before_previous) = (0.0f32, 0.0f32);
+    for &sample in samples {
+        let current = sample + coefficient * previous - before_previous;
+        before_previous = previous;
+        previous = current;
+    }
+    let power = previous * previous + before_previous * before_previous - coefficient * previous * before_previous;
+    let total: f32 = samples.iter().map(|s| s * s).sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    // A pure tone concentrates N/2 times its energy in the Goertzel bin
+    (power / (total * samples.len() as f32 / 2.0)).min(1.0)
+}
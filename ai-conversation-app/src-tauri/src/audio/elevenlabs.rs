@@ -0,0 +1,163 @@
+use crate::audio::tts::{SynthesisRequest, TtsProvider, TtsVoice};
+use crate::config::TtsConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+const API_BASE: &str = "https://api.elevenlabs.io/v1";
+const API_KEY_ENV: &str = "ELEVENLABS_API_KEY";
+const DEFAULT_MODEL: &str = "eleven_flash_v2_5";
+// Models that read <phoneme> tags; the others would speak the tag aloud
+const PHONEME_MODELS: &[&str] = &["eleven_flash_v2", "eleven_turbo_v2", "eleven_monolingual_v1"];
+// Raw 16-bit PCM, so chunks can be played without decoding
+const OUTPUT_FORMAT: &str = "pcm_22050";
+const SAMPLE_RATE: u32 = 22050;
+
+#[derive(Deserialize)]
+struct VoiceList {
+    voices: Vec<VoiceEntry>,
+}
+
+#[derive(Deserialize)]
+struct VoiceEntry {
+    voice_id: String,
+    name: String,
+}
+
+/// ElevenLabs cloud voices, streamed so playback starts before the whole sentence is ready.
+pub struct ElevenLabsProvider {
+    config: TtsConfig,
+    client: reqwest::Client,
+}
+
+impl ElevenLabsProvider {
+    pub fn new(config: TtsConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn api_key(&self) -> Result<String> {
+        self.config.resolved_api_key(API_KEY_ENV).with_context(|| {
+            format!(
+                "No ElevenLabs API key; set tts.api_key, store it in the keyring as {}, or set {}",
+                self.config.api_key_secret(),
+                API_KEY_ENV
+            )
+        })
+    }
+
+    /// Spell out words from `tts.lexicon` as phonemes, on models that take them.
+    fn apply_lexicon(&self, model: &str, text: &str) -> String {
+        if self.config.lexicon.is_empty() || !PHONEME_MODELS.contains(&model) {
+            return text.to_string();
+        }
+        text.split(' ')
+            .map(|token| {
+                let word = token.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
+                match self.config.pronunciation(word) {
+                    Some(entry) => token.replacen(
+                        word,
+                        &format!(
+                            "<phoneme alphabet=\"cmu-arpabet\" ph=\"{}\">{}</phoneme>",
+                            entry.phonemes.to_uppercase(),
+                            word
+                        ),
+                        1,
+                    ),
+                    None => token.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[async_trait]
+impl TtsProvider for ElevenLabsProvider {
+    async fn synthesize(&self, text: &str, request: &SynthesisRequest) -> Result<(Vec<f32>, u32)> {
+        let (chunks, mut received) = mpsc::unbounded_channel();
+        self.synthesize_streaming(text, request, chunks).await?;
+        let mut samples = Vec::new();
+        while let Some((chunk, _)) = received.recv().await {
+            samples.extend(chunk);
+        }
+        Ok((samples, SAMPLE_RATE))
+    }
+
+    fn streams(&self) -> bool {
+        true
+    }
+
+    async fn synthesize_streaming(&self, text: &str, request: &SynthesisRequest, chunks: mpsc::UnboundedSender<(Vec<f32>, u32)>) -> Result<()> {
+        let voice = request.voice.as_deref().unwrap_or(&self.config.voice);
+        let model = self.config.model.as_deref().unwrap_or(DEFAULT_MODEL);
+        let body = serde_json::json!({
+            "text": self.apply_lexicon(model, text),
+            "model_id": model,
+            "voice_settings": {
+                "stability": 0.5,
+                "similarity_boost": 0.75,
+                // ElevenLabs only accepts a narrow speed range
+                "speed": request.speed.unwrap_or(1.0).clamp(0.7, 1.2),
+            },
+        });
+
+        let mut response = self
+            .client
+            .post(format!("{}/text-to-speech/{}/stream", API_BASE, voice))
+            .query(&[("output_format", OUTPUT_FORMAT)])
+            .header("xi-api-key", self.api_key()?)
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to reach ElevenLabs")?
+            .error_for_status()
+            .context("ElevenLabs synthesis failed")?;
+
+        let volume = request.volume.unwrap_or(1.0);
+        // A network chunk can end halfway through a 16-bit sample
+        let mut leftover: Option<u8> = None;
+        while let Some(bytes) = response.chunk().await.context("ElevenLabs stream was interrupted")? {
+            let mut data = Vec::with_capacity(bytes.len() + 1);
+            data.extend(leftover.take());
+            data.extend_from_slice(&bytes);
+            if data.len() % 2 == 1 {
+                leftover = data.pop();
+            }
+            let samples: Vec<f32> = data
+                .chunks_exact(2)
+                .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0 * volume)
+                .collect();
+            if !samples.is_empty() && chunks.send((samples, SAMPLE_RATE)).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_voices(&self) -> Result<Vec<TtsVoice>> {
+        let voices: VoiceList = self
+            .client
+            .get(format!("{}/voices", API_BASE))
+            .header("xi-api-key", self.api_key()?)
+            .send()
+            .await
+            .context("Failed to reach ElevenLabs")?
+            .error_for_status()
+            .context("Listing ElevenLabs voices failed")?
+            .json()
+            .await
+            .context("Failed to parse ElevenLabs voice list")?;
+        Ok(voices
+            .voices
+            .into_iter()
+            .map(|voice| TtsVoice {
+                id: voice.voice_id,
+                name: voice.name,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,235 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+// The CMU Pronouncing Dictionary in its plain-text form, e.g. from github.com/cmusphinx/cmudict
+const DICTIONARY_FILE: &str = "cmudict.dict";
+
+// Pronunciations keyed by lowercase word, as lowercase ARPAbet without stress markers
+static DICTIONARY: Lazy<HashMap<String, Vec<String>>> = Lazy::new(|| {
+    let content = match crate::models::resolve_asset(DICTIONARY_FILE).and_then(|path| Ok(std::fs::read_to_string(path)?)) {
+        Ok(content) => content,
+        Err(e) => {
+            log::warn!("Pronunciation dictionary unavailable, guessing from spelling: {:#}", e);
+            return HashMap::new();
+        }
+    };
+    let dictionary = parse_dictionary(&content);
+    log::info!("Loaded {} pronunciations", dictionary.len());
+    dictionary
+});
+
+fn parse_dictionary(content: &str) -> HashMap<String, Vec<String>> {
+    let mut dictionary = HashMap::new();
+    for line in content.lines() {
+        // Comments start with ";;;" in the classic file and "#" after an entry in the new one
+        let line = line.split(" #").next().unwrap_or_default().trim();
+        if line.is_empty() || line.starts_with(";;;") {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let Some(word) = fields.next() else {
+            continue;
+        };
+        // Alternate pronunciations are listed as "word(2)"; the first one is the common one
+        if word.ends_with(')') {
+            continue;
+        }
+        dictionary.entry(word.to_lowercase()).or_insert(fields.map(normalize_phoneme).collect());
+    }
+    dictionary
+}
+
+// Phonemes are compared lowercase and without stress markers
+fn normalize_phoneme(phoneme: &str) -> String {
+    phoneme.trim_end_matches(|c: char| c.is_ascii_digit()).to_lowercase()
+}
+
+// Every ARPAbet phoneme, as normalize_phoneme leaves it
+const PHONEMES: &[&str] = &[
+    "aa", "ae", "ah", "ao", "aw", "ay", "b", "ch", "d", "dh", "eh", "er", "ey", "f", "g", "hh", "ih", "iy", "jh", "k",
+    "l", "m", "n", "ng", "ow", "oy", "p", "r", "s", "sh", "t", "th", "uh", "uw", "v", "w", "y", "z", "zh",
+];
+
+/// Whether `phonemes` is a space-separated ARPAbet pronunciation, stress markers allowed.
+pub fn is_valid_pronunciation(phonemes: &str) -> bool {
+    let mut phonemes = phonemes.split_whitespace().peekable();
+    phonemes.peek().is_some() && phonemes.all(|phoneme| PHONEMES.contains(&normalize_phoneme(phoneme).as_str()))
+}
+
+/// ARPAbet phonemes for one word: from `tts.lexicon`, the dictionary, or guessed from its spelling.
+pub fn word_to_phonemes(word: &str) -> Vec<String> {
+    let word: String = word
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '\'')
+        .collect();
+    let word = word.trim_matches('\'');
+    if word.is_empty() {
+        return Vec::new();
+    }
+    if let Some(config) = crate::config::try_get_config() {
+        if let Some(entry) = config.tts.pronunciation(word) {
+            return entry.phonemes.split_whitespace().map(normalize_phoneme).collect();
+        }
+    }
+    if let Some(phonemes) = DICTIONARY.get(word) {
+        return phonemes.clone();
+    }
+    letters_to_sounds(word)
+}
+
+/// Rough speaking time of a phoneme in seconds at normal speed; vowels are held longer.
+pub fn phoneme_duration(phoneme: &str) -> f64 {
+    match phoneme {
+        "aa" | "ae" | "ao" | "aw" | "ay" | "er" | "ey" | "ow" | "oy" | "uw" | "iy" => 0.13,
+        "ah" | "eh" | "ih" | "uh" => 0.09,
+        "p" | "b" | "t" | "d" | "k" | "g" => 0.06,
+        _ => 0.08,
+    }
+}
+
+// Spellings that map to one sound, longest first
+const GRAPHEMES: &[(&str, &[&str])] = &[
+    ("tch", &["ch"]),
+    ("igh", &["ay"]),
+    ("ough", &["ao"]),
+    ("tion", &["sh", "ah", "n"]),
+    ("ch", &["ch"]),
+    ("sh", &["sh"]),
+    ("th", &["th"]),
+    ("ph", &["f"]),
+    ("wh", &["w"]),
+    ("ng", &["ng"]),
+    ("ck", &["k"]),
+    ("qu", &["k", "w"]),
+    ("ee", &["iy"]),
+    ("ea", &["iy"]),
+    ("oo", &["uw"]),
+    ("ou", &["aw"]),
+    ("ow", &["ow"]),
+    ("oa", &["ow"]),
+    ("ai", &["ey"]),
+    ("ay", &["ey"]),
+    ("oi", &["oy"]),
+    ("oy", &["oy"]),
+    ("au", &["ao"]),
+    ("aw", &["ao"]),
+    ("er", &["er"]),
+    ("ir", &["er"]),
+    ("ur", &["er"]),
+    ("ar", &["aa", "r"]),
+    ("or", &["ao", "r"]),
+];
+
+/// Spelling-based guess for words missing from the dictionary, like names and slang.
+fn letters_to_sounds(word: &str) -> Vec<String> {
+    let chars: Vec<char> = word.chars().collect();
+    let mut phonemes: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let rest: String = chars[i..].iter().collect();
+        if let Some((grapheme, sounds)) = GRAPHEMES.iter().find(|(grapheme, _)| rest.starts_with(grapheme)) {
+            phonemes.extend(sounds.iter().map(|s| s.to_string()));
+            i += grapheme.chars().count();
+            continue;
+        }
+
+        let next = chars.get(i + 1).copied();
+        let sounds: &[&str] = match chars[i] {
+            // A final "e" after a consonant is silent, as in "make"
+            'e' if i + 1 == chars.len() && i > 0 => &[],
+            'a' => &["ae"],
+            'e' => &["eh"],
+            'i' => &["ih"],
+            'o' => &["aa"],
+            'u' => &["ah"],
+            'y' if i == 0 => &["y"],
+            'y' => &["iy"],
+            'c' if matches!(next, Some('e' | 'i' | 'y')) => &["s"],
+            'c' | 'k' | 'q' => &["k"],
+            'g' if matches!(next, Some('e' | 'i' | 'y')) => &["jh"],
+            'g' => &["g"],
+            'j' => &["jh"],
+            'x' => &["k", "s"],
+            'b' => &["b"],
+            'd' => &["d"],
+            'f' => &["f"],
+            'h' => &["hh"],
+            'l' => &["l"],
+            'm' => &["m"],
+            'n' => &["n"],
+            'p' => &["p"],
+            'r' => &["r"],
+            's' => &["s"],
+            't' => &["t"],
+            'v' => &["v"],
+            'w' => &["w"],
+            'z' => &["z"],
+            // Digits and apostrophes have no sound of their own here
+            _ => &[],
+        };
+        // Doubled consonants are pronounced once, as in "ball"
+        if i == 0 || chars[i - 1] != chars[i] {
+            phonemes.extend(sounds.iter().map(|s| s.to_string()));
+        }
+        i += 1;
+    }
+    phonemes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dictionary_keeps_the_first_pronunciation_without_stress() {
+        let dictionary = parse_dictionary(concat!(
+            ";;; comment line\n",
+            "READ R IY1 D\n",
+            "read(2) R EH1 D\n",
+            "tomato T AH0 M EY1 T OW2 # common\n",
+            "\n",
+        ));
+        assert_eq!(dictionary.len(), 2);
+        assert_eq!(dictionary["read"], ["r", "iy", "d"]);
+        assert_eq!(dictionary["tomato"], ["t", "ah", "m", "ey", "t", "ow"]);
+    }
+
+    #[test]
+    fn validates_pronunciations() {
+        assert!(is_valid_pronunciation("HH AH0 L OW1"));
+        assert!(is_valid_pronunciation("k ae t"));
+        assert!(!is_valid_pronunciation("k a t"));
+        assert!(!is_valid_pronunciation("   "));
+    }
+
+    #[test]
+    fn guesses_common_spellings() {
+        assert_eq!(letters_to_sounds("make"), ["m", "ae", "k"]);
+        assert_eq!(letters_to_sounds("ball"), ["b", "ae", "l"]);
+        assert_eq!(letters_to_sounds("night"), ["n", "ay", "t"]);
+        assert_eq!(letters_to_sounds("church"), ["ch", "er", "ch"]);
+        assert_eq!(letters_to_sounds("city"), ["s", "ih", "t", "iy"]);
+        assert_eq!(letters_to_sounds("yes"), ["y", "eh", "s"]);
+        assert_eq!(letters_to_sounds("box"), ["b", "aa", "k", "s"]);
+        assert!(letters_to_sounds("42").is_empty());
+    }
+
+    #[test]
+    fn guesses_only_produce_known_phonemes() {
+        for (grapheme, _) in GRAPHEMES {
+            assert!(is_valid_pronunciation(&letters_to_sounds(grapheme).join(" ")), "{}", grapheme);
+        }
+        for letter in 'a'..='z' {
+            let guess = letters_to_sounds(&format!("{}a", letter)).join(" ");
+            assert!(is_valid_pronunciation(&guess), "{}: {}", letter, guess);
+        }
+    }
+
+    #[test]
+    fn vowels_are_held_longest() {
+        assert!(phoneme_duration("iy") > phoneme_duration("ih"));
+        assert!(phoneme_duration("ih") > phoneme_duration("s"));
+        assert!(phoneme_duration("s") > phoneme_duration("t"));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::audio::backend::AudioDeviceKind;
+use crate::audio::processor::{AudioEvent, AudioProcessor, AudioStatus, ProcessingMode, SpeakStream, SpeechOptions};
+use crate::audio::stt::{PendingTranscription, Transcription};
+use crate::audio::tts::SynthesisResult;
+use anyhow::{Context, Result};
+use tokio::sync::{mpsc, oneshot, watch};
+
+enum AudioCommand {
+    StartListening(oneshot::Sender<Result<()>>),
+    StopListening(oneshot::Sender<Result<()>>),
+    StartPushToTalk(oneshot::Sender<Result<()>>),
+    StopPushToTalk(oneshot::Sender<Result<()>>),
+    SetDevice(AudioDeviceKind, String, oneshot::Sender<Result<()>>),
+    SetNoiseSuppression(bool, oneshot::Sender<()>),
+    TranscribeRecording(Vec<f32>, u32, oneshot::Sender<Result<PendingTranscription>>),
+    Speak(String, SpeechOptions, oneshot::Sender<Result<()>>),
+    SpeakStream(oneshot::Sender<SpeakStream>),
+    RenderSpeech(String, oneshot::Sender<Result<SynthesisResult>>),
+    PreviewVoice(String, String, oneshot::Sender<Result<()>>),
+    StopSpeaking(oneshot::Sender<Result<()>>),
+    ReloadTts(oneshot::Sender<Result<()>>),
+    Status(oneshot::Sender<AudioStatus>),
+    SubscribeMode(oneshot::Sender<watch::Receiver<ProcessingMode>>),
+    Shutdown(oneshot::Sender<Result<()>>),
+}
+
+/// Cloneable handle to the audio pipeline.
+///
+/// The AudioProcessor owns device streams and holds locks across awaits, so it
+/// can't be shared as Tauri state directly. Instead it runs on a dedicated thread
+/// with its own runtime and receives commands over a channel.
+#[derive(Clone)]
+pub struct AudioHandle {
+    commands: mpsc::UnboundedSender<AudioCommand>,
+}
+
+impl AudioHandle {
+    /// Start the processor thread. `on_event` is called for every AudioEvent it emits.
+    pub async fn spawn(on_event: impl Fn(AudioEvent) + Send + 'static) -> Result<Self> {
+        let (commands, mut receiver) = mpsc::unbounded_channel::<AudioCommand>();
+        let (ready_tx, ready_rx) = oneshot::channel::<Result<()>>();
+
+        std::thread::Builder::new()
+            .name("audio-processor".to_string())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e.into()));
+                        return;
+                    }
+                };
+
+                runtime.block_on(async move {
+                    let mut processor = match AudioProcessor::new().await {
+                        Ok(processor) => processor,
+                        Err(e) => {
+                            let _ = ready_tx.send(Err(e));
+                            return;
+                        }
+                    };
+
+                    // Subscribe before initializing, so model loading progress is forwarded too
+                    let mut events = processor.get_event_receiver();
+                    tokio::spawn(async move {
+                        loop {
+                            match events.recv().await {
+                                Ok(event) => on_event(event),
+                                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                                    log::warn!("Dropped {} audio events", skipped);
+                                }
+                                Err(_) => break,
+                            }
+                        }
+                    });
+                    if let Err(e) = processor.initialize().await {
+                        // Let the failed load reach the frontend before the runtime goes away
+                        tokio::task::yield_now().await;
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                    let _ = ready_tx.send(Ok(()));
+
+                    while let Some(command) = receiver.recv().await {
+                        match command {
+                            AudioCommand::StartListening(reply) => {
+                                let _ = reply.send(processor.start_listening().await);
+                            }
+                            AudioCommand::StopListening(reply) => {
+                                let _ = reply.send(processor.stop_listening());
+                            }
+                            AudioCommand::StartPushToTalk(reply) => {
+                                let _ = reply.send(processor.start_push_to_talk().await);
+                            }
+                            AudioCommand::StopPushToTalk(reply) => {
+                                let _ = reply.send(processor.stop_push_to_talk());
+                            }
+                            AudioCommand::SetDevice(kind, name, reply) => {
+                                let _ = reply.send(processor.set_device(kind, &name));
+                            }
+                            AudioCommand::SetNoiseSuppression(enabled, reply) => {
+                                processor.set_noise_suppression(enabled);
+                                let _ = reply.send(());
+                            }
+                            AudioCommand::TranscribeRecording(samples, sample_rate, reply) => {
+                                let _ = reply.send(processor.transcribe_recording(&samples, sample_rate));
+                            }
+                            AudioCommand::Speak(text, options, reply) => {
+                                let _ = reply.send(processor.synthesize_speech(text, options).await);
+                            }
+                            AudioCommand::SpeakStream(reply) => {
+                                let _ = reply.send(processor.speak_stream());
+                            }
+                            AudioCommand::RenderSpeech(text, reply) => {
+                                let _ = reply.send(processor.render_speech(text).await);
+                            }
+                            AudioCommand::PreviewVoice(text, voice, reply) => {
+                                let _ = reply.send(processor.preview_voice(text, voice).await);
+                            }
+                            AudioCommand::StopSpeaking(reply) => {
+                                let _ = reply.send(processor.stop_speaking());
+                            }
+                            AudioCommand::ReloadTts(reply) => {
+                                let _ = reply.send(processor.reload_tts().await);
+                            }
+                            AudioCommand::Status(reply) => {
+                                let _ = reply.send(processor.status());
+                            }
+                            AudioCommand::SubscribeMode(reply) => {
+                                let _ = reply.send(processor.subscribe_mode());
+                            }
+                            AudioCommand::Shutdown(reply) => {
+                                let _ = reply.send(processor.stop().await);
+                                break;
+                            }
+                        }
+                    }
+                    log::info!("Audio processor thread exiting");
+                });
+            })
+            .context("Failed to start audio processor thread")?;
+
+        ready_rx
+            .await
+            .context("Audio processor thread exited unexpectedly")??;
+        Ok(Self { commands })
+    }
+
+    async fn request<T>(&self, command: impl FnOnce(oneshot::Sender<T>) -> AudioCommand) -> Result<T> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| anyhow::anyhow!("Audio processor is not running"))?;
+        response.await.context("Audio processor dropped the request")
+    }
+
+    pub async fn start_listening(&self) -> Result<()> {
+        self.request(AudioCommand::StartListening).await?
+    }
+
+    pub async fn stop_listening(&self) -> Result<()> {
+        self.request(AudioCommand::StopListening).await?
+    }
+
+    /// Record without voice activity detection until `stop_push_to_talk`.
+    pub async fn start_push_to_talk(&self) -> Result<()> {
+        self.request(AudioCommand::StartPushToTalk).await?
+    }
+
+    /// Stop a push-to-talk recording and transcribe it immediately.
+    pub async fn stop_push_to_talk(&self) -> Result<()> {
+        self.request(AudioCommand::StopPushToTalk).await?
+    }
+
+    /// Switch the input or output device without restarting the pipeline.
+    pub async fn set_device(&self, kind: AudioDeviceKind, name: String) -> Result<()> {
+        self.request(|reply| AudioCommand::SetDevice(kind, name, reply)).await?
+    }
+
+    pub async fn set_noise_suppression(&self, enabled: bool) -> Result<()> {
+        self.request(|reply| AudioCommand::SetNoiseSuppression(enabled, reply)).await
+    }
+
+    /// Transcribe mono samples at `sample_rate` with the live pipeline's Whisper model.
+    pub async fn transcribe_recording(&self, samples: Vec<f32>, sample_rate: u32) -> Result<Transcription> {
+        let pending = self
+            .request(|reply| AudioCommand::TranscribeRecording(samples, sample_rate, reply))
+            .await??;
+        // Waited for here rather than on the processor thread, so a long file doesn't hold up other commands
+        pending.await.context("Transcription was dropped")?
+    }
+
+    pub async fn speak(&self, text: String) -> Result<()> {
+        self.speak_with(text, SpeechOptions::default()).await
+    }
+
+    /// Speak `text` in another voice or at another rate than the configured one.
+    pub async fn speak_with(&self, text: String, options: SpeechOptions) -> Result<()> {
+        self.request(|reply| AudioCommand::Speak(text, options, reply)).await?
+    }
+
+    /// Start a reply that is spoken sentence by sentence as its text arrives.
+    pub async fn speak_stream(&self) -> Result<SpeakStream> {
+        self.request(AudioCommand::SpeakStream).await
+    }
+
+    /// Synthesize `text` without playing it, e.g. to export its lip-sync timeline.
+    pub async fn render_speech(&self, text: String) -> Result<SynthesisResult> {
+        self.request(|reply| AudioCommand::RenderSpeech(text, reply)).await?
+    }
+
+    pub async fn preview_voice(&self, text: String, voice: String) -> Result<()> {
+        self.request(|reply| AudioCommand::PreviewVoice(text, voice, reply)).await?
+    }
+
+    pub async fn stop_speaking(&self) -> Result<()> {
+        self.request(AudioCommand::StopSpeaking).await?
+    }
+
+    /// Pick up a changed TTS provider or voice from the configuration.
+    pub async fn reload_tts(&self) -> Result<()> {
+        self.request(AudioCommand::ReloadTts).await?
+    }
+
+    pub async fn status(&self) -> Result<AudioStatus> {
+        self.request(AudioCommand::Status).await
+    }
+
+    /// Follow the processing mode, e.g. to tell when speech has finished playing.
+    pub async fn subscribe_mode(&self) -> Result<watch::Receiver<ProcessingMode>> {
+        self.request(AudioCommand::SubscribeMode).await
+    }
+
+    pub async fn shutdown(&self) -> Result<()> {
+        self.request(AudioCommand::Shutdown).await?
+    }
+}
@@ -1,214 +1,241 @@
-use crate::config::{get_config, AudioConfig};
+use crate::config::try_get_config;
 use anyhow::{Context, Result};
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, Host, Stream, StreamConfig};
-use rodio::{Decoder, OutputStream, Sink};
-use std::sync::{Arc, Mutex};
-use std::sync::mpsc::{self, Receiver, Sender};
-use tokio::sync::broadcast;
+use backend::{AudioDeviceKind, SignalLevel};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
+pub mod align;
+pub mod backend;
+pub mod decode;
+pub mod denoise;
+pub mod device_test;
+pub mod elevenlabs;
+pub mod g2p;
+pub mod handle;
+pub mod piper;
+pub mod spectrum;
+pub mod ssml;
 pub mod stt;
 pub mod tts;
+pub mod tts_cache;
+pub mod vad;
+pub mod viseme_export;
+pub mod visemes;
 pub mod processor;
 
+pub use backend::{AudioInput, AudioOutput};
+pub use handle::AudioHandle;
 pub use stt::SpeechToText;
 pub use tts::TextToSpeech;
-pub use processor::AudioProcessor;
 
 #[derive(Debug, Clone)]
 pub struct AudioFrame {
     pub data: Vec<f32>,
     pub sample_rate: u32,
-    pub channels: u16,
-    pub timestamp: u64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisemeData {
     pub phoneme: String,
+    /// The phoneme's mouth shape in `character.lip_sync.viseme_mapping`
+    #[serde(default)]
+    pub viseme: String,
     pub timestamp: f64,
     pub duration: f64,
     pub intensity: f32,
 }
 
+// Frames queued between capture and STT, several seconds of audio at typical callback sizes.
+// When STT falls that far behind, e.g. during a long transcription, capture drops the newest
+// frames rather than block the audio callback or let the queue grow without bound.
+const FRAME_QUEUE_CAPACITY: usize = 500;
+
+/// A stream that failed, e.g. because its device was unplugged, and where it was moved.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceChange {
+    pub kind: AudioDeviceKind,
+    pub device: String,
+    pub reason: String,
+}
+
 pub struct AudioManager {
-    host: Host,
-    input_device: Option<Device>,
-    output_device: Option<Device>,
-    input_stream: Option<Stream>,
-    output_stream: Option<OutputStream>,
-    audio_sender: Sender<AudioFrame>,
-    audio_receiver: Arc<Mutex<Receiver<AudioFrame>>>,
-    viseme_broadcaster: broadcast::Sender<VisemeData>,
-    is_recording: Arc<Mutex<bool>>,
-    is_playing: Arc<Mutex<bool>>,
+    input: Box<dyn AudioInput>,
+    output: Box<dyn AudioOutput>,
+    audio_sender: mpsc::Sender<AudioFrame>,
+    // Handed to the STT task by take_audio_receiver
+    audio_receiver: Option<mpsc::Receiver<AudioFrame>>,
+    playback_generation: u64,
 }
 
 impl AudioManager {
     pub fn new() -> Result<Self> {
-        let host = cpal::default_host();
-        let (audio_sender, audio_receiver) = mpsc::channel();
-        let (viseme_broadcaster, _) = broadcast::channel(1000);
+        let config = try_get_config().context("Configuration not initialized")?;
+        Ok(Self::with_backends(
+            backend::create_input(&config.audio)?,
+            backend::create_output(&config.audio)?,
+        ))
+    }
+    
+    /// Build a manager around explicit backends, e.g. a `FileInput` and `MockOutput` in tests.
+    pub fn with_backends(input: Box<dyn AudioInput>, output: Box<dyn AudioOutput>) -> Self {
+        let (audio_sender, audio_receiver) = mpsc::channel(FRAME_QUEUE_CAPACITY);
         
-        Ok(AudioManager {
-            host,
-            input_device: None,
-            output_device: None,
-            input_stream: None,
-            output_stream: None,
+        AudioManager {
+            input,
+            output,
             audio_sender,
-            audio_receiver: Arc::new(Mutex::new(audio_receiver)),
-            viseme_broadcaster,
-            is_recording: Arc::new(Mutex::new(false)),
-            is_playing: Arc::new(Mutex::new(false)),
-        })
+            audio_receiver: Some(audio_receiver),
+            playback_generation: 0,
+        }
     }
     
-    pub fn initialize(&mut self) -> Result<()> {
-        let config = get_config();
-        
-        // Initialize input device
-        self.input_device = if config.audio.input.device == "default" {
-            Some(self.host.default_input_device()
-                .context("No default input device available")?)
-        } else {
-            self.find_device_by_name(&config.audio.input.device, true)?
-        };
-        
-        // Initialize output device
-        self.output_device = if config.audio.output.device == "default" {
-            Some(self.host.default_output_device()
-                .context("No default output device available")?)
-        } else {
-            self.find_device_by_name(&config.audio.output.device, false)?
-        };
-        
-        log::info!("Audio devices initialized successfully");
+    /// Swap in another input backend, carrying on recording if the old one was.
+    pub fn replace_input(&mut self, input: Box<dyn AudioInput>) -> Result<()> {
+        let recording = self.input.is_active();
+        self.input.stop()?;
+        self.input = input;
+        if recording {
+            self.input.start(self.audio_sender.clone())?;
+        }
+        log::info!("Audio input switched to {}", self.input.name());
         Ok(())
     }
     
-    fn find_device_by_name(&self, name: &str, is_input: bool) -> Result<Option<Device>> {
-        let devices = if is_input {
-            self.host.input_devices()?
-        } else {
-            self.host.output_devices()?
-        };
-        
-        for device in devices {
-            if let Ok(device_name) = device.name() {
-                if device_name.contains(name) {
-                    return Ok(Some(device));
-                }
-            }
+    /// Switch RNNoise on or off for the running input.
+    pub fn set_noise_suppression(&self, enabled: bool) {
+        self.input.set_noise_suppression(enabled);
+        log::info!("Noise suppression {}", if enabled { "enabled" } else { "disabled" });
+    }
+    
+    /// Swap in another output backend. Queued playback is dropped rather than moved over.
+    pub fn replace_output(&mut self, output: Box<dyn AudioOutput>) -> Result<()> {
+        self.stop_playback()?;
+        self.output = output;
+        log::info!("Audio output switched to {}", self.output.name());
+        Ok(())
+    }
+    
+    /// Move any stream whose device has failed to the system default device, rebuilding it.
+    /// Returns what was moved; a stream that can't be rebuilt is reported as an error.
+    pub fn check_devices(&mut self) -> Result<Vec<DeviceChange>> {
+        let mut changes = Vec::new();
+        if let Some(reason) = self.input.failure() {
+            log::warn!("Audio input {} failed: {}", self.input.name(), reason);
+            let mut audio = try_get_config().context("Configuration not initialized")?.audio.clone();
+            audio.input.device = "default".to_string();
+            self.replace_input(backend::create_input(&audio)?)?;
+            changes.push(DeviceChange {
+                kind: AudioDeviceKind::Input,
+                device: self.input.name(),
+                reason,
+            });
+        }
+        if let Some(reason) = self.output.failure() {
+            log::warn!("Audio output {} failed: {}", self.output.name(), reason);
+            let mut audio = try_get_config().context("Configuration not initialized")?.audio.clone();
+            audio.output.device = "default".to_string();
+            self.replace_output(backend::create_output(&audio)?)?;
+            changes.push(DeviceChange {
+                kind: AudioDeviceKind::Output,
+                device: self.output.name(),
+                reason,
+            });
         }
+        Ok(changes)
+    }
+    
+    pub fn initialize(&mut self) -> Result<()> {
+        // Mobile audio sessions must be configured before any stream is opened
+        crate::platform::configure_audio_session()?;
         
-        Ok(None)
+        log::info!("Audio backends initialized: input {}, output {}", self.input.name(), self.output.name());
+        Ok(())
     }
     
     pub fn start_recording(&mut self) -> Result<()> {
-        let config = get_config();
-        let device = self.input_device.as_ref()
-            .context("Input device not initialized")?;
-        
-        let stream_config = StreamConfig {
-            channels: config.audio.input.channels,
-            sample_rate: cpal::SampleRate(config.audio.input.sample_rate),
-            buffer_size: cpal::BufferSize::Fixed(config.audio.input.buffer_size),
-        };
-        
-        let sender = self.audio_sender.clone();
-        let is_recording = self.is_recording.clone();
-        
-        let stream = device.build_input_stream(
-            &stream_config,
-            move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                if *is_recording.lock().unwrap() {
-                    let frame = AudioFrame {
-                        data: data.to_vec(),
-                        sample_rate: stream_config.sample_rate.0,
-                        channels: stream_config.channels,
-                        timestamp: std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap()
-                            .as_millis() as u64,
-                    };
-                    
-                    if let Err(e) = sender.send(frame) {
-                        log::error!("Failed to send audio frame: {}", e);
-                    }
-                }
-            },
-            |err| {
-                log::error!("Audio input stream error: {}", err);
-            },
-            None,
-        )?;
-        
-        stream.play()?;
-        self.input_stream = Some(stream);
-        *self.is_recording.lock().unwrap() = true;
-        
+        self.input.start(self.audio_sender.clone())?;
         log::info!("Audio recording started");
         Ok(())
     }
     
     pub fn stop_recording(&mut self) -> Result<()> {
-        *self.is_recording.lock().unwrap() = false;
-        
-        if let Some(stream) = self.input_stream.take() {
-            stream.pause()?;
-        }
-        
+        self.input.stop()?;
         log::info!("Audio recording stopped");
         Ok(())
     }
     
-    pub fn play_audio(&mut self, audio_data: Vec<f32>, sample_rate: u32) -> Result<()> {
-        let config = get_config();
-        
-        // Create a simple WAV-like format for rodio
-        let spec = rodio::source::SineWave::new(440.0)
-            .take_duration(std::time::Duration::from_secs(1))
-            .amplify(0.0); // Silent base
-        
-        // For now, we'll use a simple approach
-        // In a real implementation, you'd convert the f32 data to a proper audio source
+    /// Queue audio and return the playback clock time at which it becomes audible.
+    pub fn play_audio(&mut self, audio_data: Vec<f32>, sample_rate: u32) -> Result<f64> {
         log::info!("Playing audio with {} samples at {} Hz", audio_data.len(), sample_rate);
-        
-        *self.is_playing.lock().unwrap() = true;
-        
-        // TODO: Implement proper audio playback with the provided data
-        // This is a placeholder implementation
-        
-        Ok(())
+        self.output.play(&audio_data, sample_rate)
     }
     
-    pub fn get_audio_receiver(&self) -> Arc<Mutex<Receiver<AudioFrame>>> {
-        self.audio_receiver.clone()
+    pub fn stop_playback(&mut self) -> Result<()> {
+        self.playback_generation += 1;
+        self.output.stop()
     }
     
-    pub fn get_viseme_receiver(&self) -> broadcast::Receiver<VisemeData> {
-        self.viseme_broadcaster.subscribe()
+    pub fn playback_clock(&self) -> f64 {
+        self.output.clock()
     }
     
-    pub fn send_viseme(&self, viseme: VisemeData) -> Result<()> {
-        self.viseme_broadcaster.send(viseme)
-            .map_err(|e| anyhow::anyhow!("Failed to send viseme: {}", e))?;
-        Ok(())
+    /// Bumped whenever queued playback is discarded, so timed followers can tell their audio is gone.
+    pub fn playback_generation(&self) -> u64 {
+        self.playback_generation
+    }
+    
+    /// The receiving end of the frame queue, which has a single consumer. Once the first
+    /// receiver is taken a fresh queue replaces it, so take this before `start_recording`;
+    /// the previous receiver then sees the queue close when capture stops.
+    pub fn take_audio_receiver(&mut self) -> mpsc::Receiver<AudioFrame> {
+        self.audio_receiver.take().unwrap_or_else(|| {
+            let (audio_sender, audio_receiver) = mpsc::channel(FRAME_QUEUE_CAPACITY);
+            self.audio_sender = audio_sender;
+            audio_receiver
+        })
     }
     
     pub fn is_recording(&self) -> bool {
-        *self.is_recording.lock().unwrap()
+        self.input.is_active()
     }
     
     pub fn is_playing(&self) -> bool {
-        *self.is_playing.lock().unwrap()
+        self.output.is_playing()
+    }
+    
+    pub fn input_name(&self) -> String {
+        self.input.name()
+    }
+    
+    pub fn output_name(&self) -> String {
+        self.output.name()
+    }
+    
+    pub fn input_level(&self) -> f32 {
+        self.input.level()
+    }
+    
+    /// Microphone RMS and peak since this was last called, None if nothing was captured.
+    pub fn take_input_level(&self) -> Option<SignalLevel> {
+        self.input.take_level()
+    }
+    
+    pub fn output_level(&self) -> f32 {
+        self.output.level()
+    }
+    
+    pub fn input_samples(&self) -> (Vec<f32>, u32) {
+        self.input.recent_samples()
+    }
+    
+    pub fn output_samples(&self) -> (Vec<f32>, u32) {
+        self.output.recent_samples()
     }
 }
 
 impl Drop for AudioManager {
     fn drop(&mut self) {
         let _ = self.stop_recording();
-        *self.is_playing.lock().unwrap() = false;
+        let _ = self.stop_playback();
     }
 }
\ No newline at end of file
@@ -0,0 +1,120 @@
+use crate::audio::tts::{SynthesisRequest, TtsProvider, TtsVoice};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// The part of a voice's .onnx.json that playback needs
+#[derive(Deserialize)]
+struct VoiceConfig {
+    audio: VoiceAudio,
+}
+
+#[derive(Deserialize)]
+struct VoiceAudio {
+    sample_rate: u32,
+}
+
+/// Local neural speech through the Piper command-line synthesizer and its ONNX voices.
+pub struct PiperProvider {
+    binary: String,
+    default_voice: String,
+}
+
+impl PiperProvider {
+    pub fn new(binary: String, default_voice: String) -> Self {
+        Self { binary, default_voice }
+    }
+}
+
+fn voice_sample_rate(model: &Path) -> Result<u32> {
+    let config_path = model.with_extension("onnx.json");
+    let content = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read Piper voice config {}", config_path.display()))?;
+    let config: VoiceConfig = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse Piper voice config {}", config_path.display()))?;
+    Ok(config.audio.sample_rate)
+}
+
+#[async_trait]
+impl TtsProvider for PiperProvider {
+    async fn synthesize(&self, text: &str, request: &SynthesisRequest) -> Result<(Vec<f32>, u32)> {
+        // Piper runs a process per utterance; keep the wait off the async runtime
+        let binary = self.binary.clone();
+        let voice = request.voice.clone().unwrap_or_else(|| self.default_voice.clone());
+        let text = text.to_string();
+        let request = request.clone();
+        tokio::task::spawn_blocking(move || run_piper(&binary, &voice, &text, &request))
+            .await
+            .context("Piper task panicked")?
+    }
+
+    async fn list_voices(&self) -> Result<Vec<TtsVoice>> {
+        Ok(crate::models::list_piper_voices()
+            .into_iter()
+            .map(|voice| TtsVoice { id: voice.clone(), name: voice })
+            .collect())
+    }
+
+    async fn warm_up(&self) -> Result<()> {
+        // Piper loads the voice on every run, but after one run it's read from the page cache
+        let request = SynthesisRequest {
+            text: "Hello.".to_string(),
+            voice: None,
+            speed: None,
+            pitch: None,
+            volume: None,
+            generate_visemes: false,
+            ssml: false,
+        };
+        self.synthesize(&request.text, &request).await.map(|_| ())
+    }
+}
+
+fn run_piper(binary: &str, voice: &str, text: &str, request: &SynthesisRequest) -> Result<(Vec<f32>, u32)> {
+    let model = crate::models::resolve_piper_voice(voice)?;
+    let sample_rate = voice_sample_rate(&model)?;
+    // Piper stretches phoneme lengths rather than taking a speed
+    let length_scale = 1.0 / request.speed.unwrap_or(1.0).max(0.1);
+
+    let mut child = Command::new(binary)
+        .arg("--model")
+        .arg(&model)
+        .arg("--output_raw")
+        .arg("--length_scale")
+        .arg(format!("{:.3}", length_scale))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}; install Piper or set tts.piper_binary", binary))?;
+
+    // Piper reads one utterance per line; feed it from another thread so a long reply
+    // can't fill the output pipe while we're still writing the input
+    let mut stdin = child.stdin.take().context("Piper stdin unavailable")?;
+    let line = format!("{}\n", text.replace(['\r', '\n'], " "));
+    let writer = std::thread::spawn(move || stdin.write_all(line.as_bytes()));
+
+    let output = child.wait_with_output().context("Failed to wait for Piper")?;
+    writer
+        .join()
+        .map_err(|_| anyhow::anyhow!("Piper input thread panicked"))?
+        .context("Failed to send text to Piper")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Piper failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // Raw output is 16-bit little-endian mono PCM
+    let volume = request.volume.unwrap_or(1.0);
+    let samples = output
+        .stdout
+        .chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0 * volume)
+        .collect();
+    Ok((samples, sample_rate))
+}
@@ -1,9 +1,16 @@
-use crate::config::get_config;
-use crate::audio::{AudioFrame, AudioManager, SpeechToText, TextToSpeech, VisemeData};
-use crate::audio::tts::SynthesisRequest;
+use crate::config::try_get_config;
+use crate::audio::{AudioManager, DeviceChange, SpeechToText, TextToSpeech, VisemeData};
+use crate::audio::spectrum::{SpectrumAnalyzer, SpectrumFrame, SpectrumSource};
+use crate::audio::backend::{self, AudioDeviceKind, SignalLevel};
+use crate::audio::stt::{PendingTranscription, SttEvent};
+use crate::audio::ssml;
+use crate::audio::tts::{SynthesisRequest, SynthesisResult, WordTiming};
+use crate::normalization;
 use anyhow::{Context, Result};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc, watch};
 
 #[derive(Debug, Clone)]
 pub enum AudioEvent {
@@ -11,53 +18,304 @@ pub enum AudioEvent {
     SpeechEnded,
     AudioGenerated(Vec<f32>),
     VisemeGenerated(VisemeData),
+    ModeChanged(ProcessingMode),
+    Spectrum(SpectrumFrame),
+    WordBoundary(WordBoundaryEvent),
+    SpeechCancelled(SpeechCancelled),
+    SentenceBoundary(SentenceBoundaryEvent),
+    DeviceChanged(DeviceChange),
+    MicLevel(SignalLevel),
+    ModelLoad(ModelLoadProgress),
+    // The language the latest utterance was transcribed in, sent when it changes
+    LanguageDetected(String),
     Error(String),
 }
 
+/// A word of the spoken reply that has just become audible.
+#[derive(Debug, Clone, Serialize)]
+pub struct WordBoundaryEvent {
+    // Synthesis result the word belongs to; streamed replies arrive as several chunks
+    pub chunk: u64,
+    pub index: usize,
+    pub word: String,
+    // Where the word starts in the text that was synthesized, in characters
+    pub char_offset: usize,
+    // When the word is heard, from the start of that text's audio
+    pub audio_offset_ms: u64,
+    pub duration_ms: u64,
+}
+
+/// A sentence of the spoken reply whose first word has just become audible.
+#[derive(Debug, Clone, Serialize)]
+pub struct SentenceBoundaryEvent {
+    pub chunk: u64,
+    pub sentence: String,
+    pub char_offset: usize,
+    pub audio_offset_ms: u64,
+}
+
+/// How to speak a piece of text, where it differs from the tts settings.
+#[derive(Debug, Clone, Default)]
+pub struct SpeechOptions {
+    pub voice: Option<String>,
+    // Multiplies tts.speed
+    pub rate: Option<f32>,
+}
+
+/// Where playback was when stop_speaking cut the speech off.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechCancelled {
+    // How much of the text being spoken was heard
+    pub position_ms: u64,
+    // Character offset of the last word heard in that text, if any was
+    pub char_offset: Option<usize>,
+}
+
+// The text being spoken, so a stop can tell how far it got
+struct SpokenProgress {
+    generation: u64,
+    // Playback clock time its audio started
+    started_at: f64,
+    // Clock time each word becomes audible, with its character offset
+    words: Vec<(f64, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelLoadStatus {
+    Loading,
+    Ready,
+    Failed,
+}
+
+/// A model being loaded ahead of first use, for the startup loading screen.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelLoadProgress {
+    // "speech_recognition", "voice" or "llm"
+    pub stage: &'static str,
+    pub status: ModelLoadStatus,
+    pub error: Option<String>,
+}
+
+// How often word sync samples the playback clock
+const WORD_SYNC_POLL_MS: u64 = 10;
+// Microphone meter updates, about 20 per second
+const MIC_LEVEL_INTERVAL_MS: u64 = 50;
+// How often the device watchdog looks for failed streams
+const DEVICE_WATCHDOG_MS: u64 = 1000;
+
 pub struct AudioProcessor {
     audio_manager: Arc<Mutex<AudioManager>>,
     stt: Arc<Mutex<SpeechToText>>,
     tts: Arc<Mutex<TextToSpeech>>,
     event_sender: broadcast::Sender<AudioEvent>,
     is_running: Arc<Mutex<bool>>,
-    processing_mode: ProcessingMode,
+    // Observable so the STT feed and the frontend follow mode changes without polling
+    mode: Arc<watch::Sender<ProcessingMode>>,
+    // Streams take turns, so each reply is heard whole and in the order it was started
+    stream_queue: Arc<tokio::sync::Mutex<()>>,
+    // Bumped by stop_speaking so streams still being written fall silent
+    speech_generation: Arc<AtomicU64>,
+    spoken: Arc<Mutex<Option<SpokenProgress>>>,
+    // Set while the push-to-talk key is held; STT then records without VAD until it's released
+    push_to_talk: watch::Sender<bool>,
+}
+
+enum StreamText {
+    Delta(String),
+    End,
+}
+
+/// Reply text fed in as it is generated and spoken a sentence at a time, as soon as each
+/// sentence is complete. Call `finish` once the text is done; dropping the stream without
+/// finishing discards the unfinished last sentence, as for an aborted reply.
+pub struct SpeakStream {
+    text: mpsc::UnboundedSender<StreamText>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl SpeakStream {
+    pub fn push(&self, text: impl Into<String>) {
+        let _ = self.text.send(StreamText::Delta(text.into()));
+    }
+
+    pub fn finish(self) {
+        let _ = self.text.send(StreamText::End);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ProcessingMode {
     Listening,
     Speaking,
     Idle,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioStatus {
+    pub initialized: bool,
+    pub running: bool,
+    pub recording: bool,
+    pub playing: bool,
+    pub mode: ProcessingMode,
+    pub input_device: Option<String>,
+    pub output_device: Option<String>,
+    pub input_level: f32,
+    pub output_level: f32,
+}
+
+impl AudioStatus {
+    /// Status reported before initialize_audio_system has run
+    pub fn uninitialized() -> Self {
+        Self {
+            initialized: false,
+            running: false,
+            recording: false,
+            playing: false,
+            mode: ProcessingMode::Idle,
+            input_device: None,
+            output_device: None,
+            input_level: 0.0,
+            output_level: 0.0,
+        }
+    }
+}
+
+/// Emit each word of a synthesis chunk when the playback clock reaches it.
+///
+/// `starts_at` is the clock time the chunk's first sample becomes audible, so
+/// chunks queued behind earlier audio are highlighted when heard, not when queued.
+/// `utterance_starts_at` is when the first chunk of the same synthesized text did.
+/// Stops early if playback is stopped, since the remaining words will never be heard.
+async fn sync_words(
+    audio_manager: Arc<Mutex<AudioManager>>,
+    event_sender: broadcast::Sender<AudioEvent>,
+    chunk: u64,
+    words: Vec<WordTiming>,
+    starts_at: f64,
+    utterance_starts_at: f64,
+    generation: u64,
+) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(WORD_SYNC_POLL_MS));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut words = words.into_iter().peekable();
+    while words.peek().is_some() {
+        interval.tick().await;
+        let clock = {
+            let audio_manager = audio_manager.lock().unwrap();
+            if audio_manager.playback_generation() != generation {
+                return;
+            }
+            audio_manager.playback_clock()
+        };
+        while let Some(word) = words.next_if(|word| clock >= starts_at + word.start) {
+            let audio_offset_ms = ((starts_at + word.start - utterance_starts_at).max(0.0) * 1000.0) as u64;
+            if let Some(sentence) = word.sentence {
+                let event = AudioEvent::SentenceBoundary(SentenceBoundaryEvent {
+                    chunk,
+                    sentence,
+                    char_offset: word.char_offset,
+                    audio_offset_ms,
+                });
+                if event_sender.send(event).is_err() {
+                    return;
+                }
+            }
+            let event = AudioEvent::WordBoundary(WordBoundaryEvent {
+                chunk,
+                index: word.index,
+                word: word.word,
+                char_offset: word.char_offset,
+                audio_offset_ms,
+                duration_ms: (word.duration * 1000.0) as u64,
+            });
+            if event_sender.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Queue `text` for synthesis; the playback task plays it and returns to Listening afterwards.
+async fn synthesize(
+    tts: &Mutex<TextToSpeech>,
+    mode: &watch::Sender<ProcessingMode>,
+    events: &broadcast::Sender<AudioEvent>,
+    text: &str,
+    options: SpeechOptions,
+) -> Result<()> {
+    let config = try_get_config().context("Configuration not initialized")?;
+    switch_mode(mode, events, ProcessingMode::Speaking);
+    
+    let request = SynthesisRequest {
+        text: text.to_string(),
+        voice: Some(options.voice.unwrap_or_else(|| config.tts.voice.clone())),
+        speed: Some(config.tts.speed * options.rate.unwrap_or(1.0)),
+        pitch: Some(config.tts.pitch),
+        volume: Some(config.tts.volume),
+        generate_visemes: config.tts.generate_visemes,
+        ssml: ssml::is_ssml(text),
+    };
+    
+    // A copy, so a stop or a reload isn't held up behind a slow engine
+    let tts = tts.lock().unwrap().clone();
+    if let Err(e) = tts.synthesize(request).await {
+        switch_mode(mode, events, ProcessingMode::Listening);
+        return Err(e);
+    }
+    
+    // The playback task switches back to Listening when the audio has finished
+    log::info!("Speech synthesis requested for: '{}'", text);
+    Ok(())
+}
+
+/// Update the mode and announce it, skipping no-op transitions.
+fn switch_mode(mode: &watch::Sender<ProcessingMode>, events: &broadcast::Sender<AudioEvent>, next: ProcessingMode) {
+    let changed = mode.send_if_modified(|current| {
+        if *current == next {
+            return false;
+        }
+        *current = next;
+        true
+    });
+    if changed {
+        log::debug!("Switched to {:?} mode", next);
+        let _ = events.send(AudioEvent::ModeChanged(next));
+    }
+}
+
 impl AudioProcessor {
+    /// A processor on the system's audio devices. Models are loaded by `initialize`, so their
+    /// progress can be watched.
     pub async fn new() -> Result<Self> {
-        let audio_manager = Arc::new(Mutex::new(AudioManager::new()?));
+        Self::with_audio_manager(AudioManager::new()?)
+    }
+    
+    /// Build a processor around an existing audio manager, e.g. one on the mock backend in tests.
+    pub fn with_audio_manager(audio_manager: AudioManager) -> Result<Self> {
+        let audio_manager = Arc::new(Mutex::new(audio_manager));
         let stt = Arc::new(Mutex::new(SpeechToText::new()?));
-        let tts = Arc::new(Mutex::new(TextToSpeech::new()?));
+        let speech_generation = Arc::new(AtomicU64::new(0));
+        let tts = Arc::new(Mutex::new(TextToSpeech::new(speech_generation.clone())?));
         let (event_sender, _) = broadcast::channel(1000);
+        let (mode, _) = watch::channel(ProcessingMode::Idle);
         
-        let mut processor = AudioProcessor {
+        Ok(AudioProcessor {
             audio_manager,
             stt,
             tts,
             event_sender,
             is_running: Arc::new(Mutex::new(false)),
-            processing_mode: ProcessingMode::Idle,
-        };
-        
-        processor.initialize().await?;
-        Ok(processor)
-    }
-    
-    pub async fn start_processing(&mut self) -> Result<()> {
-        self.start().await
-    }
-    
-    pub async fn stop_processing(&mut self) -> Result<()> {
-        self.stop().await
+            mode: Arc::new(mode),
+            stream_queue: Arc::new(tokio::sync::Mutex::new(())),
+            speech_generation,
+            spoken: Arc::new(Mutex::new(None)),
+            push_to_talk: watch::channel(false).0,
+        })
     }
     
+    /// Open the audio session and load the speech models, reporting each model as ModelLoad
+    /// events so a loading screen can follow along.
     pub async fn initialize(&mut self) -> Result<()> {
         // Initialize audio manager
         {
@@ -65,41 +323,53 @@ impl AudioProcessor {
             audio_manager.initialize()?;
         }
         
-        // Initialize STT
-        {
-            let mut stt = self.stt.lock().unwrap();
-            stt.initialize()?;
+        // Load the Whisper model
+        self.report_model_load("speech_recognition", ModelLoadStatus::Loading, None);
+        let loaded = self.stt.lock().unwrap().initialize();
+        if let Err(e) = loaded {
+            self.report_model_load("speech_recognition", ModelLoadStatus::Failed, Some(format!("{:#}", e)));
+            return Err(e);
         }
+        self.report_model_load("speech_recognition", ModelLoadStatus::Ready, None);
         
-        // Initialize TTS
-        {
-            let mut tts = self.tts.lock().unwrap();
-            tts.initialize()?;
+        // Initialize TTS, and speak once in silence so the first reply doesn't wait for the voice
+        self.report_model_load("voice", ModelLoadStatus::Loading, None);
+        self.tts.lock().unwrap().initialize()?;
+        let provider = self.tts.lock().unwrap().provider();
+        match provider.warm_up().await {
+            Ok(()) => self.report_model_load("voice", ModelLoadStatus::Ready, None),
+            // Speaking reports the problem again, so a broken voice doesn't stop listening
+            Err(e) => {
+                log::warn!("Failed to warm up the TTS voice: {:#}", e);
+                self.report_model_load("voice", ModelLoadStatus::Failed, Some(format!("{:#}", e)));
+            }
         }
         
         log::info!("Audio processor initialized successfully");
         Ok(())
     }
     
+    fn report_model_load(&self, stage: &'static str, status: ModelLoadStatus, error: Option<String>) {
+        let _ = self.event_sender.send(AudioEvent::ModelLoad(ModelLoadProgress { stage, status, error }));
+    }
+    
     pub async fn start(&mut self) -> Result<()> {
         *self.is_running.lock().unwrap() = true;
-        self.processing_mode = ProcessingMode::Listening;
+        self.set_processing_mode(ProcessingMode::Listening);
         
-        // Start audio recording
-        {
+        // Start audio recording into a queue only the new STT task reads
+        let audio_receiver = {
             let mut audio_manager = self.audio_manager.lock().unwrap();
+            let audio_receiver = audio_manager.take_audio_receiver();
             audio_manager.start_recording()?;
-        }
-        
-        // Start STT processing
-        let audio_receiver = {
-            let audio_manager = self.audio_manager.lock().unwrap();
-            audio_manager.get_audio_receiver()
+            audio_receiver
         };
         
+        // Start STT processing
         {
+            let barge_in = try_get_config().context("Configuration not initialized")?.audio.barge_in;
             let mut stt = self.stt.lock().unwrap();
-            stt.start_processing(audio_receiver).await?;
+            stt.start_processing(audio_receiver, self.mode.subscribe(), self.push_to_talk.subscribe(), barge_in)?;
         }
         
         // Start event processing loop
@@ -113,7 +383,6 @@ impl AudioProcessor {
         let event_sender = self.event_sender.clone();
         let is_running = self.is_running.clone();
         let audio_manager = self.audio_manager.clone();
-        let tts = self.tts.clone();
         
         // STT event processing
         let stt_receiver = {
@@ -125,14 +394,24 @@ impl AudioProcessor {
         let stt_is_running = is_running.clone();
         tokio::spawn(async move {
             let mut receiver = stt_receiver;
+            let mut language: Option<String> = None;
             while *stt_is_running.lock().unwrap() {
                 match receiver.recv().await {
-                    Ok(transcription) => {
-                        if !transcription.text.trim().is_empty() {
-                            let event = AudioEvent::SpeechDetected(transcription.text);
-                            if let Err(e) = stt_event_sender.send(event) {
-                                log::error!("Failed to send STT event: {}", e);
+                    Ok(stt_event) => {
+                        let event = match stt_event {
+                            SttEvent::UtteranceEnded => AudioEvent::SpeechEnded,
+                            SttEvent::Transcription(transcription) if !transcription.text.trim().is_empty() => {
+                                if language.as_ref() != Some(&transcription.language) {
+                                    language = Some(transcription.language.clone());
+                                    let _ = stt_event_sender.send(AudioEvent::LanguageDetected(transcription.language));
+                                }
+                                AudioEvent::SpeechDetected(transcription.text)
                             }
+                            SttEvent::Transcription(_) => continue,
+                            SttEvent::Failed(message) => AudioEvent::Error(message),
+                        };
+                        if let Err(e) = stt_event_sender.send(event) {
+                            log::error!("Failed to send STT event: {}", e);
                         }
                     }
                     Err(e) => {
@@ -143,6 +422,88 @@ impl AudioProcessor {
             }
         });
         
+        // Device watchdog: an unplugged device leaves its stream dead, so fall back to the default
+        let watchdog_sender = event_sender.clone();
+        let watchdog_is_running = is_running.clone();
+        let watchdog_audio_manager = audio_manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(DEVICE_WATCHDOG_MS));
+            // With no device to fall back to, recovery fails on every tick; report it once
+            let mut last_error = None;
+            while *watchdog_is_running.lock().unwrap() {
+                interval.tick().await;
+                let checked = watchdog_audio_manager.lock().unwrap().check_devices();
+                match checked {
+                    Ok(changes) => {
+                        last_error = None;
+                        for change in changes {
+                            let _ = watchdog_sender.send(AudioEvent::DeviceChanged(change));
+                        }
+                    }
+                    Err(e) => {
+                        let message = format!("Audio device lost: {:#}", e);
+                        if last_error.as_ref() != Some(&message) {
+                            log::error!("{}", message);
+                            let _ = watchdog_sender.send(AudioEvent::Error(message.clone()));
+                            last_error = Some(message);
+                        }
+                    }
+                }
+            }
+        });
+        
+        // Microphone level, so the UI can show a meter and users can check their mic works
+        let meter_sender = event_sender.clone();
+        let meter_is_running = is_running.clone();
+        let meter_audio_manager = audio_manager.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(MIC_LEVEL_INTERVAL_MS));
+            let mut was_capturing = false;
+            while *meter_is_running.lock().unwrap() {
+                interval.tick().await;
+                let level = meter_audio_manager.lock().unwrap().take_input_level();
+                // Drop the meter to zero once when capture stops, then stay quiet until it resumes
+                if level.is_none() && !was_capturing {
+                    continue;
+                }
+                was_capturing = level.is_some();
+                let _ = meter_sender.send(AudioEvent::MicLevel(level.unwrap_or_default()));
+            }
+        });
+        
+        // Visualizer frames for mic input and TTS output
+        let visualizer = try_get_config().context("Configuration not initialized")?.audio.visualizer.clone();
+        if visualizer.enabled {
+            let viz_sender = event_sender.clone();
+            let viz_is_running = is_running.clone();
+            let viz_audio_manager = audio_manager.clone();
+            tokio::spawn(async move {
+                let analyzer = SpectrumAnalyzer::new(visualizer.bands, visualizer.waveform_points);
+                let mut interval = tokio::time::interval(tokio::time::Duration::from_secs_f32(1.0 / visualizer.fps.max(1) as f32));
+                let mut was_silent = [false; 2];
+                while *viz_is_running.lock().unwrap() {
+                    interval.tick().await;
+                    let taps = {
+                        let audio_manager = viz_audio_manager.lock().unwrap();
+                        [
+                            (SpectrumSource::Input, audio_manager.input_samples(), audio_manager.input_level()),
+                            (SpectrumSource::Output, audio_manager.output_samples(), audio_manager.output_level()),
+                        ]
+                    };
+                    for (i, (source, (samples, sample_rate), level)) in taps.into_iter().enumerate() {
+                        // Send one empty frame when a source goes quiet, then nothing until it wakes up
+                        let silent = level <= f32::EPSILON;
+                        if silent && was_silent[i] {
+                            continue;
+                        }
+                        was_silent[i] = silent;
+                        let frame = analyzer.analyze(source, &samples, sample_rate, level);
+                        let _ = viz_sender.send(AudioEvent::Spectrum(frame));
+                    }
+                }
+            });
+        }
+        
         // TTS event processing
         let tts_receiver = {
             let tts = self.tts.lock().unwrap();
@@ -152,19 +513,74 @@ impl AudioProcessor {
         let tts_event_sender = event_sender.clone();
         let tts_is_running = is_running.clone();
         let tts_audio_manager = audio_manager.clone();
+        let tts_mode = self.mode.clone();
+        let tts_speech_generation = self.speech_generation.clone();
+        let tts_spoken = self.spoken.clone();
         tokio::spawn(async move {
             let mut receiver = tts_receiver;
+            let mut chunk: u64 = 0;
+            // Playback generation and clock time of the first chunk of the text being spoken
+            let mut utterance: Option<(u64, f64)> = None;
             while *tts_is_running.lock().unwrap() {
                 match receiver.recv().await {
                     Ok(synthesis_result) => {
+                        // Synthesized before a stop but only arriving now; it was cancelled
+                        if synthesis_result.generation != tts_speech_generation.load(Ordering::SeqCst) {
+                            continue;
+                        }
+                        chunk += 1;
                         // Play the generated audio
-                        {
+                        let started = {
                             let mut audio_manager = tts_audio_manager.lock().unwrap();
-                            if let Err(e) = audio_manager.play_audio(
+                            let generation = audio_manager.playback_generation();
+                            match audio_manager.play_audio(
                                 synthesis_result.audio_data.clone(),
                                 synthesis_result.sample_rate,
                             ) {
-                                log::error!("Failed to play audio: {}", e);
+                                Ok(starts_at) => Some((starts_at, generation)),
+                                Err(e) => {
+                                    log::error!("Failed to play audio: {}", e);
+                                    let _ = tts_event_sender.send(AudioEvent::Error(format!("Failed to play audio: {:#}", e)));
+                                    None
+                                }
+                            }
+                        };
+                        
+                        if let Some((starts_at, generation)) = started {
+                            // A stop cuts the utterance short, so what plays next starts a new one
+                            let utterance_starts_at = match utterance {
+                                Some((utterance_generation, at)) if utterance_generation == generation => at,
+                                _ => starts_at,
+                            };
+                            utterance = synthesis_result.continues.then_some((generation, utterance_starts_at));
+                            {
+                                let mut spoken = tts_spoken.lock().unwrap();
+                                let same_utterance = spoken
+                                    .as_ref()
+                                    .is_some_and(|progress| progress.generation == generation && progress.started_at == utterance_starts_at);
+                                if !same_utterance {
+                                    *spoken = Some(SpokenProgress {
+                                        generation,
+                                        started_at: utterance_starts_at,
+                                        words: Vec::new(),
+                                    });
+                                }
+                                if let Some(progress) = spoken.as_mut() {
+                                    progress
+                                        .words
+                                        .extend(synthesis_result.words.iter().map(|word| (starts_at + word.start, word.char_offset)));
+                                }
+                            }
+                            if !synthesis_result.words.is_empty() {
+                                tokio::spawn(sync_words(
+                                    tts_audio_manager.clone(),
+                                    tts_event_sender.clone(),
+                                    chunk,
+                                    synthesis_result.words.clone(),
+                                    starts_at,
+                                    utterance_starts_at,
+                                    generation,
+                                ));
                             }
                         }
                         
@@ -176,18 +592,28 @@ impl AudioProcessor {
                         
                         // Send viseme events
                         for viseme in synthesis_result.visemes {
-                            let viseme_event = AudioEvent::VisemeGenerated(viseme.clone());
+                            let viseme_event = AudioEvent::VisemeGenerated(viseme);
                             if let Err(e) = tts_event_sender.send(viseme_event) {
                                 log::error!("Failed to send viseme event: {}", e);
                             }
-                            
-                            // Also send to audio manager for character animation
-                            {
-                                let audio_manager = tts_audio_manager.lock().unwrap();
-                                if let Err(e) = audio_manager.send_viseme(viseme) {
-                                    log::error!("Failed to send viseme to audio manager: {}", e);
-                                }
+                        }
+
+                        // The rest of this utterance is already on its way; keep the speakers going
+                        if synthesis_result.continues {
+                            continue;
+                        }
+                        
+                        // Hand the microphone back once the speakers have gone quiet
+                        loop {
+                            let playing = tts_audio_manager.lock().unwrap().is_playing();
+                            if !playing {
+                                break;
                             }
+                            tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+                        }
+                        tts_spoken.lock().unwrap().take();
+                        if *tts_mode.borrow() == ProcessingMode::Speaking {
+                            switch_mode(&tts_mode, &tts_event_sender, ProcessingMode::Listening);
                         }
                     }
                     Err(e) => {
@@ -201,67 +627,225 @@ impl AudioProcessor {
         Ok(())
     }
     
-    pub async fn synthesize_speech(&mut self, text: String) -> Result<()> {
-        self.synthesize_speech_internal(&text).await
+    pub async fn synthesize_speech(&mut self, text: String, options: SpeechOptions) -> Result<()> {
+        self.synthesize_speech_internal(&text, options).await
     }
     
-    async fn synthesize_speech_internal(&mut self, text: &str) -> Result<()> {
-        self.processing_mode = ProcessingMode::Speaking;
-        
-        let config = get_config();
+    /// Synthesize `text` in the configured voice without playing it, visemes included.
+    pub async fn render_speech(&self, text: String) -> Result<SynthesisResult> {
+        let config = try_get_config().context("Configuration not initialized")?;
         let request = SynthesisRequest {
-            text: text.to_string(),
+            ssml: ssml::is_ssml(&text),
+            text,
             voice: Some(config.tts.voice.clone()),
             speed: Some(config.tts.speed),
             pitch: Some(config.tts.pitch),
             volume: Some(config.tts.volume),
-            generate_visemes: config.tts.generate_visemes,
+            generate_visemes: true,
         };
+        let tts = self.tts.lock().unwrap().clone();
+        tts.render(&request).await
+    }
+    
+    /// Re-create the TTS provider and voice from the current configuration.
+    pub async fn reload_tts(&mut self) -> Result<()> {
+        // Sentences already being synthesized finish in the old voice
+        self.tts.lock().unwrap().initialize()
+    }
+
+    /// Speak `text` in `voice` instead of the configured one, e.g. to preview a voice.
+    pub async fn preview_voice(&mut self, text: String, voice: String) -> Result<()> {
+        let options = SpeechOptions {
+            voice: Some(voice),
+            ..SpeechOptions::default()
+        };
+        self.synthesize_speech_internal(&text, options).await
+    }
+    
+    async fn synthesize_speech_internal(&mut self, text: &str, options: SpeechOptions) -> Result<()> {
+        synthesize(&self.tts, &self.mode, &self.event_sender, text, options).await
+    }
+    
+    /// Start speaking a reply whose text is still being generated. Sentences are synthesized
+    /// in order and queued for playback behind any stream started earlier.
+    pub fn speak_stream(&self) -> SpeakStream {
+        let (text, mut deltas) = mpsc::unbounded_channel();
+        let tts = self.tts.clone();
+        let mode = self.mode.clone();
+        let events = self.event_sender.clone();
+        let queue = self.stream_queue.clone();
+        let speech_generation = self.speech_generation.clone();
+        let generation = speech_generation.load(Ordering::SeqCst);
         
-        {
-            let mut tts = self.tts.lock().unwrap();
-            tts.synthesize(request).await?;
+        tokio::spawn(async move {
+            let _turn = queue.lock_owned().await;
+            let mut pending = String::new();
+            loop {
+                let finished = match deltas.recv().await {
+                    Some(StreamText::Delta(delta)) => {
+                        pending.push_str(&delta);
+                        false
+                    }
+                    Some(StreamText::End) => true,
+                    // Dropped without finishing: the reply was abandoned
+                    None => return,
+                };
+                if speech_generation.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                
+                let mut sentences = normalization::split_sentences(&pending);
+                // Until the text is finished the last piece may still be growing
+                pending = if finished { String::new() } else { sentences.pop().unwrap_or_default() };
+                for sentence in sentences {
+                    if speech_generation.load(Ordering::SeqCst) != generation {
+                        return;
+                    }
+                    if let Err(e) = synthesize(&tts, &mode, &events, &sentence, SpeechOptions::default()).await {
+                        log::error!("Failed to synthesize streamed sentence: {:#}", e);
+                        let _ = events.send(AudioEvent::Error(format!("Failed to synthesize speech: {:#}", e)));
+                    }
+                }
+                if finished {
+                    return;
+                }
+            }
+        });
+        SpeakStream { text }
+    }
+    
+    /// Resume capture after `stop_listening`, starting the pipeline on first use.
+    pub async fn start_listening(&mut self) -> Result<()> {
+        if !self.is_running() {
+            return self.start().await;
         }
-        
-        // Wait for synthesis to complete
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        
-        self.processing_mode = ProcessingMode::Listening;
-        
-        log::info!("Speech synthesis requested for: '{}'", text);
+        self.audio_manager.lock().unwrap().start_recording()?;
+        self.set_processing_mode(ProcessingMode::Listening);
         Ok(())
     }
     
-    pub fn set_processing_mode(&mut self, mode: ProcessingMode) {
-        self.processing_mode = mode;
-        
-        match mode {
-            ProcessingMode::Listening => {
-                // Enable STT, disable TTS output
-                log::debug!("Switched to listening mode");
-            }
-            ProcessingMode::Speaking => {
-                // Disable STT, enable TTS output
-                log::debug!("Switched to speaking mode");
+    /// Stop capturing without tearing down STT/TTS, so listening can resume quickly.
+    pub fn stop_listening(&mut self) -> Result<()> {
+        self.audio_manager.lock().unwrap().stop_recording()?;
+        if self.get_processing_mode() == ProcessingMode::Listening {
+            self.set_processing_mode(ProcessingMode::Idle);
+        }
+        Ok(())
+    }
+    
+    /// Move input or output to the device called `name` ("default" for the system default),
+    /// rebuilding its stream in place.
+    pub fn set_device(&mut self, kind: AudioDeviceKind, name: &str) -> Result<()> {
+        let mut audio = try_get_config().context("Configuration not initialized")?.audio.clone();
+        let mut audio_manager = self.audio_manager.lock().unwrap();
+        match kind {
+            AudioDeviceKind::Input => {
+                audio.input.device = name.to_string();
+                audio_manager.replace_input(backend::create_input(&audio)?)
             }
-            ProcessingMode::Idle => {
-                // Disable both
-                log::debug!("Switched to idle mode");
+            AudioDeviceKind::Output => {
+                audio.output.device = name.to_string();
+                audio_manager.replace_output(backend::create_output(&audio)?)
             }
         }
     }
     
+    pub fn set_noise_suppression(&self, enabled: bool) {
+        self.audio_manager.lock().unwrap().set_noise_suppression(enabled);
+    }
+    
+    /// Queue a decoded recording for transcription without waiting for it to finish.
+    pub fn transcribe_recording(&self, samples: &[f32], sample_rate: u32) -> Result<PendingTranscription> {
+        self.stt.lock().unwrap().transcribe_recording(samples, sample_rate)
+    }
+    
+    /// Push-to-talk key down: interrupt any reply and record everything until the key is released.
+    pub async fn start_push_to_talk(&mut self) -> Result<()> {
+        if self.get_processing_mode() == ProcessingMode::Speaking {
+            self.stop_speaking()?;
+        }
+        self.push_to_talk.send_replace(true);
+        if let Err(e) = self.start_listening().await {
+            self.push_to_talk.send_replace(false);
+            return Err(e);
+        }
+        Ok(())
+    }
+    
+    /// Push-to-talk key up: stop recording and transcribe what was captured right away.
+    pub fn stop_push_to_talk(&mut self) -> Result<()> {
+        // Recording stops first so STT knows no more audio follows once it sees the release
+        let stopped = self.stop_listening();
+        self.push_to_talk.send_replace(false);
+        stopped
+    }
+    
+    /// Cut off the current utterance and drop any queued audio, reporting where it stopped
+    /// as SpeechCancelled.
+    pub fn stop_speaking(&mut self) -> Result<()> {
+        self.speech_generation.fetch_add(1, Ordering::SeqCst);
+        self.tts.lock().unwrap().stop_synthesis();
+        let (clock, generation) = {
+            let mut audio_manager = self.audio_manager.lock().unwrap();
+            let position = (audio_manager.playback_clock(), audio_manager.playback_generation());
+            audio_manager.stop_playback()?;
+            position
+        };
+        
+        let progress = self.spoken.lock().unwrap().take().filter(|progress| progress.generation == generation);
+        if progress.is_some() || self.get_processing_mode() == ProcessingMode::Speaking {
+            let cancelled = match progress {
+                Some(progress) => SpeechCancelled {
+                    position_ms: ((clock - progress.started_at).max(0.0) * 1000.0) as u64,
+                    char_offset: progress.words.iter().rev().find(|(at, _)| *at <= clock).map(|(_, offset)| *offset),
+                },
+                // Stopped before any audio was played
+                None => SpeechCancelled {
+                    position_ms: 0,
+                    char_offset: None,
+                },
+            };
+            let _ = self.event_sender.send(AudioEvent::SpeechCancelled(cancelled));
+        }
+        
+        if self.get_processing_mode() == ProcessingMode::Speaking {
+            let next = if self.is_recording() { ProcessingMode::Listening } else { ProcessingMode::Idle };
+            self.set_processing_mode(next);
+        }
+        Ok(())
+    }
+    
+    pub fn set_processing_mode(&self, mode: ProcessingMode) {
+        switch_mode(&self.mode, &self.event_sender, mode);
+    }
+    
     pub fn get_processing_mode(&self) -> ProcessingMode {
-        self.processing_mode.clone()
+        *self.mode.borrow()
+    }
+    
+    pub fn status(&self) -> AudioStatus {
+        let running = self.is_running();
+        let audio_manager = self.audio_manager.lock().unwrap();
+        AudioStatus {
+            initialized: true,
+            running,
+            recording: audio_manager.is_recording(),
+            playing: audio_manager.is_playing(),
+            mode: self.get_processing_mode(),
+            input_device: Some(audio_manager.input_name()),
+            output_device: Some(audio_manager.output_name()),
+            input_level: audio_manager.input_level(),
+            output_level: audio_manager.output_level(),
+        }
     }
     
     pub fn get_event_receiver(&self) -> broadcast::Receiver<AudioEvent> {
         self.event_sender.subscribe()
     }
     
-    pub fn get_viseme_receiver(&self) -> broadcast::Receiver<VisemeData> {
-        let audio_manager = self.audio_manager.lock().unwrap();
-        audio_manager.get_viseme_receiver()
+    /// Watch mode transitions, e.g. to forward them to the frontend.
+    pub fn subscribe_mode(&self) -> watch::Receiver<ProcessingMode> {
+        self.mode.subscribe()
     }
     
     pub fn is_running(&self) -> bool {
@@ -273,14 +857,9 @@ impl AudioProcessor {
         audio_manager.is_recording()
     }
     
-    pub fn is_playing(&self) -> bool {
-        let audio_manager = self.audio_manager.lock().unwrap();
-        audio_manager.is_playing()
-    }
-    
     pub async fn stop(&mut self) -> Result<()> {
         *self.is_running.lock().unwrap() = false;
-        self.processing_mode = ProcessingMode::Idle;
+        self.set_processing_mode(ProcessingMode::Idle);
         
         // Stop audio recording
         {
@@ -296,7 +875,7 @@ impl AudioProcessor {
         
         // Stop TTS synthesis
         {
-            let mut tts = self.tts.lock().unwrap();
+            let tts = self.tts.lock().unwrap();
             tts.stop_synthesis();
         }
         
@@ -309,4 +888,86 @@ impl Drop for AudioProcessor {
     fn drop(&mut self) {
         let _ = futures::executor::block_on(self.stop());
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::backend::{FileInput, MockOutput};
+    use std::time::Duration;
+
+    // Long enough for a hung pipeline to show up as a failure rather than a stuck test
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// A running processor on the mock backend, speaking with the placeholder tone, and the
+    /// samples its output has played.
+    async fn mock_processor() -> (AudioProcessor, Arc<Mutex<Vec<f32>>>) {
+        crate::config::init_builtin_config();
+        let output = MockOutput::new();
+        let played = output.played();
+        let input = FileInput::from_samples(Vec::new(), 16000, false);
+        let processor = AudioProcessor::with_audio_manager(AudioManager::with_backends(Box::new(input), Box::new(output))).unwrap();
+        // Only playback is exercised, so the speech recognition model is never loaded
+        *processor.is_running.lock().unwrap() = true;
+        processor.start_event_processing().await.unwrap();
+        (processor, played)
+    }
+
+    #[tokio::test]
+    async fn synthesized_speech_is_played_then_listening_resumes() {
+        let (mut processor, played) = mock_processor().await;
+        let mut events = processor.get_event_receiver();
+
+        processor.synthesize_speech("Hello there".to_string(), SpeechOptions::default()).await.unwrap();
+        loop {
+            match tokio::time::timeout(TIMEOUT, events.recv()).await.expect("playback never finished").unwrap() {
+                AudioEvent::ModeChanged(ProcessingMode::Listening) => break,
+                _ => continue,
+            }
+        }
+
+        assert!(!played.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn streamed_sentences_are_played_in_order() {
+        let (processor, played) = mock_processor().await;
+        let mut events = processor.get_event_receiver();
+
+        let stream = processor.speak_stream();
+        stream.push("One. Two");
+        stream.push(" words.");
+        stream.finish();
+
+        let mut lengths = Vec::new();
+        while lengths.len() < 2 {
+            match tokio::time::timeout(TIMEOUT, events.recv()).await.expect("sentence never played").unwrap() {
+                AudioEvent::AudioGenerated(samples) => lengths.push(samples.len()),
+                _ => continue,
+            }
+        }
+        // The tone lasts as long as the text, so the second sentence plays for longer
+        assert!(lengths[0] < lengths[1]);
+        assert_eq!(played.lock().unwrap().len(), lengths.iter().sum::<usize>());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn stopping_while_synthesizing_does_not_block() {
+        let (processor, _played) = mock_processor().await;
+        let stream = processor.speak_stream();
+        stream.push("A sentence long enough to still be synthesizing when it is stopped. ".repeat(20));
+        stream.finish();
+        tokio::task::yield_now().await;
+
+        let stopped = tokio::task::spawn_blocking(move || {
+            let mut processor = processor;
+            processor.stop_speaking().map(|()| processor)
+        });
+        let processor = tokio::time::timeout(TIMEOUT, stopped)
+            .await
+            .expect("stop_speaking blocked behind synthesis")
+            .unwrap()
+            .unwrap();
+        assert_ne!(processor.get_processing_mode(), ProcessingMode::Speaking);
+    }
+}
@@ -0,0 +1,105 @@
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use serde::Serialize;
+use std::sync::Arc;
+
+const FFT_SIZE: usize = 1024;
+const MIN_FREQUENCY: f32 = 40.0;
+const MAX_FREQUENCY: f32 = 16000.0;
+// Magnitudes below this are drawn as empty bands
+const FLOOR_DB: f32 = -80.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpectrumSource {
+    Input,
+    Output,
+}
+
+/// One compact visualization frame: log-spaced band energies and a decimated waveform, both 0..1.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpectrumFrame {
+    pub source: SpectrumSource,
+    pub bands: Vec<f32>,
+    pub waveform: Vec<f32>,
+    pub level: f32,
+}
+
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    bands: usize,
+    waveform_points: usize,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(bands: usize, waveform_points: usize) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(FFT_SIZE);
+        // Hann window to keep leakage from smearing the bands
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (FFT_SIZE - 1) as f32).cos())
+            .collect();
+        Self {
+            fft,
+            window,
+            bands: bands.max(1),
+            waveform_points: waveform_points.max(1),
+        }
+    }
+
+    pub fn analyze(&self, source: SpectrumSource, samples: &[f32], sample_rate: u32, level: f32) -> SpectrumFrame {
+        SpectrumFrame {
+            source,
+            bands: self.bands(samples, sample_rate),
+            waveform: self.waveform(samples),
+            level,
+        }
+    }
+
+    fn bands(&self, samples: &[f32], sample_rate: u32) -> Vec<f32> {
+        if samples.is_empty() || sample_rate == 0 {
+            return vec![0.0; self.bands];
+        }
+
+        // Analyze the newest FFT_SIZE samples, zero-padding short buffers at the front
+        let tail = &samples[samples.len().saturating_sub(FFT_SIZE)..];
+        let offset = FFT_SIZE - tail.len();
+        let mut buffer = vec![Complex::new(0.0, 0.0); FFT_SIZE];
+        for (i, &sample) in tail.iter().enumerate() {
+            buffer[offset + i] = Complex::new(sample * self.window[offset + i], 0.0);
+        }
+        self.fft.process(&mut buffer);
+
+        let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+        let nyquist = sample_rate as f32 / 2.0;
+        let max_frequency = MAX_FREQUENCY.min(nyquist);
+        let ratio = (max_frequency / MIN_FREQUENCY).powf(1.0 / self.bands as f32);
+
+        (0..self.bands)
+            .map(|band| {
+                let low = MIN_FREQUENCY * ratio.powi(band as i32);
+                let high = low * ratio;
+                let first = ((low / bin_hz) as usize).max(1);
+                let last = ((high / bin_hz).ceil() as usize).clamp(first + 1, FFT_SIZE / 2);
+                let magnitude = buffer[first..last].iter().map(|c| c.norm()).fold(0.0f32, f32::max);
+                // Normalize so a full-scale sine in the band reads 0 dB
+                let db = 20.0 * (magnitude * 4.0 / FFT_SIZE as f32).max(1e-9).log10();
+                ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    /// Peak absolute amplitude per bucket, oldest first.
+    fn waveform(&self, samples: &[f32]) -> Vec<f32> {
+        if samples.is_empty() {
+            return vec![0.0; self.waveform_points];
+        }
+        let bucket = samples.len().div_ceil(self.waveform_points);
+        let mut waveform: Vec<f32> = samples
+            .chunks(bucket)
+            .map(|chunk| chunk.iter().fold(0.0f32, |peak, s| peak.max(s.abs())).min(1.0))
+            .collect();
+        waveform.resize(self.waveform_points, 0.0);
+        waveform
+    }
+}
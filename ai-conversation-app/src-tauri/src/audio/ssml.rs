@@ -0,0 +1,399 @@
+use anyhow::{Context, Result};
+
+// Longest pause a <break> may ask for, as most SSML engines cap it
+const MAX_BREAK_SECONDS: f32 = 10.0;
+// Pause a closing </p> leaves before the next paragraph
+const PARAGRAPH_PAUSE_SECONDS: f32 = 0.5;
+
+/// A stretch of SSML for an engine that can't read the markup itself.
+#[derive(Debug, Clone)]
+pub enum SsmlPart {
+    /// Plain text spoken with rate, pitch and volume relative to the request's own.
+    Speech { text: String, rate: f32, pitch: f32, volume: f32 },
+    /// Silence, in seconds.
+    Pause(f32),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Prosody {
+    rate: f32,
+    pitch: f32,
+    volume: f32,
+}
+
+impl Default for Prosody {
+    fn default() -> Self {
+        Self { rate: 1.0, pitch: 1.0, volume: 1.0 }
+    }
+}
+
+/// Whether `text` is an SSML document rather than plain text.
+pub fn is_ssml(text: &str) -> bool {
+    text.trim_start().starts_with("<speak")
+}
+
+/// The words an SSML document speaks, with the markup and breaks left out.
+pub fn plain_text(ssml: &str) -> Result<String> {
+    Ok(parse(ssml)?
+        .into_iter()
+        .filter_map(|part| match part {
+            SsmlPart::Speech { text, .. } => Some(text),
+            SsmlPart::Pause(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// Split an SSML document into runs of text that share prosody, and the pauses between them.
+/// Supports break, prosody, emphasis and say-as; other elements are read as their content.
+pub fn parse(ssml: &str) -> Result<Vec<SsmlPart>> {
+    let mut parts = Vec::new();
+    // Prosody in effect, innermost last, with the element that set it
+    let mut styles: Vec<(String, Prosody)> = Vec::new();
+    let mut text = String::new();
+    // say-as content is gathered from this offset in `text` until the element closes
+    let mut say_as: Option<(String, usize)> = None;
+
+    let mut rest = ssml;
+    while let Some(open) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..open]));
+        let close = rest[open..]
+            .find('>')
+            .map(|close| open + close)
+            .context("Malformed SSML: unterminated tag")?;
+        let tag = rest[open + 1..close].trim();
+        rest = &rest[close + 1..];
+
+        // Comments, processing instructions and declarations aren't spoken
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+        let current = styles.last().map(|(_, prosody)| *prosody).unwrap_or_default();
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match name {
+                "prosody" | "emphasis" if styles.last().is_some_and(|(open, _)| open == name) => {
+                    flush(&mut parts, &mut text, current);
+                    styles.pop();
+                }
+                "say-as" => {
+                    if let Some((interpret_as, start)) = say_as.take() {
+                        let content = text.split_off(start.min(text.len()));
+                        text.push_str(&interpret(&interpret_as, &content));
+                    }
+                }
+                "p" => {
+                    flush(&mut parts, &mut text, current);
+                    parts.push(SsmlPart::Pause(PARAGRAPH_PAUSE_SECONDS));
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        match name {
+            "break" => {
+                flush(&mut parts, &mut text, current);
+                let seconds = attribute(attributes, "time")
+                    .and_then(|time| parse_time(&time))
+                    .or_else(|| attribute(attributes, "strength").and_then(|strength| break_strength(&strength)))
+                    .unwrap_or(0.4);
+                if seconds > 0.0 {
+                    parts.push(SsmlPart::Pause(seconds.min(MAX_BREAK_SECONDS)));
+                }
+            }
+            "prosody" if !self_closing => {
+                flush(&mut parts, &mut text, current);
+                let mut prosody = current;
+                if let Some(rate) = attribute(attributes, "rate").and_then(|rate| parse_rate(&rate)) {
+                    prosody.rate *= rate;
+                }
+                if let Some(pitch) = attribute(attributes, "pitch").and_then(|pitch| parse_pitch(&pitch)) {
+                    prosody.pitch *= pitch;
+                }
+                if let Some(volume) = attribute(attributes, "volume").and_then(|volume| parse_volume(&volume)) {
+                    prosody.volume *= volume;
+                }
+                styles.push((name.to_string(), prosody));
+            }
+            "emphasis" if !self_closing => {
+                flush(&mut parts, &mut text, current);
+                // Engines without emphasis get it as slower, louder speech
+                let (rate, volume) = match attribute(attributes, "level").as_deref() {
+                    Some("strong") => (0.85, 1.3),
+                    Some("reduced") => (1.1, 0.8),
+                    Some("none") => (1.0, 1.0),
+                    _ => (0.92, 1.15),
+                };
+                styles.push((
+                    name.to_string(),
+                    Prosody {
+                        rate: current.rate * rate,
+                        pitch: current.pitch,
+                        volume: current.volume * volume,
+                    },
+                ));
+            }
+            "say-as" if !self_closing => {
+                let interpret_as = attribute(attributes, "interpret-as").unwrap_or_default();
+                say_as = Some((interpret_as, text.len()));
+            }
+            _ => {}
+        }
+    }
+    text.push_str(&decode_entities(rest));
+    let current = styles.last().map(|(_, prosody)| *prosody).unwrap_or_default();
+    flush(&mut parts, &mut text, current);
+
+    // A pause at the very end would only delay the next utterance
+    while matches!(parts.last(), Some(SsmlPart::Pause(_))) {
+        parts.pop();
+    }
+    Ok(parts)
+}
+
+/// End the current run of text, if it says anything.
+fn flush(parts: &mut Vec<SsmlPart>, text: &mut String, prosody: Prosody) {
+    let spoken = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    text.clear();
+    if spoken.is_empty() {
+        return;
+    }
+    // Runs that sound the same are spoken as one, so the engine keeps its natural phrasing
+    if let Some(SsmlPart::Speech { text, rate, pitch, volume }) = parts.last_mut() {
+        if *rate == prosody.rate && *pitch == prosody.pitch && *volume == prosody.volume {
+            text.push(' ');
+            text.push_str(&spoken);
+            return;
+        }
+    }
+    parts.push(SsmlPart::Speech {
+        text: spoken,
+        rate: prosody.rate,
+        pitch: prosody.pitch,
+        volume: prosody.volume,
+    });
+}
+
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].trim();
+        let value = rest[equals + 1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)? + 1;
+        if key == name {
+            return Some(decode_entities(&value[1..end]));
+        }
+        rest = &value[end + 1..];
+    }
+    None
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// How say-as content is read, as text a plain engine will pronounce that way.
+fn interpret(interpret_as: &str, content: &str) -> String {
+    match interpret_as {
+        // One letter or digit at a time, e.g. "NASA" as N A S A
+        "characters" | "spell-out" | "verbatim" => content
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .join(" "),
+        "digits" | "telephone" => content
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .join(" "),
+        // Numbers, dates and times are already read sensibly by text normalization
+        _ => content.to_string(),
+    }
+}
+
+/// "500ms" or "1.5s" in seconds.
+fn parse_time(time: &str) -> Option<f32> {
+    let time = time.trim();
+    if let Some(ms) = time.strip_suffix("ms") {
+        return ms.trim().parse::<f32>().ok().map(|ms| ms / 1000.0);
+    }
+    time.strip_suffix('s')?.trim().parse().ok()
+}
+
+fn break_strength(strength: &str) -> Option<f32> {
+    match strength {
+        "none" => Some(0.0),
+        "x-weak" => Some(0.1),
+        "weak" => Some(0.25),
+        "medium" => Some(0.4),
+        "strong" => Some(0.75),
+        "x-strong" => Some(1.2),
+        _ => None,
+    }
+}
+
+/// "+10%" and "-10%" are relative; a bare "80%" or number is a multiple of normal.
+fn parse_relative(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let (number, percent) = match value.strip_suffix('%') {
+        Some(number) => (number, true),
+        None => (value, false),
+    };
+    let parsed: f32 = number.parse().ok()?;
+    let multiplier = match (value.starts_with(&['+', '-'][..]), percent) {
+        (true, true) => 1.0 + parsed / 100.0,
+        (false, true) => parsed / 100.0,
+        (true, false) => 1.0 + parsed,
+        (false, false) => parsed,
+    };
+    (multiplier >= 0.0).then_some(multiplier)
+}
+
+fn parse_rate(rate: &str) -> Option<f32> {
+    match rate {
+        "x-slow" => Some(0.5),
+        "slow" => Some(0.75),
+        "medium" | "default" => Some(1.0),
+        "fast" => Some(1.25),
+        "x-fast" => Some(1.75),
+        other => parse_relative(other).filter(|rate| *rate > 0.0),
+    }
+}
+
+fn parse_pitch(pitch: &str) -> Option<f32> {
+    match pitch {
+        "x-low" => Some(0.7),
+        "low" => Some(0.85),
+        "medium" | "default" => Some(1.0),
+        "high" => Some(1.15),
+        "x-high" => Some(1.3),
+        // Semitones, e.g. "+2st"
+        other if other.ends_with("st") => other
+            .trim_end_matches("st")
+            .parse::<f32>()
+            .ok()
+            .map(|semitones| 2f32.powf(semitones / 12.0)),
+        // Absolute pitches in Hz depend on the voice, so they're left to it
+        other if other.ends_with("Hz") => None,
+        other => parse_relative(other),
+    }
+}
+
+fn parse_volume(volume: &str) -> Option<f32> {
+    match volume {
+        "silent" => Some(0.0),
+        "x-soft" => Some(0.25),
+        "soft" => Some(0.5),
+        "medium" | "default" => Some(1.0),
+        "loud" => Some(1.3),
+        "x-loud" => Some(1.6),
+        // Decibels, e.g. "+6dB"
+        other if other.ends_with("dB") => other
+            .trim_end_matches("dB")
+            .parse::<f32>()
+            .ok()
+            .map(|db| 10f32.powf(db / 20.0)),
+        other => parse_relative(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speech(part: &SsmlPart) -> (&str, f32, f32, f32) {
+        match part {
+            SsmlPart::Speech { text, rate, pitch, volume } => (text.as_str(), *rate, *pitch, *volume),
+            SsmlPart::Pause(seconds) => panic!("expected speech, got a {}s pause", seconds),
+        }
+    }
+
+    fn pause(part: &SsmlPart) -> f32 {
+        match part {
+            SsmlPart::Pause(seconds) => *seconds,
+            SsmlPart::Speech { text, .. } => panic!("expected a pause, got {:?}", text),
+        }
+    }
+
+    #[test]
+    fn recognizes_ssml_documents() {
+        assert!(is_ssml("  <speak>Hello</speak>"));
+        assert!(!is_ssml("Hello <b>there</b>"));
+    }
+
+    #[test]
+    fn plain_text_drops_markup_and_decodes_entities() {
+        let ssml = r#"<speak>Hello <break time="500ms"/> <emphasis>world</emphasis> &amp; more<!-- aside --></speak>"#;
+        assert_eq!(plain_text(ssml).unwrap(), "Hello world & more");
+    }
+
+    #[test]
+    fn prosody_and_breaks_become_parts() {
+        let ssml = r#"<speak><prosody rate="slow" pitch="+2st">Slowly</prosody><break strength="strong"/>Now<break time="1.5s"/></speak>"#;
+        let parts = parse(ssml).unwrap();
+        assert_eq!(parts.len(), 3, "the trailing break is dropped");
+
+        let (text, rate, pitch, volume) = speech(&parts[0]);
+        assert_eq!(text, "Slowly");
+        assert_eq!(rate, 0.75);
+        assert!((pitch - 2f32.powf(2.0 / 12.0)).abs() < 1e-6);
+        assert_eq!(volume, 1.0);
+        assert_eq!(pause(&parts[1]), 0.75);
+        assert_eq!(speech(&parts[2]), ("Now", 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn nested_prosody_multiplies() {
+        let parts = parse(r#"<speak><prosody rate="50%" volume="+6dB"><prosody rate="x-fast">Hi</prosody></prosody></speak>"#).unwrap();
+        let (_, rate, _, volume) = speech(&parts[0]);
+        assert_eq!(rate, 0.875);
+        assert!((volume - 1.995).abs() < 0.01);
+    }
+
+    #[test]
+    fn runs_that_sound_the_same_are_merged() {
+        let parts = parse("<speak><p>One <say-as interpret-as=\"cardinal\">2</say-as> three</p>Four</speak>").unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(speech(&parts[0]).0, "One 2 three");
+        assert_eq!(pause(&parts[1]), PARAGRAPH_PAUSE_SECONDS);
+        assert_eq!(speech(&parts[2]).0, "Four");
+    }
+
+    #[test]
+    fn say_as_spells_out_characters_and_digits() {
+        let ssml = r#"<speak>Call <say-as interpret-as="telephone">555-1234</say-as>, or spell <say-as interpret-as="characters">NASA</say-as></speak>"#;
+        assert_eq!(plain_text(ssml).unwrap(), "Call 5 5 5 1 2 3 4, or spell N A S A");
+    }
+
+    #[test]
+    fn long_breaks_are_capped() {
+        let parts = parse(r#"<speak>Wait<break time="30s"/>done</speak>"#).unwrap();
+        assert_eq!(pause(&parts[1]), MAX_BREAK_SECONDS);
+    }
+
+    #[test]
+    fn unterminated_tags_are_an_error() {
+        assert!(parse("<speak>Hi <break").is_err());
+    }
+
+    #[test]
+    fn relative_values() {
+        assert_eq!(parse_relative("+10%"), Some(1.1));
+        assert_eq!(parse_relative("80%"), Some(0.8));
+        assert_eq!(parse_relative("-200%"), None);
+        assert_eq!(parse_rate("0"), None);
+        assert_eq!(parse_pitch("200Hz"), None);
+    }
+}
@@ -1,94 +1,376 @@
-use crate::config::get_config;
-use crate::audio::{AudioFrame, AudioManager};
+use crate::config::{try_get_config, SttConfig};
+use crate::audio::AudioFrame;
+use crate::audio::processor::ProcessingMode;
+use crate::audio::vad;
+use crate::meeting::resample_linear;
 use anyhow::{Context, Result};
-use std::sync::{Arc, Mutex};
-use std::sync::mpsc::Receiver;
-use tokio::sync::broadcast;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState};
+
+const WHISPER_SAMPLE_RATE: u32 = 16000;
+// Whisper only reads the last 224 tokens of its prompt; a long vocabulary is cut to roughly that
+const MAX_VOCABULARY_PROMPT_CHARS: usize = 800;
+// Matches whisper.cpp's own default for inference
+const LANG_DETECT_THREADS: usize = 4;
 
 #[derive(Debug, Clone)]
 pub struct TranscriptionResult {
+    pub text: String,
+    pub language: String,
+}
+
+/// What the processing loop reports as the user talks.
+#[derive(Debug, Clone)]
+pub enum SttEvent {
+    // The user stopped talking; the transcription follows once Whisper is done with it
+    UtteranceEnded,
+    Transcription(TranscriptionResult),
+    Failed(String),
+}
+
+/// What Whisper made of an utterance or a whole recording.
+#[derive(Debug, Clone, Serialize)]
+pub struct Transcription {
     pub text: String,
     pub confidence: f32,
     pub language: String,
-    pub timestamp: u64,
-    pub is_final: bool,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+/// A stretch of a transcription, timed in milliseconds from the start of the audio.
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// A transcription queued on the Whisper thread; resolves once it's done.
+pub type PendingTranscription = oneshot::Receiver<Result<Transcription>>;
+
+/// Whisper's initial prompt for `stt.custom_vocabulary`. Whisper continues the prompt's
+/// style and spellings, so listing the words makes it favour them over similar-sounding ones.
+fn vocabulary_prompt(words: &[String]) -> Option<String> {
+    let mut prompt = String::new();
+    for word in words.iter().map(|word| word.trim()).filter(|word| !word.is_empty()) {
+        if prompt.len() + word.len() + 2 > MAX_VOCABULARY_PROMPT_CHARS {
+            log::warn!("stt.custom_vocabulary is too long for Whisper's prompt; later words are ignored");
+            break;
+        }
+        if !prompt.is_empty() {
+            prompt.push_str(", ");
+        }
+        prompt.push_str(word);
+    }
+    (!prompt.is_empty()).then(|| format!("{}.", prompt))
+}
+
+/// Whether `stt.language` can be set to `code`: a Whisper language code, or "auto".
+pub fn is_supported_language(code: &str) -> bool {
+    code == "auto" || whisper_rs::get_lang_id(code).is_some()
+}
+
+/// A loaded model and the inference state it runs in. The state holds Whisper's working
+/// buffers, which are sizable, so it's made once and reused for every utterance.
+struct Whisper {
+    ctx: WhisperContext,
+    state: WhisperState,
+}
+
+impl Whisper {
+    fn load(model_path: &std::path::Path) -> Result<Self> {
+        let ctx = WhisperContext::new_with_params(&model_path.to_string_lossy(), WhisperContextParameters::default())
+            .context("Failed to initialize Whisper context")?;
+        let state = ctx.create_state().context("Failed to create Whisper state")?;
+        Ok(Self { ctx, state })
+    }
+
+    /// Which of `candidates` the utterance is most likely spoken in, by Whisper's language ID.
+    fn detect_language(&mut self, samples: &[f32], candidates: &[String]) -> Result<String> {
+        self.state
+            .pcm_to_mel(samples, LANG_DETECT_THREADS)
+            .context("Failed to prepare audio for language detection")?;
+        let (_, probabilities) = self
+            .state
+            .lang_detect(0, LANG_DETECT_THREADS)
+            .context("Whisper language detection failed")?;
+        candidates
+            .iter()
+            .filter_map(|code| Some((code, *probabilities.get(whisper_rs::get_lang_id(code)? as usize)?)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(code, _)| code.clone())
+            .context("None of stt.detect_languages is a language Whisper knows")
+    }
+
+    /// With `translate`, the text is English whatever was spoken; `language` stays the spoken one.
+    fn transcribe(
+        &mut self,
+        samples: &[f32],
+        language: &str,
+        candidates: &[String],
+        translate: bool,
+        prompt: Option<&str>,
+    ) -> Result<Transcription> {
+        // Whisper alone picks from about a hundred languages and can mistake accented speech
+        // for a neighbouring one; choosing among the languages the user speaks avoids that
+        let detected;
+        let language = if language == "auto" && !candidates.is_empty() {
+            detected = self.detect_language(samples, candidates)?;
+            detected.as_str()
+        } else {
+            language
+        };
+        
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        // "auto" lets Whisper detect the spoken language
+        params.set_language(Some(language));
+        params.set_translate(translate);
+        if let Some(prompt) = prompt {
+            params.set_initial_prompt(prompt);
+        }
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+        
+        // Each run replaces the previous utterance's results in the state
+        let state = &mut self.state;
+        state.full(params, samples).context("Whisper inference failed")?;
+        
+        let mut text = String::new();
+        let mut segments = Vec::new();
+        let (mut probability_sum, mut token_count) = (0.0f32, 0usize);
+        for segment in 0..state.full_n_segments()? {
+            let segment_text = state.full_get_segment_text_lossy(segment)?;
+            text.push_str(&segment_text);
+            if !segment_text.trim().is_empty() {
+                // Whisper timestamps are in centiseconds
+                segments.push(TranscriptSegment {
+                    start_ms: state.full_get_segment_t0(segment)?.max(0) as u64 * 10,
+                    end_ms: state.full_get_segment_t1(segment)?.max(0) as u64 * 10,
+                    text: segment_text.trim().to_string(),
+                });
+            }
+            for token in 0..state.full_n_tokens(segment)? {
+                // Timestamps and other special tokens say nothing about recognition quality
+                if state.full_get_token_id(segment, token)? >= self.ctx.token_eot() {
+                    continue;
+                }
+                probability_sum += state.full_get_token_prob(segment, token)?;
+                token_count += 1;
+            }
+        }
+        
+        let language = if language == "auto" {
+            whisper_rs::get_lang_str(state.full_lang_id_from_state()?)
+                .unwrap_or(language)
+                .to_string()
+        } else {
+            language.to_string()
+        };
+        Ok(Transcription {
+            text: text.trim().to_string(),
+            confidence: if token_count == 0 { 0.0 } else { probability_sum / token_count as f32 },
+            language,
+            segments,
+        })
+    }
+}
+
+// One utterance for the transcription thread, 16kHz mono
+struct TranscriptionJob {
+    samples: Vec<f32>,
+    language: String,
+    // Languages "auto" chooses between; empty lets Whisper choose freely
+    candidates: Vec<String>,
+    translate: bool,
+    // Custom vocabulary for Whisper to favour
+    prompt: Option<String>,
+    reply: oneshot::Sender<Result<Transcription>>,
+}
+
+/// Runs Whisper on a thread of its own, one utterance at a time in the order they were
+/// spoken. Inference takes hundreds of milliseconds of CPU, which would otherwise stall
+/// the async runtime that capture, UI events and playback share.
+#[derive(Clone)]
+struct TranscriptionWorker {
+    jobs: mpsc::UnboundedSender<TranscriptionJob>,
+}
+
+impl TranscriptionWorker {
+    /// The thread owns `whisper` and exits once every handle to the worker is dropped.
+    fn spawn(mut whisper: Whisper) -> Result<Self> {
+        let (jobs, mut queue) = mpsc::unbounded_channel::<TranscriptionJob>();
+        std::thread::Builder::new()
+            .name("whisper".to_string())
+            .spawn(move || {
+                while let Some(job) = queue.blocking_recv() {
+                    let _ = job.reply.send(whisper.transcribe(
+                        &job.samples,
+                        &job.language,
+                        &job.candidates,
+                        job.translate,
+                        job.prompt.as_deref(),
+                    ));
+                }
+            })
+            .context("Failed to start the transcription thread")?;
+        Ok(Self { jobs })
+    }
+
+    /// Queue an utterance; the result arrives on the returned channel once it's transcribed.
+    fn submit(&self, samples: Vec<f32>, stt: &SttConfig) -> Result<PendingTranscription> {
+        let (reply, result) = oneshot::channel();
+        self.jobs
+            .send(TranscriptionJob {
+                samples,
+                language: stt.language.clone(),
+                candidates: stt.detect_languages.clone(),
+                translate: stt.task == "translate",
+                prompt: vocabulary_prompt(&stt.custom_vocabulary),
+                reply,
+            })
+            .map_err(|_| anyhow::anyhow!("The transcription thread has stopped"))?;
+        Ok(result)
+    }
 }
 
 pub struct SpeechToText {
-    whisper_ctx: Option<WhisperContext>,
-    audio_buffer: Vec<f32>,
-    buffer_size: usize,
+    // Shared with the processing task, which hands it utterances to transcribe
+    worker: Option<TranscriptionWorker>,
     sample_rate: u32,
-    transcription_sender: broadcast::Sender<TranscriptionResult>,
-    is_processing: Arc<Mutex<bool>>,
-    vad_threshold: f32,
+    transcription_sender: broadcast::Sender<SttEvent>,
+    // Watched by the processing task, which otherwise sleeps until a frame arrives
+    is_processing: watch::Sender<bool>,
     min_speech_duration: f32,
-    silence_counter: usize,
 }
 
 impl SpeechToText {
     pub fn new() -> Result<Self> {
-        let config = get_config();
+        let config = try_get_config().context("Configuration not initialized")?;
         let (transcription_sender, _) = broadcast::channel(100);
         
         Ok(SpeechToText {
-            whisper_ctx: None,
-            audio_buffer: Vec::new(),
-            buffer_size: (config.stt.min_speech_duration * config.audio.input.sample_rate as f32) as usize,
+            worker: None,
             sample_rate: config.audio.input.sample_rate,
             transcription_sender,
-            is_processing: Arc::new(Mutex::new(false)),
-            vad_threshold: config.stt.silence_threshold,
+            is_processing: watch::channel(false).0,
             min_speech_duration: config.stt.min_speech_duration,
-            silence_counter: 0,
         })
     }
     
     pub fn initialize(&mut self) -> Result<()> {
-        let config = get_config();
-        
-        // Initialize Whisper context
-        let model_path = format!("models/{}.bin", config.stt.model);
+        let config = try_get_config().context("Configuration not initialized")?;
         
-        let ctx_params = WhisperContextParameters::default();
+        // Load the model and its state here, so a broken model fails initialization
+        let model_path = crate::models::resolve(&config.stt.model)?;
+        let whisper = Whisper::load(&model_path)?;
         
-        // For now, we'll use a placeholder path
-        // In a real implementation, you'd download or bundle the model
-        let whisper_ctx = WhisperContext::new_with_params(
-            &model_path,
-            ctx_params,
-        ).context("Failed to initialize Whisper context")?;
-        
-        self.whisper_ctx = Some(whisper_ctx);
+        self.worker = Some(TranscriptionWorker::spawn(whisper)?);
         
         log::info!("Speech-to-Text initialized with model: {}", config.stt.model);
         Ok(())
     }
     
-    pub async fn start_processing(&mut self, audio_receiver: Arc<Mutex<Receiver<AudioFrame>>>) -> Result<()> {
-        let config = get_config();
-        *self.is_processing.lock().unwrap() = true;
+    /// Transcribe frames from `audio_receiver` while `mode` is Listening. Frames arriving
+    /// while Speaking are dropped so the assistant doesn't hear itself, unless `barge_in` is set.
+    /// While `push_to_talk` is held, VAD is skipped: everything is kept and transcribed on release.
+    pub fn start_processing(
+        &mut self,
+        mut audio_receiver: mpsc::Receiver<AudioFrame>,
+        mode: watch::Receiver<ProcessingMode>,
+        mut push_to_talk: watch::Receiver<bool>,
+        barge_in: bool,
+    ) -> Result<()> {
+        let config = try_get_config().context("Configuration not initialized")?;
+        let worker = self
+            .worker
+            .clone()
+            .context("Speech-to-Text is not initialized")?;
+        self.is_processing.send_replace(true);
         
         let transcription_sender = self.transcription_sender.clone();
-        let is_processing = self.is_processing.clone();
-        let vad_threshold = self.vad_threshold;
+        let mut is_processing = self.is_processing.subscribe();
+        let mut vad = vad::create(&config.stt);
         let min_speech_duration = self.min_speech_duration;
-        let sample_rate = self.sample_rate;
+        let mut sample_rate = self.sample_rate;
         
         tokio::spawn(async move {
             let mut audio_buffer = Vec::new();
             let mut silence_counter = 0;
-            let silence_threshold = (0.5 * sample_rate as f32) as usize; // 0.5 seconds of silence
+            let mut push_to_talk_held = false;
             
-            while *is_processing.lock().unwrap() {
-                // Receive audio frames
-                if let Ok(receiver) = audio_receiver.try_lock() {
-                    while let Ok(frame) = receiver.try_recv() {
-                        // Voice Activity Detection (VAD)
-                        let energy = Self::calculate_energy(&frame.data);
+            loop {
+                tokio::select! {
+                    biased;
+                    changed = is_processing.changed() => {
+                        if changed.is_err() || !*is_processing.borrow() {
+                            break;
+                        }
+                    }
+                    changed = push_to_talk.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        let held = *push_to_talk.borrow_and_update();
+                        if held == push_to_talk_held {
+                            continue;
+                        }
+                        push_to_talk_held = held;
+                        if !held {
+                            // Recording stopped before the key was reported released, so what's left
+                            // in the queue is the tail of the utterance
+                            while let Ok(frame) = audio_receiver.try_recv() {
+                                if audio_buffer.is_empty() {
+                                    sample_rate = frame.sample_rate;
+                                }
+                                audio_buffer.extend_from_slice(&frame.data);
+                            }
+                            if !audio_buffer.is_empty() {
+                                Self::finish_utterance(&worker, &audio_buffer, sample_rate, &transcription_sender);
+                            }
+                        }
+                        // Whatever VAD had half-collected isn't part of what the key captures
+                        audio_buffer.clear();
+                        silence_counter = 0;
+                        vad.reset();
+                    }
+                    frame = audio_receiver.recv() => {
+                        // The queue closes when capture moves to a new one, e.g. on restart
+                        let Some(frame) = frame else {
+                            break;
+                        };
                         
-                        if energy > vad_threshold {
+                        // The key decides where the utterance starts and ends, and it counts even if
+                        // the mode has already moved on by the time the last frames are read
+                        if push_to_talk_held {
+                            if audio_buffer.is_empty() {
+                                sample_rate = frame.sample_rate;
+                            }
+                            audio_buffer.extend_from_slice(&frame.data);
+                            continue;
+                        }
+                        
+                        let accepting = match *mode.borrow() {
+                            ProcessingMode::Listening => true,
+                            ProcessingMode::Speaking => barge_in,
+                            ProcessingMode::Idle => false,
+                        };
+                        if !accepting {
+                            // Discard half-heard speech rather than stitching it to what comes after
+                            audio_buffer.clear();
+                            silence_counter = 0;
+                            vad.reset();
+                            continue;
+                        }
+                        
+                        // Frames arrive at the device's rate, which needn't be the configured one
+                        if audio_buffer.is_empty() {
+                            sample_rate = frame.sample_rate;
+                        }
+                        
+                        if vad.is_speech(&frame.data, sample_rate) {
                             // Speech detected
                             audio_buffer.extend_from_slice(&frame.data);
                             silence_counter = 0;
@@ -97,25 +379,10 @@ impl SpeechToText {
                             silence_counter += frame.data.len();
                             
                             // If we have accumulated speech and now have silence, process it
-                            if !audio_buffer.is_empty() && silence_counter > silence_threshold {
+                            // 0.5 seconds of silence
+                            if !audio_buffer.is_empty() && silence_counter > sample_rate as usize / 2 {
                                 if audio_buffer.len() > (min_speech_duration * sample_rate as f32) as usize {
-                                    // Process the accumulated audio
-                                    if let Ok(transcription) = Self::transcribe_audio(&audio_buffer, sample_rate).await {
-                                        let result = TranscriptionResult {
-                                            text: transcription,
-                                            confidence: 0.9, // Placeholder
-                                            language: config.stt.language.clone(),
-                                            timestamp: std::time::SystemTime::now()
-                                                .duration_since(std::time::UNIX_EPOCH)
-                                                .unwrap()
-                                                .as_millis() as u64,
-                                            is_final: true,
-                                        };
-                                        
-                                        if let Err(e) = transcription_sender.send(result) {
-                                            log::error!("Failed to send transcription: {}", e);
-                                        }
-                                    }
+                                    Self::finish_utterance(&worker, &audio_buffer, sample_rate, &transcription_sender);
                                 }
                                 
                                 audio_buffer.clear();
@@ -124,8 +391,6 @@ impl SpeechToText {
                         }
                     }
                 }
-                
-                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
             }
         });
         
@@ -133,38 +398,67 @@ impl SpeechToText {
         Ok(())
     }
     
-    fn calculate_energy(audio_data: &[f32]) -> f32 {
-        let sum_squares: f32 = audio_data.iter().map(|&x| x * x).sum();
-        (sum_squares / audio_data.len() as f32).sqrt()
+    /// Report the end of an utterance and queue it for transcription. What was said is sent
+    /// once Whisper is done, while the processing loop carries on listening.
+    fn finish_utterance(
+        worker: &TranscriptionWorker,
+        audio_data: &[f32],
+        sample_rate: u32,
+        transcription_sender: &broadcast::Sender<SttEvent>,
+    ) {
+        let _ = transcription_sender.send(SttEvent::UtteranceEnded);
+        let samples = resample_linear(audio_data, sample_rate, WHISPER_SAMPLE_RATE);
+        // Read per utterance so a language switched mid-session applies from the next one
+        let submitted = try_get_config()
+            .context("Configuration not initialized")
+            .and_then(|config| worker.submit(samples, &config.stt));
+        let transcription_sender = transcription_sender.clone();
+        tokio::spawn(async move {
+            let transcription = match submitted {
+                Ok(result) => result.await.unwrap_or_else(|_| Err(anyhow::anyhow!("The transcription thread dropped the utterance"))),
+                Err(e) => Err(e),
+            };
+            match transcription {
+                Ok(transcription) if !transcription.text.is_empty() => {
+                    log::debug!(
+                        "Transcribed {} speech with confidence {:.2}",
+                        transcription.language,
+                        transcription.confidence
+                    );
+                    let result = TranscriptionResult {
+                        text: transcription.text,
+                        language: transcription.language,
+                    };
+                    
+                    if let Err(e) = transcription_sender.send(SttEvent::Transcription(result)) {
+                        log::error!("Failed to send transcription: {}", e);
+                    }
+                }
+                // Noise Whisper found no words in
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("Transcription failed: {:#}", e);
+                    let _ = transcription_sender.send(SttEvent::Failed(format!("Transcription failed: {:#}", e)));
+                }
+            }
+        });
     }
     
-    async fn transcribe_audio(audio_data: &[f32], sample_rate: u32) -> Result<String> {
-        // Placeholder implementation
-        // In a real implementation, you would:
-        // 1. Resample audio to 16kHz if needed
-        // 2. Use Whisper to transcribe
-        // 3. Return the transcription
-        
-        // For now, return a placeholder
-        if audio_data.len() > 1000 {
-            Ok("[Transcribed speech placeholder]".to_string())
-        } else {
-            Ok(String::new())
-        }
+    /// Queue a whole recording, e.g. a voice memo, behind any utterances being transcribed.
+    pub fn transcribe_recording(&self, samples: &[f32], sample_rate: u32) -> Result<PendingTranscription> {
+        let worker = self.worker.as_ref().context("Speech-to-Text is not initialized")?;
+        let config = try_get_config().context("Configuration not initialized")?;
+        worker.submit(resample_linear(samples, sample_rate, WHISPER_SAMPLE_RATE), &config.stt)
     }
     
-    pub fn get_transcription_receiver(&self) -> broadcast::Receiver<TranscriptionResult> {
+    pub fn get_transcription_receiver(&self) -> broadcast::Receiver<SttEvent> {
         self.transcription_sender.subscribe()
     }
     
     pub fn stop_processing(&mut self) {
-        *self.is_processing.lock().unwrap() = false;
+        self.is_processing.send_replace(false);
         log::info!("Speech-to-Text processing stopped");
     }
-    
-    pub fn is_processing(&self) -> bool {
-        *self.is_processing.lock().unwrap()
-    }
 }
 
 impl Drop for SpeechToText {
@@ -1,10 +1,19 @@
-use crate::config::get_config;
-use crate::audio::{AudioManager, VisemeData};
+use crate::config::{try_get_config, TtsConfig};
+use crate::audio::align;
+use crate::audio::elevenlabs::ElevenLabsProvider;
+use crate::audio::g2p;
+use crate::audio::piper::PiperProvider;
+use crate::audio::ssml::{self, SsmlPart};
+use crate::audio::tts_cache::{self, CachedSpeech};
+use crate::audio::visemes;
+use crate::audio::VisemeData;
 use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::sync::broadcast;
-use rodio::{Decoder, OutputStream, Sink};
+use tokio::sync::{broadcast, mpsc};
 
 #[derive(Debug, Clone)]
 pub struct SynthesisRequest {
@@ -14,6 +23,8 @@ pub struct SynthesisRequest {
     pub pitch: Option<f32>,
     pub volume: Option<f32>,
     pub generate_visemes: bool,
+    // `text` is an SSML document rather than plain text
+    pub ssml: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -22,172 +33,454 @@ pub struct SynthesisResult {
     pub sample_rate: u32,
     pub duration: f32,
     pub visemes: Vec<VisemeData>,
+    pub words: Vec<WordTiming>,
+    // More audio of the same utterance follows straight after, so playback shouldn't pause
+    pub continues: bool,
+    // The speech generation synthesis started in; playback drops results a stop has outdated
+    pub generation: u64,
 }
 
+/// When a word is spoken, in seconds from the start of its synthesis result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordTiming {
+    pub index: usize,
+    pub word: String,
+    // Where the word starts in the synthesized text, in characters
+    pub char_offset: usize,
+    // The sentence this word opens, if it's the first of one
+    pub sentence: Option<String>,
+    pub start: f64,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TtsVoice {
+    // What tts.voice should be set to
+    pub id: String,
+    pub name: String,
+}
+
+/// A speech engine that turns text into audio.
+#[async_trait]
+pub trait TtsProvider: Send + Sync {
+    /// Mono samples for `text`, and the sample rate they were generated at.
+    async fn synthesize(&self, text: &str, request: &SynthesisRequest) -> Result<(Vec<f32>, u32)>;
+
+    /// Whether `synthesize_streaming` delivers audio before the whole utterance is ready.
+    fn streams(&self) -> bool {
+        false
+    }
+
+    /// Send audio to `chunks` as it is generated.
+    async fn synthesize_streaming(&self, text: &str, request: &SynthesisRequest, chunks: mpsc::UnboundedSender<(Vec<f32>, u32)>) -> Result<()> {
+        let audio = self.synthesize(text, request).await?;
+        let _ = chunks.send(audio);
+        Ok(())
+    }
+
+    async fn list_voices(&self) -> Result<Vec<TtsVoice>> {
+        Ok(Vec::new())
+    }
+
+    /// Whether visemes estimated from the text match the audio, i.e. it's speech at all.
+    fn supports_visemes(&self) -> bool {
+        true
+    }
+
+    /// Whether the engine reads SSML itself. Others are handed plain text, with the markup
+    /// approximated by `TextToSpeech`.
+    fn supports_ssml(&self) -> bool {
+        false
+    }
+
+    /// Load whatever the first utterance would otherwise wait for, e.g. a local voice model.
+    async fn warm_up(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+type ProviderFactory = fn(&TtsConfig) -> Arc<dyn TtsProvider>;
+
+// Every speech engine, by the name tts.provider uses for it
+const PROVIDERS: &[(&str, ProviderFactory)] = &[
+    ("piper", create_piper),
+    ("elevenlabs", create_elevenlabs),
+    ("tone", create_tone),
+];
+
+fn create_piper(config: &TtsConfig) -> Arc<dyn TtsProvider> {
+    // Missing voices are reported when speaking, so a bad config doesn't stop the app
+    if let Err(e) = crate::models::resolve_piper_voice(&config.voice) {
+        log::warn!("{:#}", e);
+    }
+    Arc::new(PiperProvider::new(config.piper_binary.clone(), config.voice.clone()))
+}
+
+fn create_elevenlabs(config: &TtsConfig) -> Arc<dyn TtsProvider> {
+    Arc::new(ElevenLabsProvider::new(config.clone()))
+}
+
+fn create_tone(_config: &TtsConfig) -> Arc<dyn TtsProvider> {
+    Arc::new(ToneProvider)
+}
+
+/// Names `tts.provider` can be set to.
+pub fn provider_names() -> Vec<&'static str> {
+    PROVIDERS.iter().map(|(name, _)| *name).collect()
+}
+
+/// The engine for `tts.provider`.
+pub fn create_provider(config: &TtsConfig) -> Arc<dyn TtsProvider> {
+    match PROVIDERS.iter().find(|(name, _)| *name == config.provider) {
+        Some((_, create)) => create(config),
+        None => {
+            log::warn!("No speech engine for TTS provider {}, using a placeholder tone", config.provider);
+            Arc::new(ToneProvider)
+        }
+    }
+}
+
+/// Stand-in tone for when no speech engine is configured, so the pipeline can still be exercised.
+struct ToneProvider;
+
+#[async_trait]
+impl TtsProvider for ToneProvider {
+    async fn synthesize(&self, text: &str, request: &SynthesisRequest) -> Result<(Vec<f32>, u32)> {
+        let config = try_get_config().context("Configuration not initialized")?;
+        
+        // Generate a simple sine wave based on text length, paced and pitched as requested
+        let speed = request.speed.unwrap_or(config.tts.speed).max(0.1);
+        let duration = text.len() as f32 * 0.1 / speed; // 100ms per character
+        let sample_rate = config.audio.output.sample_rate as f32;
+        let samples = (duration * sample_rate) as usize;
+        
+        let mut audio_data = Vec::with_capacity(samples);
+        let frequency = 440.0 * request.pitch.unwrap_or(config.tts.pitch); // A4 note
+        
+        for i in 0..samples {
+            let t = i as f32 / sample_rate;
+            let amplitude = 0.1 * request.volume.unwrap_or(config.tts.volume);
+            let sample = amplitude * (2.0 * std::f32::consts::PI * frequency * t).sin();
+            audio_data.push(sample);
+        }
+        
+        Ok((audio_data, config.audio.output.sample_rate))
+    }
+
+    fn supports_visemes(&self) -> bool {
+        false
+    }
+}
+
+// Synthesis works on a copy, so the shared one is never locked across an await
+#[derive(Clone)]
 pub struct TextToSpeech {
+    provider: Arc<dyn TtsProvider>,
     synthesis_sender: broadcast::Sender<SynthesisResult>,
-    viseme_mapping: HashMap<String, String>,
     is_synthesizing: Arc<Mutex<bool>>,
     current_voice: String,
+    // From character.lip_sync.viseme_mapping
     phoneme_to_viseme: HashMap<String, String>,
+    // Bumped when speech is stopped, so synthesis under way knows it's no longer wanted
+    speech_generation: Arc<AtomicU64>,
 }
 
 impl TextToSpeech {
-    pub fn new() -> Result<Self> {
+    pub fn new(speech_generation: Arc<AtomicU64>) -> Result<Self> {
         let (synthesis_sender, _) = broadcast::channel(100);
         
-        let mut phoneme_to_viseme = HashMap::new();
-        Self::initialize_viseme_mapping(&mut phoneme_to_viseme);
-        
         Ok(TextToSpeech {
+            provider: Arc::new(ToneProvider),
             synthesis_sender,
-            viseme_mapping: HashMap::new(),
             is_synthesizing: Arc::new(Mutex::new(false)),
             current_voice: "neural".to_string(),
-            phoneme_to_viseme,
+            phoneme_to_viseme: visemes::load_mapping("arkit")?,
+            speech_generation,
         })
     }
     
-    fn initialize_viseme_mapping(mapping: &mut HashMap<String, String>) {
-        // ARKit-compatible viseme mapping
-        // Based on Apple's ARKit facial blend shapes
-        mapping.insert("sil".to_string(), "jawOpen".to_string()); // Silence
-        mapping.insert("aa".to_string(), "jawOpen".to_string()); // 'a' in "father"
-        mapping.insert("ae".to_string(), "jawOpen".to_string()); // 'a' in "cat"
-        mapping.insert("ah".to_string(), "jawOpen".to_string()); // 'u' in "but"
-        mapping.insert("ao".to_string(), "mouthFunnel".to_string()); // 'o' in "dog"
-        mapping.insert("aw".to_string(), "mouthFunnel".to_string()); // 'ow' in "how"
-        mapping.insert("ay".to_string(), "jawOpen".to_string()); // 'i' in "bite"
-        mapping.insert("b".to_string(), "mouthClose".to_string()); // 'b' in "big"
-        mapping.insert("ch".to_string(), "mouthShrugUpper".to_string()); // 'ch' in "chip"
-        mapping.insert("d".to_string(), "tongueOut".to_string()); // 'd' in "dog"
-        mapping.insert("dh".to_string(), "tongueOut".to_string()); // 'th' in "this"
-        mapping.insert("eh".to_string(), "jawOpen".to_string()); // 'e' in "bed"
-        mapping.insert("er".to_string(), "mouthFunnel".to_string()); // 'ur' in "bird"
-        mapping.insert("ey".to_string(), "jawOpen".to_string()); // 'a' in "cake"
-        mapping.insert("f".to_string(), "mouthLowerDownRight".to_string()); // 'f' in "fish"
-        mapping.insert("g".to_string(), "jawOpen".to_string()); // 'g' in "go"
-        mapping.insert("hh".to_string(), "jawOpen".to_string()); // 'h' in "house"
-        mapping.insert("ih".to_string(), "mouthSmileLeft".to_string()); // 'i' in "bit"
-        mapping.insert("iy".to_string(), "mouthSmileLeft".to_string()); // 'ee' in "see"
-        mapping.insert("jh".to_string(), "mouthShrugUpper".to_string()); // 'j' in "jump"
-        mapping.insert("k".to_string(), "jawOpen".to_string()); // 'k' in "cat"
-        mapping.insert("l".to_string(), "tongueOut".to_string()); // 'l' in "love"
-        mapping.insert("m".to_string(), "mouthClose".to_string()); // 'm' in "man"
-        mapping.insert("n".to_string(), "tongueOut".to_string()); // 'n' in "no"
-        mapping.insert("ng".to_string(), "jawOpen".to_string()); // 'ng' in "sing"
-        mapping.insert("ow".to_string(), "mouthFunnel".to_string()); // 'o' in "go"
-        mapping.insert("oy".to_string(), "mouthFunnel".to_string()); // 'oy' in "boy"
-        mapping.insert("p".to_string(), "mouthClose".to_string()); // 'p' in "put"
-        mapping.insert("r".to_string(), "mouthFunnel".to_string()); // 'r' in "red"
-        mapping.insert("s".to_string(), "mouthShrugUpper".to_string()); // 's' in "see"
-        mapping.insert("sh".to_string(), "mouthShrugUpper".to_string()); // 'sh' in "ship"
-        mapping.insert("t".to_string(), "tongueOut".to_string()); // 't' in "top"
-        mapping.insert("th".to_string(), "tongueOut".to_string()); // 'th' in "think"
-        mapping.insert("uh".to_string(), "mouthFunnel".to_string()); // 'u' in "book"
-        mapping.insert("uw".to_string(), "mouthFunnel".to_string()); // 'oo' in "food"
-        mapping.insert("v".to_string(), "mouthLowerDownRight".to_string()); // 'v' in "voice"
-        mapping.insert("w".to_string(), "mouthFunnel".to_string()); // 'w' in "water"
-        mapping.insert("y".to_string(), "mouthSmileLeft".to_string()); // 'y' in "yes"
-        mapping.insert("z".to_string(), "mouthShrugUpper".to_string()); // 'z' in "zoo"
-        mapping.insert("zh".to_string(), "mouthShrugUpper".to_string()); // 's' in "measure"
-    }
-    
     pub fn initialize(&mut self) -> Result<()> {
-        let config = get_config();
+        let config = try_get_config().context("Configuration not initialized")?;
         self.current_voice = config.tts.voice.clone();
+        self.provider = create_provider(&config.tts);
+        let mapping = &config.character.lip_sync.viseme_mapping;
+        match visemes::load_mapping(mapping) {
+            Ok(table) => self.phoneme_to_viseme = table,
+            Err(e) => log::warn!("Keeping the current viseme mapping: {:#}", e),
+        }
         
         log::info!("Text-to-Speech initialized with voice: {}", self.current_voice);
         Ok(())
     }
     
-    pub async fn synthesize(&mut self, request: SynthesisRequest) -> Result<()> {
-        let config = get_config();
+    pub fn provider(&self) -> Arc<dyn TtsProvider> {
+        self.provider.clone()
+    }
+    
+    pub async fn synthesize(&self, request: SynthesisRequest) -> Result<()> {
+        if request.ssml && !self.provider.supports_ssml() {
+            *self.is_synthesizing.lock().unwrap() = true;
+            let result = self.synthesize_ssml(&request).await;
+            *self.is_synthesizing.lock().unwrap() = false;
+            return result;
+        }
+        
+        let config = try_get_config().context("Configuration not initialized")?;
         *self.is_synthesizing.lock().unwrap() = true;
+        let generation = self.speech_generation.load(Ordering::SeqCst);
         
-        // Generate phonemes from text (placeholder implementation)
-        let phonemes = self.text_to_phonemes(&request.text).await?;
+        let cache_key = tts_cache::key(&config.tts, &request);
+        if let Some(cached) = cache_key.as_deref().and_then(tts_cache::load) {
+            let samples = cached.audio.len();
+            // The cache outlives the viseme mapping and lip sync settings; the phonemes are what it keys on
+            let mut visemes = self.remap_visemes(cached.visemes);
+            visemes::set_intensity(&mut visemes, &cached.audio, cached.sample_rate, config.character.lip_sync.smoothing);
+            let result = SynthesisResult {
+                duration: samples as f32 / cached.sample_rate.max(1) as f32,
+                audio_data: cached.audio,
+                sample_rate: cached.sample_rate,
+                visemes,
+                words: cached.words,
+                continues: false,
+                generation,
+            };
+            *self.is_synthesizing.lock().unwrap() = false;
+            self.synthesis_sender.send(result)
+                .map_err(|e| anyhow::anyhow!("Failed to send synthesis result: {}", e))?;
+            log::info!("Played cached speech for '{}' ({} samples)", request.text, samples);
+            return Ok(());
+        }
         
-        // Generate visemes from phonemes
-        let visemes = if request.generate_visemes {
-            self.generate_visemes(&phonemes, &request.text)
-        } else {
-            Vec::new()
+        if self.provider.streams() && config.tts.streaming {
+            let result = async {
+                // An engine reading SSML gets the markup, but timings come from the words alone
+                let spoken = self.spoken_text(&request)?;
+                let (phonemes, words) = self.text_to_phonemes(&spoken).await?;
+                self.synthesize_streaming(&request, &phonemes, words, cache_key.as_deref()).await
+            }
+            .await;
+            *self.is_synthesizing.lock().unwrap() = false;
+            return result;
+        }
+        
+        let result = match self.render(&request).await {
+            Ok(result) => SynthesisResult { generation, ..result },
+            Err(e) => {
+                *self.is_synthesizing.lock().unwrap() = false;
+                return Err(e);
+            }
         };
         
-        // Generate audio (placeholder implementation)
-        let audio_data = self.generate_audio(&request.text, &request).await?;
+        if let Some(key) = &cache_key {
+            let speech = CachedSpeech {
+                audio: result.audio_data.clone(),
+                sample_rate: result.sample_rate,
+                visemes: result.visemes.clone(),
+                words: result.words.clone(),
+            };
+            if let Err(e) = tts_cache::store(key, &speech, config.tts.cache_size_mb) {
+                log::warn!("Failed to cache synthesized speech: {:#}", e);
+            }
+        }
         
-        let result = SynthesisResult {
-            audio_data: audio_data.clone(),
-            sample_rate: config.audio.output.sample_rate,
-            duration: audio_data.len() as f32 / config.audio.output.sample_rate as f32,
-            visemes,
-        };
+        let voice = request.voice.as_deref().unwrap_or(&self.current_voice);
+        log::info!("Synthesized text: '{}' in voice {} ({:.2}s)", request.text, voice, result.duration);
         
         // Send the result
         self.synthesis_sender.send(result)
             .map_err(|e| anyhow::anyhow!("Failed to send synthesis result: {}", e))?;
         
         *self.is_synthesizing.lock().unwrap() = false;
-        
-        log::info!("Synthesized text: '{}' ({} samples)", request.text, audio_data.len());
         Ok(())
     }
     
-    async fn text_to_phonemes(&self, text: &str) -> Result<Vec<(String, f64, f64)>> {
-        // Placeholder implementation
-        // In a real implementation, you would use a phonemizer or TTS engine
-        // that can output phoneme timing information
+    fn spoken_text(&self, request: &SynthesisRequest) -> Result<String> {
+        if request.ssml {
+            ssml::plain_text(&request.text)
+        } else {
+            Ok(request.text.clone())
+        }
+    }
+    
+    /// Synthesize without playing, with visemes and word timings aligned to the audio.
+    /// Engines that can't read SSML are given its plain text.
+    pub async fn render(&self, request: &SynthesisRequest) -> Result<SynthesisResult> {
+        let spoken = self.spoken_text(request)?;
+        // Generate phonemes from text (placeholder implementation)
+        let (mut phonemes, mut words) = self.text_to_phonemes(&spoken).await?;
         
+        let (audio_data, sample_rate) = if request.ssml && !self.provider.supports_ssml() {
+            let plain = SynthesisRequest {
+                text: spoken.clone(),
+                ssml: false,
+                ..request.clone()
+            };
+            self.generate_audio(&plain).await?
+        } else {
+            self.generate_audio(request).await?
+        };
+        let duration = audio_data.len() as f32 / sample_rate.max(1) as f32;
+        align::align_timings(&mut phonemes, &mut words, &audio_data, sample_rate);
+        
+        // Generate visemes from phonemes
+        let visemes = if request.generate_visemes && self.provider.supports_visemes() {
+            let mut visemes = self.generate_visemes(&phonemes, &spoken);
+            let smoothing = try_get_config().context("Configuration not initialized")?.character.lip_sync.smoothing;
+            visemes::set_intensity(&mut visemes, &audio_data, sample_rate, smoothing);
+            visemes
+        } else {
+            Vec::new()
+        };
+        
+        Ok(SynthesisResult {
+            audio_data,
+            sample_rate,
+            duration,
+            visemes,
+            words,
+            continues: false,
+            generation: self.speech_generation.load(Ordering::SeqCst),
+        })
+    }
+    
+    /// Approximate SSML for an engine that can't read it: each run of text with its own prosody
+    /// is synthesized separately and breaks become silence, all played as one utterance.
+    async fn synthesize_ssml(&self, request: &SynthesisRequest) -> Result<()> {
+        let parts = ssml::parse(&request.text)?;
+        let generation = self.speech_generation.load(Ordering::SeqCst);
+        // A pause before any speech is played at the output rate
+        let mut sample_rate = try_get_config().context("Configuration not initialized")?.audio.output.sample_rate;
+        let mut word_offset = 0;
+        let mut char_offset = 0;
+        let mut samples = 0;
+        
+        for (i, part) in parts.iter().enumerate() {
+            // Speech was stopped; the rest of the document would only be thrown away
+            if self.speech_generation.load(Ordering::SeqCst) != generation {
+                break;
+            }
+            let continues = i + 1 < parts.len();
+            let result = match part {
+                SsmlPart::Pause(seconds) => SynthesisResult {
+                    audio_data: vec![0.0; (seconds * sample_rate as f32) as usize],
+                    sample_rate,
+                    duration: *seconds,
+                    visemes: Vec::new(),
+                    words: Vec::new(),
+                    continues,
+                    generation,
+                },
+                SsmlPart::Speech { text, rate, pitch, volume } => {
+                    let part_request = SynthesisRequest {
+                        text: text.clone(),
+                        voice: request.voice.clone(),
+                        speed: Some(request.speed.unwrap_or(1.0) * rate),
+                        pitch: Some(request.pitch.unwrap_or(1.0) * pitch),
+                        volume: Some((request.volume.unwrap_or(1.0) * volume).clamp(0.0, 1.0)),
+                        generate_visemes: request.generate_visemes,
+                        ssml: false,
+                    };
+                    let (audio_data, part_rate) = self.generate_audio(&part_request).await?;
+                    sample_rate = part_rate;
+                    let duration = audio_data.len() as f32 / sample_rate.max(1) as f32;
+                    
+                    let (mut phonemes, mut words) = self.text_to_phonemes(text).await?;
+                    align::align_timings(&mut phonemes, &mut words, &audio_data, sample_rate);
+                    // Words are placed in the document's plain text, not just this run
+                    for word in &mut words {
+                        word.index += word_offset;
+                        word.char_offset += char_offset;
+                    }
+                    word_offset += words.len();
+                    char_offset += text.chars().count() + 1;
+                    let visemes = if request.generate_visemes && self.provider.supports_visemes() {
+                        let mut visemes = self.generate_visemes(&phonemes, text);
+                        let smoothing = try_get_config().context("Configuration not initialized")?.character.lip_sync.smoothing;
+                        visemes::set_intensity(&mut visemes, &audio_data, sample_rate, smoothing);
+                        visemes
+                    } else {
+                        Vec::new()
+                    };
+                    SynthesisResult {
+                        audio_data,
+                        sample_rate,
+                        duration,
+                        visemes,
+                        words,
+                        continues,
+                        generation,
+                    }
+                }
+            };
+            samples += result.audio_data.len();
+            self.synthesis_sender.send(result)
+                .map_err(|e| anyhow::anyhow!("Failed to send synthesis result: {}", e))?;
+        }
+        
+        log::info!("Synthesized SSML in {} parts ({} samples)", parts.len(), samples);
+        Ok(())
+    }
+    
+    async fn text_to_phonemes(&self, text: &str) -> Result<(Vec<(String, f64, f64)>, Vec<WordTiming>)> {
+        // Durations are estimates; render aligns them to the real audio
         let words: Vec<&str> = text.split_whitespace().collect();
         let mut phonemes = Vec::new();
+        let mut word_timings = Vec::with_capacity(words.len());
         let mut current_time = 0.0;
+        let mut char_offset = 0;
+        let mut rest = text;
         
-        for word in words {
-            // Simple mapping for demonstration
-            let word_phonemes = self.word_to_phonemes(word);
-            let phoneme_duration = 0.1; // 100ms per phoneme
+        for (index, word) in words.iter().enumerate() {
+            let word_start = current_time;
+            let skipped = rest.find(word).unwrap_or(0);
+            char_offset += rest[..skipped].chars().count();
+            rest = &rest[skipped + word.len()..];
+            
+            let opens_sentence = index == 0 || ends_sentence(words[index - 1]);
+            let sentence = opens_sentence.then(|| {
+                let length = words[index..]
+                    .iter()
+                    .position(|word| ends_sentence(word))
+                    .map_or(words.len() - index, |last| last + 1);
+                words[index..index + length].join(" ")
+            });
             
-            for phoneme in word_phonemes {
+            for phoneme in g2p::word_to_phonemes(word) {
+                let phoneme_duration = g2p::phoneme_duration(&phoneme);
                 phonemes.push((phoneme, current_time, phoneme_duration));
                 current_time += phoneme_duration;
             }
             
+            word_timings.push(WordTiming {
+                index,
+                word: word.to_string(),
+                char_offset,
+                sentence,
+                start: word_start,
+                duration: current_time - word_start,
+            });
+            char_offset += word.chars().count();
+            
             // Add silence between words
             phonemes.push(("sil".to_string(), current_time, 0.05));
             current_time += 0.05;
         }
         
-        Ok(phonemes)
-    }
-    
-    fn word_to_phonemes(&self, word: &str) -> Vec<String> {
-        // Very simple phoneme mapping for demonstration
-        // In a real implementation, you'd use a proper phonemizer
-        match word.to_lowercase().as_str() {
-            "hello" => vec!["hh".to_string(), "eh".to_string(), "l".to_string(), "ow".to_string()],
-            "world" => vec!["w".to_string(), "er".to_string(), "l".to_string(), "d".to_string()],
-            "how" => vec!["hh".to_string(), "aw".to_string()],
-            "are" => vec!["aa".to_string(), "r".to_string()],
-            "you" => vec!["y".to_string(), "uw".to_string()],
-            _ => {
-                // Fallback: create phonemes based on characters
-                word.chars().map(|c| c.to_string()).collect()
-            }
-        }
+        Ok((phonemes, word_timings))
     }
     
     fn generate_visemes(&self, phonemes: &[(String, f64, f64)], text: &str) -> Vec<VisemeData> {
         let mut visemes = Vec::new();
         
         for (phoneme, start_time, duration) in phonemes {
-            if let Some(viseme_name) = self.phoneme_to_viseme.get(phoneme) {
+            if let Some(viseme_name) = self.viseme_for(phoneme) {
                 let viseme = VisemeData {
                     phoneme: phoneme.clone(),
+                    viseme: viseme_name.clone(),
                     timestamp: *start_time,
                     duration: *duration,
-                    intensity: 1.0, // Full intensity for now
+                    // Until set_intensity measures the audio; streamed speech keeps it
+                    intensity: 1.0,
                 };
                 visemes.push(viseme);
             }
@@ -197,47 +490,125 @@ impl TextToSpeech {
         visemes
     }
     
-    async fn generate_audio(&self, text: &str, request: &SynthesisRequest) -> Result<Vec<f32>> {
-        let config = get_config();
-        
-        // Placeholder implementation
-        // In a real implementation, you would:
-        // 1. Use a TTS engine (like Coqui TTS, Festival, or cloud services)
-        // 2. Apply voice settings (speed, pitch, volume)
-        // 3. Return the audio samples
-        
-        // For now, generate a simple sine wave based on text length
-        let duration = text.len() as f32 * 0.1; // 100ms per character
-        let sample_rate = config.audio.output.sample_rate as f32;
-        let samples = (duration * sample_rate) as usize;
+    /// The viseme showing `phoneme`; a mapping file that leaves it out gets its rest shape.
+    fn viseme_for(&self, phoneme: &str) -> Option<&String> {
+        self.phoneme_to_viseme.get(phoneme).or_else(|| self.phoneme_to_viseme.get("sil"))
+    }
+    
+    fn remap_visemes(&self, visemes: Vec<VisemeData>) -> Vec<VisemeData> {
+        visemes
+            .into_iter()
+            .filter_map(|viseme| {
+                let name = self.viseme_for(&viseme.phoneme)?.clone();
+                Some(VisemeData { viseme: name, ..viseme })
+            })
+            .collect()
+    }
+    
+    async fn generate_audio(&self, request: &SynthesisRequest) -> Result<(Vec<f32>, u32)> {
+        self.provider.synthesize(&request.text, request).await
+    }
+    
+    /// Hand each chunk to playback as it arrives. The total length isn't known up front, so
+    /// visemes and word timings keep their estimates and travel with the first chunk.
+    /// With a `cache_key`, the whole utterance is cached once it has arrived.
+    async fn synthesize_streaming(
+        &self,
+        request: &SynthesisRequest,
+        phonemes: &[(String, f64, f64)],
+        words: Vec<WordTiming>,
+        cache_key: Option<&str>,
+    ) -> Result<()> {
+        let (chunks, mut received) = mpsc::unbounded_channel();
+        let generation = self.speech_generation.load(Ordering::SeqCst);
+        let provider = self.provider.clone();
+        let synthesis = provider.synthesize_streaming(&request.text, request, chunks);
         
-        let mut audio_data = Vec::with_capacity(samples);
-        let frequency = 440.0; // A4 note
+        let forward = async {
+            let mut timing = Some((
+                if request.generate_visemes && provider.supports_visemes() {
+                    self.generate_visemes(phonemes, &request.text)
+                } else {
+                    Vec::new()
+                },
+                words,
+            ));
+            let mut cached = cache_key.and_then(|_| timing.clone()).map(|(visemes, words)| CachedSpeech {
+                audio: Vec::new(),
+                sample_rate: 0,
+                visemes,
+                words,
+            });
+            // Hold one chunk back so the last one can be marked as the end of the utterance
+            let mut held: Option<(Vec<f32>, u32)> = None;
+            let mut samples = 0;
+            loop {
+                let next = received.recv().await;
+                if let Some((audio_data, sample_rate)) = held.take() {
+                    samples += audio_data.len();
+                    if let Some(speech) = &mut cached {
+                        // Chunks at different rates can't share one file; leave such replies uncached
+                        if speech.sample_rate == 0 || speech.sample_rate == sample_rate {
+                            speech.sample_rate = sample_rate;
+                            speech.audio.extend_from_slice(&audio_data);
+                        } else {
+                            cached = None;
+                        }
+                    }
+                    let (visemes, words) = timing.take().unwrap_or_default();
+                    let result = SynthesisResult {
+                        duration: audio_data.len() as f32 / sample_rate.max(1) as f32,
+                        audio_data,
+                        sample_rate,
+                        visemes,
+                        words,
+                        continues: next.is_some(),
+                        generation,
+                    };
+                    if self.synthesis_sender.send(result).is_err() {
+                        log::error!("Failed to send synthesis chunk: no playback listening");
+                    }
+                }
+                match next {
+                    Some(chunk) => held = Some(chunk),
+                    None => break,
+                }
+            }
+            (samples, cached)
+        };
         
-        for i in 0..samples {
-            let t = i as f32 / sample_rate;
-            let amplitude = 0.1 * request.volume.unwrap_or(config.tts.volume);
-            let sample = amplitude * (2.0 * std::f32::consts::PI * frequency * t).sin();
-            audio_data.push(sample);
+        let (result, (samples, cached)) = tokio::join!(synthesis, forward);
+        log::info!("Streamed synthesis of '{}' ({} samples)", request.text, samples);
+        // A stopped or failed stream is incomplete and mustn't be replayed from the cache
+        let complete = result.is_ok() && self.speech_generation.load(Ordering::SeqCst) == generation;
+        if let (Some(key), Some(speech), true) = (cache_key, cached, complete) {
+            if !speech.audio.is_empty() {
+                let stored = try_get_config()
+                    .context("Configuration not initialized")
+                    .and_then(|config| tts_cache::store(key, &speech, config.tts.cache_size_mb));
+                if let Err(e) = stored {
+                    log::warn!("Failed to cache synthesized speech: {:#}", e);
+                }
+            }
         }
-        
-        Ok(audio_data)
+        result
     }
     
     pub fn get_synthesis_receiver(&self) -> broadcast::Receiver<SynthesisResult> {
         self.synthesis_sender.subscribe()
     }
     
-    pub fn is_synthesizing(&self) -> bool {
-        *self.is_synthesizing.lock().unwrap()
-    }
-    
-    pub fn stop_synthesis(&mut self) {
+    pub fn stop_synthesis(&self) {
         *self.is_synthesizing.lock().unwrap() = false;
         log::info!("Text-to-Speech synthesis stopped");
     }
 }
 
+/// Whether `word` closes a sentence, allowing for a closing quote or bracket after the punctuation.
+fn ends_sentence(word: &str) -> bool {
+    word.trim_end_matches(&['"', '\'', ')', '\u{201D}'][..]).ends_with(&['.', '!', '?'][..])
+}
+
 impl Drop for TextToSpeech {
     fn drop(&mut self) {
         self.stop_synthesis();
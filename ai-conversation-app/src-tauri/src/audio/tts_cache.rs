@@ -0,0 +1,156 @@
+use crate::audio::tts::{SynthesisRequest, WordTiming};
+use crate::audio::VisemeData;
+use crate::config::TtsConfig;
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Long replies rarely repeat word for word; caching them would only push out the short phrases that do
+const MAX_CACHED_CHARS: usize = 200;
+
+static CACHE_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Keep synthesized speech in `dir` (under the app cache directory). Without this, as in
+/// headless mode, nothing is cached.
+pub fn init_cache_dir(dir: PathBuf) {
+    if CACHE_DIR.set(dir).is_err() {
+        log::warn!("TTS cache directory already initialized");
+    }
+}
+
+/// Synthesized speech as it's stored: audio in a WAV file, timings in a JSON file beside it.
+pub struct CachedSpeech {
+    pub audio: Vec<f32>,
+    pub sample_rate: u32,
+    pub visemes: Vec<VisemeData>,
+    pub words: Vec<WordTiming>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Timings {
+    visemes: Vec<VisemeData>,
+    words: Vec<WordTiming>,
+}
+
+/// The cache entry for `request` as spoken by the engine in `config`, or None when it
+/// shouldn't be cached at all.
+pub fn key(config: &TtsConfig, request: &SynthesisRequest) -> Option<String> {
+    if CACHE_DIR.get().is_none() || config.cache_size_mb == 0 || request.text.chars().count() > MAX_CACHED_CHARS {
+        return None;
+    }
+    // Everything that changes the audio; the lexicon does for engines that take phonemes
+    let identity = serde_json::json!({
+        "provider": config.provider,
+        "model": config.model,
+        "voice": request.voice.as_deref().unwrap_or(&config.voice),
+        "text": request.text,
+        "ssml": request.ssml,
+        "visemes": request.generate_visemes,
+        "speed": request.speed,
+        "pitch": request.pitch,
+        "volume": request.volume,
+        "lexicon": config.lexicon.iter().map(|entry| (&entry.word, &entry.phonemes)).collect::<Vec<_>>(),
+    });
+    let hash = Sha256::digest(identity.to_string().as_bytes());
+    Some(hash.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn entry_paths(key: &str) -> Option<(PathBuf, PathBuf)> {
+    let dir = CACHE_DIR.get()?;
+    Some((dir.join(format!("{}.wav", key)), dir.join(format!("{}.json", key))))
+}
+
+/// Cached speech for `key`, marking it as recently used.
+pub fn load(key: &str) -> Option<CachedSpeech> {
+    let (audio_path, timings_path) = entry_paths(key)?;
+    if !audio_path.exists() {
+        return None;
+    }
+    match read_entry(&audio_path, &timings_path) {
+        Ok(speech) => {
+            // Eviction goes by modification time, so a hit keeps the entry around
+            if let Err(e) = File::options().append(true).open(&audio_path).and_then(|file| file.set_modified(SystemTime::now())) {
+                log::debug!("Failed to mark cached speech as used: {}", e);
+            }
+            Some(speech)
+        }
+        Err(e) => {
+            log::warn!("Discarding unreadable cached speech {}: {:#}", audio_path.display(), e);
+            let _ = fs::remove_file(&audio_path);
+            let _ = fs::remove_file(&timings_path);
+            None
+        }
+    }
+}
+
+fn read_entry(audio_path: &Path, timings_path: &Path) -> Result<CachedSpeech> {
+    let mut reader = hound::WavReader::open(audio_path).context("Failed to open cached audio")?;
+    let sample_rate = reader.spec().sample_rate;
+    let audio = reader.samples::<f32>().collect::<Result<Vec<_>, _>>().context("Failed to read cached audio")?;
+    let timings: Timings = serde_json::from_str(&fs::read_to_string(timings_path).context("Failed to read cached timings")?)
+        .context("Failed to parse cached timings")?;
+    Ok(CachedSpeech {
+        audio,
+        sample_rate,
+        visemes: timings.visemes,
+        words: timings.words,
+    })
+}
+
+/// Store speech under `key`, then evict the least recently used entries beyond `max_mb`.
+pub fn store(key: &str, speech: &CachedSpeech, max_mb: u64) -> Result<()> {
+    let Some((audio_path, timings_path)) = entry_paths(key) else {
+        return Ok(());
+    };
+    let dir = audio_path.parent().context("Cache entry has no directory")?;
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create TTS cache directory {}", dir.display()))?;
+
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: speech.sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(&audio_path, spec).context("Failed to create cached audio")?;
+    for sample in &speech.audio {
+        writer.write_sample(*sample)?;
+    }
+    writer.finalize().context("Failed to write cached audio")?;
+    let timings = Timings {
+        visemes: speech.visemes.clone(),
+        words: speech.words.clone(),
+    };
+    fs::write(&timings_path, serde_json::to_string(&timings)?).context("Failed to write cached timings")?;
+
+    evict(dir, max_mb * 1024 * 1024)
+}
+
+fn evict(dir: &Path, max_bytes: u64) -> Result<()> {
+    let mut entries = Vec::new();
+    let mut total = 0;
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("wav") {
+            continue;
+        }
+        let timings_path = path.with_extension("json");
+        let metadata = entry.metadata()?;
+        let size = metadata.len() + fs::metadata(&timings_path).map(|m| m.len()).unwrap_or(0);
+        total += size;
+        entries.push((metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), size, path, timings_path));
+    }
+    entries.sort_by_key(|(used, ..)| *used);
+    for (_, size, audio_path, timings_path) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        fs::remove_file(&audio_path)?;
+        let _ = fs::remove_file(&timings_path);
+        total -= size;
+    }
+    Ok(())
+}
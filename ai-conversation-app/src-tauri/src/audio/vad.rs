@@ -0,0 +1,128 @@
+use crate::config::SttConfig;
+use crate::meeting::resample_linear;
+use anyhow::{Context, Result};
+use ort::session::Session;
+use ort::value::Tensor;
+
+const SILERO_MODEL: &str = "silero_vad.onnx";
+const SILERO_SAMPLE_RATE: u32 = 16000;
+// Samples per inference at 16kHz, plus the tail of the previous window the model expects in front
+const SILERO_WINDOW: usize = 512;
+const SILERO_CONTEXT: usize = 64;
+// Recurrent state carried between windows, shaped [2, 1, 128]
+const SILERO_STATE: usize = 2 * 128;
+
+/// Decides whether a stretch of microphone audio contains speech.
+pub trait VoiceActivityDetector: Send {
+    /// `samples` are mono at `sample_rate`; frames of any length may be passed.
+    fn is_speech(&mut self, samples: &[f32], sample_rate: u32) -> bool;
+    /// Forget what was heard so far, e.g. between utterances.
+    fn reset(&mut self);
+}
+
+/// Pick the detector from config, falling back to the energy threshold if Silero can't load.
+pub fn create(config: &SttConfig) -> Box<dyn VoiceActivityDetector> {
+    if config.vad_enabled && config.vad_backend == "silero" {
+        match SileroVad::load(config.vad_threshold) {
+            Ok(vad) => return Box::new(vad),
+            Err(e) => log::warn!("Falling back to the energy VAD: {:#}", e),
+        }
+    }
+    Box::new(EnergyVad {
+        threshold: config.silence_threshold,
+    })
+}
+
+/// Speech is anything louder than a fixed RMS level. Cheap, but typing and other noise trips it.
+pub struct EnergyVad {
+    threshold: f32,
+}
+
+impl VoiceActivityDetector for EnergyVad {
+    fn is_speech(&mut self, samples: &[f32], _sample_rate: u32) -> bool {
+        if samples.is_empty() {
+            return false;
+        }
+        let sum_squares: f32 = samples.iter().map(|&x| x * x).sum();
+        (sum_squares / samples.len() as f32).sqrt() > self.threshold
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// The Silero neural VAD, run through ONNX Runtime on 32ms windows of 16kHz audio.
+pub struct SileroVad {
+    session: Session,
+    threshold: f32,
+    state: Vec<f32>,
+    context: Vec<f32>,
+    // Resampled audio not yet filling a whole window
+    pending: Vec<f32>,
+    speaking: bool,
+}
+
+impl SileroVad {
+    pub fn load(threshold: f32) -> Result<Self> {
+        let path = crate::models::resolve_asset(SILERO_MODEL)?;
+        let session = Session::builder()
+            .and_then(|builder| builder.with_intra_threads(1))
+            .and_then(|builder| builder.commit_from_file(&path))
+            .with_context(|| format!("Failed to load Silero VAD from {}", path.display()))?;
+        log::info!("Silero VAD loaded from {}", path.display());
+        Ok(Self {
+            session,
+            threshold,
+            state: vec![0.0; SILERO_STATE],
+            context: vec![0.0; SILERO_CONTEXT],
+            pending: Vec::new(),
+            speaking: false,
+        })
+    }
+
+    /// Speech probability for one window.
+    fn infer(&mut self, window: &[f32]) -> Result<f32> {
+        let mut input = Vec::with_capacity(SILERO_CONTEXT + SILERO_WINDOW);
+        input.extend_from_slice(&self.context);
+        input.extend_from_slice(window);
+        self.context.copy_from_slice(&window[window.len() - SILERO_CONTEXT..]);
+
+        let outputs = self.session.run(ort::inputs![
+            "input" => Tensor::from_array(([1usize, input.len()], input))?,
+            "state" => Tensor::from_array(([2usize, 1, 128], self.state.clone()))?,
+            "sr" => Tensor::from_array(([1usize], vec![SILERO_SAMPLE_RATE as i64]))?,
+        ]?)?;
+        let (_, state) = outputs["stateN"].try_extract_raw_tensor::<f32>()?;
+        self.state.copy_from_slice(state);
+        let (_, probability) = outputs["output"].try_extract_raw_tensor::<f32>()?;
+        probability.first().copied().context("Silero VAD returned no probability")
+    }
+}
+
+impl VoiceActivityDetector for SileroVad {
+    fn is_speech(&mut self, samples: &[f32], sample_rate: u32) -> bool {
+        self.pending.extend(resample_linear(samples, sample_rate, SILERO_SAMPLE_RATE));
+        while self.pending.len() >= SILERO_WINDOW {
+            let window: Vec<f32> = self.pending.drain(..SILERO_WINDOW).collect();
+            match self.infer(&window) {
+                Ok(probability) => {
+                    // Hysteresis so speech doesn't flicker off on short dips inside words
+                    self.speaking = if self.speaking {
+                        probability > self.threshold - 0.15
+                    } else {
+                        probability > self.threshold
+                    };
+                }
+                Err(e) => log::error!("Silero VAD inference failed: {:#}", e),
+            }
+        }
+        // Frames shorter than a window keep the last decision
+        self.speaking
+    }
+
+    fn reset(&mut self) {
+        self.state.fill(0.0);
+        self.context.fill(0.0);
+        self.pending.clear();
+        self.speaking = false;
+    }
+}
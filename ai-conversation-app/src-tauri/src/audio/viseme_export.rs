@@ -0,0 +1,122 @@
+use crate::audio::tts::{SynthesisResult, WordTiming};
+use crate::audio::VisemeData;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+/// Rhubarb Lip Sync's JSON output, which Blender, Unity and Spine importers read.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RhubarbExport {
+    metadata: RhubarbMetadata,
+    mouth_cues: Vec<MouthCue>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RhubarbMetadata {
+    sound_file: String,
+    duration: f64,
+}
+
+#[derive(Serialize)]
+struct MouthCue {
+    start: f64,
+    end: f64,
+    value: char,
+}
+
+/// Everything the pipeline knows about the speech, in its own terms.
+#[derive(Serialize)]
+struct RawExport<'a> {
+    text: &'a str,
+    sound_file: String,
+    duration: f64,
+    visemes: &'a [VisemeData],
+    words: &'a [WordTiming],
+}
+
+/// The Preston Blair mouth shape Rhubarb names A-H for a phoneme, X being the mouth at rest.
+fn rhubarb_shape(phoneme: &str) -> char {
+    match phoneme {
+        "p" | "b" | "m" => 'A',
+        "eh" | "ae" | "ey" | "ah" => 'C',
+        "aa" | "ay" | "aw" => 'D',
+        "ao" | "er" => 'E',
+        "uw" | "uh" | "ow" | "oy" | "w" => 'F',
+        "f" | "v" => 'G',
+        "l" => 'H',
+        "sil" => 'X',
+        // Most consonants and "ee" show slightly parted, clenched teeth
+        _ => 'B',
+    }
+}
+
+/// Contiguous cues from the start of the audio to its end, gaps filled with the rest shape.
+fn mouth_cues(visemes: &[VisemeData], duration: f64) -> Vec<MouthCue> {
+    let mut cues: Vec<MouthCue> = Vec::new();
+    let mut push = |start: f64, end: f64, value: char| {
+        // Rhubarb writes times to the centisecond
+        let (start, end) = ((start * 100.0).round() / 100.0, (end.min(duration) * 100.0).round() / 100.0);
+        if end <= start {
+            return;
+        }
+        match cues.last_mut() {
+            Some(last) if last.value == value => last.end = end,
+            _ => cues.push(MouthCue { start, end, value }),
+        }
+    };
+    let mut time = 0.0;
+    for viseme in visemes {
+        if viseme.timestamp > time {
+            push(time, viseme.timestamp, 'X');
+        }
+        let start = viseme.timestamp.max(time);
+        push(start, viseme.timestamp + viseme.duration, rhubarb_shape(&viseme.phoneme));
+        time = time.max(viseme.timestamp + viseme.duration);
+    }
+    push(time, duration, 'X');
+    cues
+}
+
+/// Write the speech to a WAV file beside `path` and its viseme timeline to `path`, as
+/// Rhubarb Lip Sync JSON ("rhubarb") or the pipeline's own visemes and word timings ("json").
+pub fn export(text: &str, speech: &SynthesisResult, path: &Path, format: &str) -> Result<()> {
+    if !matches!(format, "rhubarb" | "json") {
+        return Err(anyhow::anyhow!("Unknown viseme export format {}; use \"rhubarb\" or \"json\"", format));
+    }
+    let sound_path = path.with_extension("wav");
+    if sound_path == path {
+        return Err(anyhow::anyhow!("The timeline needs a .json path; the audio is written beside it as .wav"));
+    }
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: speech.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&sound_path, spec)
+        .with_context(|| format!("Failed to create {}", sound_path.display()))?;
+    for sample in &speech.audio_data {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize().context("Failed to write exported audio")?;
+
+    let sound_file = sound_path.to_string_lossy().into_owned();
+    let duration = speech.duration as f64;
+    let content = if format == "rhubarb" {
+        serde_json::to_string_pretty(&RhubarbExport {
+            metadata: RhubarbMetadata { sound_file, duration },
+            mouth_cues: mouth_cues(&speech.visemes, duration),
+        })?
+    } else {
+        serde_json::to_string_pretty(&RawExport {
+            text,
+            sound_file,
+            duration,
+            visemes: &speech.visemes,
+            words: &speech.words,
+        })?
+    };
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,215 @@
+use crate::audio::VisemeData;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Built-in phoneme-to-viseme tables, by the name `character.lip_sync.viseme_mapping` takes.
+pub const PRESETS: &[&str] = &["arkit", "oculus", "preston_blair"];
+
+// Each table lists a viseme with the ARPAbet phonemes (as g2p leaves them) that show it.
+// ARKit facial blend shapes, as Apple's face tracking and Ready Player Me avatars name them
+const ARKIT: &[(&str, &[&str])] = &[
+    ("jawOpen", &["sil", "aa", "ae", "ah", "ay", "eh", "ey", "g", "hh", "k", "ng"]),
+    ("mouthFunnel", &["ao", "aw", "er", "ow", "oy", "r", "uh", "uw", "w"]),
+    ("mouthClose", &["p", "b", "m"]),
+    ("mouthShrugUpper", &["ch", "jh", "s", "sh", "z", "zh"]),
+    ("tongueOut", &["d", "dh", "l", "n", "t", "th"]),
+    ("mouthLowerDownRight", &["f", "v"]),
+    ("mouthSmileLeft", &["ih", "iy", "y"]),
+];
+
+// The 15 Oculus OVR Lip Sync visemes, also used by VRChat avatars
+const OCULUS: &[(&str, &[&str])] = &[
+    ("sil", &["sil"]),
+    ("PP", &["p", "b", "m"]),
+    ("FF", &["f", "v"]),
+    ("TH", &["th", "dh"]),
+    ("DD", &["t", "d"]),
+    ("kk", &["k", "g", "ng", "hh"]),
+    ("CH", &["ch", "jh", "sh", "zh"]),
+    ("SS", &["s", "z"]),
+    ("nn", &["n", "l"]),
+    ("RR", &["r", "er"]),
+    ("aa", &["aa", "ae", "ah", "ay", "aw"]),
+    ("E", &["eh", "ey"]),
+    ("I", &["ih", "iy", "y"]),
+    ("O", &["ao", "ow", "oy"]),
+    ("U", &["uh", "uw", "w"]),
+];
+
+// The classic Preston Blair mouth chart of 2D animation
+const PRESTON_BLAIR: &[(&str, &[&str])] = &[
+    ("rest", &["sil"]),
+    ("MBP", &["p", "b", "m"]),
+    ("FV", &["f", "v"]),
+    ("L", &["l", "th", "dh"]),
+    ("WQ", &["w", "uw"]),
+    ("O", &["ao", "ow", "oy"]),
+    ("U", &["uh", "er"]),
+    ("AI", &["aa", "ae", "ah", "ay", "aw", "ih"]),
+    ("E", &["eh", "ey", "iy", "y"]),
+    ("etc", &["t", "d", "k", "g", "ng", "hh", "n", "r", "s", "z", "ch", "jh", "sh", "zh"]),
+];
+
+// VRM's five vowel shapes, which VMC protocol receivers animate the mouth with whatever the
+// avatar's own mapping; closed-lip and other consonants aren't listed and close the mouth
+const VRM: &[(&str, &[&str])] = &[
+    ("A", &["aa", "ae", "ah", "ay", "aw", "hh"]),
+    ("I", &["ih", "iy", "y", "ch", "jh", "sh", "zh", "s", "z"]),
+    ("U", &["uh", "uw", "w", "r", "er"]),
+    ("E", &["eh", "ey", "t", "d", "n", "l", "k", "g", "ng", "th", "dh"]),
+    ("O", &["ao", "ow", "oy"]),
+];
+
+/// VRM's mouth shapes, as VMC protocol receivers name them.
+pub const VRM_MOUTH_SHAPES: &[&str] = &["A", "I", "U", "E", "O"];
+
+/// The VRM mouth shape that shows `phoneme`, or None for a closed mouth.
+pub fn vrm_mouth_shape(phoneme: &str) -> Option<&'static str> {
+    VRM.iter()
+        .find(|(_, phonemes)| phonemes.contains(&phoneme))
+        .map(|(shape, _)| *shape)
+}
+
+/// The phoneme-to-viseme table named by `mapping`: a preset, or the path of a YAML file
+/// mapping ARPAbet phonemes (as in the lexicon) to the avatar's own viseme names.
+pub fn load_mapping(mapping: &str) -> Result<HashMap<String, String>> {
+    let preset = match mapping.trim().to_lowercase().as_str() {
+        "arkit" => Some(ARKIT),
+        "oculus" | "ovr" => Some(OCULUS),
+        "preston_blair" | "preston-blair" => Some(PRESTON_BLAIR),
+        _ => None,
+    };
+    if let Some(preset) = preset {
+        return Ok(preset
+            .iter()
+            .flat_map(|(viseme, phonemes)| phonemes.iter().map(|phoneme| (phoneme.to_string(), viseme.to_string())))
+            .collect());
+    }
+
+    let path = Path::new(mapping.trim());
+    if !path.is_file() {
+        return Err(anyhow::anyhow!(
+            "Unknown viseme mapping {}; use one of {} or the path of a YAML mapping file",
+            mapping,
+            PRESETS.join(", ")
+        ));
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read viseme mapping {}", path.display()))?;
+    let table: HashMap<String, String> = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse viseme mapping {}", path.display()))?;
+    if table.is_empty() {
+        return Err(anyhow::anyhow!("Viseme mapping {} maps no phonemes", path.display()));
+    }
+    Ok(table
+        .into_iter()
+        .map(|(phoneme, viseme)| (phoneme.trim().to_lowercase(), viseme))
+        .collect())
+}
+
+/// Open the mouth as far as the speech is loud: each viseme's intensity becomes the RMS of its
+/// stretch of `audio` relative to the utterance's loud visemes, eased into its predecessor's by
+/// `smoothing` (0 follows the audio exactly) so the jaw doesn't chatter.
+pub fn set_intensity(visemes: &mut [VisemeData], audio: &[f32], sample_rate: u32, smoothing: f32) {
+    let rate = sample_rate as f64;
+    let levels: Vec<f32> = visemes
+        .iter()
+        .map(|viseme| {
+            let start = ((viseme.timestamp * rate) as usize).min(audio.len());
+            let end = (((viseme.timestamp + viseme.duration) * rate).ceil() as usize).clamp(start, audio.len());
+            let segment = &audio[start..end];
+            if segment.is_empty() {
+                return 0.0;
+            }
+            (segment.iter().map(|s| s * s).sum::<f32>() / segment.len() as f32).sqrt()
+        })
+        .collect();
+
+    let mut spoken: Vec<f32> = visemes
+        .iter()
+        .zip(&levels)
+        .filter(|(viseme, _)| viseme.phoneme != "sil")
+        .map(|(_, level)| *level)
+        .collect();
+    spoken.sort_by(|a, b| a.total_cmp(b));
+    // The 90th percentile rather than the loudest, so one shout doesn't leave the rest mumbled
+    let reference = spoken.get(spoken.len() * 9 / 10).copied().unwrap_or(0.0);
+    if reference <= f32::EPSILON {
+        return;
+    }
+
+    let smoothing = smoothing.clamp(0.0, 0.95);
+    let mut previous: Option<f32> = None;
+    for (viseme, level) in visemes.iter_mut().zip(levels) {
+        let target = (level / reference).min(1.0);
+        let intensity = previous.map_or(target, |previous| previous * smoothing + target * (1.0 - smoothing));
+        viseme.intensity = intensity;
+        previous = Some(intensity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viseme(phoneme: &str, timestamp: f64, duration: f64) -> VisemeData {
+        VisemeData {
+            phoneme: phoneme.to_string(),
+            viseme: String::new(),
+            timestamp,
+            duration,
+            intensity: 0.5,
+        }
+    }
+
+    #[test]
+    fn presets_map_every_phoneme() {
+        let arkit = load_mapping("arkit").unwrap();
+        assert!(arkit.keys().all(|phoneme| phoneme == "sil" || crate::audio::g2p::is_valid_pronunciation(phoneme)));
+        for preset in PRESETS {
+            let table = load_mapping(preset).unwrap();
+            let mut phonemes: Vec<_> = table.keys().collect();
+            let mut expected: Vec<_> = arkit.keys().collect();
+            phonemes.sort();
+            expected.sort();
+            assert_eq!(phonemes, expected, "{}", preset);
+        }
+        assert_eq!(load_mapping(" OVR ").unwrap()["p"], "PP");
+        assert_eq!(load_mapping("preston-blair").unwrap()["uw"], "WQ");
+    }
+
+    #[test]
+    fn unknown_mappings_are_an_error() {
+        assert!(load_mapping("no_such_preset").is_err());
+    }
+
+    #[test]
+    fn closed_lip_sounds_have_no_vrm_shape() {
+        assert_eq!(vrm_mouth_shape("aa"), Some("A"));
+        assert_eq!(vrm_mouth_shape("ow"), Some("O"));
+        assert_eq!(vrm_mouth_shape("m"), None);
+        assert_eq!(vrm_mouth_shape("sil"), None);
+        assert!(VRM.iter().all(|(shape, _)| VRM_MOUTH_SHAPES.contains(shape)));
+    }
+
+    #[test]
+    fn intensity_follows_loudness() {
+        // One second at half volume, then one at a quarter, sampled at 10 Hz
+        let audio: Vec<f32> = (0..20).map(|i| if i < 10 { 0.5 } else { 0.25 }).collect();
+        let mut visemes = vec![viseme("aa", 0.0, 1.0), viseme("m", 1.0, 1.0)];
+        set_intensity(&mut visemes, &audio, 10, 0.0);
+        assert!((visemes[0].intensity - 1.0).abs() < 1e-6);
+        assert!((visemes[1].intensity - 0.5).abs() < 1e-6);
+
+        set_intensity(&mut visemes, &audio, 10, 0.5);
+        assert!((visemes[1].intensity - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn silence_leaves_intensity_alone() {
+        let mut visemes = vec![viseme("aa", 0.0, 1.0), viseme("sil", 1.0, 1.0)];
+        set_intensity(&mut visemes, &[0.0; 20], 10, 0.0);
+        assert!(visemes.iter().all(|viseme| viseme.intensity == 0.5));
+    }
+}
@@ -0,0 +1,153 @@
+pub mod vmc;
+
+use crate::audio::VisemeData;
+use crate::character::gaze::LookAt;
+use crate::character::idle::{Blink, IdleAnimation};
+use crate::character::CharacterState;
+use anyhow::{Context, Result};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+// Frames buffered per client; one that falls further behind skips ahead rather than lag
+const CLIENT_BUFFER: usize = 256;
+
+/// What external renderers receive, one JSON text message per frame, tagged by "type".
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AvatarFrame {
+    /// A viseme becoming visible, with the blendshape weights that show it.
+    Viseme {
+        phoneme: String,
+        viseme: String,
+        intensity: f32,
+        duration_ms: u64,
+        blendshapes: HashMap<String, f32>,
+    },
+    State {
+        state: CharacterState,
+    },
+    Emotion {
+        emotion: String,
+    },
+    Blink(Blink),
+    MicroExpression(IdleAnimation),
+    IdleGesture(IdleAnimation),
+    LookAt(LookAt),
+    /// What the user said, or what the assistant is replying; `is_final` is false for
+    /// pieces of a reply still being written.
+    Transcript {
+        role: &'static str,
+        text: String,
+        is_final: bool,
+    },
+}
+
+impl AvatarFrame {
+    pub fn viseme(viseme: &VisemeData) -> Self {
+        AvatarFrame::Viseme {
+            phoneme: viseme.phoneme.clone(),
+            viseme: viseme.viseme.clone(),
+            intensity: viseme.intensity,
+            duration_ms: (viseme.duration * 1000.0) as u64,
+            blendshapes: HashMap::from([(viseme.viseme.clone(), viseme.intensity)]),
+        }
+    }
+}
+
+/// A WebSocket server on localhost that streams avatar frames to every connected client,
+/// for renderers such as VSeeFace, Unity or Godot outside the webview. Stops when dropped.
+pub struct AvatarServer {
+    frames: broadcast::Sender<String>,
+    accept: JoinHandle<()>,
+    port: u16,
+}
+
+impl AvatarServer {
+    /// Listen on 127.0.0.1:`port`; only local programs can connect.
+    pub async fn start(port: u16) -> Result<Self> {
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let listener = TcpListener::bind(address)
+            .await
+            .with_context(|| format!("Failed to listen on {}", address))?;
+        let (frames, _) = broadcast::channel(CLIENT_BUFFER);
+        let accept_frames = frames.clone();
+        let accept = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let frames = accept_frames.subscribe();
+                        tokio::spawn(async move {
+                            if let Err(e) = serve_client(stream, frames).await {
+                                log::debug!("Avatar client {} disconnected: {:#}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!("Failed to accept avatar client: {}", e),
+                }
+            }
+        });
+        log::info!("Avatar server listening on ws://{}", address);
+        Ok(Self { frames, accept, port })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Stop accepting clients and wait until the port is free to listen on again.
+    pub async fn stop(mut self) {
+        self.accept.abort();
+        let _ = (&mut self.accept).await;
+    }
+
+    /// Send `frame` to every connected client; nothing happens when none are.
+    pub fn send(&self, frame: &AvatarFrame) {
+        if self.frames.receiver_count() == 0 {
+            return;
+        }
+        match serde_json::to_string(frame) {
+            Ok(json) => {
+                let _ = self.frames.send(json);
+            }
+            Err(e) => log::warn!("Failed to serialize avatar frame: {}", e),
+        }
+    }
+}
+
+impl Drop for AvatarServer {
+    fn drop(&mut self) {
+        // Clients end once the sender is gone and their last frame is out
+        self.accept.abort();
+    }
+}
+
+async fn serve_client(stream: TcpStream, mut frames: broadcast::Receiver<String>) -> Result<()> {
+    let socket = tokio_tungstenite::accept_async(stream).await.context("WebSocket handshake failed")?;
+    let (mut outgoing, mut incoming) = socket.split();
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(json) => outgoing.send(Message::Text(json)).await?,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::debug!("Avatar client fell behind by {} frames", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    let _ = outgoing.send(Message::Close(None)).await;
+                    return Ok(());
+                }
+            },
+            // Clients only listen; reading keeps pings answered and notices them leave
+            message = incoming.next() => match message {
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+            },
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use super::AvatarFrame;
+use crate::audio::visemes;
+use crate::character::CharacterState;
+use anyhow::{Context, Result};
+use std::net::UdpSocket;
+
+// VMC protocol addresses: set a blendshape's value, then apply everything set since the last apply
+const BLEND_VALUE: &str = "/VMC/Ext/Blend/Val";
+const BLEND_APPLY: &str = "/VMC/Ext/Blend/Apply";
+
+// The avatar's emotions as VRM expression presets; "confused" has no preset, so it goes out
+// under its own name for avatars that define it
+const EMOTION_SHAPES: &[(&str, &str)] = &[
+    ("happy", "Joy"),
+    ("sad", "Sorrow"),
+    ("angry", "Angry"),
+    ("surprised", "Surprised"),
+    ("confused", "Confused"),
+];
+
+// Eye angle at which a VRM Look* preset is fully on
+const LOOK_FULL_DEGREES: f32 = 25.0;
+
+/// Sends the avatar's lip sync, emotions and eye direction as VMC protocol blendshapes over
+/// OSC/UDP, so VTube Studio, VSeeFace and other VMC receivers can animate a model from them.
+pub struct VmcSender {
+    socket: UdpSocket,
+}
+
+impl VmcSender {
+    /// Send to the VMC receiver listening at `host`:`port`.
+    pub fn connect(host: &str, port: u16) -> Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0)).context("Failed to open a UDP socket")?;
+        socket
+            .connect((host, port))
+            .with_context(|| format!("Failed to resolve VMC receiver {}:{}", host, port))?;
+        // Frames are sent from event handlers, which mustn't wait on the network
+        socket.set_nonblocking(true)?;
+        log::info!("Sending VMC blendshapes to {}:{}", host, port);
+        Ok(Self { socket })
+    }
+
+    /// Send whatever part of `frame` a VMC receiver can show; the rest is ignored.
+    pub fn send(&self, frame: &AvatarFrame) {
+        let shapes: Vec<(&str, f32)> = match frame {
+            AvatarFrame::Viseme { phoneme, intensity, .. } => {
+                let open = visemes::vrm_mouth_shape(phoneme);
+                visemes::VRM_MOUTH_SHAPES
+                    .iter()
+                    .map(|shape| (*shape, if open == Some(*shape) { *intensity } else { 0.0 }))
+                    .collect()
+            }
+            // Close the mouth once speech stops, however the last viseme left it
+            AvatarFrame::State { state } if *state != CharacterState::Talking => {
+                visemes::VRM_MOUTH_SHAPES.iter().map(|shape| (*shape, 0.0)).collect()
+            }
+            AvatarFrame::Emotion { emotion } => EMOTION_SHAPES
+                .iter()
+                .map(|(name, shape)| (*shape, if name == emotion { 1.0 } else { 0.0 }))
+                .collect(),
+            // Receivers turn the head from their own tracking, so only the eyes go out
+            AvatarFrame::LookAt(look) => {
+                let yaw = (look.eye_yaw / LOOK_FULL_DEGREES).clamp(-1.0, 1.0);
+                let pitch = (look.eye_pitch / LOOK_FULL_DEGREES).clamp(-1.0, 1.0);
+                vec![
+                    ("LookLeft", (-yaw).max(0.0)),
+                    ("LookRight", yaw.max(0.0)),
+                    ("LookUp", pitch.max(0.0)),
+                    ("LookDown", (-pitch).max(0.0)),
+                ]
+            }
+            _ => return,
+        };
+
+        let mut packets: Vec<Vec<u8>> = shapes
+            .into_iter()
+            .map(|(shape, value)| osc_message(BLEND_VALUE, &[OscArg::Str(shape), OscArg::Float(value)]))
+            .collect();
+        packets.push(osc_message(BLEND_APPLY, &[]));
+        for packet in packets {
+            // Nobody listening is normal: the receiver may not be running yet
+            if let Err(e) = self.socket.send(&packet) {
+                log::debug!("Failed to send VMC message: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+enum OscArg<'a> {
+    Str(&'a str),
+    Float(f32),
+}
+
+/// One OSC 1.0 message: the address, a type tag string and the arguments, each string
+/// null-terminated and padded to four bytes, numbers big-endian.
+fn osc_message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut message = Vec::new();
+    push_osc_string(&mut message, address);
+    let tags: String = std::iter::once(',')
+        .chain(args.iter().map(|arg| match arg {
+            OscArg::Str(_) => 's',
+            OscArg::Float(_) => 'f',
+        }))
+        .collect();
+    push_osc_string(&mut message, &tags);
+    for arg in args {
+        match arg {
+            OscArg::Str(text) => push_osc_string(&mut message, text),
+            OscArg::Float(value) => message.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+    message
+}
+
+fn push_osc_string(message: &mut Vec<u8>, text: &str) {
+    message.extend_from_slice(text.as_bytes());
+    message.push(0);
+    while !message.len().is_multiple_of(4) {
+        message.push(0);
+    }
+}
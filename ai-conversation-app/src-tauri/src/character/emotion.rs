@@ -0,0 +1,134 @@
+use crate::config::LlmConfig;
+use crate::llm::{self, ChatMessage};
+use anyhow::{Context, Result};
+
+/// Expressions the avatar can show, as change_character_emotion takes them.
+pub const EMOTIONS: &[&str] = &["neutral", "happy", "sad", "angry", "surprised", "confused"];
+
+// Replies longer than this are classified from their start, where the tone is usually set
+const MAX_CLASSIFIED_CHARS: usize = 1500;
+// Below this score the reply reads as neutral
+const MIN_SCORE: f32 = 1.0;
+
+// Words and phrases that suggest an emotion, with how strongly
+const CUES: &[(&str, &str, f32)] = &[
+    ("glad", "happy", 1.0),
+    ("happy", "happy", 1.0),
+    ("great", "happy", 0.6),
+    ("wonderful", "happy", 1.0),
+    ("awesome", "happy", 1.0),
+    ("love", "happy", 0.8),
+    ("congratulations", "happy", 1.5),
+    ("congrats", "happy", 1.5),
+    ("fantastic", "happy", 1.0),
+    ("delighted", "happy", 1.2),
+    ("enjoy", "happy", 0.6),
+    ("haha", "happy", 1.0),
+    ("yay", "happy", 1.2),
+    ("well done", "happy", 1.0),
+    ("sorry to hear", "sad", 1.5),
+    ("sad", "sad", 1.0),
+    ("unfortunately", "sad", 0.8),
+    ("condolences", "sad", 1.5),
+    ("passed away", "sad", 1.5),
+    ("hard time", "sad", 1.0),
+    ("heartbreaking", "sad", 1.5),
+    ("lonely", "sad", 1.0),
+    ("miss you", "sad", 1.0),
+    ("angry", "angry", 1.0),
+    ("outrageous", "angry", 1.2),
+    ("unacceptable", "angry", 1.2),
+    ("furious", "angry", 1.5),
+    ("ridiculous", "angry", 0.8),
+    ("how dare", "angry", 1.5),
+    ("wow", "surprised", 1.2),
+    ("whoa", "surprised", 1.2),
+    ("amazing", "surprised", 0.8),
+    ("incredible", "surprised", 1.0),
+    ("unbelievable", "surprised", 1.0),
+    ("surprising", "surprised", 1.0),
+    ("no way", "surprised", 1.0),
+    ("astonishing", "surprised", 1.2),
+    ("not sure", "confused", 1.0),
+    ("unclear", "confused", 1.0),
+    ("confusing", "confused", 1.0),
+    ("hmm", "confused", 1.0),
+    ("i don't know", "confused", 1.2),
+    ("could you clarify", "confused", 1.2),
+    ("what do you mean", "confused", 1.2),
+    ("did you mean", "confused", 0.8),
+];
+
+// Words that turn the cue right after them around, e.g. "not happy"
+const NEGATIONS: &[&str] = &["not", "no", "never", "isn't", "wasn't", "don't", "didn't"];
+
+/// The emotion a reply reads as, from the words and punctuation in it. Cheap enough to run
+/// on every reply.
+pub fn infer_from_text(reply: &str) -> &'static str {
+    let text = reply.chars().take(MAX_CLASSIFIED_CHARS).collect::<String>().to_lowercase();
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\''))
+        .collect();
+    // Phrases are matched against the words rejoined, so punctuation doesn't hide them
+    let joined = format!(" {} ", words.join(" "));
+
+    let mut scores: Vec<(&'static str, f32)> = EMOTIONS.iter().map(|emotion| (*emotion, 0.0)).collect();
+    for (cue, emotion, weight) in CUES {
+        let hits = if cue.contains(' ') {
+            joined.matches(&format!(" {} ", cue)).count()
+        } else {
+            words
+                .iter()
+                .enumerate()
+                .filter(|(index, word)| *word == cue && !(*index > 0 && NEGATIONS.contains(&words[index - 1])))
+                .count()
+        };
+        if let Some((_, score)) = scores.iter_mut().find(|(name, _)| name == emotion) {
+            *score += hits as f32 * weight;
+        }
+    }
+
+    // Exclamations make whatever the reply feels stronger
+    let emphasis = 1.0 + 0.2 * text.matches('!').count().min(3) as f32;
+    scores
+        .into_iter()
+        .map(|(emotion, score)| (emotion, score * emphasis))
+        .filter(|(_, score)| *score >= MIN_SCORE)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or("neutral", |(emotion, _)| emotion)
+}
+
+/// What the avatar shows back to a user whose face reads as `expression`: sympathy for sadness
+/// and fear, calm for anger, and otherwise the user's own mood. None leaves the avatar as it is.
+pub fn empathize(expression: &str) -> Option<&'static str> {
+    match expression {
+        "happy" => Some("happy"),
+        "surprised" => Some("surprised"),
+        "sad" | "fearful" => Some("sad"),
+        "angry" | "disgusted" | "contemptuous" => Some("neutral"),
+        _ => None,
+    }
+}
+
+/// The emotion a reply reads as, judged by the LLM. Slower and costs a request, but reads
+/// tone the word lists miss.
+pub async fn infer_with_llm(config: &LlmConfig, reply: &str) -> Result<&'static str> {
+    let reply: String = reply.chars().take(MAX_CLASSIFIED_CHARS).collect();
+    let messages = vec![
+        ChatMessage::system(format!(
+            "You read the tone of an assistant's reply for its animated avatar. Reply with only one \
+             of these words: {}.",
+            EMOTIONS.join(", ")
+        )),
+        ChatMessage::user(reply),
+    ];
+    let answer = llm::chat_completion(config, &messages, Some(0.0)).await?.to_lowercase();
+    // Models sometimes answer in a sentence; take the first emotion named
+    EMOTIONS
+        .iter()
+        .filter_map(|emotion| answer.find(emotion).map(|position| (position, *emotion)))
+        .min()
+        .map(|(_, emotion)| emotion)
+        .context("Emotion reply named no known emotion")
+}
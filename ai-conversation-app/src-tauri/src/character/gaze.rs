@@ -0,0 +1,48 @@
+use crate::vision::head_pose::Gaze;
+use serde::Serialize;
+
+// A typical webcam's field of view, to turn where the user is in the frame into an angle
+const CAMERA_FOV_DEGREES: (f32, f32) = (60.0, 40.0);
+// The eyes make up most of the turn; the head follows with the rest
+const HEAD_SHARE: f32 = 0.3;
+// Eyes don't turn further than this; the head makes up the difference
+const MAX_EYE_DEGREES: (f32, f32) = (25.0, 15.0);
+// A user looking away gets a glance the same way, this much of their turn
+const GLANCE_SHARE: f32 = 0.2;
+
+/// Where the avatar looks, in degrees: yaw turns toward the avatar's right, pitch up.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct LookAt {
+    pub head_yaw: f32,
+    pub head_pitch: f32,
+    pub eye_yaw: f32,
+    pub eye_pitch: f32,
+    // Whether the user is looking back, so the avatar is making eye contact
+    pub eye_contact: bool,
+}
+
+/// Where the avatar looks to keep eye contact with the user at `gaze`, or straight ahead
+/// once they're gone.
+pub fn look_at(gaze: Option<&Gaze>) -> LookAt {
+    let Some(gaze) = gaze else {
+        return LookAt::default();
+    };
+    // The camera faces the way the avatar does, so the frame's right is the avatar's right
+    let mut yaw = gaze.x * CAMERA_FOV_DEGREES.0 / 2.0;
+    let mut pitch = gaze.y * CAMERA_FOV_DEGREES.1 / 2.0;
+    if let Some(head) = gaze.head.filter(|_| !gaze.facing) {
+        // Hopenet's yaw is positive with the user turned to their left, the avatar's right
+        yaw += head.yaw * GLANCE_SHARE;
+        pitch += head.pitch * GLANCE_SHARE;
+    }
+
+    let eye_yaw = (yaw * (1.0 - HEAD_SHARE)).clamp(-MAX_EYE_DEGREES.0, MAX_EYE_DEGREES.0);
+    let eye_pitch = (pitch * (1.0 - HEAD_SHARE)).clamp(-MAX_EYE_DEGREES.1, MAX_EYE_DEGREES.1);
+    LookAt {
+        head_yaw: yaw - eye_yaw,
+        head_pitch: pitch - eye_pitch,
+        eye_yaw,
+        eye_pitch,
+        eye_contact: gaze.facing,
+    }
+}
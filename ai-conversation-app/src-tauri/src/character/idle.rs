@@ -0,0 +1,133 @@
+use super::CharacterState;
+use crate::config::CharacterConfig;
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Blinks closer together than this look like a twitch, whatever blink_rate says
+const MIN_BLINK_INTERVAL: f32 = 1.0;
+const BLINK_MS: u64 = 150;
+// Chance that a blink comes as a quick pair, as people often do
+const DOUBLE_BLINK_CHANCE: f32 = 0.15;
+// Seconds between micro-expressions and between idle gestures, before jitter
+const MICRO_EXPRESSION_INTERVAL: (f32, f32) = (6.0, 15.0);
+const IDLE_GESTURE_INTERVAL: (f32, f32) = (10.0, 25.0);
+
+const MICRO_EXPRESSIONS: &[&str] = &["brow_raise", "half_smile", "lip_press", "squint", "brow_furrow"];
+const IDLE_GESTURES: &[&str] = &["look_around", "head_tilt", "shift_weight", "glance_down", "breath"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Blink {
+    pub duration_ms: u64,
+    pub double: bool,
+}
+
+/// A brief expression or movement to play over whatever the avatar is doing.
+#[derive(Debug, Clone, Serialize)]
+pub struct IdleAnimation {
+    pub name: &'static str,
+    pub intensity: f32,
+    pub duration_ms: u64,
+}
+
+pub enum IdleEvent {
+    Blink(Blink),
+    MicroExpression(IdleAnimation),
+    IdleGesture(IdleAnimation),
+}
+
+/// Decides when the avatar blinks, twitches and fidgets. Intervals are jittered so nothing
+/// repeats like clockwork; micro-expressions wait while the avatar talks and gestures only
+/// happen while it's idle, so they never fight the lip sync or a listening pose.
+pub struct IdleScheduler {
+    // xorshift state; the timing only needs to look irregular
+    seed: u64,
+    next_blink: Instant,
+    next_micro_expression: Instant,
+    next_gesture: Instant,
+}
+
+impl Default for IdleScheduler {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0x2545_f491_4f6c_dd1d)
+            | 1;
+        let now = Instant::now();
+        let mut scheduler = Self {
+            seed,
+            next_blink: now,
+            next_micro_expression: now,
+            next_gesture: now,
+        };
+        scheduler.next_blink = now + scheduler.jitter(3.0, 0.4);
+        scheduler.next_micro_expression = now + scheduler.between(MICRO_EXPRESSION_INTERVAL);
+        scheduler.next_gesture = now + scheduler.between(IDLE_GESTURE_INTERVAL);
+        scheduler
+    }
+}
+
+impl IdleScheduler {
+    /// When the next event is due.
+    pub fn next_due(&self) -> Instant {
+        self.next_blink.min(self.next_micro_expression).min(self.next_gesture)
+    }
+
+    /// The events due at `now`, each rescheduled after it's returned.
+    pub fn due(&mut self, now: Instant, state: CharacterState, config: &CharacterConfig) -> Vec<IdleEvent> {
+        let mut events = Vec::new();
+        let expressions = &config.facial_expressions;
+
+        if now >= self.next_blink {
+            let interval = expressions.blink_rate.max(MIN_BLINK_INTERVAL);
+            self.next_blink = now + self.jitter(interval, 0.4);
+            if config.enabled && expressions.enabled {
+                let double = self.unit() < DOUBLE_BLINK_CHANCE;
+                events.push(IdleEvent::Blink(Blink { duration_ms: BLINK_MS, double }));
+            }
+        }
+
+        if now >= self.next_micro_expression {
+            self.next_micro_expression = now + self.between(MICRO_EXPRESSION_INTERVAL);
+            if config.enabled && expressions.enabled && state != CharacterState::Talking {
+                let name = self.pick(MICRO_EXPRESSIONS);
+                let intensity = 0.2 + 0.3 * self.unit();
+                let duration_ms = 400 + (self.unit() * 800.0) as u64;
+                events.push(IdleEvent::MicroExpression(IdleAnimation { name, intensity, duration_ms }));
+            }
+        }
+
+        if now >= self.next_gesture {
+            self.next_gesture = now + self.between(IDLE_GESTURE_INTERVAL);
+            if config.enabled && state == CharacterState::Idle {
+                let name = self.pick(IDLE_GESTURES);
+                let intensity = 0.4 + 0.6 * self.unit();
+                let duration_ms = 1500 + (self.unit() * 2500.0) as u64;
+                events.push(IdleEvent::IdleGesture(IdleAnimation { name, intensity, duration_ms }));
+            }
+        }
+        events
+    }
+
+    // Uniform in [0, 1)
+    fn unit(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// `seconds`, give or take `spread` of it.
+    fn jitter(&mut self, seconds: f32, spread: f32) -> Duration {
+        let factor = 1.0 + spread * (2.0 * self.unit() - 1.0);
+        Duration::from_secs_f32(seconds * factor)
+    }
+
+    fn between(&mut self, (low, high): (f32, f32)) -> Duration {
+        Duration::from_secs_f32(low + (high - low) * self.unit())
+    }
+
+    fn pick(&mut self, names: &[&'static str]) -> &'static str {
+        names[((self.unit() * names.len() as f32) as usize).min(names.len() - 1)]
+    }
+}
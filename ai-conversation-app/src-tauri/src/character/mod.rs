@@ -0,0 +1,102 @@
+pub mod emotion;
+pub mod gaze;
+pub mod idle;
+pub mod profiles;
+
+use crate::audio::processor::ProcessingMode;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// How long the avatar shows it was cut off before going back to what the pipeline is doing.
+pub const INTERRUPTED_HOLD: Duration = Duration::from_millis(800);
+
+/// What the avatar is animating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharacterState {
+    Idle,
+    Listening,
+    Thinking,
+    Talking,
+    Interrupted,
+}
+
+/// Something that happened which may change what the avatar should be doing.
+#[derive(Debug, Clone, Copy)]
+pub enum CharacterInput {
+    Mode(ProcessingMode),
+    // An LLM request went out, or finished with a reply or an error
+    ThinkingStarted,
+    ThinkingFinished,
+    // The user talked over the reply or it was stopped
+    Interrupted,
+    // Time has passed; ends an interruption that has been shown for long enough
+    Settle,
+}
+
+/// Sent as "character-state" when the state changes.
+#[derive(Debug, Clone, Serialize)]
+pub struct CharacterStateChange {
+    pub state: CharacterState,
+    pub previous: CharacterState,
+}
+
+/// The avatar's state, derived from the audio pipeline's mode and the LLM requests in flight.
+/// Talking wins over thinking, which wins over listening; an interruption is shown briefly
+/// over all of them.
+pub struct CharacterStateMachine {
+    state: CharacterState,
+    mode: ProcessingMode,
+    // Several turns may be in flight with turn_queue.concurrency above one
+    thinking: usize,
+    interrupted_at: Option<Instant>,
+}
+
+impl Default for CharacterStateMachine {
+    fn default() -> Self {
+        Self {
+            state: CharacterState::Idle,
+            mode: ProcessingMode::Idle,
+            thinking: 0,
+            interrupted_at: None,
+        }
+    }
+}
+
+impl CharacterStateMachine {
+    pub fn state(&self) -> CharacterState {
+        self.state
+    }
+
+    /// Apply `input`, returning the change if the state changed.
+    pub fn handle(&mut self, input: CharacterInput) -> Option<CharacterStateChange> {
+        match input {
+            CharacterInput::Mode(mode) => self.mode = mode,
+            CharacterInput::ThinkingStarted => self.thinking += 1,
+            CharacterInput::ThinkingFinished => self.thinking = self.thinking.saturating_sub(1),
+            CharacterInput::Interrupted => self.interrupted_at = Some(Instant::now()),
+            CharacterInput::Settle => {
+                if self.interrupted_at.is_some_and(|at| at.elapsed() >= INTERRUPTED_HOLD) {
+                    self.interrupted_at = None;
+                }
+            }
+        }
+
+        let previous = self.state;
+        self.state = if self.interrupted_at.is_some() {
+            CharacterState::Interrupted
+        } else if self.mode == ProcessingMode::Speaking {
+            CharacterState::Talking
+        } else if self.thinking > 0 {
+            CharacterState::Thinking
+        } else if self.mode == ProcessingMode::Listening {
+            CharacterState::Listening
+        } else {
+            CharacterState::Idle
+        };
+        (self.state != previous).then_some(CharacterStateChange {
+            state: self.state,
+            previous,
+        })
+    }
+}
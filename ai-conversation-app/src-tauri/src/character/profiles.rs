@@ -0,0 +1,229 @@
+use crate::audio::{g2p, tts, visemes};
+use crate::config::{AnimationConfig, Pronunciation};
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static PROFILES_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+// A .aichar package is a JSON document with this format name and at most this version
+const PACKAGE_FORMAT: &str = "aichar";
+const PACKAGE_VERSION: u32 = 1;
+// Packages come from other people; anything bigger than this isn't a character
+const MAX_PACKAGE_BYTES: u64 = 1024 * 1024;
+const MAX_PROMPT_CHARS: usize = 20_000;
+
+/// Keep character profiles in `dir` (under the app data directory). Without this, as in
+/// headless mode, there are no profiles to switch between.
+pub fn init_profiles_dir(dir: PathBuf) {
+    if PROFILES_DIR.set(dir).is_err() {
+        log::warn!("Character profiles directory already initialized");
+    }
+}
+
+/// An assistant the user can switch to: how it looks, sounds and behaves. Empty fields leave
+/// the current setting alone when switching.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterProfile {
+    // The file name without its extension; not stored in the file
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub avatar_url: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tts_provider: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub voice: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<f32>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub system_prompt: String,
+    // Whether the avatar shows the emotion its replies read as
+    #[serde(default = "default_emotion_mapping")]
+    pub emotion_mapping: bool,
+    // A viseme mapping preset; files don't travel with the character
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub viseme_mapping: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animations: Option<AnimationConfig>,
+    // Names and words the character says its own way, added to tts.lexicon
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lexicon: Vec<Pronunciation>,
+}
+
+fn default_emotion_mapping() -> bool {
+    true
+}
+
+/// A character as one shareable .aichar file.
+#[derive(Serialize, Deserialize)]
+struct CharacterPackage {
+    format: String,
+    version: u32,
+    character: CharacterProfile,
+}
+
+fn profiles_dir() -> Result<&'static PathBuf> {
+    PROFILES_DIR.get().context("Character profiles are not available")
+}
+
+/// Profiles are YAML or JSON files; which one is told by the extension.
+fn read_profile(path: &Path) -> Result<CharacterProfile> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut profile: CharacterProfile = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?
+    } else {
+        serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?
+    };
+    profile.id = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_string();
+    Ok(profile)
+}
+
+fn profile_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml" | "json")))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Every profile, by name. Files that can't be read are skipped with a warning.
+pub fn list() -> Result<Vec<CharacterProfile>> {
+    let Some(dir) = PROFILES_DIR.get() else {
+        return Ok(Vec::new());
+    };
+    let mut profiles: Vec<CharacterProfile> = profile_files(dir)?
+        .iter()
+        .filter_map(|path| match read_profile(path) {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                log::warn!("Skipping character profile: {:#}", e);
+                None
+            }
+        })
+        .collect();
+    profiles.sort_by_key(|profile| profile.name.to_lowercase());
+    Ok(profiles)
+}
+
+/// The profile stored as `id`.
+pub fn load(id: &str) -> Result<CharacterProfile> {
+    let dir = profiles_dir()?;
+    profile_files(dir)?
+        .iter()
+        .find(|path| path.file_stem().and_then(|stem| stem.to_str()) == Some(id))
+        .with_context(|| format!("No character named {}", id))
+        .and_then(|path| read_profile(path))
+}
+
+/// Whether `profile` can be stored and switched to.
+fn validate(profile: &CharacterProfile) -> Result<()> {
+    if profile.name.chars().count() > 100 {
+        return Err(anyhow::anyhow!("A character name must be at most 100 characters"));
+    }
+    if !profile.avatar_url.is_empty() && !profile.avatar_url.starts_with("https://") && !profile.avatar_url.starts_with("http://") {
+        return Err(anyhow::anyhow!("The avatar must be an http(s) URL: {}", profile.avatar_url));
+    }
+    if !profile.tts_provider.is_empty() && !tts::provider_names().contains(&profile.tts_provider.as_str()) {
+        return Err(anyhow::anyhow!("Unknown TTS provider: {}", profile.tts_provider));
+    }
+    if profile.speed.is_some_and(|speed| !(0.25..=4.0).contains(&speed)) || profile.pitch.is_some_and(|pitch| !(0.25..=4.0).contains(&pitch)) {
+        return Err(anyhow::anyhow!("Speed and pitch must be between 0.25 and 4"));
+    }
+    if profile.system_prompt.chars().count() > MAX_PROMPT_CHARS {
+        return Err(anyhow::anyhow!("The system prompt must be at most {} characters", MAX_PROMPT_CHARS));
+    }
+    if !profile.viseme_mapping.is_empty() && !visemes::PRESETS.contains(&profile.viseme_mapping.as_str()) {
+        return Err(anyhow::anyhow!(
+            "Unknown viseme mapping {}; use one of {}",
+            profile.viseme_mapping,
+            visemes::PRESETS.join(", ")
+        ));
+    }
+    if let Some(entry) = profile
+        .lexicon
+        .iter()
+        .find(|entry| entry.word.trim().is_empty() || entry.word.contains(char::is_whitespace) || !g2p::is_valid_pronunciation(&entry.phonemes))
+    {
+        return Err(anyhow::anyhow!("Invalid pronunciation for {:?}: {}", entry.word, entry.phonemes));
+    }
+    Ok(())
+}
+
+/// Store a new profile as YAML, under an id made from its name.
+pub fn create(mut profile: CharacterProfile) -> Result<CharacterProfile> {
+    profile.name = profile.name.trim().to_string();
+    validate(&profile)?;
+    let id = slug(&profile.name);
+    if id.is_empty() {
+        return Err(anyhow::anyhow!("A character needs a name with letters or digits in it"));
+    }
+    let dir = profiles_dir()?;
+    if load(&id).is_ok() {
+        return Err(anyhow::anyhow!("A character named {} already exists", profile.name));
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    profile.id = String::new();
+    let path = dir.join(format!("{}.yaml", id));
+    fs::write(&path, serde_yaml::to_string(&profile)?).with_context(|| format!("Failed to write {}", path.display()))?;
+    profile.id = id;
+    Ok(profile)
+}
+
+/// Write the profile stored as `id` to `path` as a .aichar package.
+pub fn export(id: &str, path: &Path) -> Result<()> {
+    let mut character = load(id)?;
+    character.id = String::new();
+    let package = CharacterPackage {
+        format: PACKAGE_FORMAT.to_string(),
+        version: PACKAGE_VERSION,
+        character,
+    };
+    fs::write(path, serde_json::to_string_pretty(&package)?).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Check the .aichar package at `path` and store its character as a new profile.
+pub fn import(path: &Path) -> Result<CharacterProfile> {
+    let size = fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?.len();
+    if size > MAX_PACKAGE_BYTES {
+        return Err(anyhow::anyhow!("{} is too large to be a character package", path.display()));
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let package: CharacterPackage = serde_json::from_str(&content).context("Not a character package")?;
+    if package.format != PACKAGE_FORMAT {
+        return Err(anyhow::anyhow!("Not a character package: format is {}", package.format));
+    }
+    if package.version > PACKAGE_VERSION {
+        return Err(anyhow::anyhow!(
+            "The character package is version {}; this app reads up to version {}",
+            package.version,
+            PACKAGE_VERSION
+        ));
+    }
+    create(package.character)
+}
+
+/// "Dr. Ada Lovelace" -> "dr-ada-lovelace", safe as a file name on every platform.
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use once_cell::sync::OnceCell;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use anyhow::{Context, Result};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +18,28 @@ pub struct AppConfig {
     pub memory: MemoryConfig,
     pub logging: LoggingConfig,
     pub development: DevelopmentConfig,
+    #[serde(default)]
+    pub shortcuts: ShortcutConfig,
+    #[serde(default)]
+    pub translation: TranslationConfig,
+    #[serde(default)]
+    pub meeting: MeetingConfig,
+    #[serde(default)]
+    pub accessibility: AccessibilityConfig,
+    #[serde(default)]
+    pub headless: HeadlessConfig,
+    #[serde(default)]
+    pub updates: UpdateConfig,
+    #[serde(default)]
+    pub quiet_hours: QuietHoursConfig,
+    #[serde(default)]
+    pub focus: FocusConfig,
+    #[serde(default)]
+    pub avatar_server: AvatarServerConfig,
+    #[serde(default)]
+    pub vmc: VmcConfig,
+    #[serde(default)]
+    pub rest_api: RestApiConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +47,16 @@ pub struct AppSettings {
     pub name: String,
     pub version: String,
     pub window: WindowConfig,
+    // Never phone home: no update checks or other requests to the project's servers
+    #[serde(default)]
+    pub offline_mode: bool,
+    // BCP 47 tag for backend messages, spoken phrases and date/number handling
+    #[serde(default = "default_locale")]
+    pub locale: String,
+}
+
+fn default_locale() -> String {
+    "en-US".to_string()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,12 +66,60 @@ pub struct WindowConfig {
     pub resizable: bool,
     pub fullscreen: bool,
     pub always_on_top: bool,
+    // The close button hides the main window to the system tray instead of quitting
+    #[serde(default = "default_close_to_tray")]
+    pub close_to_tray: bool,
+}
+
+fn default_close_to_tray() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioConfig {
     pub input: AudioInputConfig,
     pub output: AudioOutputConfig,
+    // "cpal" for real devices, "file" to replay simulation_file as the microphone
+    #[serde(default = "default_audio_backend")]
+    pub backend: String,
+    #[serde(default)]
+    pub simulation_file: Option<String>,
+    // Keep transcribing while the assistant speaks so the user can interrupt it
+    #[serde(default)]
+    pub barge_in: bool,
+    // Send what the user says to the assistant and speak its reply
+    #[serde(default = "default_voice_replies")]
+    pub voice_replies: bool,
+    #[serde(default)]
+    pub visualizer: VisualizerConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisualizerConfig {
+    pub enabled: bool,
+    // Frames per second for each of input and output
+    pub fps: u32,
+    pub bands: usize,
+    pub waveform_points: usize,
+}
+
+impl Default for VisualizerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fps: 30,
+            bands: 32,
+            waveform_points: 64,
+        }
+    }
+}
+
+fn default_audio_backend() -> String {
+    "cpal".to_string()
+}
+
+fn default_voice_replies() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,13 +146,40 @@ pub struct SttConfig {
     pub provider: String,
     pub model: String,
     pub language: String,
+    // With language "auto", the languages detection chooses between; empty allows any
+    #[serde(default)]
+    pub detect_languages: Vec<String>,
+    // "transcribe" keeps the spoken language; "translate" has Whisper write English instead
+    #[serde(default = "default_stt_task")]
+    pub task: String,
+    // Names, product terms and jargon Whisper should expect to hear
+    #[serde(default)]
+    pub custom_vocabulary: Vec<String>,
     pub real_time: bool,
     pub vad_enabled: bool,
+    // "energy" compares loudness against silence_threshold; "silero" runs the neural VAD
+    #[serde(default = "default_vad_backend")]
+    pub vad_backend: String,
+    // Speech probability above which Silero reports speech
+    #[serde(default = "default_vad_threshold")]
+    pub vad_threshold: f32,
     pub silence_threshold: f32,
     pub min_speech_duration: f32,
     pub max_speech_duration: f32,
 }
 
+fn default_stt_task() -> String {
+    "transcribe".to_string()
+}
+
+fn default_vad_backend() -> String {
+    "energy".to_string()
+}
+
+fn default_vad_threshold() -> f32 {
+    0.5
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TtsConfig {
     pub provider: String,
@@ -81,6 +190,57 @@ pub struct TtsConfig {
     pub streaming: bool,
     pub low_latency: bool,
     pub generate_visemes: bool,
+    // Piper executable, found on PATH unless a full path is given
+    #[serde(default = "default_piper_binary")]
+    pub piper_binary: String,
+    // Cloud providers only; like the LLM key, it can also live in the OS keyring
+    #[serde(default)]
+    pub api_key: Option<String>,
+    // Provider model, e.g. an ElevenLabs model id; the provider's default when unset
+    #[serde(default)]
+    pub model: Option<String>,
+    // Pronunciations that override the dictionary, for names and brands
+    #[serde(default)]
+    pub lexicon: Vec<Pronunciation>,
+    // Disk space for replaying short phrases without synthesizing them again; 0 turns caching off
+    #[serde(default = "default_tts_cache_size_mb")]
+    pub cache_size_mb: u64,
+}
+
+/// How one word is spoken, as ARPAbet phonemes, e.g. "B R IH1 T AH0" for "Britta".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pronunciation {
+    pub word: String,
+    pub phonemes: String,
+}
+
+impl TtsConfig {
+    /// Keyring entry the provider's API key is stored under.
+    pub fn api_key_secret(&self) -> String {
+        format!("{}-api-key", self.provider)
+    }
+
+    /// The API key from config, the OS keyring or the environment, in that order.
+    pub fn resolved_api_key(&self, env_var: &str) -> Option<String> {
+        self.api_key
+            .clone()
+            .filter(|key| !key.is_empty())
+            .or_else(|| crate::platform::load_secret(&self.api_key_secret()))
+            .or_else(|| std::env::var(env_var).ok())
+    }
+
+    /// The lexicon entry for `word`, ignoring case.
+    pub fn pronunciation(&self, word: &str) -> Option<&Pronunciation> {
+        self.lexicon.iter().find(|entry| entry.word.to_lowercase() == word.to_lowercase())
+    }
+}
+
+fn default_piper_binary() -> String {
+    "piper".to_string()
+}
+
+fn default_tts_cache_size_mb() -> u64 {
+    100
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,7 +252,245 @@ pub struct LlmConfig {
     pub top_p: f32,
     pub stream: bool,
     pub context_window: u32,
+    // Summarize turns that no longer fit in context_window rather than leave them out
+    #[serde(default = "default_summarize_context")]
+    pub summarize_context: bool,
     pub system_prompt: String,
+    // OpenAI-compatible endpoint; defaults per provider when unset
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub turn_queue: TurnQueueConfig,
+    // How long Ollama keeps the model loaded after a request, e.g. "5m"; "-1" keeps it loaded
+    #[serde(default = "default_keep_alive")]
+    pub keep_alive: String,
+    // Load the model while the app starts instead of on the first message; only Ollama and llamacpp need it
+    #[serde(default)]
+    pub preload: bool,
+    #[serde(default)]
+    pub tools: ToolsConfig,
+    #[serde(default)]
+    pub pricing: PricingConfig,
+    #[serde(default)]
+    pub fallback: FallbackConfig,
+    #[serde(default)]
+    pub llama_cpp: LlamaCppConfig,
+}
+
+fn default_summarize_context() -> bool {
+    true
+}
+
+fn default_keep_alive() -> String {
+    "5m".to_string()
+}
+
+/// Functions the model may call while answering; needs a model that supports function calling.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolsConfig {
+    // Replies are delivered whole rather than streamed while tools are on
+    pub enabled: bool,
+    // Tools on offer, by name
+    pub allowed: Vec<String>,
+    // Rounds of tool calls a reply may make before it must answer
+    pub max_rounds: u32,
+    // Programs run_command may start, each after the user confirms; empty disables the tool
+    #[serde(default)]
+    pub allowed_commands: Vec<String>,
+    #[serde(default = "default_command_timeout_seconds")]
+    pub command_timeout_seconds: u32,
+    // Directories read_file may read from, subdirectories included; empty disables the tool
+    #[serde(default)]
+    pub file_directories: Vec<String>,
+    #[serde(default)]
+    pub web_search: WebSearchConfig,
+}
+
+fn default_command_timeout_seconds() -> u32 {
+    30
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed: ["get_current_time", "open_url", "read_file", "web_search"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            max_rounds: 4,
+            allowed_commands: Vec::new(),
+            command_timeout_seconds: default_command_timeout_seconds(),
+            file_directories: Vec::new(),
+            web_search: WebSearchConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSearchConfig {
+    // "duckduckgo" (instant answers, no key), "searxng" or "brave"
+    pub backend: String,
+    // The SearxNG instance, e.g. "http://localhost:8888"
+    pub base_url: String,
+    // Brave only; like the LLM key, it can also live in the OS keyring
+    #[serde(default)]
+    pub api_key: Option<String>,
+    pub max_results: u32,
+}
+
+impl Default for WebSearchConfig {
+    fn default() -> Self {
+        Self {
+            backend: "duckduckgo".to_string(),
+            base_url: String::new(),
+            api_key: None,
+            max_results: 5,
+        }
+    }
+}
+
+impl WebSearchConfig {
+    /// The Brave API key from config, the OS keyring or the environment, in that order.
+    pub fn resolved_api_key(&self) -> Option<String> {
+        self.api_key
+            .clone()
+            .filter(|key| !key.is_empty())
+            .or_else(|| crate::platform::load_secret("brave-search-api-key"))
+            .or_else(|| std::env::var("BRAVE_SEARCH_API_KEY").ok())
+    }
+}
+
+/// In-process inference for `provider: "llamacpp"`; the GGUF file is `model`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlamaCppConfig {
+    // Layers offloaded to the GPU; 0 runs on the CPU only
+    pub gpu_layers: u32,
+    // 0 uses every core
+    pub threads: u32,
+}
+
+/// Retries and backup backends for chat requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackConfig {
+    // Backends tried in order once the configured one has failed
+    pub providers: Vec<FallbackProviderConfig>,
+    // Retries on each backend before moving on; 0 tries each once
+    pub max_retries: u32,
+    // Wait before the first retry, doubled for each one after
+    pub initial_backoff_ms: u64,
+    // A request taking longer than this counts as failed, streamed replies included
+    pub timeout_seconds: u32,
+}
+
+impl Default for FallbackConfig {
+    fn default() -> Self {
+        Self {
+            providers: Vec::new(),
+            max_retries: 2,
+            initial_backoff_ms: 500,
+            timeout_seconds: 120,
+        }
+    }
+}
+
+/// A backup backend; settings not given here are shared with the main one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackProviderConfig {
+    pub provider: String,
+    pub model: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    // Falls back to the keyring entry and environment variable for `provider`
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+/// What the model charges, for usage statistics; leave at 0 for models that run locally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricingConfig {
+    // Per million prompt tokens
+    pub input_per_million: f64,
+    // Per million generated tokens
+    pub output_per_million: f64,
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            input_per_million: 0.0,
+            output_per_million: 0.0,
+        }
+    }
+}
+
+/// Per-provider limits shared by every LLM request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    // 0 disables the limit
+    pub requests_per_minute: u32,
+    pub max_concurrent: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 60,
+            max_concurrent: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnQueueConfig {
+    // User turns sent to the LLM at once; replies are still delivered in order, but a turn
+    // doesn't see the exchanges still in flight alongside it
+    pub concurrency: u32,
+    pub max_pending: usize,
+    // "queue", "drop_oldest" or "coalesce"
+    pub policy: String,
+}
+
+impl Default for TurnQueueConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            max_pending: 4,
+            policy: "coalesce".to_string(),
+        }
+    }
+}
+
+impl LlmConfig {
+    pub fn resolved_base_url(&self) -> String {
+        match &self.base_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None if self.provider == "openai" => "https://api.openai.com/v1".to_string(),
+            None if self.provider == "ollama" => "http://localhost:11434".to_string(),
+            None if self.provider == "gemini" => "https://generativelanguage.googleapis.com/v1beta".to_string(),
+            None => "http://localhost:8080/v1".to_string(),
+        }
+    }
+
+    /// Keyring entry the provider's API key is stored under.
+    pub fn api_key_secret(&self) -> String {
+        format!("{}-api-key", self.provider)
+    }
+
+    pub fn resolved_api_key(&self) -> Option<String> {
+        self.api_key
+            .clone()
+            .filter(|key| !key.is_empty())
+            .or_else(|| crate::platform::load_secret(&self.api_key_secret()))
+            .or_else(|| match self.provider.as_str() {
+                "gemini" => std::env::var("GEMINI_API_KEY").ok(),
+                _ => std::env::var("OPENAI_API_KEY").ok(),
+            })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -104,11 +502,69 @@ pub struct VisionConfig {
     pub object_detection: bool,
     pub face_detection: bool,
     pub emotion_recognition: bool,
+    // Face probability above which the face detector reports a face
+    #[serde(default = "default_face_threshold")]
+    pub face_threshold: f32,
+    // Score above which the object detector reports an object
+    #[serde(default = "default_object_threshold")]
+    pub object_threshold: f32,
+    // Start listening when someone sits down in front of the camera
+    #[serde(default = "default_auto_wake")]
+    pub auto_wake: bool,
+    // How long no face is seen before the user counts as gone
+    #[serde(default = "default_away_seconds")]
+    pub away_seconds: u32,
+    // Screenshots sent to the LLM are shrunk to fit this; [0, 0] sends them at full size
+    #[serde(default = "default_screen_resolution")]
+    pub screen_resolution: [u32; 2],
+    #[serde(default)]
+    pub ocr: OcrConfig,
+}
+
+fn default_face_threshold() -> f32 {
+    0.7
+}
+
+fn default_object_threshold() -> f32 {
+    0.5
+}
+
+fn default_auto_wake() -> bool {
+    true
+}
+
+fn default_away_seconds() -> u32 {
+    30
+}
+
+fn default_screen_resolution() -> [u32; 2] {
+    [1280, 800]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OcrConfig {
+    // The Tesseract executable, on PATH or a full path
+    pub tesseract_binary: String,
+    // Tesseract language codes joined with "+", e.g. "eng+deu"; each needs its traineddata installed
+    pub languages: String,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            tesseract_binary: "tesseract".to_string(),
+            languages: "eng".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CharacterConfig {
     pub enabled: bool,
+    // Id of the character profile last switched to; empty when none has been
+    #[serde(default)]
+    pub profile: String,
     pub provider: String,
     pub avatar_url: String,
     pub animations: AnimationConfig,
@@ -137,11 +593,20 @@ pub struct LipSyncConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FacialExpressionConfig {
     pub enabled: bool,
+    // Show the emotion each reply reads as
     pub emotion_mapping: bool,
+    // How replies are read: "lexicon" (local word lists) or "llm"
+    #[serde(default = "default_emotion_source")]
+    pub emotion_source: String,
+    // Average seconds between blinks
     pub blink_rate: f32,
     pub eye_tracking: bool,
 }
 
+fn default_emotion_source() -> String {
+    "lexicon".to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenderingConfig {
     pub quality: String,
@@ -166,10 +631,76 @@ pub struct PerformanceConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryConfig {
     pub enabled: bool,
+    // Messages kept in the live chat (0 keeps all)
     pub max_history: u32,
     pub context_retention: u32,
     pub save_conversations: bool,
+    // Seconds of quiet after which a conversation is closed and the next turn starts a new one
     pub conversation_timeout: u32,
+    // Stored conversations kept, the oldest beyond it deleted (0 keeps all). A key of its own,
+    // since `max_history` already caps the live chat
+    #[serde(default = "default_max_conversations")]
+    pub max_conversations: u32,
+    // Have the LLM title and tag conversations once they end, and every `tag_every_turns` turns
+    #[serde(default = "default_topic_tagging")]
+    pub topic_tagging: bool,
+    #[serde(default = "default_tag_every_turns")]
+    pub tag_every_turns: u32,
+    // Seconds the current conversation is quiet before it's tagged and learned from; 0 waits
+    // until it ends
+    #[serde(default = "default_tag_idle_seconds")]
+    pub tag_idle_seconds: u32,
+    // Learn facts about the user (name, preferences, recurring topics) from conversations and
+    // add them to the prompt
+    #[serde(default = "default_user_profile")]
+    pub user_profile: bool,
+    // Embed past exchanges and remembered facts, and add the `recall_k` closest to each prompt
+    #[serde(default = "default_semantic_recall")]
+    pub semantic_recall: bool,
+    // Served by the LLM provider's embeddings endpoint
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+    #[serde(default = "default_recall_k")]
+    pub recall_k: u32,
+    // Cosine similarity below which a memory isn't considered relevant
+    #[serde(default = "default_recall_min_score")]
+    pub recall_min_score: f32,
+}
+
+fn default_max_conversations() -> u32 {
+    500
+}
+
+fn default_topic_tagging() -> bool {
+    true
+}
+
+fn default_tag_every_turns() -> u32 {
+    10
+}
+
+fn default_tag_idle_seconds() -> u32 {
+    120
+}
+
+fn default_user_profile() -> bool {
+    true
+}
+
+fn default_semantic_recall() -> bool {
+    true
+}
+
+fn default_embedding_model() -> String {
+    "nomic-embed-text".to_string()
+}
+
+fn default_recall_k() -> u32 {
+    3
+}
+
+fn default_recall_min_score() -> f32 {
+    0.5
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,7 +719,302 @@ pub struct DevelopmentConfig {
     pub hot_reload: bool,
     pub performance_monitoring: bool,
     pub error_reporting: bool,
+    // Opt-in: collect anonymous usage counters and upload them to `telemetry_endpoint`
     pub telemetry: bool,
+    #[serde(default = "default_telemetry_endpoint")]
+    pub telemetry_endpoint: String,
+    #[serde(default = "default_telemetry_upload_hours")]
+    pub telemetry_upload_hours: u32,
+}
+
+fn default_telemetry_endpoint() -> String {
+    "https://telemetry.ai-conversation-app.dev/v1/batches".to_string()
+}
+
+fn default_telemetry_upload_hours() -> u32 {
+    24
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShortcutConfig {
+    pub push_to_talk: PushToTalkConfig,
+    // Toggles system-wide dictation; empty disables the shortcut
+    #[serde(default = "default_dictation_shortcut")]
+    pub dictation: String,
+    // Reads the current selection (or clipboard) aloud; empty disables the shortcut
+    #[serde(default = "default_read_aloud_shortcut")]
+    pub read_aloud: String,
+}
+
+fn default_dictation_shortcut() -> String {
+    "Ctrl+Shift+D".to_string()
+}
+
+fn default_read_aloud_shortcut() -> String {
+    "Ctrl+Shift+R".to_string()
+}
+
+impl Default for ShortcutConfig {
+    fn default() -> Self {
+        Self {
+            push_to_talk: PushToTalkConfig::default(),
+            dictation: default_dictation_shortcut(),
+            read_aloud: default_read_aloud_shortcut(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushToTalkConfig {
+    pub enabled: bool,
+    pub shortcut: String,
+    // "hold" = capture while the key is held down, "toggle" = press once to start, again to stop
+    pub mode: String,
+}
+
+impl Default for PushToTalkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            shortcut: "Ctrl+Shift+Space".to_string(),
+            mode: "hold".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationConfig {
+    // "llm" uses the configured LLM, "libretranslate" uses a LibreTranslate-compatible server
+    pub provider: String,
+    pub endpoint: String,
+    pub source_language: String,
+    pub target_language: String,
+    // Target language code -> TTS voice, so replies are spoken with a matching voice
+    pub voices: std::collections::HashMap<String, String>,
+}
+
+impl Default for TranslationConfig {
+    fn default() -> Self {
+        Self {
+            provider: "llm".to_string(),
+            endpoint: "http://localhost:5000".to_string(),
+            source_language: "auto".to_string(),
+            target_language: "es".to_string(),
+            voices: std::collections::HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeetingConfig {
+    // "default" picks the platform loopback (WASAPI output, PulseAudio monitor, BlackHole)
+    pub loopback_device: String,
+    pub chunk_seconds: f32,
+    pub diarization: bool,
+    pub summarize: bool,
+}
+
+impl Default for MeetingConfig {
+    fn default() -> Self {
+        Self {
+            loopback_device: "default".to_string(),
+            chunk_seconds: 15.0,
+            diarization: true,
+            summarize: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessibilityConfig {
+    pub announcements: bool,
+    pub reduced_motion: bool,
+    pub high_contrast: bool,
+    // "brief" or "verbose" announcement wording
+    pub verbosity: String,
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        Self {
+            announcements: true,
+            reduced_motion: false,
+            high_contrast: false,
+            verbosity: "brief".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeadlessConfig {
+    // Phrase that must start a request; empty to respond to everything
+    pub wake_word: String,
+    // How long the assistant keeps listening for follow-ups without the wake word
+    pub follow_up_seconds: f32,
+    // Speak replies through the OS voice (say, espeak-ng, SAPI)
+    pub system_voice: bool,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            wake_word: "hey assistant".to_string(),
+            follow_up_seconds: 8.0,
+            system_voice: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    // Check on startup and then every `check_interval_hours`
+    pub auto_check: bool,
+    pub check_interval_hours: u32,
+    // "stable" or "beta"
+    pub channel: String,
+    // Release feed URL; `{channel}` is replaced with the channel name
+    pub feed_url: String,
+    // Download available updates in the background so installing is instant
+    pub auto_download: bool,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            auto_check: true,
+            check_interval_hours: 24,
+            channel: "stable".to_string(),
+            feed_url: "https://releases.ai-conversation-app.dev/{channel}/latest.json".to_string(),
+            auto_download: true,
+        }
+    }
+}
+
+/// When the assistant holds back speech nobody asked for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHoursConfig {
+    pub enabled: bool,
+    pub schedules: Vec<QuietSchedule>,
+    // ICS feed (https:// or webcal://) whose timed events count as meetings; empty to disable
+    pub calendar_url: String,
+    pub calendar_refresh_minutes: u32,
+    // Speak the messages held back once quiet hours end
+    pub announce_deferred: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietSchedule {
+    pub name: String,
+    // "mon", "tue", ...; empty for every day
+    #[serde(default)]
+    pub days: Vec<String>,
+    // Local "HH:MM"; an end before the start runs past midnight
+    pub start: String,
+    pub end: String,
+}
+
+impl Default for QuietHoursConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            schedules: vec![QuietSchedule {
+                name: "sleep".to_string(),
+                days: Vec::new(),
+                start: "22:00".to_string(),
+                end: "07:00".to_string(),
+            }],
+            calendar_url: String::new(),
+            calendar_refresh_minutes: 15,
+            announce_deferred: true,
+        }
+    }
+}
+
+/// Suppress interruptions while a call, presentation or fullscreen app is in front.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusConfig {
+    pub enabled: bool,
+    pub poll_seconds: u32,
+    // Matched case-insensitively against the foreground app's name
+    pub video_call_apps: Vec<String>,
+    // Suppress only while these run fullscreen (slideshows)
+    pub presentation_apps: Vec<String>,
+    // Also suppress for any other fullscreen app, such as games and videos
+    pub suppress_fullscreen: bool,
+}
+
+impl Default for FocusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_seconds: 2,
+            video_call_apps: ["zoom", "teams", "webex", "facetime", "skype", "slack huddle"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            presentation_apps: ["powerpnt", "powerpoint", "keynote", "impress"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            suppress_fullscreen: true,
+        }
+    }
+}
+
+/// Stream the avatar's lip sync, state and transcripts to renderers outside the app.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvatarServerConfig {
+    pub enabled: bool,
+    // WebSocket port on 127.0.0.1
+    pub port: u16,
+}
+
+impl Default for AvatarServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7620,
+        }
+    }
+}
+
+/// Send lip sync and emotions as VMC protocol blendshapes, for VTube Studio, VSeeFace and the like.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VmcConfig {
+    pub enabled: bool,
+    // Where the VMC receiver listens for OSC over UDP
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for VmcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 39539,
+        }
+    }
+}
+
+/// Let scripts and other apps on this machine drive the assistant over HTTP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestApiConfig {
+    pub enabled: bool,
+    // HTTP port on 127.0.0.1
+    pub port: u16,
+    // Sent as `Authorization: Bearer <token>`; generated when the API is first enabled
+    pub token: String,
+}
+
+impl Default for RestApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7621,
+            token: String::new(),
+        }
+    }
 }
 
 impl AppConfig {
@@ -202,33 +1028,295 @@ impl AppConfig {
         Ok(config)
     }
     
-    pub fn load_default() -> Result<Self> {
-        // Try multiple possible paths for the config file
-        let possible_paths = [
-            "config/config.yaml",
-            "../config/config.yaml",
-            "src-tauri/config.yaml",
-            "./config.yaml"
-        ];
-        
-        for path in &possible_paths {
-            if std::path::Path::new(path).exists() {
-                return Self::load_from_file(path);
+    /// The configuration compiled into the app, also written out on first launch.
+    pub fn builtin() -> Self {
+        serde_yaml::from_str(DEFAULT_CONFIG).expect("built-in configuration is valid")
+    }
+
+    /// Catch values that parse but can't work, before they're applied or saved.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if self.app.window.width == 0 || self.app.window.height == 0 {
+            problems.push("app.window size must be non-zero".to_string());
+        }
+        if self.audio.input.sample_rate == 0 || self.audio.output.sample_rate == 0 {
+            problems.push("audio sample rates must be non-zero".to_string());
+        }
+        if self.audio.input.channels == 0 || self.audio.output.channels == 0 {
+            problems.push("audio channel counts must be non-zero".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.audio.output.volume) {
+            problems.push("audio.output.volume must be between 0 and 1".to_string());
+        }
+        if !matches!(self.stt.task.as_str(), "transcribe" | "translate") {
+            problems.push("stt.task must be \"transcribe\" or \"translate\"".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.stt.vad_threshold) {
+            problems.push("stt.vad_threshold must be between 0 and 1".to_string());
+        }
+        if self.tts.speed <= 0.0 {
+            problems.push("tts.speed must be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.tts.volume) {
+            problems.push("tts.volume must be between 0 and 1".to_string());
+        }
+        if self.llm.model.trim().is_empty() {
+            problems.push("llm.model must not be empty".to_string());
+        }
+        if self.llm.max_tokens == 0 {
+            problems.push("llm.max_tokens must be non-zero".to_string());
+        }
+        if !(0.0..=2.0).contains(&self.llm.temperature) {
+            problems.push("llm.temperature must be between 0 and 2".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.llm.top_p) {
+            problems.push("llm.top_p must be between 0 and 1".to_string());
+        }
+        if self.llm.pricing.input_per_million < 0.0 || self.llm.pricing.output_per_million < 0.0 {
+            problems.push("llm.pricing must not be negative".to_string());
+        }
+        if self.llm.provider == "llamacpp" && !cfg!(feature = "llama-cpp") {
+            problems.push("llm.provider \"llamacpp\" needs a build with the llama-cpp feature".to_string());
+        }
+        if self.llm.fallback.timeout_seconds == 0 {
+            problems.push("llm.fallback.timeout_seconds must be non-zero".to_string());
+        }
+        if self.llm.fallback.providers.iter().any(|backup| backup.provider.trim().is_empty() || backup.model.trim().is_empty()) {
+            problems.push("llm.fallback.providers each need a provider and a model".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.vision.face_threshold) {
+            problems.push("vision.face_threshold must be between 0 and 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.vision.object_threshold) {
+            problems.push("vision.object_threshold must be between 0 and 1".to_string());
+        }
+        if self.vision.ocr.languages.trim().is_empty() {
+            problems.push("vision.ocr.languages must not be empty".to_string());
+        }
+        if self.memory.conversation_timeout == 0 {
+            problems.push("memory.conversation_timeout must be non-zero".to_string());
+        }
+        if !(-1.0..=1.0).contains(&self.memory.recall_min_score) {
+            problems.push("memory.recall_min_score must be between -1 and 1".to_string());
+        }
+        if self.avatar_server.port == 0 {
+            problems.push("avatar_server.port must be non-zero".to_string());
+        }
+        if self.vmc.host.trim().is_empty() || self.vmc.port == 0 {
+            problems.push("vmc needs a host and a non-zero port".to_string());
+        }
+        if self.rest_api.port == 0 {
+            problems.push("rest_api.port must be non-zero".to_string());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Invalid configuration: {}", problems.join("; ")))
+        }
+    }
+}
+
+// The defaults shipped with the app
+const DEFAULT_CONFIG: &str = include_str!("../../config.yaml");
+
+// Where configuration was looked for before it moved to the platform config directory;
+// still used by development checkouts and when that directory can't be written
+const LEGACY_CONFIG_PATHS: [&str; 4] = [
+    "config/config.yaml",
+    "../config/config.yaml",
+    "src-tauri/config.yaml",
+    "./config.yaml",
+];
+
+/// `ai-desktop/config.yaml` in the platform config directory, e.g. `~/.config` on Linux.
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("ai-desktop").join("config.yaml"))
+}
+
+/// The config file to use, creating it in the platform config directory on first launch.
+fn find_config_file() -> Result<PathBuf> {
+    let legacy = LEGACY_CONFIG_PATHS.iter().map(Path::new).find(|path| path.exists());
+    let Some(path) = user_config_path() else {
+        return legacy
+            .map(Path::to_path_buf)
+            .context("No platform config directory and no configuration file in the working directory");
+    };
+    if path.exists() {
+        return Ok(path);
+    }
+
+    // Carry over a config from the old locations if there is one
+    let initial = match legacy {
+        Some(legacy) => fs::read_to_string(legacy)
+            .with_context(|| format!("Failed to read configuration file {}", legacy.display()))?,
+        None => DEFAULT_CONFIG.to_string(),
+    };
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, initial));
+    match (written, legacy) {
+        (Ok(()), _) => Ok(path),
+        (Err(_), Some(legacy)) => Ok(legacy.to_path_buf()),
+        (Err(e), None) => Err(anyhow::Error::new(e).context(format!("Failed to write default configuration to {}", path.display()))),
+    }
+}
+
+/// Merge `patch` into `base`: mappings are merged key by key, anything else replaces what was there.
+fn merge_yaml(base: &mut serde_yaml::Value, patch: serde_yaml::Value) {
+    match (base, patch) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
             }
         }
-        
-        Err(anyhow::anyhow!("Configuration file not found in any of the expected locations: {:?}", possible_paths))
+        (base, patch) => *base = patch,
     }
-    
+}
+
+// Global configuration instance. Updates swap in a new one; readers keep the one they were
+// handed until they let go of it.
+static CONFIG: RwLock<Option<Arc<AppConfig>>> = RwLock::new(None);
+// Where the configuration was loaded from, and where updates are saved
+static CONFIG_PATH: OnceCell<PathBuf> = OnceCell::new();
 
+// The lock only guards swapping one Arc for another, so a panic while it was held can't have
+// left the configuration half-written
+fn read_config() -> RwLockReadGuard<'static, Option<Arc<AppConfig>>> {
+    CONFIG.read().unwrap_or_else(PoisonError::into_inner)
 }
 
-// Global configuration instance
-use once_cell::sync::OnceCell;
-static CONFIG: OnceCell<AppConfig> = OnceCell::new();
+fn write_config() -> RwLockWriteGuard<'static, Option<Arc<AppConfig>>> {
+    CONFIG.write().unwrap_or_else(PoisonError::into_inner)
+}
 
 pub fn init_config() -> Result<()> {
-    let config = AppConfig::load_default()?;
-    CONFIG.set(config).map_err(|_| anyhow::anyhow!("Configuration already initialized"))?;
+    let (config, path) = match find_config_file() {
+        Ok(path) => (AppConfig::load_from_file(&path)?, Some(path)),
+        Err(e) => {
+            // Still start, on defaults that changes can't be saved to
+            log::warn!("Using the built-in configuration: {:#}", e);
+            (AppConfig::builtin(), None)
+        }
+    };
+    let mut current = write_config();
+    if current.is_some() {
+        return Err(anyhow::anyhow!("Configuration already initialized"));
+    }
+    *current = Some(Arc::new(config));
+    if let Some(path) = path {
+        let _ = CONFIG_PATH.set(path);
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Run on the built-in configuration, unless a configuration is already in place.
+#[cfg(test)]
+pub(crate) fn init_builtin_config() {
+    write_config().get_or_insert_with(|| Arc::new(AppConfig::builtin()));
+}
+
+/// The running configuration, or `None` before `init_config`.
+pub fn try_get_config() -> Option<Arc<AppConfig>> {
+    read_config().clone()
+}
+
+pub fn config_path() -> Option<&'static Path> {
+    CONFIG_PATH.get().map(PathBuf::as_path)
+}
+
+/// Re-read the config file after it changed on disk. Returns the previous and new configuration,
+/// or None if the file matches what's already running, as it does right after `update_config`.
+pub fn reload_config() -> Result<Option<(Arc<AppConfig>, Arc<AppConfig>)>> {
+    let path = CONFIG_PATH.get().context("Configuration isn't backed by a file")?;
+    let reloaded = AppConfig::load_from_file(path)?;
+    reloaded.validate()?;
+
+    let mut current = write_config();
+    let previous = current.clone().context("Configuration not initialized")?;
+    if serde_yaml::to_value(&*previous)? == serde_yaml::to_value(&reloaded)? {
+        return Ok(None);
+    }
+    let reloaded = Arc::new(reloaded);
+    *current = Some(reloaded.clone());
+    Ok(Some((previous, reloaded)))
+}
+
+/// Deep-merge `patch` into the running configuration, validate it and save it to the config file.
+/// Returns the previous and new configuration; nothing changes if any step fails.
+///
+/// Only the keys in `patch` change in the file, but it is written back through serde_yaml,
+/// which drops comments. The first save keeps the file as it was beside it, as config.yaml.orig.
+pub fn update_config(patch: serde_json::Value) -> Result<(Arc<AppConfig>, Arc<AppConfig>)> {
+    let patch = serde_yaml::to_value(patch).context("Failed to read configuration patch")?;
+    if !patch.is_mapping() {
+        return Err(anyhow::anyhow!("Configuration patch must be an object"));
+    }
+    let path = CONFIG_PATH.get().context("Configuration isn't backed by a file")?;
+
+    // Hold the lock throughout so concurrent updates can't lose each other's changes
+    let mut current = write_config();
+    let existing = current.clone().context("Configuration not initialized")?;
+    let mut merged = serde_yaml::to_value(&*existing).context("Failed to serialize configuration")?;
+    merge_yaml(&mut merged, patch.clone());
+    let updated: AppConfig = serde_yaml::from_value(merged).context("Configuration patch doesn't fit the configuration")?;
+    updated.validate()?;
+
+    // Merge into the file as written rather than dumping every default into it
+    let content = fs::read_to_string(path).context("Failed to read configuration file")?;
+    let mut file: serde_yaml::Value = serde_yaml::from_str(&content).context("Failed to parse YAML configuration")?;
+    merge_yaml(&mut file, patch);
+    let original = path.with_extension("yaml.orig");
+    if content.lines().any(|line| line.trim_start().starts_with('#')) && !original.exists() {
+        fs::write(&original, &content).with_context(|| format!("Failed to keep a copy of {}", path.display()))?;
+    }
+    let content = serde_yaml::to_string(&file).context("Failed to serialize configuration")?;
+    fs::write(path, content).with_context(|| format!("Failed to write configuration file {}", path.display()))?;
+
+    let updated = Arc::new(updated);
+    *current = Some(updated.clone());
+    Ok((existing, updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> serde_yaml::Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn merge_keeps_keys_the_patch_leaves_out() {
+        let mut base = yaml("tts:\n  voice: amy\n  speed: 1.0\nllm:\n  model: small\n");
+        merge_yaml(&mut base, yaml("tts:\n  speed: 1.5\n"));
+        assert_eq!(base, yaml("tts:\n  voice: amy\n  speed: 1.5\nllm:\n  model: small\n"));
+    }
+
+    #[test]
+    fn merge_adds_new_keys_and_replaces_lists_whole() {
+        let mut base = yaml("stt:\n  custom_vocabulary: [Tauri, Whisper]\n");
+        merge_yaml(&mut base, yaml("stt:\n  custom_vocabulary: [Piper]\n  language: de\n"));
+        assert_eq!(base, yaml("stt:\n  custom_vocabulary: [Piper]\n  language: de\n"));
+    }
+
+    #[test]
+    fn builtin_config_is_valid() {
+        AppConfig::builtin().validate().unwrap();
+    }
+
+    #[test]
+    fn validation_reports_every_problem() {
+        let mut config = AppConfig::builtin();
+        config.tts.speed = 0.0;
+        config.llm.model = "  ".to_string();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("tts.speed must be positive"), "{}", error);
+        assert!(error.contains("llm.model must not be empty"), "{}", error);
+    }
+}
@@ -0,0 +1,205 @@
+use super::{now_ms, ConversationStore};
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS session_events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL,
+        value TEXT,
+        confidence REAL,
+        duration_ms INTEGER NOT NULL DEFAULT 0,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS session_events_conversation ON session_events(conversation_id, kind);
+";
+
+// Words too common to say anything about what a conversation is about
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "because", "been", "before", "being", "could", "does", "doing",
+    "from", "have", "having", "here", "just", "know", "like", "make", "more", "much", "only", "other",
+    "really", "should", "some", "than", "that", "their", "them", "then", "there", "these", "they",
+    "thing", "things", "think", "this", "those", "very", "want", "what", "when", "where", "which",
+    "while", "will", "with", "would", "your", "yours", "please", "thanks", "thank", "okay",
+];
+const MAX_TOPICS: usize = 5;
+
+/// Something that happened during a session, recorded alongside its turns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionEvent {
+    // The assistant spoke for this long
+    AssistantSpeech,
+    // The assistant listened for this long
+    Listening,
+    // The user cut the assistant off
+    Interruption,
+    // The user's detected emotion
+    Emotion,
+}
+
+impl SessionEvent {
+    fn as_str(self) -> &'static str {
+        match self {
+            SessionEvent::AssistantSpeech => "assistant_speech",
+            SessionEvent::Listening => "listening",
+            SessionEvent::Interruption => "interruption",
+            SessionEvent::Emotion => "emotion",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmotionSample {
+    pub emotion: String,
+    pub confidence: Option<f64>,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionAnalytics {
+    pub conversation_id: i64,
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub talk_time_ms: u64,
+    pub listen_time_ms: u64,
+    // Share of voiced time the assistant was talking, 0..1
+    pub talk_ratio: f64,
+    pub user_turns: u32,
+    pub assistant_turns: u32,
+    pub interruptions: u32,
+    pub emotions: Vec<EmotionSample>,
+    pub emotion_counts: BTreeMap<String, u32>,
+    pub topics: Vec<String>,
+}
+
+impl ConversationStore {
+    /// Record an event against the current conversation, starting one if needed.
+    pub fn record_session_event(
+        &self,
+        event: SessionEvent,
+        value: Option<&str>,
+        confidence: Option<f64>,
+        duration_ms: u64,
+    ) -> Result<()> {
+        let conversation_id = match self.current_conversation()? {
+            Some(id) => id,
+            None => self.start_conversation()?,
+        };
+        self.conn.execute(
+            "INSERT INTO session_events (conversation_id, kind, value, confidence, duration_ms, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![conversation_id, event.as_str(), value, confidence, duration_ms as i64, now_ms() as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Statistics for a conversation, defaulting to the current or most recent one.
+    pub fn session_analytics(&self, conversation_id: Option<i64>) -> Result<SessionAnalytics> {
+        let conversation_id = match conversation_id {
+            Some(id) => id,
+            None => self
+                .conn
+                .query_row("SELECT id FROM conversations ORDER BY updated_at DESC LIMIT 1", [], |row| row.get(0))
+                .optional()?
+                .context("There are no conversations yet")?,
+        };
+
+        let (created_at, updated_at): (i64, i64) = self
+            .conn
+            .query_row(
+                "SELECT created_at, updated_at FROM conversations WHERE id = ?1",
+                params![conversation_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .with_context(|| format!("Conversation {} not found", conversation_id))?;
+        let last_event: Option<i64> = self.conn.query_row(
+            "SELECT MAX(created_at) FROM session_events WHERE conversation_id = ?1",
+            params![conversation_id],
+            |row| row.get(0),
+        )?;
+        let ended_at = last_event.map_or(updated_at, |last| last.max(updated_at));
+
+        let sum_duration = |event: SessionEvent| -> Result<u64> {
+            let total: i64 = self.conn.query_row(
+                "SELECT COALESCE(SUM(duration_ms), 0) FROM session_events WHERE conversation_id = ?1 AND kind = ?2",
+                params![conversation_id, event.as_str()],
+                |row| row.get(0),
+            )?;
+            Ok(total as u64)
+        };
+        let talk_time_ms = sum_duration(SessionEvent::AssistantSpeech)?;
+        let listen_time_ms = sum_duration(SessionEvent::Listening)?;
+        let interruptions: u32 = self.conn.query_row(
+            "SELECT COUNT(*) FROM session_events WHERE conversation_id = ?1 AND kind = ?2",
+            params![conversation_id, SessionEvent::Interruption.as_str()],
+            |row| row.get(0),
+        )?;
+
+        let mut statement = self.conn.prepare(
+            "SELECT value, confidence, created_at FROM session_events
+             WHERE conversation_id = ?1 AND kind = ?2 AND value IS NOT NULL ORDER BY created_at",
+        )?;
+        let emotions = statement
+            .query_map(params![conversation_id, SessionEvent::Emotion.as_str()], |row| {
+                Ok(EmotionSample {
+                    emotion: row.get(0)?,
+                    confidence: row.get(1)?,
+                    timestamp: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut emotion_counts = BTreeMap::new();
+        for sample in &emotions {
+            *emotion_counts.entry(sample.emotion.clone()).or_insert(0) += 1;
+        }
+
+        let mut statement = self.conn.prepare("SELECT role, content FROM turns WHERE conversation_id = ?1")?;
+        let turns = statement
+            .query_map(params![conversation_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let user_turns = turns.iter().filter(|(role, _)| role == "user").count() as u32;
+        let assistant_turns = turns.iter().filter(|(role, _)| role == "assistant").count() as u32;
+        // Prefer the LLM's topic tags; fall back to keywords until the conversation is tagged
+        let mut topics = self.conversation_tags(conversation_id)?;
+        if topics.is_empty() {
+            topics = extract_topics(turns.iter().filter(|(role, _)| role == "user").map(|(_, content)| content.as_str()));
+        }
+
+        let voiced = talk_time_ms + listen_time_ms;
+        Ok(SessionAnalytics {
+            conversation_id,
+            started_at: created_at as u64,
+            duration_ms: ended_at.saturating_sub(created_at) as u64,
+            talk_time_ms,
+            listen_time_ms,
+            talk_ratio: if voiced == 0 { 0.0 } else { talk_time_ms as f64 / voiced as f64 },
+            user_turns,
+            assistant_turns,
+            interruptions,
+            emotions,
+            emotion_counts,
+            topics,
+        })
+    }
+}
+
+/// The most frequent meaningful words the user said, most frequent first.
+fn extract_topics<'a>(texts: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for text in texts {
+        for word in text.split(|c: char| !c.is_alphanumeric() && c != '\'') {
+            let word = word.trim_matches('\'').to_lowercase();
+            if word.chars().count() < 4 || word.chars().all(|c| c.is_ascii_digit()) || STOPWORDS.contains(&word.as_str()) {
+                continue;
+            }
+            *counts.entry(word).or_insert(0) += 1;
+        }
+    }
+    let mut ranked: Vec<(String, u32)> = counts.into_iter().filter(|(_, count)| *count > 1).collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.into_iter().take(MAX_TOPICS).map(|(word, _)| word).collect()
+}
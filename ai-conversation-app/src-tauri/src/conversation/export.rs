@@ -0,0 +1,192 @@
+use super::topics::ConversationSummary;
+use super::{ConversationStore, ConversationTurn};
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use rusqlite::params;
+use serde::Serialize;
+
+/// One LLM request made during the conversation.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedUsage {
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    // Counted here rather than reported by the server
+    pub estimated: bool,
+    pub cost: f64,
+    pub created_at: u64,
+}
+
+/// Something recorded during the session: speech and listening with how long they lasted,
+/// interruptions and the user's emotions.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedEvent {
+    pub kind: String,
+    pub value: Option<String>,
+    pub confidence: Option<f64>,
+    pub duration_ms: u64,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportTotals {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+    // How long the assistant spoke and listened
+    pub talk_time_ms: u64,
+    pub listen_time_ms: u64,
+}
+
+/// Everything stored about a conversation, as export_conversation writes it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationExport {
+    #[serde(flatten)]
+    pub summary: ConversationSummary,
+    pub exported_at: u64,
+    pub turns: Vec<ConversationTurn>,
+    pub usage: Vec<ExportedUsage>,
+    pub events: Vec<ExportedEvent>,
+    pub totals: ExportTotals,
+}
+
+impl ConversationStore {
+    /// Conversation `conversation_id` with its turns, LLM usage and session events.
+    pub fn conversation_export(&self, conversation_id: i64) -> Result<ConversationExport> {
+        let summary = self.conversation_summary(conversation_id)?;
+        let turns = self.conversation_turns(conversation_id)?;
+
+        let mut statement = self.conn.prepare(
+            "SELECT provider, model, prompt_tokens, completion_tokens, estimated, cost, created_at
+             FROM llm_usage WHERE conversation_id = ?1 ORDER BY created_at",
+        )?;
+        let usage = statement
+            .query_map(params![conversation_id], |row| {
+                Ok(ExportedUsage {
+                    provider: row.get(0)?,
+                    model: row.get(1)?,
+                    prompt_tokens: row.get::<_, i64>(2)? as u64,
+                    completion_tokens: row.get::<_, i64>(3)? as u64,
+                    estimated: row.get(4)?,
+                    cost: row.get(5)?,
+                    created_at: row.get::<_, i64>(6)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut statement = self.conn.prepare(
+            "SELECT kind, value, confidence, duration_ms, created_at
+             FROM session_events WHERE conversation_id = ?1 ORDER BY created_at, id",
+        )?;
+        let events = statement
+            .query_map(params![conversation_id], |row| {
+                Ok(ExportedEvent {
+                    kind: row.get(0)?,
+                    value: row.get(1)?,
+                    confidence: row.get(2)?,
+                    duration_ms: row.get::<_, i64>(3)? as u64,
+                    created_at: row.get::<_, i64>(4)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut totals = ExportTotals::default();
+        for request in &usage {
+            totals.prompt_tokens += request.prompt_tokens;
+            totals.completion_tokens += request.completion_tokens;
+            totals.cost += request.cost;
+        }
+        for event in &events {
+            match event.kind.as_str() {
+                "assistant_speech" => totals.talk_time_ms += event.duration_ms,
+                "listening" => totals.listen_time_ms += event.duration_ms,
+                _ => {}
+            }
+        }
+
+        Ok(ConversationExport {
+            summary,
+            exported_at: super::now_ms(),
+            turns,
+            usage,
+            events,
+            totals,
+        })
+    }
+}
+
+/// Render a conversation as "markdown", a chat log to read, or "json", with everything stored.
+pub fn render(export: &ConversationExport, format: &str) -> Result<String> {
+    match format {
+        "json" => Ok(serde_json::to_string_pretty(export)?),
+        "markdown" | "md" => Ok(markdown(export)),
+        other => Err(anyhow::anyhow!("Unsupported export format: {}", other)),
+    }
+}
+
+fn markdown(export: &ConversationExport) -> String {
+    let summary = &export.summary;
+    let title = summary
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("Conversation {}", summary.id));
+    let mut out = format!("# {}\n\n", title);
+
+    let mut details = vec![
+        format!("Started {}", local_time(summary.created_at, "%Y-%m-%d %H:%M")),
+        format!("{} turns", export.turns.len()),
+    ];
+    if !summary.tags.is_empty() {
+        let tags: Vec<String> = summary.tags.iter().map(|t| format!("#{}", t)).collect();
+        details.push(tags.join(" "));
+    }
+    let totals = &export.totals;
+    if totals.prompt_tokens + totals.completion_tokens > 0 {
+        details.push(format!("{} tokens", totals.prompt_tokens + totals.completion_tokens));
+    }
+    if totals.talk_time_ms + totals.listen_time_ms > 0 {
+        details.push(format!(
+            "spoke {}, listened {}",
+            duration(totals.talk_time_ms),
+            duration(totals.listen_time_ms)
+        ));
+    }
+    out.push_str(&details.join(" · "));
+    out.push_str("\n\n---\n");
+
+    for turn in &export.turns {
+        let speaker = match turn.role.as_str() {
+            "user" => "You".to_string(),
+            "assistant" => "Assistant".to_string(),
+            role => {
+                let mut chars = role.chars();
+                chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+            }
+        };
+        out.push_str(&format!("\n**{}** · {}", speaker, local_time(turn.created_at, "%H:%M")));
+        if turn.interrupted {
+            out.push_str(" *(interrupted)*");
+        }
+        out.push_str(&format!("\n\n{}\n", turn.content.trim()));
+    }
+    out
+}
+
+// `ms` since the epoch in the local time zone
+fn local_time(ms: u64, format: &str) -> String {
+    Local
+        .timestamp_millis_opt(ms as i64)
+        .single()
+        .map(|time| time.format(format).to_string())
+        .unwrap_or_default()
+}
+
+fn duration(ms: u64) -> String {
+    let seconds = ms / 1000;
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    }
+}
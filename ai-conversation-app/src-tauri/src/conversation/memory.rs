@@ -0,0 +1,212 @@
+use super::{now_ms, ConversationStore};
+use crate::config::LlmConfig;
+use crate::llm::{self, ChatMessage};
+use anyhow::{Context, Result};
+use rusqlite::params;
+use serde::Serialize;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS memories (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind TEXT NOT NULL,
+        conversation_id INTEGER REFERENCES conversations(id) ON DELETE CASCADE,
+        content TEXT NOT NULL,
+        model TEXT NOT NULL,
+        embedding BLOB NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS memories_model ON memories(model);
+";
+
+// Long exchanges are cut before embedding; the start says most about what was discussed
+const MAX_EMBED_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryKind {
+    // A user turn and the reply to it
+    Exchange,
+    // Something the user asked to have remembered
+    Fact,
+}
+
+impl MemoryKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MemoryKind::Exchange => "exchange",
+            MemoryKind::Fact => "fact",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Memory {
+    pub id: i64,
+    pub kind: MemoryKind,
+    pub conversation_id: Option<i64>,
+    pub content: String,
+    pub created_at: u64,
+    // Cosine similarity to the query it was recalled for
+    pub score: f32,
+}
+
+/// Unit-length vectors for one embedding model, searched by brute force. Personal histories
+/// stay small enough that a scan beats maintaining an approximate index.
+pub(super) struct MemoryIndex {
+    model: String,
+    entries: Vec<IndexEntry>,
+}
+
+struct IndexEntry {
+    id: i64,
+    conversation_id: Option<i64>,
+    vector: Vec<f32>,
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+impl ConversationStore {
+    /// Store a memory with its embedding from `model`.
+    pub fn add_memory(&self, kind: MemoryKind, conversation_id: Option<i64>, content: &str, model: &str, embedding: &[f32]) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO memories (kind, conversation_id, content, model, embedding, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![kind.as_str(), conversation_id, content, model, encode_vector(embedding), now_ms() as i64],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        if let Some(index) = self.memory_index.borrow_mut().as_mut().filter(|index| index.model == model) {
+            index.entries.push(IndexEntry {
+                id,
+                conversation_id,
+                vector: normalize(embedding),
+            });
+        }
+        Ok(id)
+    }
+
+    /// The `k` memories closest to `query`, skipping those from `exclude_conversation`, which is
+    /// usually the one already in the prompt. Only memories embedded by `model` are compared.
+    pub fn search_memories(
+        &self,
+        model: &str,
+        query: &[f32],
+        k: usize,
+        min_score: f32,
+        exclude_conversation: Option<i64>,
+    ) -> Result<Vec<Memory>> {
+        if self.memory_index.borrow().as_ref().is_none_or(|index| index.model != model) {
+            *self.memory_index.borrow_mut() = Some(self.load_memory_index(model)?);
+        }
+        let query = normalize(query);
+        let mut scored: Vec<(i64, f32)> = {
+            let index = self.memory_index.borrow();
+            let Some(index) = index.as_ref() else {
+                return Ok(Vec::new());
+            };
+            index
+                .entries
+                .iter()
+                .filter(|entry| exclude_conversation.is_none() || entry.conversation_id != exclude_conversation)
+                .filter(|entry| entry.vector.len() == query.len())
+                .map(|entry| (entry.id, entry.vector.iter().zip(&query).map(|(a, b)| a * b).sum()))
+                .filter(|(_, score)| *score >= min_score)
+                .collect()
+        };
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+
+        let mut memories = Vec::with_capacity(scored.len());
+        for (id, score) in scored {
+            // Memories of deleted conversations linger in the index until it's reloaded
+            let memory = self.conn.query_row(
+                "SELECT kind, conversation_id, content, created_at FROM memories WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(Memory {
+                        id,
+                        kind: if row.get::<_, String>(0)? == "fact" { MemoryKind::Fact } else { MemoryKind::Exchange },
+                        conversation_id: row.get(1)?,
+                        content: row.get(2)?,
+                        created_at: row.get::<_, i64>(3)? as u64,
+                        score,
+                    })
+                },
+            );
+            match memory {
+                Ok(memory) => memories.push(memory),
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(memories)
+    }
+
+    fn load_memory_index(&self, model: &str) -> Result<MemoryIndex> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT id, conversation_id, embedding FROM memories WHERE model = ?1")?;
+        let entries = statement
+            .query_map(params![model], |row| {
+                Ok(IndexEntry {
+                    id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    vector: normalize(&decode_vector(&row.get::<_, Vec<u8>>(2)?)),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        log::info!("Loaded {} memories embedded with {}", entries.len(), model);
+        Ok(MemoryIndex {
+            model: model.to_string(),
+            entries,
+        })
+    }
+}
+
+/// Embed one text with the configured provider.
+pub async fn embed(config: &LlmConfig, model: &str, text: &str) -> Result<Vec<f32>> {
+    let text: String = text.chars().take(MAX_EMBED_CHARS).collect();
+    llm::provider_for(config)
+        .embed(model, &[text])
+        .await?
+        .into_iter()
+        .next()
+        .context("Embedding provider returned no vector")
+}
+
+/// How an exchange is worded when it's stored and embedded.
+pub fn exchange_text(prompt: &str, reply: &str) -> String {
+    format!("User: {}\nAssistant: {}", prompt.trim(), reply.trim())
+}
+
+/// A system message handing recalled memories to the LLM as background.
+pub fn context_message(memories: &[Memory]) -> ChatMessage {
+    let listed = memories
+        .iter()
+        .map(|memory| match memory.kind {
+            MemoryKind::Fact => format!("- Fact: {}", memory.content),
+            MemoryKind::Exchange => format!("- Earlier exchange:\n{}", memory.content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    ChatMessage::system(format!(
+        "Relevant memories from earlier conversations. Use them only if they help with the current message:\n{}",
+        listed
+    ))
+}
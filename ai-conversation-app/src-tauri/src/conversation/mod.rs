@@ -0,0 +1,329 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::path::Path;
+
+pub mod analytics;
+pub mod export;
+pub mod memory;
+pub mod profile;
+pub mod retention;
+pub mod search;
+pub mod topics;
+pub mod usage;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationTurn {
+    pub id: i64,
+    pub conversation_id: i64,
+    pub role: String,
+    pub content: String,
+    pub created_at: u64,
+    // A reply the user stopped before it was finished
+    pub interrupted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Bookmark {
+    pub id: i64,
+    pub conversation_id: i64,
+    pub turn_id: Option<i64>,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: u64,
+    // The bookmarked turn, so lists and exports don't need a second lookup
+    pub turn_role: Option<String>,
+    pub turn_content: Option<String>,
+}
+
+pub struct ConversationStore {
+    conn: Connection,
+    // Idle gap after which the next turn starts a new conversation
+    timeout_ms: u64,
+    // Loaded on the first recall, for the embedding model it was asked about
+    memory_index: RefCell<Option<memory::MemoryIndex>>,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS conversations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        title TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL,
+        ended_at INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS turns (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        interrupted INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS turns_conversation ON turns(conversation_id, id);
+    CREATE TABLE IF NOT EXISTS bookmarks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+        turn_id INTEGER REFERENCES turns(id) ON DELETE SET NULL,
+        note TEXT,
+        tags TEXT NOT NULL DEFAULT '',
+        created_at INTEGER NOT NULL
+    );
+";
+
+const BOOKMARK_SELECT: &str = "
+    SELECT b.id, b.conversation_id, b.turn_id, b.note, b.tags, b.created_at, t.role, t.content
+    FROM bookmarks b LEFT JOIN turns t ON t.id = b.turn_id
+";
+
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+// Tags are stored comma-joined with surrounding commas so `LIKE '%,tag,%'` matches exactly
+fn encode_tags(tags: &[String]) -> String {
+    let cleaned: Vec<String> = tags
+        .iter()
+        .map(|t| t.trim().to_lowercase().replace(',', " "))
+        .filter(|t| !t.is_empty())
+        .collect();
+    if cleaned.is_empty() {
+        String::new()
+    } else {
+        format!(",{},", cleaned.join(","))
+    }
+}
+
+fn decode_tags(tags: &str) -> Vec<String> {
+    tags.split(',').filter(|t| !t.is_empty()).map(str::to_string).collect()
+}
+
+fn bookmark_from_row(row: &rusqlite::Row) -> rusqlite::Result<Bookmark> {
+    let tags: String = row.get(4)?;
+    Ok(Bookmark {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        turn_id: row.get(2)?,
+        note: row.get(3)?,
+        tags: decode_tags(&tags),
+        created_at: row.get::<_, i64>(5)? as u64,
+        turn_role: row.get(6)?,
+        turn_content: row.get(7)?,
+    })
+}
+
+impl ConversationStore {
+    pub fn open(path: impl AsRef<Path>, timeout_secs: u32) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create data directory {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open conversation store {}", path.display()))?;
+        Self::with_connection(conn, timeout_secs)
+    }
+
+    /// Store that lives only for this session, used when the data directory is unavailable.
+    pub fn in_memory(timeout_secs: u32) -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?, timeout_secs)
+    }
+
+    fn with_connection(conn: Connection, timeout_secs: u32) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")
+            .context("Failed to configure conversation store")?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create conversation tables")?;
+        // Stores created before replies could be interrupted lack the column
+        let has_interrupted: bool = conn
+            .query_row("SELECT COUNT(*) > 0 FROM pragma_table_info('turns') WHERE name = 'interrupted'", [], |row| row.get(0))
+            .context("Failed to inspect conversation tables")?;
+        if !has_interrupted {
+            conn.execute_batch("ALTER TABLE turns ADD COLUMN interrupted INTEGER NOT NULL DEFAULT 0")
+                .context("Failed to upgrade conversation tables")?;
+        }
+        // Likewise for conversations before they were closed on timeout
+        let has_ended_at: bool = conn
+            .query_row("SELECT COUNT(*) > 0 FROM pragma_table_info('conversations') WHERE name = 'ended_at'", [], |row| row.get(0))
+            .context("Failed to inspect conversation tables")?;
+        if !has_ended_at {
+            conn.execute_batch("ALTER TABLE conversations ADD COLUMN ended_at INTEGER")
+                .context("Failed to upgrade conversation tables")?;
+        }
+        conn.execute_batch(analytics::SCHEMA)
+            .context("Failed to create session analytics tables")?;
+        conn.execute_batch(topics::SCHEMA)
+            .context("Failed to create conversation topic tables")?;
+        conn.execute_batch(memory::SCHEMA)
+            .context("Failed to create memory tables")?;
+        conn.execute_batch(usage::SCHEMA)
+            .context("Failed to create LLM usage tables")?;
+        conn.execute_batch(profile::SCHEMA)
+            .context("Failed to create user profile tables")?;
+        search::create_index(&conn).context("Failed to create the history search index")?;
+        Ok(Self {
+            conn,
+            timeout_ms: timeout_secs as u64 * 1000,
+            memory_index: RefCell::new(None),
+        })
+    }
+
+    /// The conversation new turns belong to, if the last one is still fresh and not closed.
+    pub fn current_conversation(&self) -> Result<Option<i64>> {
+        let latest: Option<(i64, i64)> = self
+            .conn
+            .query_row(
+                "SELECT id, updated_at FROM conversations WHERE ended_at IS NULL ORDER BY updated_at DESC LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(latest
+            .filter(|(_, updated_at)| now_ms().saturating_sub(*updated_at as u64) < self.timeout_ms)
+            .map(|(id, _)| id))
+    }
+
+    pub fn start_conversation(&self) -> Result<i64> {
+        let now = now_ms() as i64;
+        self.conn.execute(
+            "INSERT INTO conversations (created_at, updated_at) VALUES (?1, ?1)",
+            params![now],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Append a turn to the current conversation, starting a new one after the idle timeout.
+    pub fn append_turn(&self, role: &str, content: &str) -> Result<ConversationTurn> {
+        self.append_turn_marked(role, content, false)
+    }
+
+    /// Like `append_turn`, recording whether the turn is a reply that was cut short.
+    pub fn append_turn_marked(&self, role: &str, content: &str, interrupted: bool) -> Result<ConversationTurn> {
+        let conversation_id = match self.current_conversation()? {
+            Some(id) => id,
+            None => self.start_conversation()?,
+        };
+        let now = now_ms();
+        self.conn.execute(
+            "INSERT INTO turns (conversation_id, role, content, created_at, interrupted) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![conversation_id, role, content, now as i64, interrupted],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        self.conn.execute(
+            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
+            params![now as i64, conversation_id],
+        )?;
+        Ok(ConversationTurn {
+            id,
+            conversation_id,
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: now,
+            interrupted,
+        })
+    }
+
+    /// "Remember this": bookmark the latest turn of the current conversation.
+    pub fn bookmark_current(&self, note: Option<&str>, tags: &[String]) -> Result<Bookmark> {
+        let conversation_id = self
+            .current_conversation()?
+            .context("There is no active conversation to bookmark")?;
+        let turn_id: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM turns WHERE conversation_id = ?1 ORDER BY id DESC LIMIT 1",
+                params![conversation_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        self.insert_bookmark(conversation_id, turn_id, note, tags)
+    }
+
+    /// Attach a note and tags to a specific turn.
+    pub fn annotate_turn(&self, turn_id: i64, note: Option<&str>, tags: &[String]) -> Result<Bookmark> {
+        let conversation_id: i64 = self
+            .conn
+            .query_row(
+                "SELECT conversation_id FROM turns WHERE id = ?1",
+                params![turn_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .with_context(|| format!("Turn {} not found", turn_id))?;
+        self.insert_bookmark(conversation_id, Some(turn_id), note, tags)
+    }
+
+    fn insert_bookmark(&self, conversation_id: i64, turn_id: Option<i64>, note: Option<&str>, tags: &[String]) -> Result<Bookmark> {
+        let note = note.map(str::trim).filter(|n| !n.is_empty());
+        self.conn.execute(
+            "INSERT INTO bookmarks (conversation_id, turn_id, note, tags, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![conversation_id, turn_id, note, encode_tags(tags), now_ms() as i64],
+        )?;
+        self.get_bookmark(self.conn.last_insert_rowid())
+    }
+
+    pub fn get_bookmark(&self, id: i64) -> Result<Bookmark> {
+        self.conn
+            .query_row(&format!("{} WHERE b.id = ?1", BOOKMARK_SELECT), params![id], bookmark_from_row)
+            .optional()?
+            .with_context(|| format!("Bookmark {} not found", id))
+    }
+
+    /// List bookmarks, newest first, optionally filtered by a tag (the bookmark's own or its
+    /// conversation's topic tags) and a free-text query that matches the note, the tags or
+    /// the bookmarked turn.
+    pub fn list_bookmarks(&self, query: Option<&str>, tag: Option<&str>) -> Result<Vec<Bookmark>> {
+        let query = query.map(str::trim).filter(|q| !q.is_empty()).map(|q| format!("%{}%", q));
+        let tag = tag.map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty());
+        let tag_pattern = tag.as_ref().map(|t| format!("%,{},%", t));
+        let mut statement = self.conn.prepare(&format!(
+            "{} WHERE (?1 IS NULL OR b.note LIKE ?1 OR b.tags LIKE ?1 OR t.content LIKE ?1)
+               AND (?2 IS NULL OR b.tags LIKE ?2
+                    OR EXISTS (SELECT 1 FROM conversation_tags ct
+                               WHERE ct.conversation_id = b.conversation_id AND ct.tag = ?3))
+             ORDER BY b.created_at DESC",
+            BOOKMARK_SELECT
+        ))?;
+        let bookmarks = statement
+            .query_map(params![query, tag_pattern, tag], bookmark_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(bookmarks)
+    }
+
+    pub fn delete_bookmark(&self, id: i64) -> Result<()> {
+        let deleted = self.conn.execute("DELETE FROM bookmarks WHERE id = ?1", params![id])?;
+        if deleted == 0 {
+            return Err(anyhow::anyhow!("Bookmark {} not found", id));
+        }
+        Ok(())
+    }
+}
+
+/// Render bookmarks as "markdown" or "json".
+pub fn export_bookmarks(bookmarks: &[Bookmark], format: &str) -> Result<String> {
+    match format {
+        "json" => Ok(serde_json::to_string_pretty(bookmarks)?),
+        "markdown" | "md" => {
+            let mut out = String::from("# Bookmarks\n");
+            for bookmark in bookmarks {
+                out.push_str(&format!("\n## Conversation {}, bookmark {}\n\n", bookmark.conversation_id, bookmark.id));
+                if let (Some(role), Some(content)) = (&bookmark.turn_role, &bookmark.turn_content) {
+                    out.push_str(&format!("> **{}:** {}\n\n", role, content.replace('\n', "\n> ")));
+                }
+                if let Some(note) = &bookmark.note {
+                    out.push_str(&format!("{}\n\n", note));
+                }
+                if !bookmark.tags.is_empty() {
+                    let tags: Vec<String> = bookmark.tags.iter().map(|t| format!("#{}", t)).collect();
+                    out.push_str(&format!("{}\n", tags.join(" ")));
+                }
+            }
+            Ok(out)
+        }
+        other => Err(anyhow::anyhow!("Unsupported export format: {}", other)),
+    }
+}
@@ -0,0 +1,309 @@
+use super::{now_ms, ConversationStore, ConversationTurn};
+use crate::config::LlmConfig;
+use crate::llm::{self, ChatMessage};
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS user_facts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        category TEXT NOT NULL,
+        content TEXT NOT NULL,
+        source TEXT NOT NULL,
+        conversation_id INTEGER REFERENCES conversations(id) ON DELETE SET NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS user_profile_progress (
+        conversation_id INTEGER PRIMARY KEY REFERENCES conversations(id) ON DELETE CASCADE,
+        extracted_turns INTEGER NOT NULL,
+        extracted_at INTEGER NOT NULL
+    );
+";
+
+/// Kinds of facts kept about the user, in the order the profile lists them. There's only ever
+/// one "name".
+pub const CATEGORIES: &[&str] = &["name", "preference", "topic", "detail"];
+
+// Conversations shorter than this say too little about the user
+const MIN_TURNS: u32 = 2;
+// Cap per pass so a long history doesn't flood the LLM on upgrade
+const MAX_PER_PASS: usize = 3;
+// Turns sent to the LLM per extraction; the most recent ones if the conversation is longer
+const MAX_EXTRACTION_TURNS: usize = 40;
+// Facts kept in the prompt, and facts one extraction may add
+const MAX_PROMPT_FACTS: usize = 50;
+const MAX_NEW_FACTS: usize = 10;
+const MAX_FACT_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FactSource {
+    // Picked up from a conversation by the extraction pass
+    Conversation,
+    // Added or edited by the user, which the extraction pass leaves alone
+    User,
+}
+
+impl FactSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            FactSource::Conversation => "conversation",
+            FactSource::User => "user",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserFact {
+    pub id: i64,
+    pub category: String,
+    pub content: String,
+    pub source: FactSource,
+    // The conversation it was learned in
+    pub conversation_id: Option<i64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// What an extraction pass learned: facts to add and the ids of facts it found outdated.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProfileUpdate {
+    #[serde(default)]
+    pub add: Vec<NewFact>,
+    #[serde(default)]
+    pub outdated: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewFact {
+    pub category: String,
+    pub content: String,
+}
+
+const FACT_SELECT: &str = "SELECT id, category, content, source, conversation_id, created_at, updated_at FROM user_facts";
+
+fn fact_from_row(row: &rusqlite::Row) -> rusqlite::Result<UserFact> {
+    Ok(UserFact {
+        id: row.get(0)?,
+        category: row.get(1)?,
+        content: row.get(2)?,
+        source: if row.get::<_, String>(3)? == "user" { FactSource::User } else { FactSource::Conversation },
+        conversation_id: row.get(4)?,
+        created_at: row.get::<_, i64>(5)? as u64,
+        updated_at: row.get::<_, i64>(6)? as u64,
+    })
+}
+
+// The category and content as stored, or why they can't be
+fn clean_fact(category: &str, content: &str) -> Result<(String, String)> {
+    let category = category.trim().to_lowercase();
+    if !CATEGORIES.contains(&category.as_str()) {
+        return Err(anyhow::anyhow!("Unknown fact category {}; use one of {}", category, CATEGORIES.join(", ")));
+    }
+    let content = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if content.is_empty() {
+        return Err(anyhow::anyhow!("Fact must not be empty"));
+    }
+    Ok((category, content.chars().take(MAX_FACT_CHARS).collect()))
+}
+
+impl ConversationStore {
+    /// Everything known about the user, by category, oldest first within each.
+    pub fn user_facts(&self) -> Result<Vec<UserFact>> {
+        let mut statement = self.conn.prepare(&format!("{} ORDER BY created_at, id", FACT_SELECT))?;
+        let mut facts = statement
+            .query_map([], fact_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        facts.sort_by_key(|fact| CATEGORIES.iter().position(|category| *category == fact.category));
+        Ok(facts)
+    }
+
+    pub fn get_user_fact(&self, id: i64) -> Result<UserFact> {
+        self.conn
+            .query_row(&format!("{} WHERE id = ?1", FACT_SELECT), params![id], fact_from_row)
+            .optional()?
+            .with_context(|| format!("Fact {} not found", id))
+    }
+
+    /// Keep a fact about the user. A new name replaces the old one, and a fact already known is
+    /// returned rather than stored twice.
+    pub fn add_user_fact(&self, category: &str, content: &str, source: FactSource, conversation_id: Option<i64>) -> Result<UserFact> {
+        let (category, content) = clean_fact(category, content)?;
+        let existing: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM user_facts WHERE category = ?1 AND lower(content) = lower(?2)",
+                params![category, content],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(id) = existing {
+            return self.get_user_fact(id);
+        }
+        if category == "name" {
+            self.conn.execute("DELETE FROM user_facts WHERE category = 'name'", [])?;
+        }
+        let now = now_ms() as i64;
+        self.conn.execute(
+            "INSERT INTO user_facts (category, content, source, conversation_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            params![category, content, source.as_str(), conversation_id, now],
+        )?;
+        self.get_user_fact(self.conn.last_insert_rowid())
+    }
+
+    /// Change a fact. An edited fact counts as the user's own, so extraction won't drop it.
+    pub fn update_user_fact(&self, id: i64, category: Option<&str>, content: &str) -> Result<UserFact> {
+        let current = self.get_user_fact(id)?;
+        let (category, content) = clean_fact(category.unwrap_or(&current.category), content)?;
+        if category == "name" {
+            self.conn.execute("DELETE FROM user_facts WHERE category = 'name' AND id != ?1", params![id])?;
+        }
+        self.conn.execute(
+            "UPDATE user_facts SET category = ?1, content = ?2, source = ?3, updated_at = ?4 WHERE id = ?5",
+            params![category, content, FactSource::User.as_str(), now_ms() as i64, id],
+        )?;
+        self.get_user_fact(id)
+    }
+
+    pub fn delete_user_fact(&self, id: i64) -> Result<()> {
+        let deleted = self.conn.execute("DELETE FROM user_facts WHERE id = ?1", params![id])?;
+        if deleted == 0 {
+            return Err(anyhow::anyhow!("Fact {} not found", id));
+        }
+        Ok(())
+    }
+
+    /// Conversations with turns not yet learned from: finished ones, and the current one once
+    /// it has been quiet for `idle_secs` (never when 0).
+    pub fn conversations_due_for_profile(&self, idle_secs: u32) -> Result<Vec<i64>> {
+        let current = self.current_conversation()?;
+        let idle_since = now_ms().saturating_sub(idle_secs as u64 * 1000) as i64;
+        let mut statement = self.conn.prepare(
+            "SELECT c.id, COUNT(t.id), COALESCE(p.extracted_turns, 0), c.updated_at
+             FROM conversations c
+             JOIN turns t ON t.conversation_id = c.id
+             LEFT JOIN user_profile_progress p ON p.conversation_id = c.id
+             GROUP BY c.id
+             ORDER BY c.updated_at DESC",
+        )?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, u32>(1)?, row.get::<_, u32>(2)?, row.get::<_, i64>(3)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(rows
+            .into_iter()
+            .filter(|(id, turns, extracted, updated_at)| {
+                let finished = Some(*id) != current || (idle_secs > 0 && *updated_at <= idle_since);
+                *turns >= MIN_TURNS && *turns > *extracted && finished
+            })
+            .take(MAX_PER_PASS)
+            .map(|(id, _, _, _)| id)
+            .collect())
+    }
+
+    /// Store what extraction learned from `conversation_id`'s first `extracted_turns` turns,
+    /// returning whether any fact changed. Facts the user set are never dropped as outdated.
+    pub fn apply_profile_update(&self, conversation_id: i64, update: &ProfileUpdate, extracted_turns: u32) -> Result<bool> {
+        let started = now_ms();
+        let mut changed = false;
+        for id in &update.outdated {
+            changed |= self.conn.execute(
+                "DELETE FROM user_facts WHERE id = ?1 AND source = ?2",
+                params![id, FactSource::Conversation.as_str()],
+            )? > 0;
+        }
+        for fact in update.add.iter().take(MAX_NEW_FACTS) {
+            // The user's own name is theirs to change
+            if fact.category.trim().eq_ignore_ascii_case("name") && self.user_set_name()? {
+                continue;
+            }
+            match self.add_user_fact(&fact.category, &fact.content, FactSource::Conversation, Some(conversation_id)) {
+                // A fact already known comes back as it was
+                Ok(stored) => changed |= stored.created_at >= started,
+                Err(e) => log::debug!("Skipped extracted fact: {:#}", e),
+            }
+        }
+        self.conn.execute(
+            "INSERT INTO user_profile_progress (conversation_id, extracted_turns, extracted_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(conversation_id) DO UPDATE SET extracted_turns = ?2, extracted_at = ?3",
+            params![conversation_id, extracted_turns, now_ms() as i64],
+        )?;
+        Ok(changed)
+    }
+
+    fn user_set_name(&self) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM user_facts WHERE category = 'name' AND source = ?1",
+            params![FactSource::User.as_str()],
+            |row| row.get(0),
+        )?)
+    }
+}
+
+/// Ask the LLM what `turns` say about the user that `facts` don't already, and which of
+/// `facts` they show to be outdated.
+pub async fn extract_facts(config: &LlmConfig, facts: &[UserFact], turns: &[ConversationTurn]) -> Result<ProfileUpdate> {
+    let known = if facts.is_empty() {
+        "(nothing yet)".to_string()
+    } else {
+        facts
+            .iter()
+            .map(|fact| format!("{}. [{}] {}", fact.id, fact.category, fact.content))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let transcript = turns[turns.len().saturating_sub(MAX_EXTRACTION_TURNS)..]
+        .iter()
+        .map(|turn| format!("{}: {}", turn.role, turn.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let messages = vec![
+        ChatMessage::system(format!(
+            "You keep a short profile of facts about the user of a voice assistant. Given what is known and a \
+             conversation, reply with only a JSON object of the form \
+             {{\"add\": [{{\"category\": \"...\", \"content\": \"...\"}}], \"outdated\": [1]}}. \
+             Categories are {}: the user's name, their preferences, topics they keep coming back to, and \
+             other lasting details about them. Add only lasting facts about the user that the conversation \
+             states, as short third-person sentences (for a name, just the name), and nothing already known. List under \"outdated\" the \
+             numbers of known facts the conversation contradicts. Reply with empty lists if there is nothing.",
+            CATEGORIES.join(", ")
+        )),
+        ChatMessage::user(format!("Known facts:\n{}\n\nConversation:\n{}", known, transcript)),
+    ];
+
+    let reply = llm::chat_completion(config, &messages, Some(0.1)).await?;
+    // Models like to wrap JSON in prose or code fences; take the outermost object
+    let json = reply
+        .find('{')
+        .zip(reply.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &reply[start..=end])
+        .context("Profile reply contained no JSON object")?;
+    serde_json::from_str(json).context("Failed to parse profile reply")
+}
+
+/// A system message telling the LLM what it knows about the user, or None with nothing known.
+pub fn context_message(facts: &[UserFact]) -> Option<ChatMessage> {
+    if facts.is_empty() {
+        return None;
+    }
+    let listed = facts
+        .iter()
+        .take(MAX_PROMPT_FACTS)
+        .map(|fact| match fact.category.as_str() {
+            "name" => format!("- Their name is {}", fact.content),
+            _ => format!("- {}", fact.content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(ChatMessage::system(format!(
+        "What you know about the user from earlier conversations. Let it shape your answers naturally, \
+         without reciting it:\n{}",
+        listed
+    )))
+}
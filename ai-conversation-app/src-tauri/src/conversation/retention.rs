@@ -0,0 +1,57 @@
+use super::{now_ms, ConversationStore};
+use anyhow::Result;
+use rusqlite::params;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+impl ConversationStore {
+    /// Mark conversations idle for longer than the timeout as ended, returning their ids. An
+    /// ended conversation takes no more turns; the next one starts a new conversation.
+    pub fn close_idle_conversations(&self) -> Result<Vec<i64>> {
+        let idle_since = now_ms().saturating_sub(self.timeout_ms) as i64;
+        let mut statement = self
+            .conn
+            .prepare("UPDATE conversations SET ended_at = updated_at WHERE ended_at IS NULL AND updated_at < ?1 RETURNING id")?;
+        let closed = statement
+            .query_map(params![idle_since], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        Ok(closed)
+    }
+
+    /// Delete the oldest conversations beyond the newest `keep`, with everything recorded about
+    /// them, returning how many went. 0 keeps them all.
+    pub fn prune_conversations(&self, keep: u32) -> Result<usize> {
+        if keep == 0 {
+            return Ok(0);
+        }
+        let deleted = self.conn.execute(
+            "DELETE FROM conversations WHERE id IN
+                (SELECT id FROM conversations ORDER BY updated_at DESC, id DESC LIMIT -1 OFFSET ?1)",
+            params![keep],
+        )?;
+        self.forget_deleted(deleted);
+        Ok(deleted)
+    }
+
+    /// Delete conversations last active more than `days` days ago, with everything recorded
+    /// about them, returning how many went. 0 deletes every conversation.
+    pub fn purge_conversations(&self, days: u32) -> Result<usize> {
+        let before = now_ms().saturating_sub(days as u64 * DAY_MS) as i64;
+        let deleted = if days == 0 {
+            self.conn.execute("DELETE FROM conversations", [])?
+        } else {
+            self.conn.execute("DELETE FROM conversations WHERE updated_at < ?1", params![before])?
+        };
+        self.forget_deleted(deleted);
+        Ok(deleted)
+    }
+
+    // Turns, bookmarks, events, topics and memories go with their conversation; the recall
+    // index is reloaded rather than left pointing at them
+    fn forget_deleted(&self, deleted: usize) {
+        if deleted > 0 {
+            *self.memory_index.borrow_mut() = None;
+            log::info!("Deleted {} conversations", deleted);
+        }
+    }
+}
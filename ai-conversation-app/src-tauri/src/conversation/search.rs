@@ -0,0 +1,116 @@
+use super::topics::ConversationSummary;
+use super::{ConversationStore, ConversationTurn};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// Kept in step with `turns` by triggers, so every stored turn is searchable
+const SCHEMA: &str = "
+    CREATE VIRTUAL TABLE IF NOT EXISTS turns_fts USING fts5(
+        content,
+        content = 'turns',
+        content_rowid = 'id',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TRIGGER IF NOT EXISTS turns_fts_insert AFTER INSERT ON turns BEGIN
+        INSERT INTO turns_fts (rowid, content) VALUES (new.id, new.content);
+    END;
+    CREATE TRIGGER IF NOT EXISTS turns_fts_delete AFTER DELETE ON turns BEGIN
+        INSERT INTO turns_fts (turns_fts, rowid, content) VALUES ('delete', old.id, old.content);
+    END;
+    CREATE TRIGGER IF NOT EXISTS turns_fts_update AFTER UPDATE OF content ON turns BEGIN
+        INSERT INTO turns_fts (turns_fts, rowid, content) VALUES ('delete', old.id, old.content);
+        INSERT INTO turns_fts (rowid, content) VALUES (new.id, new.content);
+    END;
+";
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 100;
+// Words of context around the match in each snippet
+const SNIPPET_WORDS: u32 = 12;
+
+/// A stored turn that matched a history search.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryMatch {
+    pub turn: ConversationTurn,
+    // The matching part of the turn, matched words in **bold**
+    pub snippet: String,
+    pub conversation: ConversationSummary,
+}
+
+/// Create the full-text index, filling it from the turns already stored the first time.
+pub(super) fn create_index(conn: &Connection) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'turns_fts'",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch(SCHEMA)?;
+    if !exists {
+        conn.execute("INSERT INTO turns_fts (turns_fts) VALUES ('rebuild')", [])
+            .context("Failed to index stored turns")?;
+    }
+    Ok(())
+}
+
+/// `query` as an FTS5 expression: every word has to appear, each as a word or the start of
+/// one, so "discuss" finds "discussed". Quoting keeps punctuation from reading as syntax.
+fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|word| word.trim_matches('\''))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+impl ConversationStore {
+    /// Turns matching `query` across all conversations, best matches first, with where they
+    /// matched and the conversation each belongs to.
+    pub fn search_history(&self, query: &str, limit: Option<u32>) -> Result<Vec<HistoryMatch>> {
+        let Some(expression) = match_expression(query) else {
+            return Ok(Vec::new());
+        };
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let mut statement = self.conn.prepare(
+            "SELECT t.id, t.conversation_id, t.role, t.content, t.created_at, t.interrupted,
+                    snippet(turns_fts, 0, '**', '**', '…', ?2)
+             FROM turns_fts JOIN turns t ON t.id = turns_fts.rowid
+             WHERE turns_fts MATCH ?1
+             ORDER BY bm25(turns_fts), t.created_at DESC
+             LIMIT ?3",
+        )?;
+        let matches = statement
+            .query_map(params![expression, SNIPPET_WORDS, limit], |row| {
+                Ok((
+                    ConversationTurn {
+                        id: row.get(0)?,
+                        conversation_id: row.get(1)?,
+                        role: row.get(2)?,
+                        content: row.get(3)?,
+                        created_at: row.get::<_, i64>(4)? as u64,
+                        interrupted: row.get(5)?,
+                    },
+                    row.get::<_, String>(6)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut conversations: HashMap<i64, ConversationSummary> = HashMap::new();
+        let mut results = Vec::with_capacity(matches.len());
+        for (turn, snippet) in matches {
+            let conversation = match conversations.get(&turn.conversation_id) {
+                Some(conversation) => conversation.clone(),
+                None => {
+                    let conversation = self.conversation_summary(turn.conversation_id)?;
+                    conversations.insert(turn.conversation_id, conversation.clone());
+                    conversation
+                }
+            };
+            results.push(HistoryMatch { turn, snippet, conversation });
+        }
+        Ok(results)
+    }
+}
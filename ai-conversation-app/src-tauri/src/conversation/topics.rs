@@ -0,0 +1,225 @@
+use super::{now_ms, ConversationStore, ConversationTurn};
+use crate::config::LlmConfig;
+use crate::llm::{self, ChatMessage};
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS conversation_tags (
+        conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (conversation_id, tag)
+    );
+    CREATE INDEX IF NOT EXISTS conversation_tags_tag ON conversation_tags(tag);
+    CREATE TABLE IF NOT EXISTS conversation_topics (
+        conversation_id INTEGER PRIMARY KEY REFERENCES conversations(id) ON DELETE CASCADE,
+        tagged_turns INTEGER NOT NULL,
+        tagged_at INTEGER NOT NULL
+    );
+";
+
+// Conversations shorter than this don't have a topic yet
+const MIN_TURNS: u32 = 2;
+// Cap per tagging pass so a long untagged history doesn't flood the LLM on upgrade
+const MAX_PER_PASS: usize = 3;
+// Turns sent to the LLM when tagging; the most recent ones if the conversation is longer
+const MAX_TAGGING_TURNS: usize = 40;
+const MAX_TAGS: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationSummary {
+    pub id: i64,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub turn_count: u32,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TopicSummary {
+    pub title: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl ConversationStore {
+    /// Conversations with new turns to tag: finished ones with any untagged turns, and the
+    /// current one once `every_turns` turns have accumulated since it was last tagged or it has
+    /// been quiet for `idle_secs` (never when 0).
+    pub fn conversations_due_for_tagging(&self, every_turns: u32, idle_secs: u32) -> Result<Vec<i64>> {
+        let current = self.current_conversation()?;
+        let idle_since = now_ms().saturating_sub(idle_secs as u64 * 1000) as i64;
+        let mut statement = self.conn.prepare(
+            "SELECT c.id, COUNT(t.id), COALESCE(ct.tagged_turns, 0), c.updated_at
+             FROM conversations c
+             JOIN turns t ON t.conversation_id = c.id
+             LEFT JOIN conversation_topics ct ON ct.conversation_id = c.id
+             GROUP BY c.id
+             ORDER BY c.updated_at DESC",
+        )?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, u32>(1)?, row.get::<_, u32>(2)?, row.get::<_, i64>(3)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(rows
+            .into_iter()
+            .filter(|(id, turns, tagged, updated_at)| {
+                let untagged = turns.saturating_sub(*tagged);
+                *turns >= MIN_TURNS
+                    && if Some(*id) == current {
+                        let idle = idle_secs > 0 && *updated_at <= idle_since;
+                        untagged >= every_turns.max(1) || (untagged > 0 && idle)
+                    } else {
+                        untagged > 0
+                    }
+            })
+            .take(MAX_PER_PASS)
+            .map(|(id, _, _, _)| id)
+            .collect())
+    }
+
+    pub fn conversation_turns(&self, conversation_id: i64) -> Result<Vec<ConversationTurn>> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, conversation_id, role, content, created_at, interrupted FROM turns WHERE conversation_id = ?1 ORDER BY id",
+        )?;
+        let turns = statement
+            .query_map(params![conversation_id], |row| {
+                Ok(ConversationTurn {
+                    id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    role: row.get(2)?,
+                    content: row.get(3)?,
+                    created_at: row.get::<_, i64>(4)? as u64,
+                    interrupted: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(turns)
+    }
+
+    /// Store a generated title and tags, replacing earlier ones.
+    pub fn set_conversation_topics(&self, conversation_id: i64, summary: &TopicSummary, tagged_turns: u32) -> Result<()> {
+        let title = summary.title.trim();
+        self.conn.execute(
+            "UPDATE conversations SET title = ?1 WHERE id = ?2",
+            params![(!title.is_empty()).then_some(title), conversation_id],
+        )?;
+        self.conn.execute("DELETE FROM conversation_tags WHERE conversation_id = ?1", params![conversation_id])?;
+        for tag in normalize_tags(&summary.tags) {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag) VALUES (?1, ?2)",
+                params![conversation_id, tag],
+            )?;
+        }
+        self.conn.execute(
+            "INSERT INTO conversation_topics (conversation_id, tagged_turns, tagged_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(conversation_id) DO UPDATE SET tagged_turns = ?2, tagged_at = ?3",
+            params![conversation_id, tagged_turns, now_ms() as i64],
+        )?;
+        Ok(())
+    }
+
+    pub fn conversation_tags(&self, conversation_id: i64) -> Result<Vec<String>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT tag FROM conversation_tags WHERE conversation_id = ?1 ORDER BY tag")?;
+        let tags = statement
+            .query_map(params![conversation_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(tags)
+    }
+
+    pub fn conversation_summary(&self, conversation_id: i64) -> Result<ConversationSummary> {
+        let (title, created_at, updated_at, turn_count) = self
+            .conn
+            .query_row(
+                "SELECT c.title, c.created_at, c.updated_at,
+                        (SELECT COUNT(*) FROM turns t WHERE t.conversation_id = c.id)
+                 FROM conversations c WHERE c.id = ?1",
+                params![conversation_id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, u32>(3)?,
+                    ))
+                },
+            )
+            .optional()?
+            .with_context(|| format!("Conversation {} not found", conversation_id))?;
+        Ok(ConversationSummary {
+            id: conversation_id,
+            title,
+            tags: self.conversation_tags(conversation_id)?,
+            turn_count,
+            created_at: created_at as u64,
+            updated_at: updated_at as u64,
+        })
+    }
+
+    /// List conversations, newest first, optionally filtered by topic tag and a free-text
+    /// query matching the title or any turn.
+    pub fn list_conversations(&self, query: Option<&str>, tag: Option<&str>) -> Result<Vec<ConversationSummary>> {
+        let query = query.map(str::trim).filter(|q| !q.is_empty()).map(|q| format!("%{}%", q));
+        let tag = tag.map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty());
+        let mut statement = self.conn.prepare(
+            "SELECT c.id FROM conversations c
+             WHERE (?1 IS NULL OR c.title LIKE ?1
+                    OR EXISTS (SELECT 1 FROM turns t WHERE t.conversation_id = c.id AND t.content LIKE ?1))
+               AND (?2 IS NULL
+                    OR EXISTS (SELECT 1 FROM conversation_tags ct WHERE ct.conversation_id = c.id AND ct.tag = ?2))
+             ORDER BY c.updated_at DESC",
+        )?;
+        let ids = statement
+            .query_map(params![query, tag], |row| row.get::<_, i64>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        ids.into_iter().map(|id| self.conversation_summary(id)).collect()
+    }
+}
+
+/// Ask the LLM for a one-line title and a few topic tags.
+pub async fn generate_topics(config: &LlmConfig, turns: &[ConversationTurn]) -> Result<TopicSummary> {
+    let transcript = turns[turns.len().saturating_sub(MAX_TAGGING_TURNS)..]
+        .iter()
+        .map(|turn| format!("{}: {}", turn.role, turn.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let messages = vec![
+        ChatMessage::system(format!(
+            "You label conversations. Reply with only a JSON object of the form \
+             {{\"title\": \"...\", \"tags\": [\"...\"]}}. The title is one short line of at most eight words. \
+             Give at most {} tags, each one or two lowercase words naming a topic.",
+            MAX_TAGS
+        )),
+        ChatMessage::user(transcript),
+    ];
+
+    let reply = llm::chat_completion(config, &messages, Some(0.2)).await?;
+    // Models like to wrap JSON in prose or code fences; take the outermost object
+    let json = reply
+        .find('{')
+        .zip(reply.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &reply[start..=end])
+        .context("Topic reply contained no JSON object")?;
+    let mut summary: TopicSummary = serde_json::from_str(json).context("Failed to parse topic reply")?;
+    summary.tags = normalize_tags(&summary.tags);
+    Ok(summary)
+}
+
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase().replace(',', " ");
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized.truncate(MAX_TAGS);
+    normalized
+}
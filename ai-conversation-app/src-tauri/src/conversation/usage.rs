@@ -0,0 +1,171 @@
+use super::{now_ms, ConversationStore};
+use crate::llm::usage::UsageRecord;
+use anyhow::Result;
+use rusqlite::params;
+use serde::Serialize;
+
+pub(super) const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS llm_usage (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        conversation_id INTEGER REFERENCES conversations(id) ON DELETE SET NULL,
+        provider TEXT NOT NULL,
+        model TEXT NOT NULL,
+        prompt_tokens INTEGER NOT NULL,
+        completion_tokens INTEGER NOT NULL,
+        estimated INTEGER NOT NULL DEFAULT 0,
+        cost REAL NOT NULL DEFAULT 0,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS llm_usage_created ON llm_usage(created_at);
+    CREATE INDEX IF NOT EXISTS llm_usage_conversation ON llm_usage(conversation_id);
+";
+
+// Conversations listed in the stats, costliest first
+const MAX_CONVERSATIONS: usize = 20;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub requests: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+    // Requests whose tokens were counted here rather than reported by the server
+    pub estimated_requests: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyUsage {
+    // Local date, YYYY-MM-DD
+    pub date: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelUsage {
+    pub provider: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationUsage {
+    pub conversation_id: i64,
+    pub title: Option<String>,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageStats {
+    pub since: u64,
+    pub total: UsageTotals,
+    // Oldest first, days without requests left out
+    pub by_day: Vec<DailyUsage>,
+    pub by_model: Vec<ModelUsage>,
+    pub by_conversation: Vec<ConversationUsage>,
+}
+
+const TOTALS: &str = "COUNT(*), COALESCE(SUM(u.prompt_tokens), 0), COALESCE(SUM(u.completion_tokens), 0),
+                      COALESCE(SUM(u.cost), 0), COALESCE(SUM(u.estimated), 0)";
+
+// The five TOTALS columns starting at `first`
+fn totals_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<UsageTotals> {
+    Ok(UsageTotals {
+        requests: row.get(first)?,
+        prompt_tokens: row.get::<_, i64>(first + 1)? as u64,
+        completion_tokens: row.get::<_, i64>(first + 2)? as u64,
+        cost: row.get(first + 3)?,
+        estimated_requests: row.get(first + 4)?,
+    })
+}
+
+impl ConversationStore {
+    /// Record a finished LLM request against the current conversation, if there is one;
+    /// background requests between conversations count toward the day only.
+    pub fn record_usage(&self, record: &UsageRecord) -> Result<()> {
+        let conversation_id = self.current_conversation()?;
+        self.conn.execute(
+            "INSERT INTO llm_usage (conversation_id, provider, model, prompt_tokens, completion_tokens, estimated, cost, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                conversation_id,
+                record.provider,
+                record.model,
+                record.usage.prompt_tokens,
+                record.usage.completion_tokens,
+                record.estimated,
+                record.cost,
+                now_ms() as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Token use and cost over the last `days` days, in total and by day, model and conversation.
+    pub fn usage_stats(&self, days: u32) -> Result<UsageStats> {
+        let since = now_ms().saturating_sub(days as u64 * DAY_MS) as i64;
+
+        let total = self.conn.query_row(
+            &format!("SELECT {} FROM llm_usage u WHERE u.created_at >= ?1", TOTALS),
+            params![since],
+            |row| totals_from_row(row, 0),
+        )?;
+
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT date(u.created_at / 1000, 'unixepoch', 'localtime') AS day, {}
+             FROM llm_usage u WHERE u.created_at >= ?1 GROUP BY day ORDER BY day",
+            TOTALS
+        ))?;
+        let by_day = statement
+            .query_map(params![since], |row| {
+                Ok(DailyUsage {
+                    date: row.get(0)?,
+                    totals: totals_from_row(row, 1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT u.provider, u.model, {}
+             FROM llm_usage u WHERE u.created_at >= ?1 GROUP BY u.provider, u.model ORDER BY SUM(u.cost) DESC, COUNT(*) DESC",
+            TOTALS
+        ))?;
+        let by_model = statement
+            .query_map(params![since], |row| {
+                Ok(ModelUsage {
+                    provider: row.get(0)?,
+                    model: row.get(1)?,
+                    totals: totals_from_row(row, 2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT u.conversation_id, c.title, {}
+             FROM llm_usage u JOIN conversations c ON c.id = u.conversation_id
+             WHERE u.created_at >= ?1 GROUP BY u.conversation_id
+             ORDER BY SUM(u.cost) DESC, SUM(u.prompt_tokens + u.completion_tokens) DESC LIMIT ?2",
+            TOTALS
+        ))?;
+        let by_conversation = statement
+            .query_map(params![since, MAX_CONVERSATIONS as i64], |row| {
+                Ok(ConversationUsage {
+                    conversation_id: row.get(0)?,
+                    title: row.get(1)?,
+                    totals: totals_from_row(row, 2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(UsageStats {
+            since: since as u64,
+            total,
+            by_day,
+            by_model,
+            by_conversation,
+        })
+    }
+}
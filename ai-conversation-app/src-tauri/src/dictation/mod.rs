@@ -0,0 +1,292 @@
+use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum DictationAction {
+    Type(String),
+    Newline,
+    /// Delete the given number of characters before the cursor
+    Erase(usize),
+}
+
+// Spoken punctuation, longest phrases first so "exclamation mark" wins over "mark"
+const PUNCTUATION: &[(&str, &str)] = &[
+    ("exclamation point", "!"),
+    ("exclamation mark", "!"),
+    ("question mark", "?"),
+    ("full stop", "."),
+    ("semicolon", ";"),
+    ("period", "."),
+    ("comma", ","),
+    ("colon", ":"),
+    ("dash", " -"),
+];
+
+const NEWLINE_COMMANDS: &[(&str, usize)] = &[("new paragraph", 2), ("new line", 1), ("newline", 1)];
+
+const SCRATCH_COMMANDS: &[&str] = &["scratch that", "delete that", "undo that"];
+
+struct TypedChunk {
+    length: usize,
+    at_sentence_start: bool,
+    at_line_start: bool,
+}
+
+enum Token {
+    Word(String),
+    Punctuation(&'static str),
+    Newlines(usize),
+    Scratch,
+}
+
+/// Turns final transcripts into keystroke actions, handling spoken
+/// punctuation, line breaks and "scratch that".
+pub struct DictationSession {
+    pub active: bool,
+    // Each chunk typed so far with the cursor state before it, for "scratch that"
+    typed_chunks: Vec<TypedChunk>,
+    at_sentence_start: bool,
+    at_line_start: bool,
+}
+
+impl DictationSession {
+    pub fn new() -> Self {
+        Self {
+            active: false,
+            typed_chunks: Vec::new(),
+            at_sentence_start: true,
+            at_line_start: true,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.active = true;
+        self.typed_chunks.clear();
+        self.at_sentence_start = true;
+        self.at_line_start = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.typed_chunks.clear();
+    }
+
+    pub fn process_transcript(&mut self, transcript: &str) -> Vec<DictationAction> {
+        let mut actions = Vec::new();
+        let mut chunk = String::new();
+        let mut chunk_start = (self.at_sentence_start, self.at_line_start);
+
+        for token in tokenize(transcript) {
+            match token {
+                Token::Word(word) => {
+                    if !self.at_line_start || !chunk.is_empty() {
+                        chunk.push(' ');
+                    }
+                    if self.at_sentence_start {
+                        let mut chars = word.chars();
+                        if let Some(first) = chars.next() {
+                            chunk.extend(first.to_uppercase());
+                            chunk.push_str(chars.as_str());
+                        }
+                    } else {
+                        chunk.push_str(&word);
+                    }
+                    self.at_sentence_start = false;
+                    self.at_line_start = false;
+                }
+                Token::Punctuation(mark) => {
+                    chunk.push_str(mark);
+                    self.at_sentence_start = matches!(mark, "." | "?" | "!");
+                }
+                Token::Newlines(count) => {
+                    self.flush_chunk(&mut chunk, chunk_start, &mut actions);
+                    for _ in 0..count {
+                        actions.push(DictationAction::Newline);
+                    }
+                    self.typed_chunks.push(TypedChunk {
+                        length: count,
+                        at_sentence_start: self.at_sentence_start,
+                        at_line_start: self.at_line_start,
+                    });
+                    self.at_line_start = true;
+                    self.at_sentence_start = true;
+                    chunk_start = (true, true);
+                }
+                Token::Scratch => {
+                    if !chunk.is_empty() {
+                        chunk.clear();
+                    } else if let Some(scratched) = self.typed_chunks.pop() {
+                        actions.push(DictationAction::Erase(scratched.length));
+                        chunk_start = (scratched.at_sentence_start, scratched.at_line_start);
+                    }
+                    (self.at_sentence_start, self.at_line_start) = chunk_start;
+                }
+            }
+        }
+
+        self.flush_chunk(&mut chunk, chunk_start, &mut actions);
+        actions
+    }
+
+    fn flush_chunk(&mut self, chunk: &mut String, chunk_start: (bool, bool), actions: &mut Vec<DictationAction>) {
+        if chunk.is_empty() {
+            return;
+        }
+        self.typed_chunks.push(TypedChunk {
+            length: chunk.chars().count(),
+            at_sentence_start: chunk_start.0,
+            at_line_start: chunk_start.1,
+        });
+        actions.push(DictationAction::Type(std::mem::take(chunk)));
+    }
+}
+
+impl Default for DictationSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn tokenize(transcript: &str) -> Vec<Token> {
+    // Whisper already inserts punctuation; keep it attached but treat spoken commands as tokens
+    let lowered = transcript.to_lowercase();
+    let words: Vec<&str> = transcript.split_whitespace().collect();
+    let lowered_words: Vec<String> = lowered
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| c.is_ascii_punctuation()).to_string())
+        .collect();
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    'outer: while i < words.len() {
+        let rest = lowered_words[i..].join(" ");
+
+        for command in SCRATCH_COMMANDS {
+            if starts_with_phrase(&rest, command) {
+                tokens.push(Token::Scratch);
+                i += command.split_whitespace().count();
+                continue 'outer;
+            }
+        }
+        for (command, count) in NEWLINE_COMMANDS {
+            if starts_with_phrase(&rest, command) {
+                tokens.push(Token::Newlines(*count));
+                i += command.split_whitespace().count();
+                continue 'outer;
+            }
+        }
+        for (spoken, mark) in PUNCTUATION {
+            if starts_with_phrase(&rest, spoken) {
+                tokens.push(Token::Punctuation(mark));
+                i += spoken.split_whitespace().count();
+                continue 'outer;
+            }
+        }
+
+        tokens.push(Token::Word(words[i].to_string()));
+        i += 1;
+    }
+    tokens
+}
+
+fn starts_with_phrase(text: &str, phrase: &str) -> bool {
+    text == phrase || text.starts_with(&format!("{} ", phrase))
+}
+
+/// Types dictation actions into whichever application currently has focus.
+#[cfg(desktop)]
+pub fn inject_actions(actions: &[DictationAction]) -> Result<()> {
+    use anyhow::Context;
+    use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+
+    let mut enigo = Enigo::new(&Settings::default()).context("Failed to initialize keystroke injection")?;
+
+    for action in actions {
+        match action {
+            DictationAction::Type(text) => {
+                enigo.text(text).context("Failed to type dictated text")?;
+            }
+            DictationAction::Newline => {
+                enigo.key(Key::Return, Direction::Click).context("Failed to type newline")?;
+            }
+            DictationAction::Erase(count) => {
+                for _ in 0..*count {
+                    enigo.key(Key::Backspace, Direction::Click).context("Failed to erase text")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(mobile)]
+pub fn inject_actions(_actions: &[DictationAction]) -> Result<()> {
+    Err(crate::platform::desktop_only("System-wide dictation"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> DictationAction {
+        DictationAction::Type(text.to_string())
+    }
+
+    #[test]
+    fn spoken_punctuation_is_typed_and_starts_sentences() {
+        let mut session = DictationSession::new();
+        assert_eq!(
+            session.process_transcript("hello world period how are you question mark"),
+            vec![typed("Hello world. How are you?")]
+        );
+        // The next transcript carries on the same line, after a space
+        assert_eq!(session.process_transcript("fine thanks"), vec![typed(" Fine thanks")]);
+    }
+
+    #[test]
+    fn punctuation_from_the_transcriber_is_kept() {
+        let mut session = DictationSession::new();
+        assert_eq!(session.process_transcript("Hello, world."), vec![typed("Hello, world.")]);
+    }
+
+    #[test]
+    fn line_breaks_split_the_typed_text() {
+        let mut session = DictationSession::new();
+        assert_eq!(
+            session.process_transcript("first new line second new paragraph third"),
+            vec![
+                typed("First"),
+                DictationAction::Newline,
+                typed("Second"),
+                DictationAction::Newline,
+                DictationAction::Newline,
+                typed("Third"),
+            ]
+        );
+    }
+
+    #[test]
+    fn scratch_that_erases_the_last_chunk() {
+        let mut session = DictationSession::new();
+        session.process_transcript("hello there");
+        assert_eq!(session.process_transcript("scratch that"), vec![DictationAction::Erase(11)]);
+        // The cursor is back at the start of the line
+        assert_eq!(session.process_transcript("goodbye"), vec![typed("Goodbye")]);
+    }
+
+    #[test]
+    fn scratch_that_drops_words_not_typed_yet() {
+        let mut session = DictationSession::new();
+        assert_eq!(session.process_transcript("hello scratch that goodbye"), vec![typed("Goodbye")]);
+    }
+
+    #[test]
+    fn scratching_a_line_break_erases_it() {
+        let mut session = DictationSession::new();
+        session.process_transcript("first new line");
+        assert_eq!(session.process_transcript("scratch that"), vec![DictationAction::Erase(1)]);
+        assert_eq!(session.process_transcript("scratch that"), vec![DictationAction::Erase(5)]);
+        assert_eq!(session.process_transcript("scratch that"), Vec::new());
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// File types text can be extracted from, by extension.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["txt", "md", "markdown", "pdf", "rst", "csv", "log"];
+
+// Bigger files are refused rather than read into memory
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+pub fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| SUPPORTED_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// The text of the document at `path`: PDFs have their text layer extracted, anything else
+/// is read as UTF-8. Blocks; scanned PDFs without a text layer come back empty.
+pub fn extract_text(path: &Path) -> Result<String> {
+    if !is_supported(path) {
+        return Err(anyhow::anyhow!(
+            "Can't read {}; supported files are {}",
+            path.display(),
+            SUPPORTED_EXTENSIONS.join(", ")
+        ));
+    }
+    let size = std::fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?.len();
+    if size > MAX_FILE_BYTES {
+        return Err(anyhow::anyhow!("{} is too large to read ({} MB)", path.display(), size / (1024 * 1024)));
+    }
+
+    let text = if path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("pdf")) {
+        pdf_extract::extract_text(path).map_err(|e| anyhow::anyhow!("Failed to extract text from {}: {}", path.display(), e))?
+    } else {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+    Ok(normalize_whitespace(&text))
+}
+
+/// Collapse the runs of spaces and blank lines PDF extraction leaves, keeping paragraph breaks.
+fn normalize_whitespace(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        normalized.push_str(&line);
+        blank_lines = 0;
+    }
+    normalized
+}
+
+/// Split `text` into pieces of at most `max_chars` characters, breaking between paragraphs
+/// where possible, then between lines, then wherever the limit falls.
+pub fn chunk(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for paragraph in text.split("\n\n") {
+        for piece in split_long(paragraph, max_chars) {
+            let separator = if current.is_empty() { 0 } else { 2 };
+            if current.chars().count() + separator + piece.chars().count() > max_chars && !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(&piece);
+        }
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+// A paragraph longer than `max_chars`, split at line breaks, or mid-line when a line is too
+fn split_long(paragraph: &str, max_chars: usize) -> Vec<String> {
+    if paragraph.chars().count() <= max_chars {
+        return vec![paragraph.to_string()];
+    }
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for line in paragraph.lines() {
+        let line: Vec<char> = line.chars().collect();
+        for part in line.chunks(max_chars) {
+            let part: String = part.iter().collect();
+            if current.chars().count() + 1 + part.chars().count() > max_chars && !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(&part);
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
@@ -0,0 +1,183 @@
+use crate::config::FocusConfig;
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// The application that owns the foreground window.
+#[derive(Debug, Clone)]
+pub struct ForegroundApp {
+    pub name: String,
+    pub pid: u32,
+    // The window covers its whole monitor
+    pub fullscreen: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusKind {
+    VideoCall,
+    Presentation,
+    // Games, videos and anything else running fullscreen
+    Fullscreen,
+}
+
+/// Why interruptions are currently suppressed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FocusSuppression {
+    pub app: String,
+    pub kind: FocusKind,
+}
+
+/// Decide whether the foreground app should suppress interruptions.
+pub fn classify(app: &ForegroundApp, config: &FocusConfig) -> Option<FocusSuppression> {
+    // Our own windows never count, even when the character view is fullscreen
+    if app.pid == std::process::id() {
+        return None;
+    }
+    let name = app.name.to_lowercase();
+    let matches = |apps: &[String]| apps.iter().any(|entry| !entry.is_empty() && name.contains(&entry.to_lowercase()));
+
+    let kind = if matches(&config.video_call_apps) {
+        FocusKind::VideoCall
+    } else if app.fullscreen && matches(&config.presentation_apps) {
+        FocusKind::Presentation
+    } else if app.fullscreen && config.suppress_fullscreen {
+        FocusKind::Fullscreen
+    } else {
+        return None;
+    };
+    Some(FocusSuppression {
+        app: app.name.clone(),
+        kind,
+    })
+}
+
+/// Look up the foreground window. Blocks briefly (it may run a helper process); call it off
+/// the async runtime.
+#[cfg(target_os = "windows")]
+pub fn foreground_app() -> Result<Option<ForegroundApp>> {
+    use windows_sys::Win32::Foundation::{CloseHandle, RECT};
+    use windows_sys::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST};
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetDesktopWindow, GetForegroundWindow, GetShellWindow, GetWindowRect, GetWindowThreadProcessId,
+    };
+
+    unsafe {
+        let window = GetForegroundWindow();
+        // The desktop itself covers the whole monitor but isn't an app
+        if window.is_null() || window == GetShellWindow() || window == GetDesktopWindow() {
+            return Ok(None);
+        }
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(window, &mut pid);
+
+        let mut rect: RECT = std::mem::zeroed();
+        let mut monitor: MONITORINFO = std::mem::zeroed();
+        monitor.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        let fullscreen = GetWindowRect(window, &mut rect) != 0
+            && GetMonitorInfoW(MonitorFromWindow(window, MONITOR_DEFAULTTONEAREST), &mut monitor) != 0
+            && rect.left <= monitor.rcMonitor.left
+            && rect.top <= monitor.rcMonitor.top
+            && rect.right >= monitor.rcMonitor.right
+            && rect.bottom >= monitor.rcMonitor.bottom;
+
+        let mut name = String::new();
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if !process.is_null() {
+            let mut buffer = [0u16; 1024];
+            let mut length = buffer.len() as u32;
+            if QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut length) != 0 {
+                let path = String::from_utf16_lossy(&buffer[..length as usize]);
+                name = std::path::Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or(path);
+            }
+            CloseHandle(process);
+        }
+
+        Ok(Some(ForegroundApp { name, pid, fullscreen }))
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn foreground_app() -> Result<Option<ForegroundApp>> {
+    // Reading AXFullScreen needs the accessibility permission; without it the app is
+    // still identified, just never reported as fullscreen
+    const SCRIPT: &str = r#"
+        tell application "System Events"
+            set frontApp to first application process whose frontmost is true
+            set isFullscreen to false
+            try
+                set isFullscreen to value of attribute "AXFullScreen" of front window of frontApp
+            end try
+            return (name of frontApp) & "|" & (unix id of frontApp) & "|" & isFullscreen
+        end tell
+    "#;
+    let output = std::process::Command::new("osascript")
+        .args(["-e", SCRIPT])
+        .output()
+        .context("Failed to run osascript")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "osascript failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut fields = stdout.trim().rsplitn(3, '|');
+    let fullscreen = fields.next() == Some("true");
+    let pid = fields.next().and_then(|pid| pid.trim().parse().ok()).unwrap_or(0);
+    let name = fields.next().unwrap_or_default().to_string();
+    Ok((!name.is_empty()).then_some(ForegroundApp { name, pid, fullscreen }))
+}
+
+/// X11 (including XWayland apps) through xprop; native Wayland offers no way to see other
+/// apps' windows.
+#[cfg(all(desktop, not(any(target_os = "windows", target_os = "macos"))))]
+pub fn foreground_app() -> Result<Option<ForegroundApp>> {
+    let xprop = |args: &[&str]| -> Result<String> {
+        let output = std::process::Command::new("xprop")
+            .args(args)
+            .output()
+            .context("Failed to run xprop; install it to enable focus-app detection")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "xprop failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    // "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007"
+    let active = xprop(&["-root", "_NET_ACTIVE_WINDOW"])?;
+    let Some(window) = active.split_whitespace().last().filter(|id| id.starts_with("0x") && *id != "0x0") else {
+        return Ok(None);
+    };
+
+    let properties = xprop(&["-id", window, "_NET_WM_STATE", "WM_CLASS", "_NET_WM_PID"])?;
+    let mut app = ForegroundApp {
+        name: String::new(),
+        pid: 0,
+        fullscreen: false,
+    };
+    for line in properties.lines() {
+        let Some((property, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        if property.starts_with("_NET_WM_STATE") {
+            app.fullscreen = value.contains("_NET_WM_STATE_FULLSCREEN");
+        } else if property.starts_with("WM_CLASS") {
+            // WM_CLASS is "instance", "class"; the class names the application
+            app.name = value.rsplit(", ").next().unwrap_or(value).trim_matches('"').to_string();
+        } else if property.starts_with("_NET_WM_PID") {
+            app.pid = value.trim().parse().unwrap_or(0);
+        }
+    }
+    Ok(Some(app))
+}
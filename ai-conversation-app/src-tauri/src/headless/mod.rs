@@ -0,0 +1,367 @@
+use crate::config::{self, AppConfig};
+use crate::i18n;
+use crate::llm::{self, ChatMessage};
+use crate::meeting;
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+const WHISPER_SAMPLE_RATE: u32 = 16000;
+// Trailing silence that ends an utterance
+const END_OF_SPEECH: Duration = Duration::from_millis(700);
+// Conversation turns kept as LLM context
+const MAX_HISTORY: usize = 20;
+
+/// `--headless` runs the voice loop without creating any windows.
+pub fn requested() -> bool {
+    std::env::args().any(|arg| arg == "--headless")
+}
+
+pub fn run() -> Result<()> {
+    let config = config::try_get_config().context("Headless mode needs a valid config.yaml")?;
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start async runtime")?;
+    runtime.block_on(voice_loop(config))
+}
+
+/// Splits the microphone feed into utterances using a simple energy gate.
+struct UtteranceDetector {
+    threshold: f32,
+    min_samples: usize,
+    max_samples: usize,
+    silence_samples: usize,
+    buffer: Vec<f32>,
+    speaking: bool,
+    silence: usize,
+}
+
+impl UtteranceDetector {
+    fn new(config: &AppConfig, sample_rate: u32) -> Self {
+        let rate = sample_rate as f32;
+        Self {
+            threshold: config.stt.silence_threshold,
+            min_samples: (config.stt.min_speech_duration * rate) as usize,
+            max_samples: (config.stt.max_speech_duration * rate) as usize,
+            silence_samples: (END_OF_SPEECH.as_secs_f32() * rate) as usize,
+            buffer: Vec::new(),
+            speaking: false,
+            silence: 0,
+        }
+    }
+
+    fn push(&mut self, chunk: &[f32]) -> Option<Vec<f32>> {
+        let rms = (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len().max(1) as f32).sqrt();
+        if rms >= self.threshold {
+            self.speaking = true;
+            self.silence = 0;
+        } else if self.speaking {
+            self.silence += chunk.len();
+        }
+
+        if !self.speaking {
+            return None;
+        }
+        self.buffer.extend_from_slice(chunk);
+
+        if self.silence >= self.silence_samples || self.buffer.len() >= self.max_samples {
+            let utterance = std::mem::take(&mut self.buffer);
+            self.speaking = false;
+            self.silence = 0;
+            if utterance.len() >= self.min_samples {
+                return Some(utterance);
+            }
+        }
+        None
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.speaking = false;
+        self.silence = 0;
+    }
+}
+
+async fn voice_loop(config: Arc<AppConfig>) -> Result<()> {
+    let model_path = crate::models::resolve(&config.stt.model)?;
+    let whisper_ctx = Arc::new(
+        WhisperContext::new_with_params(&model_path.to_string_lossy(), WhisperContextParameters::default())
+            .context("Failed to load Whisper model")?,
+    );
+
+    let running = Arc::new(AtomicBool::new(true));
+    let (audio_tx, mut audio_rx) = mpsc::unbounded_channel::<Vec<f32>>();
+    let (sample_rate, capture_thread) = start_capture(&config, running.clone(), audio_tx)?;
+    let mut detector = UtteranceDetector::new(&config, sample_rate);
+
+    let wake_word = normalize(&config.headless.wake_word);
+    let follow_up = Duration::from_secs_f32(config.headless.follow_up_seconds.max(0.0));
+    let mut awake_until: Option<Instant> = None;
+    let mut history = vec![ChatMessage::system(config.llm.system_prompt.clone())];
+
+    println!("Headless assistant ready (model: {}, llm: {}).", config.stt.model, config.llm.model);
+    if wake_word.is_empty() {
+        println!("{}", i18n::tr("headless-listening"));
+    } else {
+        println!("{}", i18n::tr_with("headless-wake-word", &[("wake_word", config.headless.wake_word.as_str())]));
+    }
+
+    loop {
+        let chunk = tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            chunk = audio_rx.recv() => match chunk {
+                Some(chunk) => chunk,
+                None => break,
+            },
+        };
+
+        let Some(utterance) = detector.push(&chunk) else {
+            continue;
+        };
+
+        let samples = meeting::resample_linear(&utterance, sample_rate, WHISPER_SAMPLE_RATE);
+        let ctx = whisper_ctx.clone();
+        let language = config.stt.language.clone();
+        let transcript = match tokio::task::spawn_blocking(move || transcribe(&ctx, &samples, &language)).await? {
+            Ok(text) if !text.is_empty() => text,
+            Ok(_) => continue,
+            Err(e) => {
+                log::error!("Transcription failed: {}", e);
+                // Only apologize when the user was talking to us
+                if wake_word.is_empty() || awake_until.is_some_and(|until| Instant::now() < until) {
+                    speak_phrase(&config, "spoken-didnt-catch").await;
+                    while audio_rx.try_recv().is_ok() {}
+                    detector.reset();
+                }
+                continue;
+            }
+        };
+
+        let awake = awake_until.is_some_and(|until| Instant::now() < until);
+        let request = if wake_word.is_empty() || awake {
+            transcript.clone()
+        } else {
+            match strip_wake_word(&transcript, &wake_word) {
+                Some(rest) => rest,
+                None => {
+                    log::debug!("Ignoring speech without wake word: {}", transcript);
+                    continue;
+                }
+            }
+        };
+
+        println!("You: {}", transcript);
+        if request.is_empty() {
+            // Wake word on its own: wait for the actual request
+            awake_until = Some(Instant::now() + follow_up.max(Duration::from_secs(5)));
+            println!("{}", i18n::tr("headless-listening"));
+            continue;
+        }
+
+        history.push(ChatMessage::user(request));
+        let reply = match llm::chat_completion(&config.llm, &history, None).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                history.pop();
+                eprintln!("LLM request failed: {:#}", e);
+                speak_phrase(&config, "spoken-llm-unavailable").await;
+                continue;
+            }
+        };
+        println!("Assistant: {}", reply);
+        history.push(ChatMessage::assistant(reply.clone()));
+        if history.len() > MAX_HISTORY + 1 {
+            // Keep the system prompt, drop the oldest exchange
+            history.drain(1..3);
+        }
+
+        if config.headless.system_voice {
+            let text = reply.clone();
+            if let Err(e) = tokio::task::spawn_blocking(move || speak_with_system_voice(&text)).await? {
+                log::error!("Speech output failed: {}", e);
+            }
+            // Drop whatever the mic picked up while we were talking
+            while audio_rx.try_recv().is_ok() {}
+            detector.reset();
+        }
+        awake_until = Some(Instant::now() + follow_up);
+    }
+
+    println!("{}", i18n::tr("headless-shutting-down"));
+    running.store(false, Ordering::SeqCst);
+    let _ = capture_thread.join();
+    Ok(())
+}
+
+fn start_capture(
+    config: &AppConfig,
+    running: Arc<AtomicBool>,
+    audio_tx: mpsc::UnboundedSender<Vec<f32>>,
+) -> Result<(u32, std::thread::JoinHandle<()>)> {
+    crate::platform::configure_audio_session()?;
+
+    // cpal streams are not Send, so the stream lives on its own thread
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<u32>>();
+    let device_name = config.audio.input.device.clone();
+    let handle = std::thread::spawn(move || {
+        let stream = (|| -> Result<(cpal::Stream, u32)> {
+            let host = cpal::default_host();
+            let device = meeting::find_input_device(&host, &device_name)?;
+            let supported = device.default_input_config()?;
+            let sample_format = supported.sample_format();
+            let stream_config: cpal::StreamConfig = supported.into();
+            let channels = stream_config.channels as usize;
+            let on_error = |err| log::error!("Headless capture stream error: {}", err);
+
+            let stream = match sample_format {
+                cpal::SampleFormat::F32 => {
+                    let tx = audio_tx.clone();
+                    device.build_input_stream(
+                        &stream_config,
+                        move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                            let _ = tx.send(meeting::downmix(data, channels));
+                        },
+                        on_error,
+                        None,
+                    )?
+                }
+                cpal::SampleFormat::I16 => {
+                    let tx = audio_tx.clone();
+                    device.build_input_stream(
+                        &stream_config,
+                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                            let samples: Vec<f32> = data.iter().map(|&s| s as f32 / i16::MAX as f32).collect();
+                            let _ = tx.send(meeting::downmix(&samples, channels));
+                        },
+                        on_error,
+                        None,
+                    )?
+                }
+                other => return Err(anyhow::anyhow!("Unsupported capture sample format: {:?}", other)),
+            };
+            stream.play()?;
+            Ok((stream, stream_config.sample_rate.0))
+        })();
+
+        match stream {
+            Ok((stream, sample_rate)) => {
+                let _ = ready_tx.send(Ok(sample_rate));
+                while running.load(Ordering::SeqCst) {
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                drop(stream);
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+            }
+        }
+    });
+
+    let sample_rate = ready_rx
+        .recv()
+        .context("Capture thread exited unexpectedly")??;
+    Ok((sample_rate, handle))
+}
+
+fn transcribe(ctx: &WhisperContext, samples: &[f32], language: &str) -> Result<String> {
+    let mut state = ctx.create_state().context("Failed to create Whisper state")?;
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_language(Some(language));
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_special(false);
+    params.set_print_timestamps(false);
+
+    state.full(params, samples).context("Whisper inference failed")?;
+
+    let mut text = String::new();
+    for i in 0..state.full_n_segments()? {
+        text.push_str(&state.full_get_segment_text_lossy(i)?);
+    }
+    Ok(text.trim().to_string())
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the request following the wake word, or None if it wasn't said.
+fn strip_wake_word(transcript: &str, wake_word: &str) -> Option<String> {
+    let normalized = normalize(transcript);
+    let index = normalized.find(wake_word)?;
+    let wake_words = wake_word.split_whitespace().count();
+    let preceding = normalized[..index].split_whitespace().count();
+    // Keep the original casing and punctuation of whatever follows the wake word
+    let rest = transcript
+        .split_whitespace()
+        .skip(preceding + wake_words)
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(rest.trim_start_matches([',', '.', '!', '?']).trim().to_string())
+}
+
+/// Print and speak a localized system phrase such as "I didn't catch that".
+async fn speak_phrase(config: &AppConfig, key: &str) {
+    let text = i18n::tr(key);
+    println!("Assistant: {}", text);
+    if !config.headless.system_voice {
+        return;
+    }
+    match tokio::task::spawn_blocking(move || speak_with_system_voice(&text)).await {
+        Ok(Err(e)) => log::error!("Speech output failed: {}", e),
+        Err(e) => log::error!("Speech task failed: {}", e),
+        Ok(Ok(())) => {}
+    }
+}
+
+fn speak_with_system_voice(text: &str) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("say");
+        command.arg(text);
+        command
+    };
+
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("powershell");
+        command.args([
+            "-NoProfile",
+            "-Command",
+            "Add-Type -AssemblyName System.Speech; (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak([Console]::In.ReadToEnd())",
+        ]);
+        command.stdin(std::process::Stdio::piped());
+        command
+    };
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = {
+        let mut command = std::process::Command::new("espeak-ng");
+        command.arg(text);
+        command
+    };
+
+    let mut child = command.spawn().context("Failed to start system speech synthesizer")?;
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::io::Write;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).context("Failed to send text to speech synthesizer")?;
+        }
+    }
+
+    let status = child.wait().context("System speech synthesizer failed")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("System speech synthesizer exited with {}", status));
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+## Screen reader announcements
+
+a11y-listening-started = Höre zu
+a11y-listening-started-verbose = Der Assistent hört zu. Sprich jetzt.
+a11y-listening-stopped = Zuhören beendet
+a11y-listening-stopped-verbose = Der Assistent hört nicht mehr zu.
+a11y-thinking = Denke nach
+a11y-thinking-verbose = Der Assistent denkt über eine Antwort nach.
+a11y-reply-ready = Antwort bereit
+a11y-reply-ready-verbose = Die Antwort des Assistenten ist bereit und wird vorgelesen.
+a11y-speaking-stopped = Sprechen beendet
+a11y-speaking-stopped-verbose = Der Assistent spricht nicht mehr.
+a11y-mode-changed = Modus geändert
+a11y-error = Etwas ist schiefgelaufen
+a11y-translating = Übersetze
+a11y-translation-failed = Übersetzung fehlgeschlagen
+a11y-dictation-on = Diktat an
+a11y-dictation-off = Diktat aus
+a11y-translation-on = Übersetzungsmodus an
+a11y-translation-off = Übersetzungsmodus aus
+a11y-summarizing-meeting = Fasse das Meeting zusammen
+
+## Voice commands
+
+voice-confirm = Möchtest du wirklich { $action }? Sag "ja" zum Bestätigen oder "abbrechen".
+voice-cancelled = Okay, ich werde nicht { $action }.
+intent-open-sidepanel = die Seitenleiste öffnen
+intent-start-listening = mit dem Zuhören beginnen
+intent-stop-listening = mit dem Zuhören aufhören
+intent-stop-speaking = mit dem Sprechen aufhören
+intent-switch-persona = zur Persona { $name } wechseln
+intent-change-emotion = { $emotion } aussehen
+intent-open-devtools = die Entwicklerwerkzeuge öffnen
+intent-bookmark = diesen Moment merken
+intent-quit = die Anwendung beenden
+intent-confirm = bestätigen
+intent-cancel = abbrechen
+
+## Errors
+
+error-audio-not-initialized = Das Audiosystem ist nicht initialisiert
+error-translation-inactive = Der Übersetzungsmodus ist nicht aktiv
+error-nothing-to-read = Es gibt nichts vorzulesen
+error-meeting-running = Die Besprechungstranskription läuft bereits
+error-meeting-not-running = Die Besprechungstranskription läuft nicht
+
+## Spoken system phrases
+
+spoken-didnt-catch = Entschuldigung, das habe ich nicht verstanden.
+spoken-llm-unavailable = Entschuldigung, ich kann das Sprachmodell gerade nicht erreichen.
+spoken-voice-preview = Hallo! So klinge ich, wenn ich mit dir spreche.
+spoken-deferred-intro = Das kam rein, während du beschäftigt warst.
+spoken-update-available = Version { $version } der App kann installiert werden.
+
+## Headless console
+
+headless-listening = Höre zu...
+headless-wake-word = Sag "{ $wake_word }", um zu beginnen.
+headless-shutting-down = Wird beendet.
+
+## Text normalization
+
+speech-link = Link
+symbol-and = und
+symbol-percent = Prozent
+symbol-plus = plus
+symbol-equals = gleich
+symbol-at = at
+symbol-about = etwa
+spoken-date = { $day } { $month } { $year }
+month-1 = Januar
+month-2 = Februar
+month-3 = März
+month-4 = April
+month-5 = Mai
+month-6 = Juni
+month-7 = Juli
+month-8 = August
+month-9 = September
+month-10 = Oktober
+month-11 = November
+month-12 = Dezember
+
+## Tray menu
+
+tray-show = Hauptfenster anzeigen
+tray-toggle-sidepanel = Seitenleiste ein-/ausblenden
+tray-mute = Mikrofon stummschalten
+tray-pause = Assistent pausieren
+tray-quit = Beenden
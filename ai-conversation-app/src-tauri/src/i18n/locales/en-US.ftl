@@ -0,0 +1,90 @@
+## Screen reader announcements
+
+a11y-listening-started = Listening
+a11y-listening-started-verbose = The assistant is listening. Speak now.
+a11y-listening-stopped = Stopped listening
+a11y-listening-stopped-verbose = The assistant has stopped listening.
+a11y-thinking = Thinking
+a11y-thinking-verbose = The assistant is thinking about a reply.
+a11y-reply-ready = Reply ready
+a11y-reply-ready-verbose = The assistant's reply is ready and is being spoken.
+a11y-speaking-stopped = Stopped speaking
+a11y-speaking-stopped-verbose = The assistant has stopped speaking.
+a11y-mode-changed = Mode changed
+a11y-error = Something went wrong
+a11y-translating = Translating
+a11y-translation-failed = Translation failed
+a11y-dictation-on = Dictation on
+a11y-dictation-off = Dictation off
+a11y-translation-on = Translation mode on
+a11y-translation-off = Translation mode off
+a11y-summarizing-meeting = Summarizing the meeting
+
+## Voice commands
+
+voice-confirm = Are you sure you want to { $action }? Say "yes" to confirm or "cancel".
+voice-cancelled = Okay, I won't { $action }.
+intent-open-sidepanel = open the side panel
+intent-start-listening = start listening
+intent-stop-listening = stop listening
+intent-stop-speaking = stop speaking
+intent-switch-persona = switch to the { $name } persona
+intent-change-emotion = look { $emotion }
+intent-open-devtools = open the developer tools
+intent-bookmark = bookmark this moment
+intent-quit = quit the application
+intent-confirm = confirm
+intent-cancel = cancel
+
+## Errors
+
+error-audio-not-initialized = Audio system not initialized
+error-translation-inactive = Translation mode is not active
+error-nothing-to-read = Nothing to read
+error-meeting-running = Meeting transcription is already running
+error-meeting-not-running = Meeting transcription is not running
+
+## Spoken system phrases
+
+spoken-didnt-catch = Sorry, I didn't catch that.
+spoken-llm-unavailable = Sorry, I can't reach the language model right now.
+spoken-voice-preview = Hi! This is how I'll sound when I talk to you.
+spoken-deferred-intro = Here's what came in while you were busy.
+spoken-update-available = Version { $version } of the app is ready to install.
+
+## Headless console
+
+headless-listening = Listening...
+headless-wake-word = Say "{ $wake_word }" to start.
+headless-shutting-down = Shutting down.
+
+## Text normalization
+
+speech-link = link
+symbol-and = and
+symbol-percent = percent
+symbol-plus = plus
+symbol-equals = equals
+symbol-at = at
+symbol-about = about
+spoken-date = { $month } { $day }, { $year }
+month-1 = January
+month-2 = February
+month-3 = March
+month-4 = April
+month-5 = May
+month-6 = June
+month-7 = July
+month-8 = August
+month-9 = September
+month-10 = October
+month-11 = November
+month-12 = December
+
+## Tray menu
+
+tray-show = Show main window
+tray-toggle-sidepanel = Toggle side panel
+tray-mute = Mute microphone
+tray-pause = Pause assistant
+tray-quit = Quit
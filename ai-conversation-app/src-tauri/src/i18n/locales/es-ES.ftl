@@ -0,0 +1,90 @@
+## Screen reader announcements
+
+a11y-listening-started = Escuchando
+a11y-listening-started-verbose = El asistente está escuchando. Habla ahora.
+a11y-listening-stopped = Ya no escucho
+a11y-listening-stopped-verbose = El asistente ha dejado de escuchar.
+a11y-thinking = Pensando
+a11y-thinking-verbose = El asistente está pensando una respuesta.
+a11y-reply-ready = Respuesta lista
+a11y-reply-ready-verbose = La respuesta del asistente está lista y se está leyendo.
+a11y-speaking-stopped = Ya no hablo
+a11y-speaking-stopped-verbose = El asistente ha dejado de hablar.
+a11y-mode-changed = Modo cambiado
+a11y-error = Algo salió mal
+a11y-translating = Traduciendo
+a11y-translation-failed = La traducción falló
+a11y-dictation-on = Dictado activado
+a11y-dictation-off = Dictado desactivado
+a11y-translation-on = Modo de traducción activado
+a11y-translation-off = Modo de traducción desactivado
+a11y-summarizing-meeting = Resumiendo la reunión
+
+## Voice commands
+
+voice-confirm = ¿Seguro que quieres { $action }? Di "sí" para confirmar o "cancelar".
+voice-cancelled = De acuerdo, no voy a { $action }.
+intent-open-sidepanel = abrir el panel lateral
+intent-start-listening = empezar a escuchar
+intent-stop-listening = dejar de escuchar
+intent-stop-speaking = dejar de hablar
+intent-switch-persona = cambiar a la personalidad { $name }
+intent-change-emotion = poner cara { $emotion }
+intent-open-devtools = abrir las herramientas de desarrollo
+intent-bookmark = guardar este momento
+intent-quit = salir de la aplicación
+intent-confirm = confirmar
+intent-cancel = cancelar
+
+## Errors
+
+error-audio-not-initialized = El sistema de audio no está inicializado
+error-translation-inactive = El modo de traducción no está activo
+error-nothing-to-read = No hay nada que leer
+error-meeting-running = La transcripción de la reunión ya está en marcha
+error-meeting-not-running = La transcripción de la reunión no está en marcha
+
+## Spoken system phrases
+
+spoken-didnt-catch = Perdona, no te he entendido.
+spoken-llm-unavailable = Perdona, ahora mismo no puedo conectar con el modelo de lenguaje.
+spoken-voice-preview = ¡Hola! Así es como sonaré cuando hable contigo.
+spoken-deferred-intro = Esto es lo que llegó mientras estabas ocupado.
+spoken-update-available = La versión { $version } de la aplicación está lista para instalarse.
+
+## Headless console
+
+headless-listening = Escuchando...
+headless-wake-word = Di "{ $wake_word }" para empezar.
+headless-shutting-down = Cerrando.
+
+## Text normalization
+
+speech-link = enlace
+symbol-and = y
+symbol-percent = por ciento
+symbol-plus = más
+symbol-equals = igual a
+symbol-at = arroba
+symbol-about = aproximadamente
+spoken-date = { $day } de { $month } de { $year }
+month-1 = enero
+month-2 = febrero
+month-3 = marzo
+month-4 = abril
+month-5 = mayo
+month-6 = junio
+month-7 = julio
+month-8 = agosto
+month-9 = septiembre
+month-10 = octubre
+month-11 = noviembre
+month-12 = diciembre
+
+## Tray menu
+
+tray-show = Mostrar la ventana principal
+tray-toggle-sidepanel = Mostrar u ocultar el panel lateral
+tray-mute = Silenciar el micrófono
+tray-pause = Pausar el asistente
+tray-quit = Salir
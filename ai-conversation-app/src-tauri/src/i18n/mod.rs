@@ -0,0 +1,157 @@
+use crate::config;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use once_cell::sync::OnceCell;
+use unic_langid::LanguageIdentifier;
+
+const DEFAULT_LOCALE: &str = "en-US";
+
+// Bundled translations; a locale without its own file falls back by language, then to English
+const RESOURCES: &[(&str, &str)] = &[
+    ("en-US", include_str!("locales/en-US.ftl")),
+    ("es-ES", include_str!("locales/es-ES.ftl")),
+    ("de-DE", include_str!("locales/de-DE.ftl")),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateOrder {
+    MonthDayYear,
+    DayMonthYear,
+    YearMonthDay,
+}
+
+/// How the configured locale writes dates and numbers.
+#[derive(Debug, Clone)]
+pub struct LocaleFormat {
+    pub language: String,
+    pub date_order: DateOrder,
+    pub decimal_separator: char,
+    pub group_separator: char,
+}
+
+impl LocaleFormat {
+    fn for_locale(locale: &LanguageIdentifier) -> Self {
+        let language = locale.language.as_str().to_string();
+        let region = locale.region.map(|r| r.as_str().to_string()).unwrap_or_default();
+        let date_order = match (language.as_str(), region.as_str()) {
+            ("en", "US") | ("en", "") => DateOrder::MonthDayYear,
+            ("zh" | "ja" | "ko" | "hu" | "lt", _) => DateOrder::YearMonthDay,
+            _ => DateOrder::DayMonthYear,
+        };
+        let (decimal_separator, group_separator) = match (language.as_str(), region.as_str()) {
+            ("en" | "zh" | "ja" | "ko" | "he" | "th", _) => ('.', ','),
+            ("de", "CH") => ('.', '\''),
+            // These group digits with a (non-breaking) space
+            ("fr" | "sv" | "nb" | "fi" | "cs" | "pl" | "ru" | "uk", _) => (',', '\u{a0}'),
+            _ => (',', '.'),
+        };
+        Self {
+            language,
+            date_order,
+            decimal_separator,
+            group_separator,
+        }
+    }
+}
+
+struct Localizer {
+    format: LocaleFormat,
+    bundle: FluentBundle<FluentResource>,
+    // English, consulted for messages the selected locale doesn't translate
+    fallback: Option<FluentBundle<FluentResource>>,
+}
+
+static LOCALIZER: OnceCell<Localizer> = OnceCell::new();
+
+fn build_bundle(tag: &str, source: &str) -> Option<FluentBundle<FluentResource>> {
+    let locale: LanguageIdentifier = tag.parse().ok()?;
+    let resource = match FluentResource::try_new(source.to_string()) {
+        Ok(resource) => resource,
+        Err((resource, errors)) => {
+            log::warn!("Ignoring {} syntax errors in the {} translations", errors.len(), tag);
+            resource
+        }
+    };
+    let mut bundle = FluentBundle::new_concurrent(vec![locale]);
+    // Isolation marks would be read out by TTS engines
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        log::warn!("Duplicate messages in the {} translations: {:?}", tag, errors);
+    }
+    Some(bundle)
+}
+
+/// Pick the bundled resource for `locale`: exact tag first, then any resource of the same language.
+fn resolve_resource(locale: &LanguageIdentifier) -> (&'static str, &'static str) {
+    let exact = RESOURCES
+        .iter()
+        .find(|(tag, _)| tag.parse::<LanguageIdentifier>().is_ok_and(|candidate| &candidate == locale));
+    let same_language = || {
+        RESOURCES.iter().find(|(tag, _)| {
+            tag.parse::<LanguageIdentifier>()
+                .is_ok_and(|candidate| candidate.language == locale.language)
+        })
+    };
+    exact.or_else(same_language).copied().unwrap_or(RESOURCES[0])
+}
+
+fn localizer() -> &'static Localizer {
+    LOCALIZER.get_or_init(|| {
+        let configured = config::try_get_config()
+            .map(|c| c.app.locale.clone())
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+        let locale: LanguageIdentifier = configured.parse().unwrap_or_else(|_| {
+            log::warn!("Unknown locale {}, using {}", configured, DEFAULT_LOCALE);
+            DEFAULT_LOCALE.parse().unwrap()
+        });
+
+        let (tag, source) = resolve_resource(&locale);
+        let bundle = build_bundle(tag, source).expect("bundled locale tags are valid");
+        let fallback = if tag == DEFAULT_LOCALE {
+            None
+        } else {
+            build_bundle(DEFAULT_LOCALE, RESOURCES[0].1)
+        };
+        log::info!("Using {} translations for locale {}", tag, locale);
+
+        Localizer {
+            format: LocaleFormat::for_locale(&locale),
+            bundle,
+            fallback,
+        }
+    })
+}
+
+/// Date and number conventions of the configured locale.
+pub fn locale_format() -> &'static LocaleFormat {
+    &localizer().format
+}
+
+/// Translate a message without arguments.
+pub fn tr(key: &str) -> String {
+    tr_with(key, &[])
+}
+
+/// Translate a message, filling `{ $name }` placeables from `args`. Unknown keys come back unchanged.
+pub fn tr_with(key: &str, args: &[(&str, &str)]) -> String {
+    let localizer = localizer();
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.to_string());
+    }
+
+    for bundle in std::iter::once(&localizer.bundle).chain(localizer.fallback.as_ref()) {
+        let Some(pattern) = bundle.get_message(key).and_then(|message| message.value()) else {
+            continue;
+        };
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+        if !errors.is_empty() {
+            log::warn!("Errors formatting message {}: {:?}", key, errors);
+        }
+        return text.into_owned();
+    }
+
+    log::warn!("Missing translation for {}", key);
+    key.to_string()
+}
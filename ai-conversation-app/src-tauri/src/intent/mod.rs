@@ -0,0 +1,321 @@
+use crate::i18n;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum AppIntent {
+    OpenSidepanel,
+    StartListening,
+    StopListening,
+    StopSpeaking,
+    SwitchPersona(String),
+    ChangeEmotion(String),
+    OpenDevtools,
+    Bookmark,
+    Quit,
+    Confirm,
+    Cancel,
+}
+
+impl AppIntent {
+    /// Destructive intents are only executed after an explicit spoken confirmation
+    pub fn is_destructive(&self) -> bool {
+        matches!(self, AppIntent::Quit)
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            AppIntent::OpenSidepanel => i18n::tr("intent-open-sidepanel"),
+            AppIntent::StartListening => i18n::tr("intent-start-listening"),
+            AppIntent::StopListening => i18n::tr("intent-stop-listening"),
+            AppIntent::StopSpeaking => i18n::tr("intent-stop-speaking"),
+            AppIntent::SwitchPersona(name) => i18n::tr_with("intent-switch-persona", &[("name", name.as_str())]),
+            AppIntent::ChangeEmotion(emotion) => i18n::tr_with("intent-change-emotion", &[("emotion", emotion.as_str())]),
+            AppIntent::OpenDevtools => i18n::tr("intent-open-devtools"),
+            AppIntent::Bookmark => i18n::tr("intent-bookmark"),
+            AppIntent::Quit => i18n::tr("intent-quit"),
+            AppIntent::Confirm => i18n::tr("intent-confirm"),
+            AppIntent::Cancel => i18n::tr("intent-cancel"),
+        }
+    }
+}
+
+struct IntentPattern {
+    // Phrases may contain a single `{}` placeholder that captures the remaining words
+    phrases: &'static [&'static str],
+    // Phrases that also turn up in ordinary sentences, so they only count after a wake prefix
+    addressed_phrases: &'static [&'static str],
+    build: fn(Option<String>) -> Option<AppIntent>,
+}
+
+const PATTERNS: &[IntentPattern] = &[
+    IntentPattern {
+        phrases: &["yes", "yes please", "confirm", "do it", "yes do it", "i'm sure", "im sure"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::Confirm),
+    },
+    IntentPattern {
+        phrases: &["no", "no thanks", "cancel", "never mind", "nevermind", "don't", "dont"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::Cancel),
+    },
+    IntentPattern {
+        phrases: &["open the side panel", "open side panel", "show the side panel", "show side panel", "open the panel", "show the panel"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::OpenSidepanel),
+    },
+    IntentPattern {
+        phrases: &["start listening", "listen to me", "wake up"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::StartListening),
+    },
+    IntentPattern {
+        phrases: &["stop listening", "go to sleep", "stop recording"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::StopListening),
+    },
+    IntentPattern {
+        phrases: &["stop talking", "stop speaking", "be quiet", "shut up", "silence"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::StopSpeaking),
+    },
+    IntentPattern {
+        phrases: &["switch to the {} persona", "switch to {} persona", "switch persona to {}"],
+        addressed_phrases: &["become the {}", "be a {}"],
+        build: |name| name.map(AppIntent::SwitchPersona),
+    },
+    IntentPattern {
+        phrases: &["show {} emotion"],
+        addressed_phrases: &["look {}", "be {}", "act {}"],
+        build: |emotion| {
+            emotion
+                .filter(|e| EMOTIONS.contains(&e.as_str()))
+                .map(AppIntent::ChangeEmotion)
+        },
+    },
+    IntentPattern {
+        phrases: &["open the developer tools", "open developer tools", "open dev tools", "open devtools"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::OpenDevtools),
+    },
+    IntentPattern {
+        phrases: &["remember this", "remember that", "bookmark this", "bookmark that", "save this moment"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::Bookmark),
+    },
+    IntentPattern {
+        phrases: &["quit", "quit the app", "quit the application", "exit the app", "exit the application", "close the app", "close the application"],
+        addressed_phrases: &[],
+        build: |_| Some(AppIntent::Quit),
+    },
+];
+
+const EMOTIONS: &[&str] = &["neutral", "happy", "sad", "angry", "surprised", "confused", "thinking"];
+
+// Addressing the assistant by name marks an utterance as meant for it
+const WAKE_PREFIXES: &[&str] = &["hey assistant ", "assistant "];
+// Leading words people naturally put in front of a command
+const POLITE_PREFIXES: &[&str] = &["please ", "can you ", "could you ", "would you "];
+
+pub struct IntentMatcher {
+    confirmation_timeout: Duration,
+    pending: Option<(AppIntent, Instant)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntentDecision {
+    /// Execute this intent now
+    Execute(AppIntent),
+    /// A destructive intent was recognized and is waiting for "yes"/"confirm"
+    NeedsConfirmation(AppIntent),
+    /// The pending destructive intent was cancelled
+    Cancelled(AppIntent),
+    /// The utterance is not an app-control command and should go to the conversation
+    NotACommand,
+}
+
+impl IntentMatcher {
+    pub fn new(confirmation_timeout: Duration) -> Self {
+        Self {
+            confirmation_timeout,
+            pending: None,
+        }
+    }
+
+    pub fn match_intent(text: &str) -> Option<AppIntent> {
+        let normalized = normalize(text);
+        let (addressed, utterance) = WAKE_PREFIXES
+            .iter()
+            .find_map(|prefix| normalized.strip_prefix(prefix))
+            .map_or((false, normalized.as_str()), |rest| (true, rest));
+        let mut command = utterance.to_string();
+        for prefix in POLITE_PREFIXES {
+            if let Some(rest) = command.strip_prefix(prefix) {
+                command = rest.to_string();
+            }
+        }
+        for suffix in [" please", " now"] {
+            if let Some(rest) = command.strip_suffix(suffix) {
+                command = rest.to_string();
+            }
+        }
+
+        for pattern in PATTERNS {
+            let addressed_phrases = if addressed { pattern.addressed_phrases } else { &[] };
+            for phrase in pattern.phrases.iter().chain(addressed_phrases) {
+                // A lone word like "quit" is only a command when it's all that was said
+                let said = if phrase.contains(' ') { command.as_str() } else { utterance };
+                if let Some(capture) = match_phrase(phrase, said) {
+                    if let Some(intent) = (pattern.build)(capture) {
+                        return Some(intent);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Resolve an utterance against the confirmation convention: destructive
+    /// intents are parked until the next utterance confirms or cancels them.
+    pub fn decide(&mut self, text: &str) -> IntentDecision {
+        let pending = self
+            .pending
+            .take()
+            .filter(|(_, at)| at.elapsed() < self.confirmation_timeout)
+            .map(|(intent, _)| intent);
+
+        match (Self::match_intent(text), pending) {
+            (Some(AppIntent::Confirm), Some(intent)) => IntentDecision::Execute(intent),
+            (Some(AppIntent::Cancel), Some(intent)) => IntentDecision::Cancelled(intent),
+            (Some(AppIntent::Confirm), None) | (Some(AppIntent::Cancel), None) => IntentDecision::NotACommand,
+            (Some(intent), _) if intent.is_destructive() => {
+                self.pending = Some((intent.clone(), Instant::now()));
+                IntentDecision::NeedsConfirmation(intent)
+            }
+            (Some(intent), _) => IntentDecision::Execute(intent),
+            (None, _) => IntentDecision::NotACommand,
+        }
+    }
+}
+
+impl Default for IntentMatcher {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10))
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '\'')
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn match_phrase(phrase: &str, text: &str) -> Option<Option<String>> {
+    match phrase.split_once("{}") {
+        None => (phrase == text).then_some(None),
+        Some((prefix, suffix)) => {
+            let capture = text.strip_prefix(prefix)?.strip_suffix(suffix)?.trim();
+            if capture.is_empty() {
+                None
+            } else {
+                Some(Some(capture.to_string()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VoiceCommandOutcome {
+    pub handled: bool,
+    pub intent: Option<AppIntent>,
+    pub awaiting_confirmation: bool,
+    pub message: String,
+}
+
+impl VoiceCommandOutcome {
+    pub fn not_handled() -> Self {
+        Self {
+            handled: false,
+            intent: None,
+            awaiting_confirmation: false,
+            message: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_commands_with_polite_wording() {
+        assert_eq!(IntentMatcher::match_intent("Stop talking!"), Some(AppIntent::StopSpeaking));
+        assert_eq!(IntentMatcher::match_intent("Could you open the side panel, please?"), Some(AppIntent::OpenSidepanel));
+        assert_eq!(IntentMatcher::match_intent("Hey assistant, quit the app"), Some(AppIntent::Quit));
+        assert_eq!(IntentMatcher::match_intent("Quit."), Some(AppIntent::Quit));
+        assert_eq!(
+            IntentMatcher::match_intent("switch to the pirate persona"),
+            Some(AppIntent::SwitchPersona("pirate".to_string()))
+        );
+    }
+
+    #[test]
+    fn loose_phrases_need_the_wake_prefix() {
+        assert_eq!(IntentMatcher::match_intent("be a good friend to him"), None);
+        assert_eq!(IntentMatcher::match_intent("you should be happy"), None);
+        assert_eq!(IntentMatcher::match_intent("be happy"), None);
+        assert_eq!(IntentMatcher::match_intent("look sad"), None);
+        assert_eq!(
+            IntentMatcher::match_intent("Assistant, be a pirate"),
+            Some(AppIntent::SwitchPersona("pirate".to_string()))
+        );
+        assert_eq!(
+            IntentMatcher::match_intent("hey assistant look happy"),
+            Some(AppIntent::ChangeEmotion("happy".to_string()))
+        );
+        // Only known emotions count, even when addressed
+        assert_eq!(IntentMatcher::match_intent("assistant look over there"), None);
+    }
+
+    #[test]
+    fn single_words_must_be_the_whole_utterance() {
+        assert_eq!(IntentMatcher::match_intent("I want to quit my job"), None);
+        assert_eq!(IntentMatcher::match_intent("can you quit"), None);
+        assert_eq!(IntentMatcher::match_intent("silence is golden"), None);
+        assert_eq!(IntentMatcher::match_intent("Silence!"), Some(AppIntent::StopSpeaking));
+        assert_eq!(IntentMatcher::match_intent("assistant silence"), Some(AppIntent::StopSpeaking));
+    }
+
+    #[test]
+    fn destructive_intents_wait_for_confirmation() {
+        let mut matcher = IntentMatcher::default();
+        assert_eq!(matcher.decide("quit the app"), IntentDecision::NeedsConfirmation(AppIntent::Quit));
+        assert_eq!(matcher.decide("yes"), IntentDecision::Execute(AppIntent::Quit));
+        // Nothing is pending any more
+        assert_eq!(matcher.decide("yes"), IntentDecision::NotACommand);
+
+        assert_eq!(matcher.decide("quit the app"), IntentDecision::NeedsConfirmation(AppIntent::Quit));
+        assert_eq!(matcher.decide("never mind"), IntentDecision::Cancelled(AppIntent::Quit));
+        assert_eq!(matcher.decide("stop talking"), IntentDecision::Execute(AppIntent::StopSpeaking));
+    }
+
+    #[test]
+    fn a_pending_intent_is_dropped_by_anything_else() {
+        let mut matcher = IntentMatcher::default();
+        matcher.decide("quit the app");
+        assert_eq!(matcher.decide("what's the weather like"), IntentDecision::NotACommand);
+        assert_eq!(matcher.decide("yes"), IntentDecision::NotACommand);
+    }
+
+    #[test]
+    fn confirmation_times_out() {
+        let mut matcher = IntentMatcher::new(Duration::ZERO);
+        assert_eq!(matcher.decide("quit the app"), IntentDecision::NeedsConfirmation(AppIntent::Quit));
+        assert_eq!(matcher.decide("yes"), IntentDecision::NotACommand);
+    }
+}
@@ -1,18 +1,224 @@
-use std::sync::Mutex;
+use futures::stream::{FuturesOrdered, StreamExt};
+use futures::FutureExt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::{State, Manager, AppHandle, Emitter};
+#[cfg(desktop)]
 use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut, ShortcutState, GlobalShortcutExt};
 
+mod accessibility;
+mod api;
+mod audio;
+mod avatar_server;
+mod character;
 mod config;
+mod conversation;
+mod dictation;
+mod documents;
+#[cfg(desktop)]
+mod focus;
+mod headless;
+mod i18n;
+mod intent;
+mod llm;
+mod meeting;
+mod models;
+mod normalization;
+mod onboarding;
+mod platform;
+mod quiet_hours;
+mod read_aloud;
+mod search;
+mod telemetry;
+mod translation;
+mod updater;
+mod vision;
 
+use accessibility::{AccessibilitySettings, AnnouncementKind};
+use api::{ApiHandler, ApiRequest, ApiServer};
+use audio::processor::{AudioEvent, AudioStatus, ModelLoadProgress, ModelLoadStatus, ProcessingMode, SpeechOptions};
+use audio::backend::{AudioDeviceInfo, AudioDeviceKind};
+use audio::device_test::{DeviceTestReport, IssueSeverity};
+use audio::stt::Transcription;
+use audio::tts::TtsVoice;
+use audio::AudioHandle;
+use avatar_server::vmc::VmcSender;
+use avatar_server::{AvatarFrame, AvatarServer};
+use character::idle::{IdleEvent, IdleScheduler};
+use character::profiles::CharacterProfile;
+use character::{CharacterInput, CharacterState, CharacterStateMachine};
+use conversation::analytics::{SessionAnalytics, SessionEvent};
+use conversation::memory::{Memory, MemoryKind};
+use conversation::profile::{FactSource, UserFact};
+use conversation::search::HistoryMatch;
+use conversation::topics::ConversationSummary;
+use conversation::usage::UsageStats;
+use conversation::{Bookmark, ConversationStore, ConversationTurn};
+use dictation::{DictationAction, DictationSession};
+use intent::{AppIntent, IntentDecision, IntentMatcher, VoiceCommandOutcome};
+use llm::tools::{ToolCallEvent, ToolRegistry};
+use llm::turn_queue::{QueuedTurn, TurnQueue, TurnQueued};
+use llm::{CancellationToken, ChatMessage, ImageData};
+use meeting::{MeetingRecorder, MeetingTranscript};
+use models::ModelInfo;
+use onboarding::{Onboarding, OnboardingProgress, OnboardingStep};
+use quiet_hours::{QuietHours, QuietStatus};
+use read_aloud::{ReadAloudProgress, ReadAloudSession, ReadAloudStatus};
+use telemetry::{TelemetryPreview, TelemetryRecorder};
+use translation::{CaptionEvent, TranslationResult, TranslationSession};
+use updater::{UpdateInfo, UpdateSession};
+use vision::camera::{Camera, CameraStatus};
+use vision::emotion::Expression;
+use vision::head_pose::Gaze;
+use vision::objects::DetectedObject;
+use vision::presence::PresenceEvent;
+use vision::VisionEvent;
+
+// Set once initialize_audio_system has started the pipeline
+#[derive(Default)]
+struct AudioState(Mutex<Option<AudioHandle>>);
+
+// Held while the pipeline starts, so startup warm-up and the frontend don't both start one
+#[derive(Default)]
+struct AudioInitState(tokio::sync::Mutex<()>);
+
+// What startup warm-up has loaded so far, for a frontend that opens after the events went out
 #[derive(Default)]
-struct AudioState(Mutex<bool>);
+struct ModelLoadState(Mutex<ModelLoadReport>);
 
 #[derive(Default)]
 struct SidepanelState(Mutex<bool>);
 
+#[derive(Default)]
+struct PushToTalkState(Mutex<bool>);
+
+#[derive(Default)]
+struct VoiceControlState(Mutex<IntentMatcher>);
+
+#[derive(Default)]
+struct TranslationState(Mutex<TranslationSession>);
+
+#[derive(Default)]
+struct DictationState(Mutex<DictationSession>);
+
+#[derive(Default)]
+struct ReadAloudState(Mutex<ReadAloudSession>);
+
+#[derive(Default)]
+struct MeetingState(Mutex<Option<MeetingRecorder>>);
+
+#[derive(Default)]
+struct UpdateState(Mutex<UpdateSession>);
+
+// Opened in setup once the app data directory is known
+struct ConversationState(Mutex<ConversationStore>);
+
+// Current processing mode and when it began, for talk/listen time analytics
+#[derive(Default)]
+struct SessionTimingState(Mutex<Option<(ProcessingMode, std::time::Instant)>>);
+
+// What the avatar is animating, from audio modes and LLM requests
+#[derive(Default)]
+struct CharacterStateStore(Mutex<CharacterStateMachine>);
+
+// Running while avatar_server.enabled is set
+#[derive(Default)]
+struct AvatarServerState(Mutex<Option<AvatarServer>>);
+
+// Set while vmc.enabled is
+#[derive(Default)]
+struct VmcState(Mutex<Option<VmcSender>>);
+
+// Running while rest_api.enabled is set
+#[derive(Default)]
+struct RestApiState(Mutex<Option<ApiServer>>);
+
+// Set while a background tagging pass runs so passes don't overlap
+#[derive(Default)]
+struct TopicTaggingState(AtomicBool);
+
+// Set while a background pass learns about the user, likewise
+#[derive(Default)]
+struct UserProfileState(AtomicBool);
+
+// Set from the tray: spoken input goes unanswered and proactive speech is dropped until resumed
+#[derive(Default)]
+struct AssistantPausedState(AtomicBool);
+
+// Loaded in setup from the app data directory
+struct TelemetryState(Mutex<TelemetryRecorder>);
+
+#[derive(Default)]
+struct QuietHoursState(Mutex<QuietHours>);
+
+// Opened in setup from the app data directory
+struct OnboardingState(Mutex<Onboarding>);
+
+// Models currently downloading, so a second click doesn't start another download
+#[derive(Default)]
+struct ModelDownloadState(Mutex<std::collections::HashSet<String>>);
+
+// Tool calls waiting on the user's go-ahead, by the id sent in "tool-confirmation"
+#[derive(Default)]
+struct ToolConfirmationState {
+    next_id: AtomicU64,
+    pending: Mutex<std::collections::HashMap<u64, tokio::sync::oneshot::Sender<bool>>>,
+}
+
+// The camera's latest frame and when it came in, sent along with the next turn while vision is on
+#[derive(Default)]
+struct VisionFrameState(Mutex<Option<(ImageData, std::time::Instant)>>);
+
+// The camera while vision is on
+#[derive(Default)]
+struct CameraState(Mutex<Option<Camera>>);
+
+// The user's facial expression as last read and when, for the next LLM request
+#[derive(Default)]
+struct ExpressionState(Mutex<Option<(Expression, std::time::Instant)>>);
+
+// What the object detector saw last, for look_at_camera
+#[derive(Default)]
+struct ObjectsState(Mutex<Vec<DetectedObject>>);
+
+// Replies being generated, by turn id, so they can be stopped partway
+#[derive(Default)]
+struct GenerationState(Mutex<std::collections::HashMap<u64, CancellationToken>>);
+
+// User turns waiting for the LLM and the history they are answered with
+struct ChatState {
+    queue: Mutex<TurnQueue>,
+    history: Mutex<Vec<ChatMessage>>,
+    // Nudges the turn worker when a turn is queued
+    wake: tokio::sync::mpsc::UnboundedSender<()>,
+}
+
+struct ShutdownState {
+    started: AtomicBool,
+    // Set right before the final exit so ExitRequested lets it through
+    completed: AtomicBool,
+    cancelled: tokio::sync::watch::Sender<bool>,
+}
+
+impl Default for ShutdownState {
+    fn default() -> Self {
+        Self {
+            started: AtomicBool::new(false),
+            completed: AtomicBool::new(false),
+            cancelled: tokio::sync::watch::channel(false).0,
+        }
+    }
+}
+
 impl AudioState {
-    fn new(value: bool) -> Self {
-        Self(Mutex::new(value))
+    fn handle(&self) -> Result<AudioHandle, String> {
+        self.0
+            .lock()
+            .map_err(|e| format!("Failed to lock audio state: {}", e))?
+            .clone()
+            .ok_or_else(|| i18n::tr("error-audio-not-initialized"))
     }
 }
 
@@ -28,68 +234,644 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+async fn current_audio_status(audio_state: &AudioState) -> AudioStatus {
+    match audio_state.handle() {
+        Ok(handle) => handle.status().await.unwrap_or_else(|e| {
+            eprintln!("Failed to query audio status: {}", e);
+            AudioStatus::uninitialized()
+        }),
+        Err(_) => AudioStatus::uninitialized(),
+    }
+}
+
+async fn emit_audio_status(app: &AppHandle) {
+    let status = current_audio_status(&app.state::<AudioState>()).await;
+    if let Err(e) = app.emit("audio-status-changed", status) {
+        eprintln!("Failed to emit audio status: {}", e);
+    }
+}
+
+/// Forward pipeline events to the frontend as Tauri events.
+fn forward_audio_event(app: &AppHandle, event: AudioEvent) {
+    let result = match event {
+        AudioEvent::SpeechDetected(text) => {
+            // Speech while the assistant is talking means the user barged in
+            let interrupted = current_session_mode(app) == Some(ProcessingMode::Speaking);
+            if interrupted {
+                record_session_event(app, SessionEvent::Interruption, None, None, 0);
+                update_character(app, CharacterInput::Interrupted);
+            }
+            send_avatar_frame(app, AvatarFrame::Transcript {
+                role: "user",
+                text: text.clone(),
+                is_final: true,
+            });
+            if config::try_get_config().is_some_and(|c| c.audio.voice_replies) {
+                let voice_app = app.clone();
+                let spoken = text.clone();
+                tauri::async_runtime::spawn(async move { reply_to_speech(&voice_app, spoken, interrupted).await });
+            }
+            app.emit("speech-detected", text)
+        }
+        AudioEvent::SpeechEnded => app.emit("speech-ended", ()),
+        // Raw samples are too large to ship over IPC; the frontend only needs to know audio started
+        AudioEvent::AudioGenerated(samples) => app.emit("audio-generated", samples.len()),
+        AudioEvent::VisemeGenerated(viseme) => {
+            send_avatar_frame(app, AvatarFrame::viseme(&viseme));
+            app.emit("viseme", viseme)
+        }
+        AudioEvent::Spectrum(frame) => app.emit("audio-spectrum", frame),
+        AudioEvent::WordBoundary(word) => app.emit("tts-word-boundary", word),
+        AudioEvent::SpeechCancelled(cancelled) => {
+            update_character(app, CharacterInput::Interrupted);
+            app.emit("speech-cancelled", cancelled)
+        }
+        AudioEvent::SentenceBoundary(sentence) => app.emit("tts-sentence-boundary", sentence),
+        AudioEvent::MicLevel(level) => app.emit("mic-level", level),
+        AudioEvent::LanguageDetected(language) => app.emit("stt-language-detected", language),
+        AudioEvent::ModelLoad(progress) => {
+            report_model_load(app, Some(progress), false);
+            return;
+        }
+        AudioEvent::DeviceChanged(change) => {
+            let status_app = app.clone();
+            tauri::async_runtime::spawn(async move { emit_audio_status(&status_app).await });
+            app.emit("device-changed", change)
+        }
+        AudioEvent::ModeChanged(mode) => {
+            record_mode_change(app, mode);
+            update_character(app, CharacterInput::Mode(mode));
+            let status_app = app.clone();
+            tauri::async_runtime::spawn(async move { emit_audio_status(&status_app).await });
+            app.emit("audio-mode-changed", mode)
+        }
+        AudioEvent::Error(message) => {
+            track(app, |t| t.error("audio"));
+            accessibility::announce(app, AnnouncementKind::Error, Some(&message));
+            app.emit("audio-error", message)
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to forward audio event: {}", e);
+    }
+}
+
+/// Record a warm-up stage's progress, or that warm-up is `done`, and send the whole report
+/// to the frontend as "model-load-progress".
+fn report_model_load(app: &AppHandle, progress: Option<ModelLoadProgress>, done: bool) {
+    let report = {
+        let state = app.state::<ModelLoadState>();
+        let Ok(mut report) = state.0.lock() else {
+            return;
+        };
+        if let Some(progress) = progress {
+            report.stages.retain(|stage| stage.stage != progress.stage);
+            report.stages.push(progress);
+        }
+        report.done |= done;
+        report.clone()
+    };
+    if let Err(e) = app.emit("model-load-progress", report) {
+        eprintln!("Failed to emit model load progress: {}", e);
+    }
+}
+
+/// Load the speech models, and the LLM when `llm.preload` is set, while the app starts, so
+/// the first utterance doesn't wait ten seconds or more for them.
+async fn warm_up_models(app: AppHandle) {
+    if let Err(e) = initialize_audio_system(app.clone(), app.state::<AudioState>()).await {
+        eprintln!("Failed to warm up speech models: {}", e);
+    }
+
+    if let Some(app_config) = config::try_get_config().filter(|c| c.llm.preload) {
+        let progress = |status, error| ModelLoadProgress {
+            stage: "llm",
+            status,
+            error,
+        };
+        report_model_load(&app, Some(progress(ModelLoadStatus::Loading, None)), false);
+        match llm::provider_for(&app_config.llm).preload().await {
+            Ok(()) => report_model_load(&app, Some(progress(ModelLoadStatus::Ready, None)), false),
+            Err(e) => {
+                eprintln!("Failed to preload the LLM: {:#}", e);
+                report_model_load(&app, Some(progress(ModelLoadStatus::Failed, Some(format!("{:#}", e)))), false);
+            }
+        }
+    }
+    report_model_load(&app, None, true);
+}
+
 #[tauri::command]
-async fn initialize_audio_system(audio_state: State<'_, AudioState>) -> Result<String, String> {
-    let mut state_guard = audio_state.0.lock().map_err(|e| format!("Failed to lock audio state: {}", e))?;
-    *state_guard = true;
-    Ok("Audio system initialized successfully".to_string())
+fn get_model_load_progress(state: State<'_, ModelLoadState>) -> Result<ModelLoadReport, String> {
+    state
+        .0
+        .lock()
+        .map(|report| report.clone())
+        .map_err(|e| format!("Failed to lock model load state: {}", e))
+}
+
+/// The voice loop: answer a final transcription unless it's dictation or a voice command,
+/// or translate it in translation mode.
+async fn reply_to_speech(app: &AppHandle, text: String, interrupted: bool) {
+    if interrupted {
+        // The user talked over the reply; stop it rather than answer on top of it
+        cancel_generations(app);
+        if let Ok(handle) = app.state::<AudioState>().handle() {
+            if let Err(e) = handle.stop_speaking().await {
+                eprintln!("Failed to stop speaking: {}", e);
+            }
+        }
+    }
+
+    let dictating = app.state::<DictationState>().0.lock().map(|session| session.active).unwrap_or(false);
+    if dictating || app.state::<AssistantPausedState>().0.load(Ordering::SeqCst) {
+        return;
+    }
+    match handle_voice_command(text.clone(), app.clone(), app.state::<VoiceControlState>()).await {
+        Ok(outcome) if outcome.handled => return,
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Failed to handle voice command: {}", e);
+            return;
+        }
+    }
+    // In translation mode speech is interpreted rather than answered
+    let translating = app.state::<TranslationState>().0.lock().map(|session| session.active).unwrap_or(false);
+    if translating {
+        if let Err(e) = translate_utterance(text, app.clone(), app.state::<TranslationState>()).await {
+            eprintln!("Failed to translate utterance: {}", e);
+        }
+        return;
+    }
+    if let Err(e) = queue_user_turn(app, text) {
+        eprintln!("Failed to queue spoken turn: {}", e);
+    }
 }
 
 #[tauri::command]
-async fn start_listening(audio_state: State<'_, AudioState>) -> Result<String, String> {
-    let state_guard = audio_state.0.lock().map_err(|e| format!("Failed to lock audio state: {}", e))?;
+async fn initialize_audio_system(app: AppHandle, audio_state: State<'_, AudioState>) -> Result<String, String> {
+    let init_state = app.state::<AudioInitState>();
+    let _initializing = init_state.0.lock().await;
+    if audio_state.handle().is_ok() {
+        return Ok("Audio system already initialized".to_string());
+    }
     
-    if *state_guard {
-        Ok("Started listening".to_string())
-    } else {
-        Err("Audio system not initialized".to_string())
+    let event_app = app.clone();
+    let handle = AudioHandle::spawn(move |event| forward_audio_event(&event_app, event))
+        .await
+        .map_err(|e| format!("Failed to initialize audio system: {:#}", e))?;
+    
+    *audio_state.0.lock().map_err(|e| format!("Failed to lock audio state: {}", e))? = Some(handle);
+    emit_audio_status(&app).await;
+    Ok("Audio system initialized successfully".to_string())
+}
+
+#[tauri::command]
+async fn get_audio_status(audio_state: State<'_, AudioState>) -> Result<AudioStatus, String> {
+    Ok(current_audio_status(&audio_state).await)
+}
+
+#[tauri::command]
+async fn start_listening(app: AppHandle, audio_state: State<'_, AudioState>) -> Result<String, String> {
+    let handle = audio_state.handle()?;
+    handle.start_listening().await.map_err(|e| format!("Failed to start listening: {:#}", e))?;
+    track(&app, |t| t.feature("listening"));
+    emit_audio_status(&app).await;
+    accessibility::announce(&app, AnnouncementKind::ListeningStarted, None);
+    Ok("Started listening".to_string())
+}
+
+#[tauri::command]
+async fn stop_listening(app: AppHandle, audio_state: State<'_, AudioState>) -> Result<String, String> {
+    let handle = audio_state.handle()?;
+    handle.stop_listening().await.map_err(|e| format!("Failed to stop listening: {:#}", e))?;
+    emit_audio_status(&app).await;
+    accessibility::announce(&app, AnnouncementKind::ListeningStopped, None);
+    Ok("Stopped listening".to_string())
+}
+
+#[tauri::command]
+async fn start_speaking(text: String, app: AppHandle, audio_state: State<'_, AudioState>) -> Result<String, String> {
+    if text.trim().is_empty() {
+        return Err("Nothing to speak".to_string());
     }
+    let handle = audio_state.handle()?;
+    accessibility::announce(&app, AnnouncementKind::ReplyReady, None);
+    handle.speak(text.clone()).await.map_err(|e| format!("Failed to speak: {:#}", e))?;
+    Ok(format!("Started speaking: {}", text))
 }
 
 #[tauri::command]
-async fn stop_listening(audio_state: State<'_, AudioState>) -> Result<String, String> {
-    let state_guard = audio_state.0.lock().map_err(|e| format!("Failed to lock audio state: {}", e))?;
-    
-    if *state_guard {
-        Ok("Stopped listening".to_string())
-    } else {
-        Err("Audio system not initialized".to_string())
+async fn stop_speaking(app: AppHandle, audio_state: State<'_, AudioState>) -> Result<String, String> {
+    let handle = audio_state.handle()?;
+    if current_session_mode(&app) == Some(ProcessingMode::Speaking) {
+        record_session_event(&app, SessionEvent::Interruption, None, None, 0);
     }
+    // Don't let a reply still being generated start talking again
+    cancel_generations(&app);
+    handle.stop_speaking().await.map_err(|e| format!("Failed to stop speaking: {:#}", e))?;
+    accessibility::announce(&app, AnnouncementKind::SpeakingStopped, None);
+    Ok("Stopped speaking".to_string())
 }
 
 #[tauri::command]
-async fn start_speaking(text: String, audio_state: State<'_, AudioState>) -> Result<String, String> {
-    let state_guard = audio_state.0.lock().map_err(|e| format!("Failed to lock audio state: {}", e))?;
-    
-    if *state_guard {
-        Ok(format!("Started speaking: {}", text))
-    } else {
-        Err("Audio system not initialized".to_string())
+async fn synthesize_speech(text: String, audio_state: State<'_, AudioState>) -> Result<String, String> {
+    if text.trim().is_empty() {
+        return Err("Nothing to speak".to_string());
     }
+    let handle = audio_state.handle()?;
+    handle.speak(text.clone()).await.map_err(|e| format!("Failed to synthesize speech: {:#}", e))?;
+    Ok(format!("Synthesized speech for: {}", text))
 }
 
+/// Microphones the audio backend can open, for the device picker.
 #[tauri::command]
-async fn stop_speaking(audio_state: State<'_, AudioState>) -> Result<String, String> {
-    let state_guard = audio_state.0.lock().map_err(|e| format!("Failed to lock audio state: {}", e))?;
-    
-    if *state_guard {
-        Ok("Stopped speaking".to_string())
-    } else {
-        Err("Audio system not initialized".to_string())
+async fn list_audio_input_devices() -> Result<Vec<AudioDeviceInfo>, String> {
+    audio::backend::list_devices(AudioDeviceKind::Input).map_err(|e| format!("Failed to list input devices: {:#}", e))
+}
+
+#[tauri::command]
+async fn list_audio_output_devices() -> Result<Vec<AudioDeviceInfo>, String> {
+    audio::backend::list_devices(AudioDeviceKind::Output).map_err(|e| format!("Failed to list output devices: {:#}", e))
+}
+
+/// Switch the microphone or speakers and save the choice. A running pipeline moves its stream
+/// over in place rather than restarting.
+#[tauri::command]
+async fn set_audio_device(kind: AudioDeviceKind, name: String, app: AppHandle) -> Result<String, String> {
+    let name = name.trim().to_string();
+    if name != "default" {
+        let devices = audio::backend::list_devices(kind).map_err(|e| format!("Failed to list audio devices: {:#}", e))?;
+        if !devices.iter().any(|device| device.name == name) {
+            return Err(format!("Audio device '{}' not found", name));
+        }
     }
+    if let Ok(handle) = app.state::<AudioState>().handle() {
+        handle
+            .set_device(kind, name.clone())
+            .await
+            .map_err(|e| format!("Failed to switch audio device: {:#}", e))?;
+    }
+
+    let section = match kind {
+        AudioDeviceKind::Input => "input",
+        AudioDeviceKind::Output => "output",
+    };
+    let (_, updated) = config::update_config(serde_json::json!({ "audio": { section: { "device": name } } }))
+        .map_err(|e| format!("Failed to save audio device: {:#}", e))?;
+    // The pipeline is already on the new device, so only the frontend needs to hear about it
+    app.emit("config-changed", updated.clone())
+        .map_err(|e| format!("Failed to emit config changed event: {}", e))?;
+    emit_audio_status(&app).await;
+    Ok(format!("Audio {} set to {}", section, name))
 }
 
+/// Setup wizard check: play a tone, record a few seconds and report levels and problems.
 #[tauri::command]
-async fn synthesize_speech(text: String, audio_state: State<'_, AudioState>) -> Result<String, String> {
-    let state_guard = audio_state.0.lock().map_err(|e| format!("Failed to lock audio state: {}", e))?;
-    
-    if *state_guard {
-        Ok(format!("Synthesized speech for: {}", text))
-    } else {
-        Err("Audio system not initialized".to_string())
+async fn set_noise_suppression(enabled: bool, app: AppHandle) -> Result<(), String> {
+    if let Ok(handle) = app.state::<AudioState>().handle() {
+        handle
+            .set_noise_suppression(enabled)
+            .await
+            .map_err(|e| format!("Failed to set noise suppression: {:#}", e))?;
+    }
+    let (_, updated) = config::update_config(serde_json::json!({ "audio": { "input": { "noise_suppression": enabled } } }))
+        .map_err(|e| format!("Failed to save noise suppression: {:#}", e))?;
+    // Capture already switched over, so only the frontend needs to hear about it
+    app.emit("config-changed", updated.clone())
+        .map_err(|e| format!("Failed to emit config changed event: {}", e))
+}
+
+/// Switch the recognition language, or to "auto" detection, from the next utterance on.
+#[tauri::command]
+async fn set_stt_language(language: String, app: AppHandle) -> Result<(), String> {
+    let language = language.trim().to_lowercase();
+    if !audio::stt::is_supported_language(&language) {
+        return Err(format!("Unsupported speech recognition language: {}", language));
+    }
+    // STT reads the language for every utterance, so saving it is all it takes
+    let (_, updated) = config::update_config(serde_json::json!({ "stt": { "language": language } }))
+        .map_err(|e| format!("Failed to save speech recognition language: {:#}", e))?;
+    app.emit("config-changed", updated.clone())
+        .map_err(|e| format!("Failed to emit config changed event: {}", e))
+}
+
+/// Have speech written as spoken ("transcribe") or in English ("translate"), from the next utterance on.
+#[tauri::command]
+async fn set_stt_task(task: String, app: AppHandle) -> Result<(), String> {
+    // update_config rejects anything but the two tasks Whisper knows
+    let (_, updated) = config::update_config(serde_json::json!({ "stt": { "task": task.trim() } }))
+        .map_err(|e| format!("Failed to save speech recognition task: {:#}", e))?;
+    app.emit("config-changed", updated.clone())
+        .map_err(|e| format!("Failed to emit config changed event: {}", e))
+}
+
+/// Save `stt.custom_vocabulary` and tell the frontend; the next utterance picks it up.
+fn save_stt_vocabulary(vocabulary: Vec<String>, app: &AppHandle) -> Result<Vec<String>, String> {
+    let (_, updated) = config::update_config(serde_json::json!({ "stt": { "custom_vocabulary": vocabulary } }))
+        .map_err(|e| format!("Failed to save custom vocabulary: {:#}", e))?;
+    app.emit("config-changed", updated.clone())
+        .map_err(|e| format!("Failed to emit config changed event: {}", e))?;
+    Ok(updated.stt.custom_vocabulary.clone())
+}
+
+/// Teach speech recognition a name or term; returns the whole vocabulary.
+#[tauri::command]
+async fn add_stt_vocabulary(word: String, app: AppHandle) -> Result<Vec<String>, String> {
+    let word = word.trim();
+    if word.is_empty() {
+        return Err("Vocabulary entries can't be empty".to_string());
+    }
+    let mut vocabulary = config::try_get_config().ok_or("Configuration not initialized")?.stt.custom_vocabulary.clone();
+    if vocabulary.iter().any(|existing| existing.eq_ignore_ascii_case(word)) {
+        return Ok(vocabulary);
+    }
+    vocabulary.push(word.to_string());
+    save_stt_vocabulary(vocabulary, &app)
+}
+
+#[tauri::command]
+async fn remove_stt_vocabulary(word: String, app: AppHandle) -> Result<Vec<String>, String> {
+    let word = word.trim();
+    let mut vocabulary = config::try_get_config().ok_or("Configuration not initialized")?.stt.custom_vocabulary.clone();
+    vocabulary.retain(|existing| !existing.eq_ignore_ascii_case(word));
+    save_stt_vocabulary(vocabulary, &app)
+}
+
+/// Transcribe a WAV, MP3 or Ogg file with the loaded Whisper model, timestamps included.
+#[tauri::command]
+async fn transcribe_file(path: String, audio_state: State<'_, AudioState>) -> Result<Transcription, String> {
+    let handle = audio_state.handle()?;
+    let (samples, sample_rate) = tauri::async_runtime::spawn_blocking(move || {
+        audio::decode::decode_file(std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| format!("Failed to decode audio file: {}", e))?
+    .map_err(|e| format!("Failed to decode audio file: {:#}", e))?;
+    handle
+        .transcribe_recording(samples, sample_rate)
+        .await
+        .map_err(|e| format!("Failed to transcribe audio file: {:#}", e))
+}
+
+#[tauri::command]
+async fn test_audio_devices(input_device: Option<String>, output_device: Option<String>, duration_seconds: Option<f32>) -> Result<DeviceTestReport, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let audio_config = app_config.audio.clone();
+    let input_device = input_device.unwrap_or_else(|| audio_config.input.device.clone());
+    let output_device = output_device.unwrap_or_else(|| audio_config.output.device.clone());
+    let duration = duration_seconds.unwrap_or(3.0);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        audio::device_test::run(&audio_config, &input_device, &output_device, duration)
+    })
+    .await
+    .map_err(|e| format!("Audio device test failed: {}", e))?
+    .map_err(|e| format!("Audio device test failed: {}", e))
+}
+
+fn open_onboarding(app: &tauri::App) -> Onboarding {
+    match app.path().app_data_dir() {
+        Ok(dir) => Onboarding::load(dir.join("onboarding.json")),
+        Err(e) => {
+            eprintln!("Failed to resolve app data directory, keeping onboarding progress in memory: {}", e);
+            Onboarding::in_memory()
+        }
+    }
+}
+
+/// Apply a change to the wizard, persist it and tell the frontend.
+fn update_onboarding(app: &AppHandle, change: impl FnOnce(&mut Onboarding)) -> Result<OnboardingProgress, String> {
+    let progress = {
+        let onboarding_state = app.state::<OnboardingState>();
+        let mut onboarding = onboarding_state
+            .0
+            .lock()
+            .map_err(|e| format!("Failed to lock onboarding state: {}", e))?;
+        change(&mut onboarding);
+        onboarding.save().map_err(|e| format!("Failed to save onboarding state: {:#}", e))?;
+        onboarding.progress().clone()
+    };
+    app.emit("onboarding-updated", progress.clone())
+        .map_err(|e| format!("Failed to emit onboarding update: {}", e))?;
+    Ok(progress)
+}
+
+/// Whether the first-run wizard should be shown, and where it left off.
+#[tauri::command]
+async fn get_onboarding_state(onboarding_state: State<'_, OnboardingState>) -> Result<OnboardingProgress, String> {
+    let onboarding = onboarding_state
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock onboarding state: {}", e))?;
+    Ok(onboarding.progress().clone())
+}
+
+/// Wizard mic test: the step completes once the devices pass without errors.
+#[tauri::command]
+async fn onboarding_test_microphone(input_device: Option<String>, output_device: Option<String>, app: AppHandle) -> Result<DeviceTestReport, String> {
+    let report = test_audio_devices(input_device, output_device, None).await?;
+    let passed = report.issues.iter().all(|issue| issue.severity != IssueSeverity::Error);
+    update_onboarding(&app, |onboarding| {
+        let choices = onboarding.choices_mut();
+        choices.input_device = Some(report.input_device.clone());
+        choices.output_device = Some(report.output_device.clone());
+        if passed {
+            onboarding.complete_step(OnboardingStep::MicTest);
+        }
+    })?;
+    Ok(report)
+}
+
+#[tauri::command]
+async fn onboarding_download_model(model: Option<String>, app: AppHandle) -> Result<String, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let model = model.unwrap_or_else(|| app_config.stt.model.clone());
+    let path = download_whisper_model(&app, &model).await?;
+    update_onboarding(&app, |onboarding| {
+        onboarding.choices_mut().stt_model = Some(model.clone());
+        onboarding.complete_step(OnboardingStep::ModelDownload);
+    })?;
+    Ok(format!("Downloaded model {} to {}", model, path.display()))
+}
+
+/// Store the LLM provider's API key in the OS keyring rather than the config file.
+#[tauri::command]
+async fn onboarding_set_api_key(api_key: String, app: AppHandle) -> Result<String, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return Err("The API key must not be empty".to_string());
+    }
+    platform::store_secret(&app_config.llm.api_key_secret(), api_key).map_err(|e| e.to_string())?;
+    update_onboarding(&app, |onboarding| {
+        onboarding.choices_mut().api_key_stored = true;
+        onboarding.complete_step(OnboardingStep::ApiKey);
+    })?;
+    Ok(format!("Stored the {} API key in the system keyring", app_config.llm.provider))
+}
+
+#[tauri::command]
+async fn list_tts_voices() -> Result<Vec<TtsVoice>, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    audio::tts::create_provider(&app_config.tts)
+        .list_voices()
+        .await
+        .map_err(|e| format!("Failed to list voices: {:#}", e))
+}
+
+/// Teach the voice and lip-sync how to say `word`, as ARPAbet phonemes like "B R IH1 T AH0".
+#[tauri::command]
+async fn add_pronunciation(word: String, phonemes: String, app: AppHandle) -> Result<(), String> {
+    let word = word.trim();
+    let phonemes = phonemes.split_whitespace().collect::<Vec<_>>().join(" ");
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err("Pronunciations are for single words".to_string());
+    }
+    if !audio::g2p::is_valid_pronunciation(&phonemes) {
+        return Err(format!("Not an ARPAbet pronunciation: {}", phonemes));
+    }
+    let mut lexicon = config::try_get_config().ok_or("Configuration not initialized")?.tts.lexicon.clone();
+    lexicon.retain(|entry| entry.word.to_lowercase() != word.to_lowercase());
+    lexicon.push(config::Pronunciation {
+        word: word.to_string(),
+        phonemes,
+    });
+    save_lexicon(lexicon, &app).await
+}
+
+#[tauri::command]
+async fn remove_pronunciation(word: String, app: AppHandle) -> Result<(), String> {
+    let word = word.trim().to_lowercase();
+    let mut lexicon = config::try_get_config().ok_or("Configuration not initialized")?.tts.lexicon.clone();
+    lexicon.retain(|entry| entry.word.to_lowercase() != word);
+    save_lexicon(lexicon, &app).await
+}
+
+async fn save_lexicon(lexicon: Vec<config::Pronunciation>, app: &AppHandle) -> Result<(), String> {
+    let (previous, updated) = config::update_config(serde_json::json!({ "tts": { "lexicon": lexicon } }))
+        .map_err(|e| format!("Failed to save pronunciation: {:#}", e))?;
+    // Cloud providers keep a copy of the TTS settings, so they're reloaded with it
+    apply_config_change(app, &previous, &updated).await;
+    Ok(())
+}
+
+#[tauri::command]
+fn list_tts_providers() -> Vec<&'static str> {
+    audio::tts::provider_names()
+}
+
+/// Switch speech engines; the pipeline picks the new one up without restarting.
+#[tauri::command]
+async fn set_tts_provider(provider: String, app: AppHandle) -> Result<(), String> {
+    let provider = provider.trim();
+    if !audio::tts::provider_names().contains(&provider) {
+        return Err(format!("Unknown TTS provider: {}", provider));
+    }
+    let (previous, updated) = config::update_config(serde_json::json!({ "tts": { "provider": provider } }))
+        .map_err(|e| format!("Failed to save TTS provider: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+    Ok(())
+}
+
+/// Speak with `voice`, an id from list_tts_voices, from the next reply on.
+#[tauri::command]
+async fn set_tts_voice(voice: String, app: AppHandle) -> Result<(), String> {
+    let voice = voice.trim();
+    if voice.is_empty() {
+        return Err("The voice must not be empty".to_string());
+    }
+    let (previous, updated) = config::update_config(serde_json::json!({ "tts": { "voice": voice } }))
+        .map_err(|e| format!("Failed to save TTS voice: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+    Ok(())
+}
+
+#[tauri::command]
+fn list_viseme_mappings() -> Vec<&'static str> {
+    audio::visemes::PRESETS.to_vec()
+}
+
+/// Switch the avatar's viseme names to a preset from list_viseme_mappings or a YAML file of
+/// `phoneme: viseme` pairs, from the next utterance on.
+#[tauri::command]
+async fn set_viseme_mapping(mapping: String, app: AppHandle) -> Result<(), String> {
+    let mapping = mapping.trim();
+    audio::visemes::load_mapping(mapping).map_err(|e| format!("{:#}", e))?;
+    let (previous, updated) =
+        config::update_config(serde_json::json!({ "character": { "lip_sync": { "viseme_mapping": mapping } } }))
+            .map_err(|e| format!("Failed to save viseme mapping: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+    Ok(())
+}
+
+/// Synthesize `text` and save its lip-sync timeline to `path` as "rhubarb" JSON or raw "json",
+/// with the audio beside it as a WAV file.
+#[tauri::command]
+async fn export_visemes(text: String, path: String, format: String, audio_state: State<'_, AudioState>) -> Result<(), String> {
+    if text.trim().is_empty() {
+        return Err("Nothing to speak".to_string());
+    }
+    let handle = audio_state.handle()?;
+    let speech = handle
+        .render_speech(text.clone())
+        .await
+        .map_err(|e| format!("Failed to synthesize speech: {:#}", e))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        audio::viseme_export::export(&text, &speech, std::path::Path::new(&path), &format.to_lowercase())
+    })
+    .await
+    .map_err(|e| format!("Failed to export visemes: {}", e))?
+    .map_err(|e| format!("Failed to export visemes: {:#}", e))
+}
+
+#[tauri::command]
+async fn preview_voice(voice: String, text: Option<String>, audio_state: State<'_, AudioState>) -> Result<String, String> {
+    let handle = audio_state.handle()?;
+    let text = text.unwrap_or_else(|| i18n::tr("spoken-voice-preview"));
+    handle.preview_voice(text, voice.clone()).await.map_err(|e| format!("Failed to preview voice: {}", e))?;
+    Ok(format!("Previewing voice: {}", voice))
+}
+
+#[tauri::command]
+async fn onboarding_select_voice(voice: String, app: AppHandle) -> Result<OnboardingProgress, String> {
+    let voice = voice.trim().to_string();
+    if voice.is_empty() {
+        return Err("The voice must not be empty".to_string());
     }
+    update_onboarding(&app, |onboarding| {
+        onboarding.choices_mut().voice = Some(voice);
+        onboarding.complete_step(OnboardingStep::Voice);
+    })
+}
+
+#[tauri::command]
+async fn onboarding_set_wake_word(wake_word: String, app: AppHandle) -> Result<OnboardingProgress, String> {
+    let wake_word = onboarding::validate_wake_word(&wake_word).map_err(|e| e.to_string())?;
+    update_onboarding(&app, |onboarding| {
+        onboarding.choices_mut().wake_word = Some(wake_word);
+        onboarding.complete_step(OnboardingStep::WakeWord);
+    })
+}
+
+#[tauri::command]
+async fn skip_onboarding_step(step: OnboardingStep, app: AppHandle) -> Result<OnboardingProgress, String> {
+    update_onboarding(&app, |onboarding| onboarding.skip_step(step))
+}
+
+/// Close the wizard so it doesn't appear on the next launch.
+#[tauri::command]
+async fn finish_onboarding(app: AppHandle) -> Result<OnboardingProgress, String> {
+    update_onboarding(&app, |onboarding| onboarding.finish(conversation::now_ms()))
+}
+
+/// Re-run the wizard from settings.
+#[tauri::command]
+async fn restart_onboarding(app: AppHandle) -> Result<OnboardingProgress, String> {
+    update_onboarding(&app, |onboarding| onboarding.restart())
 }
 
+#[cfg(desktop)]
 #[tauri::command]
 async fn show_sidepanel(app: AppHandle, sidepanel_state: State<'_, SidepanelState>) -> Result<String, String> {
     // Try to get existing window or create it if it doesn't exist
@@ -123,21 +905,19 @@ async fn show_sidepanel(app: AppHandle, sidepanel_state: State<'_, SidepanelStat
             if monitors.len() > 1 {
                 // Get main window position to avoid overlap
                 if let Some(main_window) = app.get_webview_window("main") {
-                    if let Ok(main_monitor) = main_window.current_monitor() {
-                        if let Some(main_monitor) = main_monitor {
-                            // Find a different monitor
-                            for monitor in monitors {
-                                if monitor.name() != main_monitor.name() {
-                                    let monitor_pos = monitor.position();
-                                    let monitor_size = monitor.size();
-                                    // Position sidepanel on the right side of the secondary monitor
-                                    let x = monitor_pos.x + (monitor_size.width as i32) - 400; // 400px width
-                                    let y = monitor_pos.y + 100; // 100px from top
-                                    
-                                    window.set_position(tauri::Position::Physical(tauri::PhysicalPosition { x, y }))
-                                        .map_err(|e| format!("Failed to set position: {}", e))?;
-                                    break;
-                                }
+                    if let Ok(Some(main_monitor)) = main_window.current_monitor() {
+                        // Find a different monitor
+                        for monitor in monitors {
+                            if monitor.name() != main_monitor.name() {
+                                let monitor_pos = monitor.position();
+                                let monitor_size = monitor.size();
+                                // Position sidepanel on the right side of the secondary monitor
+                                let x = monitor_pos.x + (monitor_size.width as i32) - 400; // 400px width
+                                let y = monitor_pos.y + 100; // 100px from top
+                                
+                                window.set_position(tauri::Position::Physical(tauri::PhysicalPosition { x, y }))
+                                    .map_err(|e| format!("Failed to set position: {}", e))?;
+                                break;
                             }
                         }
                     }
@@ -154,18 +934,71 @@ async fn show_sidepanel(app: AppHandle, sidepanel_state: State<'_, SidepanelStat
     }
 }
 
+#[cfg(mobile)]
 #[tauri::command]
-async fn change_character_emotion(emotion: String, app: AppHandle) -> Result<String, String> {
-    if let Some(main_window) = app.get_webview_window("main") {
-        main_window.emit("emotion-change", emotion.clone())
-            .map_err(|e| format!("Failed to emit emotion change: {}", e))?;
-        Ok(format!("Emotion changed to: {}", emotion))
-    } else {
-        Err("Main window not found".to_string())
-    }
+async fn show_sidepanel(app: AppHandle, sidepanel_state: State<'_, SidepanelState>) -> Result<String, String> {
+    // Mobile has a single full-screen webview, so the panel is shown as an overlay inside it
+    let main_window = app.get_webview_window("main").ok_or("Main window not found")?;
+    main_window.emit("show-sidepanel", ())
+        .map_err(|e| format!("Failed to emit show sidepanel: {}", e))?;
+    let mut state_guard = sidepanel_state.0.lock().map_err(|e| format!("Failed to lock sidepanel state: {}", e))?;
+    *state_guard = true;
+    Ok("Sidepanel shown".to_string())
 }
 
-#[tauri::command]
+#[cfg(desktop)]
+async fn handle_push_to_talk(app: AppHandle, shortcut_state: ShortcutState, hold: bool) -> Result<(), String> {
+    let ptt_state = app.state::<PushToTalkState>();
+    let start = {
+        let mut active = ptt_state.0.lock().map_err(|e| format!("Failed to lock push-to-talk state: {}", e))?;
+        // Key auto-repeat fires Pressed repeatedly while held, so only act on real transitions
+        let transition = match (shortcut_state, hold, *active) {
+            (ShortcutState::Pressed, _, false) => Some(true),
+            (ShortcutState::Pressed, false, true) => Some(false),
+            (ShortcutState::Released, true, true) => Some(false),
+            _ => None,
+        };
+        match transition {
+            Some(start) => {
+                *active = start;
+                start
+            }
+            None => return Ok(()),
+        }
+    };
+
+    let handle = app.state::<AudioState>().handle()?;
+    if start {
+        handle.start_push_to_talk().await.map_err(|e| format!("Failed to start push-to-talk: {:#}", e))?;
+        accessibility::announce(&app, AnnouncementKind::ListeningStarted, None);
+    } else {
+        // Releasing the key transcribes the captured utterance right away, without waiting for silence
+        handle.stop_push_to_talk().await.map_err(|e| format!("Failed to stop push-to-talk: {:#}", e))?;
+        accessibility::announce(&app, AnnouncementKind::ListeningStopped, None);
+    }
+    emit_audio_status(&app).await;
+
+    app.emit("push-to-talk", serde_json::json!({
+        "active": start,
+        "mode": if hold { "hold" } else { "toggle" },
+    }))
+    .map_err(|e| format!("Failed to emit push-to-talk event: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn change_character_emotion(emotion: String, app: AppHandle) -> Result<String, String> {
+    send_avatar_frame(&app, AvatarFrame::Emotion { emotion: emotion.clone() });
+    if let Some(main_window) = app.get_webview_window("main") {
+        main_window.emit("emotion-change", emotion.clone())
+            .map_err(|e| format!("Failed to emit emotion change: {}", e))?;
+        Ok(format!("Emotion changed to: {}", emotion))
+    } else {
+        Err("Main window not found".to_string())
+    }
+}
+
+#[tauri::command]
 async fn update_viewport_settings(settings: serde_json::Value, app: AppHandle) -> Result<String, String> {
     if let Some(main_window) = app.get_webview_window("main") {
         main_window.emit("viewport-settings-change", settings.clone())
@@ -177,15 +1010,3157 @@ async fn update_viewport_settings(settings: serde_json::Value, app: AppHandle) -
 }
 
 #[tauri::command]
-async fn open_devtools(app: AppHandle) -> Result<String, String> {
-    // Try to open devtools for both main and sidepanel windows
-    if let Some(main_window) = app.get_webview_window("main") {
-        main_window.open_devtools();
+async fn open_devtools(app: AppHandle) -> Result<String, String> {
+    // Try to open devtools for both main and sidepanel windows
+    if let Some(main_window) = app.get_webview_window("main") {
+        main_window.open_devtools();
+    }
+    if let Some(sidepanel_window) = app.get_webview_window("sidepanel") {
+        sidepanel_window.open_devtools();
+    }
+    Ok("Developer tools opened".to_string())
+}
+
+async fn execute_intent(app: &AppHandle, intent: &AppIntent) -> Result<String, String> {
+    match intent {
+        AppIntent::OpenSidepanel => show_sidepanel(app.clone(), app.state::<SidepanelState>()).await,
+        AppIntent::StartListening => start_listening(app.clone(), app.state::<AudioState>()).await,
+        AppIntent::StopListening => stop_listening(app.clone(), app.state::<AudioState>()).await,
+        AppIntent::StopSpeaking => stop_speaking(app.clone(), app.state::<AudioState>()).await,
+        AppIntent::SwitchPersona(name) => {
+            // A spoken name that matches a character profile switches to it
+            let profile = character::profiles::list()
+                .unwrap_or_default()
+                .into_iter()
+                .find(|profile| profile.name.eq_ignore_ascii_case(name) || profile.id.eq_ignore_ascii_case(name));
+            if let Some(profile) = profile {
+                switch_character(profile.id, app.clone()).await?;
+            }
+            app.emit("persona-change", name.clone())
+                .map_err(|e| format!("Failed to emit persona change: {}", e))?;
+            Ok(format!("Switched to the {} persona", name))
+        }
+        AppIntent::ChangeEmotion(emotion) => change_character_emotion(emotion.clone(), app.clone()).await,
+        AppIntent::OpenDevtools => open_devtools(app.clone()).await,
+        AppIntent::Bookmark => {
+            bookmark_moment(None, None, app.clone()).await?;
+            Ok("Bookmarked".to_string())
+        }
+        AppIntent::Quit => {
+            request_shutdown(app);
+            Ok("Quitting application".to_string())
+        }
+        AppIntent::Confirm | AppIntent::Cancel => Ok(String::new()),
+    }
+}
+
+#[tauri::command]
+async fn handle_voice_command(text: String, app: AppHandle, voice_control: State<'_, VoiceControlState>) -> Result<VoiceCommandOutcome, String> {
+    let decision = {
+        let mut matcher = voice_control.0.lock().map_err(|e| format!("Failed to lock voice control state: {}", e))?;
+        matcher.decide(&text)
+    };
+
+    let outcome = match decision {
+        IntentDecision::Execute(intent) => {
+            track(&app, |t| t.feature("voice_command"));
+            let message = execute_intent(&app, &intent).await?;
+            VoiceCommandOutcome {
+                handled: true,
+                intent: Some(intent),
+                awaiting_confirmation: false,
+                message,
+            }
+        }
+        IntentDecision::NeedsConfirmation(intent) => VoiceCommandOutcome {
+            handled: true,
+            message: i18n::tr_with("voice-confirm", &[("action", intent.describe().as_str())]),
+            intent: Some(intent),
+            awaiting_confirmation: true,
+        },
+        IntentDecision::Cancelled(intent) => VoiceCommandOutcome {
+            handled: true,
+            message: i18n::tr_with("voice-cancelled", &[("action", intent.describe().as_str())]),
+            intent: Some(intent),
+            awaiting_confirmation: false,
+        },
+        IntentDecision::NotACommand => return Ok(VoiceCommandOutcome::not_handled()),
+    };
+
+    app.emit("voice-command", outcome.clone())
+        .map_err(|e| format!("Failed to emit voice command event: {}", e))?;
+    Ok(outcome)
+}
+
+#[tauri::command]
+async fn start_translation_mode(source_language: Option<String>, target_language: Option<String>, app: AppHandle, translation_state: State<'_, TranslationState>) -> Result<String, String> {
+    let defaults = config::try_get_config()
+        .map(|c| c.translation.clone())
+        .unwrap_or_default();
+    let source = source_language.unwrap_or(defaults.source_language);
+    let target = target_language.unwrap_or(defaults.target_language);
+
+    let mut session = translation_state.0.lock().map_err(|e| format!("Failed to lock translation state: {}", e))?;
+    session.start(source.clone(), target.clone());
+    accessibility::announce(&app, AnnouncementKind::ModeChanged, Some(&i18n::tr("a11y-translation-on")));
+    Ok(format!("Translation mode started: {} -> {}", source, target))
+}
+
+#[tauri::command]
+async fn stop_translation_mode(app: AppHandle, translation_state: State<'_, TranslationState>) -> Result<String, String> {
+    let mut session = translation_state.0.lock().map_err(|e| format!("Failed to lock translation state: {}", e))?;
+    session.stop();
+    accessibility::announce(&app, AnnouncementKind::ModeChanged, Some(&i18n::tr("a11y-translation-off")));
+    Ok("Translation mode stopped".to_string())
+}
+
+#[tauri::command]
+async fn translate_utterance(text: String, app: AppHandle, translation_state: State<'_, TranslationState>) -> Result<TranslationResult, String> {
+    let (utterance_id, source_language, target_language) = {
+        let mut session = translation_state.0.lock().map_err(|e| format!("Failed to lock translation state: {}", e))?;
+        if !session.active {
+            return Err(i18n::tr("error-translation-inactive"));
+        }
+        (session.next_utterance_id(), session.source_language.clone(), session.target_language.clone())
+    };
+
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+
+    app.emit("translation-caption", CaptionEvent {
+        utterance_id,
+        kind: "source".to_string(),
+        text: text.clone(),
+        language: source_language.clone(),
+        is_final: true,
+    })
+    .map_err(|e| format!("Failed to emit caption: {}", e))?;
+
+    let translator = translation::create_translator(&app_config.translation, &app_config.llm)
+        .map_err(|e| e.to_string())?;
+    accessibility::announce(&app, AnnouncementKind::Thinking, Some(&i18n::tr("a11y-translating")));
+    let started = std::time::Instant::now();
+    let translated_text = match unless_shutdown(&app, translator.translate(&text, &source_language, &target_language)).await? {
+        Ok(translated_text) => {
+            track(&app, |t| {
+                t.feature("translation");
+                t.timing("translation", started.elapsed());
+            });
+            translated_text
+        }
+        Err(e) => {
+            track(&app, |t| t.error("translation"));
+            accessibility::announce(&app, AnnouncementKind::Error, Some(&i18n::tr("a11y-translation-failed")));
+            return Err(format!("Translation failed: {}", e));
+        }
+    };
+
+    app.emit("translation-caption", CaptionEvent {
+        utterance_id,
+        kind: "translated".to_string(),
+        text: translated_text.clone(),
+        language: target_language.clone(),
+        is_final: true,
+    })
+    .map_err(|e| format!("Failed to emit caption: {}", e))?;
+
+    let voice = translation::voice_for_language(&app_config.translation, &target_language);
+    let handle = app.state::<AudioState>().handle()?;
+    accessibility::announce(&app, AnnouncementKind::ReplyReady, None);
+    let options = SpeechOptions {
+        voice: voice.clone(),
+        ..SpeechOptions::default()
+    };
+    handle
+        .speak_with(translated_text.clone(), options)
+        .await
+        .map_err(|e| format!("Failed to speak: {:#}", e))?;
+
+    Ok(TranslationResult {
+        utterance_id,
+        source_text: text,
+        translated_text,
+        source_language,
+        target_language,
+        voice,
+    })
+}
+
+fn set_dictation_active(app: &AppHandle, active: bool) -> Result<String, String> {
+    let dictation_state = app.state::<DictationState>();
+    let mut session = dictation_state.0.lock().map_err(|e| format!("Failed to lock dictation state: {}", e))?;
+    if active {
+        session.start();
+        track(app, |t| t.feature("dictation"));
+    } else {
+        session.stop();
+    }
+    app.emit("dictation-state", serde_json::json!({ "active": active }))
+        .map_err(|e| format!("Failed to emit dictation state: {}", e))?;
+    accessibility::announce(app, AnnouncementKind::ModeChanged, Some(&i18n::tr(if active { "a11y-dictation-on" } else { "a11y-dictation-off" })));
+    Ok(if active { "Dictation started" } else { "Dictation stopped" }.to_string())
+}
+
+#[tauri::command]
+async fn start_dictation(app: AppHandle) -> Result<String, String> {
+    set_dictation_active(&app, true)
+}
+
+#[tauri::command]
+async fn stop_dictation(app: AppHandle) -> Result<String, String> {
+    set_dictation_active(&app, false)
+}
+
+#[tauri::command]
+async fn toggle_dictation(app: AppHandle) -> Result<String, String> {
+    let active = {
+        let dictation_state = app.state::<DictationState>();
+        let session = dictation_state.0.lock().map_err(|e| format!("Failed to lock dictation state: {}", e))?;
+        session.active
+    };
+    set_dictation_active(&app, !active)
+}
+
+#[tauri::command]
+async fn dictate_transcript(text: String, app: AppHandle, dictation_state: State<'_, DictationState>) -> Result<Vec<DictationAction>, String> {
+    let actions = {
+        let mut session = dictation_state.0.lock().map_err(|e| format!("Failed to lock dictation state: {}", e))?;
+        if !session.active {
+            return Err("Dictation is not active".to_string());
+        }
+        session.process_transcript(&text)
+    };
+
+    // Keystroke injection blocks while typing, keep it off the async runtime
+    let injected = actions.clone();
+    tauri::async_runtime::spawn_blocking(move || dictation::inject_actions(&injected))
+        .await
+        .map_err(|e| format!("Dictation task failed: {}", e))?
+        .map_err(|e| format!("Failed to type dictation: {}", e))?;
+
+    app.emit("dictation-typed", actions.clone())
+        .map_err(|e| format!("Failed to emit dictation event: {}", e))?;
+    Ok(actions)
+}
+
+fn emit_read_aloud_progress(app: &AppHandle, progress: ReadAloudProgress) {
+    if let Err(e) = app.emit("read-aloud-progress", progress) {
+        eprintln!("Failed to emit read-aloud progress: {}", e);
+    }
+}
+
+async fn run_read_aloud(app: AppHandle, generation: u64) {
+    loop {
+        let next = {
+            let read_aloud_state = app.state::<ReadAloudState>();
+            let Ok(mut session) = read_aloud_state.0.lock() else { return };
+            if session.generation != generation {
+                return;
+            }
+            match session.status {
+                ReadAloudStatus::Stopped => return,
+                ReadAloudStatus::Paused => None,
+                ReadAloudStatus::Playing => match session.sentences.get(session.position).cloned() {
+                    Some(sentence) => Some((sentence, session.speed, session.progress())),
+                    None => {
+                        session.stop();
+                        emit_read_aloud_progress(&app, session.progress());
+                        return;
+                    }
+                },
+            }
+        };
+
+        let Some((sentence, speed, progress)) = next else {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            continue;
+        };
+
+        emit_read_aloud_progress(&app, progress);
+        if let Err(e) = speak_to_the_end(&app, sentence, speed).await {
+            eprintln!("Read-aloud failed to speak: {}", e);
+            // Don't race through the rest of the text while the audio is unavailable
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+
+        let read_aloud_state = app.state::<ReadAloudState>();
+        if let Ok(mut session) = read_aloud_state.0.lock() {
+            if session.generation == generation && session.status != ReadAloudStatus::Stopped {
+                session.position += 1;
+            }
+        };
+    }
+}
+
+/// Speak `sentence` at `rate` times the configured speed and wait until it has played out,
+/// or been cut off.
+async fn speak_to_the_end(app: &AppHandle, sentence: String, rate: f32) -> Result<(), String> {
+    let handle = app.state::<AudioState>().handle()?;
+    let mut mode = handle.subscribe_mode().await.map_err(|e| format!("Failed to follow playback: {:#}", e))?;
+    let options = SpeechOptions {
+        rate: Some(rate),
+        ..SpeechOptions::default()
+    };
+    handle.speak_with(sentence, options).await.map_err(|e| format!("Failed to speak: {:#}", e))?;
+    // Speaking switches the mode to Speaking before it returns; playback switches it back
+    let _ = mode.wait_for(|mode| *mode != ProcessingMode::Speaking).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn read_aloud_text(text: String, app: AppHandle, read_aloud_state: State<'_, ReadAloudState>) -> Result<ReadAloudProgress, String> {
+    let (generation, progress) = {
+        let mut session = read_aloud_state.0.lock().map_err(|e| format!("Failed to lock read-aloud state: {}", e))?;
+        let generation = session.load(&text);
+        if session.sentences.is_empty() {
+            session.stop();
+            return Err(i18n::tr("error-nothing-to-read"));
+        }
+        (generation, session.progress())
+    };
+
+    track(&app, |t| t.feature("read_aloud"));
+    tauri::async_runtime::spawn(run_read_aloud(app, generation));
+    Ok(progress)
+}
+
+#[tauri::command]
+async fn read_aloud_selection(app: AppHandle) -> Result<ReadAloudProgress, String> {
+    let text = tauri::async_runtime::spawn_blocking(read_aloud::grab_selection)
+        .await
+        .map_err(|e| format!("Selection task failed: {}", e))?
+        .map_err(|e| e.to_string())?;
+    let read_aloud_state = app.state::<ReadAloudState>();
+    read_aloud_text(text, app.clone(), read_aloud_state).await
+}
+
+fn update_read_aloud(app: &AppHandle, update: impl FnOnce(&mut ReadAloudSession)) -> Result<ReadAloudProgress, String> {
+    let read_aloud_state = app.state::<ReadAloudState>();
+    let mut session = read_aloud_state.0.lock().map_err(|e| format!("Failed to lock read-aloud state: {}", e))?;
+    update(&mut session);
+    let progress = session.progress();
+    emit_read_aloud_progress(app, progress.clone());
+    Ok(progress)
+}
+
+#[tauri::command]
+async fn pause_read_aloud(app: AppHandle) -> Result<ReadAloudProgress, String> {
+    update_read_aloud(&app, |session| {
+        if session.status == ReadAloudStatus::Playing {
+            session.status = ReadAloudStatus::Paused;
+        }
+    })
+}
+
+#[tauri::command]
+async fn resume_read_aloud(app: AppHandle) -> Result<ReadAloudProgress, String> {
+    update_read_aloud(&app, |session| {
+        if session.status == ReadAloudStatus::Paused {
+            session.status = ReadAloudStatus::Playing;
+        }
+    })
+}
+
+#[tauri::command]
+async fn stop_read_aloud(app: AppHandle) -> Result<ReadAloudProgress, String> {
+    update_read_aloud(&app, |session| session.stop())
+}
+
+#[tauri::command]
+async fn set_read_aloud_speed(speed: f32, app: AppHandle) -> Result<ReadAloudProgress, String> {
+    update_read_aloud(&app, |session| session.speed = speed.clamp(0.5, 3.0))
+}
+
+#[tauri::command]
+async fn start_meeting_transcription(app: AppHandle, meeting_state: State<'_, MeetingState>) -> Result<String, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let mut recorder = meeting_state.0.lock().map_err(|e| format!("Failed to lock meeting state: {}", e))?;
+    if recorder.is_some() {
+        return Err(i18n::tr("error-meeting-running"));
+    }
+
+    let app_entries = app.clone();
+    let started = MeetingRecorder::start(&app_config, move |entry| {
+        if let Err(e) = app_entries.emit("meeting-transcript-entry", entry) {
+            eprintln!("Failed to emit meeting transcript entry: {}", e);
+        }
+    })
+    .map_err(|e| format!("Failed to start meeting transcription: {}", e))?;
+    *recorder = Some(started);
+    track(&app, |t| t.feature("meeting"));
+    Ok("Meeting transcription started".to_string())
+}
+
+#[tauri::command]
+async fn stop_meeting_transcription(app: AppHandle, meeting_state: State<'_, MeetingState>) -> Result<MeetingTranscript, String> {
+    let recorder = {
+        let mut recorder = meeting_state.0.lock().map_err(|e| format!("Failed to lock meeting state: {}", e))?;
+        recorder.take().ok_or_else(|| i18n::tr("error-meeting-not-running"))?
+    };
+
+    let duration_ms = recorder.elapsed_ms();
+    // Joining the worker runs a final Whisper pass over the tail of the meeting
+    let entries = tauri::async_runtime::spawn_blocking(move || recorder.stop())
+        .await
+        .map_err(|e| format!("Meeting transcription task failed: {}", e))?;
+    let transcript = meeting::format_transcript(&entries);
+
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let summary = if app_config.meeting.summarize && !entries.is_empty() {
+        accessibility::announce(&app, AnnouncementKind::Thinking, Some(&i18n::tr("a11y-summarizing-meeting")));
+        match unless_shutdown(&app, meeting::summarize(&app_config.llm, &transcript)).await? {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                eprintln!("Failed to summarize meeting: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let result = MeetingTranscript {
+        entries,
+        transcript,
+        summary,
+        duration_ms,
+    };
+    app.emit("meeting-transcript-complete", result.clone())
+        .map_err(|e| format!("Failed to emit meeting transcript: {}", e))?;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn get_config() -> Result<config::AppConfig, String> {
+    config::try_get_config()
+        .map(|config| (*config).clone())
+        .ok_or_else(|| "Configuration not initialized".to_string())
+}
+
+/// Deep-merge `patch` into the configuration, save it, and apply it like an edit to the file.
+#[tauri::command]
+async fn update_config(patch: serde_json::Value, app: AppHandle) -> Result<config::AppConfig, String> {
+    let (previous, updated) = config::update_config(patch).map_err(|e| format!("Failed to update configuration: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+    Ok((*updated).clone())
+}
+
+fn section_changed<T: serde::Serialize>(old: &T, new: &T) -> bool {
+    serde_json::to_value(old).ok() != serde_json::to_value(new).ok()
+}
+
+/// Re-initialize the subsystems whose settings changed and tell the frontend with "config-changed".
+async fn apply_config_change(app: &AppHandle, old: &config::AppConfig, new: &config::AppConfig) {
+    if section_changed(&old.llm, &new.llm) {
+        llm::forget_providers();
+    }
+    // Devices and the STT model are opened when the pipeline starts, so those need a restart;
+    // the voice can be swapped in place
+    if section_changed(&old.audio, &new.audio) || section_changed(&old.stt, &new.stt) {
+        if let Err(e) = restart_audio_system(app).await {
+            eprintln!("{}", e);
+        }
+    } else if section_changed(&old.tts, &new.tts)
+        || section_changed(&old.character.lip_sync, &new.character.lip_sync)
+    {
+        if let Ok(handle) = app.state::<AudioState>().handle() {
+            if let Err(e) = handle.reload_tts().await {
+                eprintln!("Failed to reload text-to-speech: {:#}", e);
+            }
+        }
+    }
+    if section_changed(&old.avatar_server, &new.avatar_server) {
+        if let Err(e) = restart_avatar_server(app).await {
+            eprintln!("{}", e);
+        }
+    }
+    if section_changed(&old.vmc, &new.vmc) {
+        if let Err(e) = restart_vmc_output(app) {
+            eprintln!("{}", e);
+        }
+    }
+    if section_changed(&old.rest_api, &new.rest_api) {
+        if let Err(e) = restart_rest_api(app).await {
+            eprintln!("{}", e);
+        }
+    }
+    // Eye tracking watches the camera too
+    if section_changed(&old.vision, &new.vision)
+        || old.character.facial_expressions.eye_tracking != new.character.facial_expressions.eye_tracking
+    {
+        if let Err(e) = restart_camera(app).await {
+            eprintln!("{}", e);
+        }
+    }
+    if let Err(e) = app.emit("config-changed", new.clone()) {
+        eprintln!("Failed to emit config changed event: {}", e);
+    }
+}
+
+/// Respawn a running audio pipeline with the current settings, listening again if it was.
+async fn restart_audio_system(app: &AppHandle) -> Result<(), String> {
+    let Ok(handle) = app.state::<AudioState>().handle() else {
+        // Not started yet; it reads the new settings when it is
+        return Ok(());
+    };
+    let was_running = handle.status().await.map(|status| status.running).unwrap_or(false);
+    if let Err(e) = handle.shutdown().await {
+        eprintln!("Failed to stop audio system for restart: {:#}", e);
+    }
+    app.state::<AudioState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock audio state: {}", e))?
+        .take();
+
+    initialize_audio_system(app.clone(), app.state::<AudioState>()).await?;
+    if was_running {
+        start_listening(app.clone(), app.state::<AudioState>()).await?;
+    }
+    Ok(())
+}
+
+/// Reload the configuration when its file is edited and apply the difference.
+#[cfg(desktop)]
+fn spawn_config_watcher(app: &AppHandle) {
+    use notify::Watcher;
+
+    let Some(path) = config::config_path() else {
+        return;
+    };
+    let Some(file_name) = path.file_name().map(|name| name.to_os_string()) else {
+        return;
+    };
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(std::path::Path::new("."));
+
+    let (changed, mut changes) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if !event.kind.is_access() && event.paths.iter().any(|p| p.file_name() == Some(file_name.as_os_str())) {
+                let _ = changed.send(());
+            }
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Failed to watch configuration file: {}", e);
+            return;
+        }
+    };
+    // Editors often save by replacing the file, which ends a watch on the file itself
+    if let Err(e) = watcher.watch(dir, notify::RecursiveMode::NonRecursive) {
+        eprintln!("Failed to watch configuration directory {}: {}", dir.display(), e);
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        // The watch lasts as long as the watcher
+        let _watcher = watcher;
+        let mut cancelled = app.state::<ShutdownState>().cancelled.subscribe();
+        loop {
+            tokio::select! {
+                change = changes.recv() => {
+                    if change.is_none() {
+                        break;
+                    }
+                }
+                _ = cancelled.wait_for(|cancelled| *cancelled) => break,
+            }
+            // One save arrives as several events; wait until they stop before reading the file
+            while let Ok(Some(())) = tokio::time::timeout(CONFIG_RELOAD_DEBOUNCE, changes.recv()).await {}
+
+            match config::reload_config() {
+                Ok(Some((previous, reloaded))) => {
+                    eprintln!("Configuration reloaded");
+                    apply_config_change(&app, &previous, &reloaded).await;
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Ignoring configuration change: {:#}", e),
+            }
+        }
+    });
+}
+
+// How long the config file must go without events before it's re-read
+#[cfg(desktop)]
+const CONFIG_RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[tauri::command]
+async fn get_accessibility_settings() -> Result<AccessibilitySettings, String> {
+    Ok(accessibility::current_settings())
+}
+
+fn open_conversation_store(app: &tauri::App) -> ConversationStore {
+    let memory = config::try_get_config().map(|c| c.memory.clone());
+    let timeout = memory.as_ref().map(|m| m.conversation_timeout).unwrap_or(1800);
+    let persist = memory.map(|m| m.save_conversations).unwrap_or(true);
+
+    if persist {
+        match app.path().app_data_dir() {
+            Ok(dir) => match ConversationStore::open(dir.join("conversations.db"), timeout) {
+                Ok(store) => return store,
+                Err(e) => eprintln!("Failed to open conversation store, keeping conversations in memory: {:#}", e),
+            },
+            Err(e) => eprintln!("Failed to resolve app data directory, keeping conversations in memory: {}", e),
+        }
+    }
+    ConversationStore::in_memory(timeout).expect("failed to create in-memory conversation store")
+}
+
+fn with_conversations<T>(app: &AppHandle, f: impl FnOnce(&ConversationStore) -> anyhow::Result<T>) -> Result<T, String> {
+    let conversation_state = app.state::<ConversationState>();
+    let store = conversation_state.0.lock().map_err(|e| format!("Failed to lock conversation store: {}", e))?;
+    f(&store).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn record_conversation_turn(role: String, content: String, app: AppHandle) -> Result<ConversationTurn, String> {
+    let turn = with_conversations(&app, |store| store.append_turn(&role, &content))?;
+    maybe_tag_conversations(&app);
+    Ok(turn)
+}
+
+#[tauri::command]
+async fn bookmark_moment(note: Option<String>, tags: Option<Vec<String>>, app: AppHandle) -> Result<Bookmark, String> {
+    let bookmark = with_conversations(&app, |store| store.bookmark_current(note.as_deref(), &tags.unwrap_or_default()))?;
+    track(&app, |t| t.feature("bookmark"));
+    app.emit("bookmark-added", bookmark.clone())
+        .map_err(|e| format!("Failed to emit bookmark: {}", e))?;
+    Ok(bookmark)
+}
+
+#[tauri::command]
+async fn annotate_turn(turn_id: i64, note: Option<String>, tags: Option<Vec<String>>, app: AppHandle) -> Result<Bookmark, String> {
+    let bookmark = with_conversations(&app, |store| store.annotate_turn(turn_id, note.as_deref(), &tags.unwrap_or_default()))?;
+    app.emit("bookmark-added", bookmark.clone())
+        .map_err(|e| format!("Failed to emit bookmark: {}", e))?;
+    Ok(bookmark)
+}
+
+#[tauri::command]
+async fn list_bookmarks(query: Option<String>, tag: Option<String>, app: AppHandle) -> Result<Vec<Bookmark>, String> {
+    with_conversations(&app, |store| store.list_bookmarks(query.as_deref(), tag.as_deref()))
+}
+
+#[tauri::command]
+async fn delete_bookmark(id: i64, app: AppHandle) -> Result<String, String> {
+    with_conversations(&app, |store| store.delete_bookmark(id))?;
+    Ok(format!("Deleted bookmark {}", id))
+}
+
+#[tauri::command]
+async fn export_bookmarks(format: String, query: Option<String>, tag: Option<String>, app: AppHandle) -> Result<String, String> {
+    let bookmarks = with_conversations(&app, |store| store.list_bookmarks(query.as_deref(), tag.as_deref()))?;
+    conversation::export_bookmarks(&bookmarks, &format).map_err(|e| format!("Failed to export bookmarks: {}", e))
+}
+
+/// Save conversation `id` to `path` as "markdown", a readable chat log, or "json", with its
+/// timestamps, token use and audio durations, to archive or share.
+#[tauri::command]
+async fn export_conversation(id: i64, format: String, path: String, app: AppHandle) -> Result<(), String> {
+    let export = with_conversations(&app, |store| store.conversation_export(id))?;
+    let content = conversation::export::render(&export, &format.trim().to_lowercase())
+        .map_err(|e| format!("Failed to export conversation: {:#}", e))?;
+    tauri::async_runtime::spawn_blocking(move || std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e)))
+        .await
+        .map_err(|e| format!("Failed to export conversation: {}", e))??;
+    track(&app, |t| t.feature("conversation_export"));
+    Ok(())
+}
+
+/// Check the release feed and remember the result. Fails in offline mode.
+async fn run_update_check(app: &AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let config = config::try_get_config().ok_or_else(|| "Configuration not loaded".to_string())?;
+    updater::ensure_allowed(config.app.offline_mode, &config.updates).map_err(|e| e.to_string())?;
+
+    let current_version = app.package_info().version.to_string();
+    let info = unless_shutdown(app, updater::check(&current_version, &config.updates))
+        .await?
+        .map_err(|e| format!("Failed to check for updates: {:#}", e))?;
+
+    {
+        let update_state = app.state::<UpdateState>();
+        let mut session = update_state.0.lock().map_err(|e| format!("Failed to lock update state: {}", e))?;
+        if session.available.as_ref().map(|a| &a.version) != info.as_ref().map(|i| &i.version) {
+            session.downloaded = None;
+        }
+        session.available = info.clone();
+    }
+
+    if let Some(info) = &info {
+        app.emit("update-available", info.clone())
+            .map_err(|e| format!("Failed to emit update: {}", e))?;
+    }
+    Ok(info)
+}
+
+async fn download_update(app: &AppHandle, info: UpdateInfo) -> Result<std::path::PathBuf, String> {
+    {
+        let update_state = app.state::<UpdateState>();
+        let mut session = update_state.0.lock().map_err(|e| format!("Failed to lock update state: {}", e))?;
+        if session.downloading {
+            return Err("An update is already downloading".to_string());
+        }
+        session.downloading = true;
+    }
+
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map(|dir| dir.join("updates"))
+        .map_err(|e| format!("Failed to resolve update directory: {}", e));
+    let result = match dir {
+        Ok(dir) => {
+            let progress_app = app.clone();
+            let on_progress = move |progress| {
+                let _ = progress_app.emit("update-download-progress", progress);
+            };
+            unless_shutdown(app, updater::download(&info, &dir, on_progress))
+                .await
+                .and_then(|result| result.map_err(|e| format!("Failed to download update: {:#}", e)))
+        }
+        Err(e) => Err(e),
+    };
+
+    let update_state = app.state::<UpdateState>();
+    let mut session = update_state.0.lock().map_err(|e| format!("Failed to lock update state: {}", e))?;
+    session.downloading = false;
+    let path = result?;
+    if session.available.as_ref().map(|a| &a.version) == Some(&info.version) {
+        session.downloaded = Some(path.clone());
+    }
+    drop(session);
+
+    app.emit("update-downloaded", info)
+        .map_err(|e| format!("Failed to emit update: {}", e))?;
+    Ok(path)
+}
+
+#[tauri::command]
+async fn check_for_updates(app: AppHandle) -> Result<Option<UpdateInfo>, String> {
+    run_update_check(&app).await
+}
+
+#[tauri::command]
+async fn install_update(app: AppHandle) -> Result<String, String> {
+    let (available, downloaded) = {
+        let update_state = app.state::<UpdateState>();
+        let session = update_state.0.lock().map_err(|e| format!("Failed to lock update state: {}", e))?;
+        (session.available.clone(), session.downloaded.clone())
+    };
+    let info = available.ok_or_else(|| "No update is available; check for updates first".to_string())?;
+    let path = match downloaded {
+        Some(path) => path,
+        None => download_update(&app, info.clone()).await?,
+    };
+
+    updater::launch_installer(&path).map_err(|e| format!("Failed to install update: {:#}", e))?;
+    // The installer replaces the running binary, so get out of its way
+    request_shutdown(&app);
+    Ok(format!("Installing version {}", info.version))
+}
+
+/// Check for updates now and then on the configured interval until shutdown. Settings are
+/// read before each check, so turning on offline mode or auto_check applies to the next one.
+#[cfg(desktop)]
+fn spawn_update_checks(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut cancelled = app.state::<ShutdownState>().cancelled.subscribe();
+        // Only speak up about each version once
+        let mut announced: Option<String> = None;
+        while let Some(config) = config::try_get_config() {
+            let interval = Duration::from_secs(config.updates.check_interval_hours.max(1) as u64 * 3600);
+            let allowed = config.updates.auto_check && updater::ensure_allowed(config.app.offline_mode, &config.updates).is_ok();
+            if allowed {
+                match run_update_check(&app).await {
+                    Ok(Some(info)) => {
+                        if announced.as_ref() != Some(&info.version) {
+                            announced = Some(info.version.clone());
+                            speak_proactively(&app, i18n::tr_with("spoken-update-available", &[("version", info.version.as_str())])).await;
+                        }
+                        if config.updates.auto_download {
+                            if let Err(e) = download_update(&app, info).await {
+                                eprintln!("{}", e);
+                                track(&app, |t| t.error("update"));
+                                let _ = app.emit("update-error", e);
+                            }
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = cancelled.wait_for(|cancelled| *cancelled) => break,
+            }
+        }
+    });
+}
+
+// How often quiet hours are re-evaluated
+const QUIET_HOURS_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+fn quiet_hours_status(app: &AppHandle) -> Result<QuietStatus, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let quiet_state = app.state::<QuietHoursState>();
+    let quiet_hours = quiet_state.0.lock().map_err(|e| format!("Failed to lock quiet hours: {}", e))?;
+    Ok(quiet_hours.status(&app_config.quiet_hours))
+}
+
+/// Speak something the user didn't ask for, holding it back during quiet hours.
+async fn speak_proactively(app: &AppHandle, message: String) {
+    if app.state::<AssistantPausedState>().0.load(Ordering::SeqCst) {
+        return;
+    }
+    let deferred = match (config::try_get_config(), app.state::<QuietHoursState>().0.lock()) {
+        (Some(app_config), Ok(mut quiet_hours)) if quiet_hours.status(&app_config.quiet_hours).active => {
+            quiet_hours.defer(message.clone());
+            true
+        }
+        _ => false,
+    };
+    if deferred {
+        let _ = app.emit("speech-deferred", message);
+        return;
+    }
+
+    if let Ok(handle) = app.state::<AudioState>().handle() {
+        if let Err(e) = handle.speak(message).await {
+            eprintln!("Failed to speak: {}", e);
+        }
+    }
+}
+
+async fn refresh_quiet_calendar(app: &AppHandle, url: &str) -> Result<usize, String> {
+    let events = unless_shutdown(app, quiet_hours::fetch_calendar(url))
+        .await?
+        .map_err(|e| format!("Failed to refresh calendar: {:#}", e))?;
+    let count = events.len();
+    app.state::<QuietHoursState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock quiet hours: {}", e))?
+        .set_calendar(events);
+    Ok(count)
+}
+
+/// Refresh the calendar, tell the frontend when quiet hours start or end and speak
+/// whatever was held back once they do. Settings are read on every tick, so edits apply
+/// without a restart.
+fn spawn_quiet_hours_monitor(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut cancelled = app.state::<ShutdownState>().cancelled.subscribe();
+        // The URL last fetched and when; a different URL is fetched right away
+        let mut calendar_refreshed: Option<(String, std::time::Instant)> = None;
+        let mut was_active = false;
+        loop {
+            if let Some(app_config) = config::try_get_config() {
+                let settings = &app_config.quiet_hours;
+                let calendar_url = settings.calendar_url.trim();
+                let calendar_refresh = Duration::from_secs(settings.calendar_refresh_minutes.max(1) as u64 * 60);
+                let stale = calendar_refreshed
+                    .as_ref()
+                    .is_none_or(|(url, at)| url != calendar_url || at.elapsed() >= calendar_refresh);
+                if calendar_url.is_empty() {
+                    // A removed calendar shouldn't keep its meetings quiet
+                    if calendar_refreshed.take().is_some() {
+                        if let Ok(mut quiet_hours) = app.state::<QuietHoursState>().0.lock() {
+                            quiet_hours.set_calendar(Vec::new());
+                        }
+                    }
+                } else if stale {
+                    calendar_refreshed = Some((calendar_url.to_string(), std::time::Instant::now()));
+                    if let Err(e) = refresh_quiet_calendar(&app, calendar_url).await {
+                        eprintln!("{}", e);
+                    }
+                }
+
+                let (status, deferred) = {
+                    let quiet_state = app.state::<QuietHoursState>();
+                    let Ok(mut quiet_hours) = quiet_state.0.lock() else {
+                        break;
+                    };
+                    let status = quiet_hours.status(settings);
+                    let ended = was_active && !status.active;
+                    (status, if ended { quiet_hours.take_deferred() } else { Vec::new() })
+                };
+                if status.active != was_active {
+                    was_active = status.active;
+                    let _ = app.emit("quiet-hours-changed", status);
+                }
+                if settings.announce_deferred && !deferred.is_empty() {
+                    speak_proactively(&app, i18n::tr("spoken-deferred-intro")).await;
+                    for message in deferred {
+                        speak_proactively(&app, message).await;
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(QUIET_HOURS_POLL_INTERVAL) => {}
+                _ = cancelled.wait_for(|cancelled| *cancelled) => break,
+            }
+        }
+    });
+}
+
+/// Hide the always-on-top panel while suppressed; returns whether it was hidden so it can
+/// be shown again afterwards.
+#[cfg(desktop)]
+fn hide_overlay(app: &AppHandle) -> bool {
+    let Some(sidepanel) = app.get_webview_window("sidepanel") else {
+        return false;
+    };
+    if !sidepanel.is_visible().unwrap_or(false) {
+        return false;
+    }
+    match sidepanel.hide() {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to hide sidepanel: {}", e);
+            false
+        }
+    }
+}
+
+/// Watch the foreground app and suppress interruptions during calls, presentations and
+/// fullscreen apps. Only proactive speech and the overlay are affected; the wake word and
+/// push-to-talk keep working.
+#[cfg(desktop)]
+fn spawn_focus_monitor(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut cancelled = app.state::<ShutdownState>().cancelled.subscribe();
+        let mut current: Option<focus::FocusSuppression> = None;
+        let mut overlay_hidden = false;
+        // Read on every tick, so switching focus detection on or off applies right away
+        while let Some(app_config) = config::try_get_config() {
+            let poll_interval = Duration::from_secs(app_config.focus.poll_seconds.max(1) as u64);
+            let suppression = if app_config.focus.enabled {
+                let foreground = match tauri::async_runtime::spawn_blocking(focus::foreground_app).await {
+                    Ok(Ok(foreground)) => foreground,
+                    Ok(Err(e)) => {
+                        eprintln!("Focus-app detection is unavailable: {:#}", e);
+                        break;
+                    }
+                    Err(e) => {
+                        eprintln!("Focus-app detection failed: {}", e);
+                        break;
+                    }
+                };
+                foreground.and_then(|foreground| focus::classify(&foreground, &app_config.focus))
+            } else {
+                None
+            };
+
+            if suppression != current {
+                current = suppression.clone();
+                if let Ok(mut quiet_hours) = app.state::<QuietHoursState>().0.lock() {
+                    quiet_hours.set_focus(suppression.clone());
+                }
+                if suppression.is_some() {
+                    overlay_hidden |= hide_overlay(&app);
+                } else if std::mem::take(&mut overlay_hidden) {
+                    if let Some(sidepanel) = app.get_webview_window("sidepanel") {
+                        if let Err(e) = sidepanel.show() {
+                            eprintln!("Failed to show sidepanel: {}", e);
+                        }
+                    }
+                }
+                let _ = app.emit("focus-suppression-changed", suppression);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(poll_interval) => {}
+                _ = cancelled.wait_for(|cancelled| *cancelled) => break,
+            }
+        }
+
+        // Don't leave the app suppressed or the panel hidden if detection stops working
+        if *cancelled.borrow() {
+            return;
+        }
+        if current.is_some() {
+            if let Ok(mut quiet_hours) = app.state::<QuietHoursState>().0.lock() {
+                quiet_hours.set_focus(None);
+            }
+        }
+        if overlay_hidden {
+            if let Some(sidepanel) = app.get_webview_window("sidepanel") {
+                let _ = sidepanel.show();
+            }
+        }
+    });
+}
+
+#[tauri::command]
+async fn get_quiet_hours_status(app: AppHandle) -> Result<QuietStatus, String> {
+    quiet_hours_status(&app)
+}
+
+/// Switch do not disturb on (for `minutes`, or until switched off) or off.
+#[tauri::command]
+async fn set_do_not_disturb(enabled: bool, minutes: Option<u32>, app: AppHandle) -> Result<QuietStatus, String> {
+    app.state::<QuietHoursState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock quiet hours: {}", e))?
+        .set_manual(enabled, minutes);
+    let status = quiet_hours_status(&app)?;
+    app.emit("quiet-hours-changed", status.clone())
+        .map_err(|e| format!("Failed to emit quiet hours change: {}", e))?;
+    Ok(status)
+}
+
+/// Pause or resume the assistant. Pausing also drops the reply in progress and any queued turns.
+#[tauri::command]
+async fn set_assistant_paused(paused: bool, app: AppHandle) -> Result<bool, String> {
+    app.state::<AssistantPausedState>().0.store(paused, Ordering::SeqCst);
+    if paused {
+        if let Some(app_config) = config::try_get_config() {
+            llm::provider_for(&app_config.llm).abort();
+        }
+        app.state::<ChatState>()
+            .queue
+            .lock()
+            .map_err(|e| format!("Failed to lock turn queue: {}", e))?
+            .clear();
+        if let Ok(handle) = app.state::<AudioState>().handle() {
+            handle.stop_speaking().await.map_err(|e| format!("Failed to stop speaking: {:#}", e))?;
+        }
+    }
+    app.emit("assistant-paused", paused)
+        .map_err(|e| format!("Failed to emit assistant paused event: {}", e))?;
+    Ok(paused)
+}
+
+#[tauri::command]
+async fn refresh_quiet_hours_calendar(app: AppHandle) -> Result<String, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let url = app_config.quiet_hours.calendar_url.trim();
+    if url.is_empty() {
+        return Err("No calendar URL is configured".to_string());
+    }
+    let count = refresh_quiet_calendar(&app, url).await?;
+    Ok(format!("Loaded {} calendar events", count))
+}
+
+fn telemetry_enabled() -> bool {
+    config::try_get_config().map(|c| c.development.telemetry).unwrap_or(false)
+}
+
+fn open_telemetry(app: &tauri::App) -> TelemetryRecorder {
+    let version = app.package_info().version.to_string();
+    match app.path().app_data_dir() {
+        Ok(dir) => TelemetryRecorder::load(dir.join("telemetry.json"), &version),
+        Err(e) => {
+            eprintln!("Failed to resolve app data directory, keeping telemetry in memory: {}", e);
+            TelemetryRecorder::in_memory(&version)
+        }
+    }
+}
+
+/// Count something in the local telemetry batch. A no-op unless telemetry is enabled.
+fn track(app: &AppHandle, record: impl FnOnce(&mut TelemetryRecorder)) {
+    if !telemetry_enabled() {
+        return;
+    }
+    if let Some(telemetry_state) = app.try_state::<TelemetryState>() {
+        if let Ok(mut recorder) = telemetry_state.0.lock() {
+            record(&mut recorder);
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_telemetry_preview(app: AppHandle) -> Result<TelemetryPreview, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let telemetry_state = app.state::<TelemetryState>();
+    let recorder = telemetry_state.0.lock().map_err(|e| format!("Failed to lock telemetry state: {}", e))?;
+    Ok(TelemetryPreview {
+        enabled: app_config.development.telemetry,
+        uploads: app_config.development.telemetry && !app_config.app.offline_mode,
+        endpoint: app_config.development.telemetry_endpoint.clone(),
+        batch: recorder.batch().clone(),
+    })
+}
+
+// Longest the idle animations sleep, so a changed blink_rate or a disabled character applies promptly
+const IDLE_ANIMATION_POLL: Duration = Duration::from_secs(1);
+
+/// Emit "blink", "micro-expression" and "idle-gesture" events so the avatar looks alive
+/// between replies, until shutdown.
+fn spawn_idle_animations(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut cancelled = app.state::<ShutdownState>().cancelled.subscribe();
+        let mut scheduler = IdleScheduler::default();
+        loop {
+            let wait = scheduler
+                .next_due()
+                .saturating_duration_since(std::time::Instant::now())
+                .min(IDLE_ANIMATION_POLL);
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = cancelled.wait_for(|cancelled| *cancelled) => break,
+            }
+
+            let Some(app_config) = config::try_get_config() else {
+                continue;
+            };
+            let state = match app.state::<CharacterStateStore>().0.lock() {
+                Ok(machine) => machine.state(),
+                Err(_) => continue,
+            };
+            for event in scheduler.due(std::time::Instant::now(), state, &app_config.character) {
+                let result = match event {
+                    IdleEvent::Blink(blink) => {
+                        send_avatar_frame(&app, AvatarFrame::Blink(blink.clone()));
+                        app.emit("blink", blink)
+                    }
+                    IdleEvent::MicroExpression(expression) => {
+                        send_avatar_frame(&app, AvatarFrame::MicroExpression(expression.clone()));
+                        app.emit("micro-expression", expression)
+                    }
+                    IdleEvent::IdleGesture(gesture) => {
+                        send_avatar_frame(&app, AvatarFrame::IdleGesture(gesture.clone()));
+                        app.emit("idle-gesture", gesture)
+                    }
+                };
+                if let Err(e) = result {
+                    eprintln!("Failed to emit idle animation: {}", e);
+                }
+            }
+        }
+    });
+}
+
+// How often the local batch is written to disk
+const TELEMETRY_SAVE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Periodically persist the batch and upload it once it covers `telemetry_upload_hours`.
+/// Settings are read on every tick, so turning telemetry off or going offline stops uploads.
+fn spawn_telemetry_uploads(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut cancelled = app.state::<ShutdownState>().cancelled.subscribe();
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(TELEMETRY_SAVE_INTERVAL) => {}
+                _ = cancelled.wait_for(|cancelled| *cancelled) => break,
+            }
+
+            let Some(app_config) = config::try_get_config() else {
+                continue;
+            };
+            let upload = app_config.development.telemetry && !app_config.app.offline_mode;
+            let upload_after_ms = app_config.development.telemetry_upload_hours.max(1) as u64 * 3600 * 1000;
+
+            let due = {
+                let telemetry_state = app.state::<TelemetryState>();
+                let Ok(mut recorder) = telemetry_state.0.lock() else {
+                    continue;
+                };
+                if let Err(e) = recorder.save() {
+                    eprintln!("Failed to save telemetry: {:#}", e);
+                }
+                let batch = recorder.batch();
+                let due = upload
+                    && !batch.is_empty()
+                    && conversation::now_ms().saturating_sub(batch.collected_since) >= upload_after_ms;
+                // Counts recorded while the upload runs go into a fresh batch
+                due.then(|| recorder.take_batch())
+            };
+
+            if let Some(batch) = due {
+                let result = telemetry::upload(&app_config.development.telemetry_endpoint, &batch).await;
+                let telemetry_state = app.state::<TelemetryState>();
+                let Ok(mut recorder) = telemetry_state.0.lock() else {
+                    continue;
+                };
+                if let Err(e) = result {
+                    // Put the batch back and try again on the next tick
+                    eprintln!("Failed to upload telemetry: {:#}", e);
+                    recorder.restore_batch(batch);
+                }
+                if let Err(e) = recorder.save() {
+                    eprintln!("Failed to save telemetry: {:#}", e);
+                }
+            }
+        }
+    });
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+struct ModelLoadReport {
+    stages: Vec<ModelLoadProgress>,
+    // Warm-up has finished, whether or not every model loaded
+    done: bool,
+}
+
+// Replies from the turn queue, in the order the turns were submitted
+#[derive(Debug, Clone, serde::Serialize)]
+struct AssistantReply {
+    turn_id: u64,
+    prompt: String,
+    reply: String,
+    // Stopped partway; `reply` is what had been written by then
+    interrupted: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct AssistantReplyDelta {
+    turn_id: u64,
+    delta: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct AssistantReplyError {
+    turn_id: u64,
+    error: String,
+}
+
+// Sent as "tool-call" for each tool the model ran while answering a turn
+#[derive(Debug, Clone, serde::Serialize)]
+struct TurnToolCall {
+    turn_id: u64,
+    #[serde(flatten)]
+    call: ToolCallEvent,
+}
+
+// Sent as "tool-output" for each line a command prints while it runs
+#[derive(Debug, Clone, serde::Serialize)]
+struct ToolOutputLine {
+    turn_id: u64,
+    line: String,
+}
+
+// Sent as "tool-confirmation"; answered with answer_tool_confirmation
+#[derive(Debug, Clone, serde::Serialize)]
+struct ToolConfirmationRequest {
+    id: u64,
+    tool: String,
+    detail: String,
+}
+
+// How long a tool call waits for the user before it's treated as declined
+const TOOL_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The tools turn `turn_id` may use, as llm.tools allows.
+fn tool_registry(app: &AppHandle, turn_id: u64, llm_config: &config::LlmConfig) -> ToolRegistry {
+    let tools = &llm_config.tools;
+    let mut registry = llm::tools::builtin();
+    #[cfg(desktop)]
+    if !tools.allowed_commands.is_empty() {
+        let output_app = app.clone();
+        let on_output = Arc::new(move |line: String| {
+            let _ = output_app.emit("tool-output", ToolOutputLine { turn_id, line });
+        });
+        registry.register(Arc::new(llm::tools::command::RunCommand::new(
+            tools.allowed_commands.clone(),
+            Duration::from_secs(tools.command_timeout_seconds.max(1) as u64),
+            tool_confirmation(app, "run_command"),
+            on_output,
+        )));
+    }
+    let files = llm::tools::files::ReadFile::new(&tools.file_directories, llm_config.clone());
+    if !files.is_empty() {
+        registry.register(Arc::new(files));
+    }
+    if let Some(app_config) = config::try_get_config() {
+        registry.register(Arc::new(llm::tools::image_text::ReadImageText::new(
+            &tools.file_directories,
+            app_config.vision.ocr.clone(),
+        )));
+    }
+    let camera_app = app.clone();
+    registry.register(Arc::new(llm::tools::camera::CameraObjects::new(Arc::new(move || {
+        let watching = camera_app.state::<CameraState>().0.lock().ok()?.is_some();
+        let objects = camera_app.state::<ObjectsState>().0.lock().ok()?.clone();
+        watching.then_some(objects)
+    }))));
+    match search::backend_for(&tools.web_search) {
+        Ok(backend) => registry.register(Arc::new(llm::tools::web_search::WebSearch::new(
+            backend,
+            tools.web_search.max_results as usize,
+        ))),
+        Err(e) if tools.allowed.iter().any(|name| name == "web_search") => eprintln!("Web search is unavailable: {:#}", e),
+        Err(_) => {}
+    }
+    registry.retain(&tools.allowed);
+    registry
+}
+
+/// Let read_file read from `directories`, and nowhere else.
+#[tauri::command]
+async fn set_file_directories(directories: Vec<String>, app: AppHandle) -> Result<(), String> {
+    let directories: Vec<String> = directories
+        .iter()
+        .map(|directory| directory.trim().to_string())
+        .filter(|directory| !directory.is_empty())
+        .collect();
+    if let Some(missing) = directories.iter().find(|directory| !std::path::Path::new(directory).is_dir()) {
+        return Err(format!("{} isn't a directory", missing));
+    }
+    let (previous, updated) = config::update_config(serde_json::json!({ "llm": { "tools": { "file_directories": directories } } }))
+        .map_err(|e| format!("Failed to save file directories: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+    Ok(())
+}
+
+/// Ask the user, through a "tool-confirmation" event, before `tool` goes ahead.
+fn tool_confirmation(app: &AppHandle, tool: &'static str) -> llm::tools::Confirm {
+    let app = app.clone();
+    Arc::new(move |detail: String| {
+        let app = app.clone();
+        async move {
+            let confirmations = app.state::<ToolConfirmationState>();
+            let id = confirmations.next_id.fetch_add(1, Ordering::SeqCst);
+            let (answer, answered) = tokio::sync::oneshot::channel();
+            match confirmations.pending.lock() {
+                Ok(mut pending) => pending.insert(id, answer),
+                Err(_) => return false,
+            };
+            let request = ToolConfirmationRequest {
+                id,
+                tool: tool.to_string(),
+                detail,
+            };
+            if let Err(e) = app.emit("tool-confirmation", request) {
+                eprintln!("Failed to emit tool confirmation: {}", e);
+            }
+            let approved = matches!(tokio::time::timeout(TOOL_CONFIRMATION_TIMEOUT, answered).await, Ok(Ok(true)));
+            if let Ok(mut pending) = confirmations.pending.lock() {
+                pending.remove(&id);
+            }
+            approved
+        }
+        .boxed()
+    })
+}
+
+/// Let the tool call that sent "tool-confirmation" `id` go ahead, or not.
+#[tauri::command]
+fn answer_tool_confirmation(id: u64, approved: bool, confirmations: State<'_, ToolConfirmationState>) -> Result<(), String> {
+    let answer = confirmations
+        .pending
+        .lock()
+        .map_err(|e| format!("Failed to lock tool confirmations: {}", e))?
+        .remove(&id)
+        .ok_or_else(|| format!("No tool call is waiting on confirmation {}", id))?;
+    let _ = answer.send(approved);
+    Ok(())
+}
+
+/// Stop every reply being generated, keeping what each had written so far.
+fn cancel_generations(app: &AppHandle) {
+    if let Ok(generations) = app.state::<GenerationState>().0.lock() {
+        for cancel in generations.values() {
+            cancel.cancel();
+        }
+    }
+}
+
+/// Stop generating the reply to turn `message_id`. The part already written is delivered and
+/// saved, marked as interrupted. Returns false if the reply had already finished.
+#[tauri::command]
+async fn abort_generation(message_id: u64, app: AppHandle) -> Result<bool, String> {
+    let cancel = app
+        .state::<GenerationState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock generations: {}", e))?
+        .get(&message_id)
+        .cloned();
+    let Some(cancel) = cancel else {
+        return Ok(false);
+    };
+    cancel.cancel();
+    // A streamed reply may already be talking
+    if let Ok(handle) = app.state::<AudioState>().handle() {
+        handle.stop_speaking().await.map_err(|e| format!("Failed to stop speaking: {:#}", e))?;
+    }
+    Ok(true)
+}
+
+fn open_chat(app: &tauri::App) -> ChatState {
+    let llm_config = config::try_get_config().map(|c| c.llm.clone());
+    let system_prompt = llm_config.as_ref().map(|c| c.system_prompt.clone()).unwrap_or_default();
+    let queue = TurnQueue::new(&llm_config.map(|c| c.turn_queue).unwrap_or_default());
+
+    let (wake, wake_rx) = tokio::sync::mpsc::unbounded_channel();
+    tauri::async_runtime::spawn(run_turn_worker(app.handle().clone(), wake_rx));
+    ChatState {
+        queue: Mutex::new(queue),
+        history: Mutex::new(vec![ChatMessage::system(system_prompt)]),
+        wake,
+    }
+}
+
+/// Drain the turn queue: up to `turn_queue.concurrency` requests in flight, replies delivered in submission order.
+async fn run_turn_worker(app: AppHandle, mut wake: tokio::sync::mpsc::UnboundedReceiver<()>) {
+    let Some(concurrency) = config::try_get_config().map(|c| c.llm.turn_queue.concurrency.max(1) as usize) else {
+        return;
+    };
+    let mut in_flight = FuturesOrdered::new();
+
+    // ChatState is managed after this task starts; it's in place by the time the first turn arrives
+    if wake.recv().await.is_none() {
+        return;
+    }
+    loop {
+        while in_flight.len() < concurrency {
+            let chat_state = app.state::<ChatState>();
+            let Some(turn) = chat_state.queue.lock().ok().and_then(|mut queue| queue.pop()) else {
+                break;
+            };
+            // Read per turn so a changed model or endpoint applies to the next request
+            let Some(app_config) = config::try_get_config() else {
+                deliver_reply(&app, turn, Err("Configuration not initialized".to_string()), false, false).await;
+                continue;
+            };
+            // The turn joins the history with its reply, so turns in flight together don't
+            // see each other and the history keeps each question next to its answer
+            let mut messages = chat_state.history.lock().map(|history| history.clone()).unwrap_or_default();
+            messages.push(ChatMessage::user(turn.text.clone()));
+
+            let cancel = CancellationToken::default();
+            if let Ok(mut generations) = app.state::<GenerationState>().0.lock() {
+                generations.insert(turn.id, cancel.clone());
+            }
+            let work_app = app.clone();
+            update_character(&app, CharacterInput::ThinkingStarted);
+            in_flight.push_back(async move {
+                let mut messages = build_context(&work_app, &app_config.llm, messages, &turn.text).await;
+                // Only this request sees the frame; the history keeps the text
+                if let (Some(frame), Some(newest)) = (latest_vision_frame(&work_app), messages.last_mut()) {
+                    newest.images.push(frame);
+                }
+                let provider = llm::provider_for(&app_config.llm);
+                // Speaking while the reply streams in only keeps replies in order one turn at a time
+                let speak_as_streamed = app_config.tts.streaming && concurrency == 1;
+                let reply = async {
+                    let tools = tool_registry(&work_app, turn.id, &app_config.llm);
+                    if app_config.llm.tools.enabled && !tools.is_empty() {
+                        let events_app = work_app.clone();
+                        let turn_id = turn.id;
+                        let on_call = move |call| {
+                            let _ = events_app.emit("tool-call", TurnToolCall { turn_id, call });
+                        };
+                        let completion =
+                            llm::tools::complete_with_tools(provider.as_ref(), &tools, &messages, app_config.llm.tools.max_rounds, on_call);
+                        // Nothing has been written yet when a reply that isn't streamed is stopped
+                        return match cancel.run(completion).await {
+                            Some(result) => (result, false, false),
+                            None => (Ok(String::new()), false, true),
+                        };
+                    }
+                    if !app_config.llm.stream {
+                        return match cancel.run(provider.chat(&messages, None)).await {
+                            Some(result) => (result, false, false),
+                            None => (Ok(String::new()), false, true),
+                        };
+                    }
+                    // Show the reply as it's written; it's still delivered whole, in order, below
+                    let (deltas, delta_rx) = tokio::sync::mpsc::unbounded_channel();
+                    let speaker =
+                        tauri::async_runtime::spawn(stream_reply(work_app.clone(), turn.id, delta_rx, speak_as_streamed, cancel.clone()));
+                    match cancel.run(provider.stream(&messages, None, deltas)).await {
+                        Some(Ok(reply)) => {
+                            let (spoken, _) = speaker.await.unwrap_or_default();
+                            (Ok(reply), spoken, false)
+                        }
+                        Some(Err(e)) => {
+                            // Don't speak the rest of an aborted or failed reply
+                            speaker.abort();
+                            (Err(e), false, false)
+                        }
+                        // Dropping the request closed `deltas`, so the speaker ends with the text it got
+                        None => {
+                            let (_, partial) = speaker.await.unwrap_or_default();
+                            (Ok(partial), false, true)
+                        }
+                    }
+                };
+                let (result, spoken, interrupted) = match unless_shutdown(&work_app, reply).await {
+                    Ok((result, spoken, interrupted)) => (result.map_err(|e| format!("LLM request failed: {:#}", e)), spoken, interrupted),
+                    Err(e) => (Err(e), false, false),
+                };
+                if let Ok(mut generations) = work_app.state::<GenerationState>().0.lock() {
+                    generations.remove(&turn.id);
+                }
+                update_character(&work_app, CharacterInput::ThinkingFinished);
+                (turn, result, spoken, interrupted)
+            });
+        }
+
+        tokio::select! {
+            Some((turn, result, spoken, interrupted)) = in_flight.next(), if !in_flight.is_empty() => {
+                deliver_reply(&app, turn, result, spoken, interrupted).await;
+            }
+            woken = wake.recv() => {
+                if woken.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Forward a streaming reply to the frontend and, with `speak`, to TTS a sentence at a time.
+/// Returns whether the reply was spoken, and the text received, which is all of it unless
+/// `cancel` stopped the reply partway.
+async fn stream_reply(
+    app: AppHandle,
+    turn_id: u64,
+    mut deltas: tokio::sync::mpsc::UnboundedReceiver<String>,
+    speak: bool,
+    cancel: CancellationToken,
+) -> (bool, String) {
+    let speech = match app.state::<AudioState>().handle() {
+        Ok(handle) if speak => match handle.speak_stream().await {
+            Ok(speech) => Some(speech),
+            Err(e) => {
+                eprintln!("Failed to start streamed speech: {}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+    let mut received = String::new();
+    while let Some(delta) = deltas.recv().await {
+        received.push_str(&delta);
+        if let Some(speech) = &speech {
+            speech.push(delta.clone());
+        }
+        send_avatar_frame(&app, AvatarFrame::Transcript {
+            role: "assistant",
+            text: delta.clone(),
+            is_final: false,
+        });
+        let _ = app.emit("assistant-reply-delta", AssistantReplyDelta { turn_id, delta });
+    }
+    if cancel.is_cancelled() {
+        // Dropping the speech unfinished abandons the sentence it was waiting to complete
+        return (false, received);
+    }
+    // The stream has ended, so whatever is left is the final sentence
+    match speech {
+        Some(speech) => {
+            speech.finish();
+            (true, received)
+        }
+        None => (false, received),
+    }
+}
+
+/// Feed `input` to the avatar's state machine and send any change as "character-state".
+/// An interruption settles back once it has been shown for long enough.
+fn update_character(app: &AppHandle, input: CharacterInput) {
+    let change = match app.state::<CharacterStateStore>().0.lock() {
+        Ok(mut machine) => machine.handle(input),
+        Err(e) => {
+            eprintln!("Failed to lock character state: {}", e);
+            return;
+        }
+    };
+    if matches!(input, CharacterInput::Interrupted) {
+        let settle_app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(character::INTERRUPTED_HOLD).await;
+            update_character(&settle_app, CharacterInput::Settle);
+        });
+    }
+    if let Some(change) = change {
+        send_avatar_frame(app, AvatarFrame::State { state: change.state });
+        if let Err(e) = app.emit("character-state", change) {
+            eprintln!("Failed to emit character state: {}", e);
+        }
+    }
+}
+
+/// What the avatar is animating now, for a frontend that opens after the last "character-state".
+#[tauri::command]
+fn get_character_state(character_state: State<'_, CharacterStateStore>) -> Result<CharacterState, String> {
+    character_state
+        .0
+        .lock()
+        .map(|machine| machine.state())
+        .map_err(|e| format!("Failed to lock character state: {}", e))
+}
+
+/// Stream `frame` to external renderers: clients of the avatar server and the VMC receiver,
+/// whichever are on.
+fn send_avatar_frame(app: &AppHandle, frame: AvatarFrame) {
+    if let Ok(server) = app.state::<AvatarServerState>().0.lock() {
+        if let Some(server) = server.as_ref() {
+            server.send(&frame);
+        }
+    }
+    if let Ok(vmc) = app.state::<VmcState>().0.lock() {
+        if let Some(vmc) = vmc.as_ref() {
+            vmc.send(&frame);
+        }
+    }
+}
+
+/// Stop the avatar server and, if avatar_server.enabled is set, start it on the configured port.
+async fn restart_avatar_server(app: &AppHandle) -> Result<(), String> {
+    let running = app
+        .state::<AvatarServerState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock avatar server: {}", e))?
+        .take();
+    if let Some(server) = running {
+        server.stop().await;
+    }
+
+    let Some(settings) = config::try_get_config().map(|c| c.avatar_server.clone()) else {
+        return Ok(());
+    };
+    if !settings.enabled {
+        return Ok(());
+    }
+    let server = AvatarServer::start(settings.port)
+        .await
+        .map_err(|e| format!("Failed to start avatar server: {:#}", e))?;
+    app.state::<AvatarServerState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock avatar server: {}", e))?
+        .replace(server);
+    Ok(())
+}
+
+/// Start or stop sending VMC blendshapes as vmc.enabled says, to the configured receiver.
+fn restart_vmc_output(app: &AppHandle) -> Result<(), String> {
+    let settings = config::try_get_config().map(|c| c.vmc.clone()).filter(|vmc| vmc.enabled);
+    let sender = match settings {
+        Some(settings) => Some(
+            VmcSender::connect(&settings.host, settings.port).map_err(|e| format!("Failed to start VMC output: {:#}", e))?,
+        ),
+        None => None,
+    };
+    *app.state::<VmcState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock VMC output: {}", e))? = sender;
+    Ok(())
+}
+
+/// Turn VMC output on or off and choose the receiver it sends to.
+#[tauri::command]
+async fn set_vmc_output(enabled: bool, host: String, port: u16, app: AppHandle) -> Result<(), String> {
+    let (previous, updated) =
+        config::update_config(serde_json::json!({ "vmc": { "enabled": enabled, "host": host.trim(), "port": port } }))
+            .map_err(|e| format!("Failed to save VMC settings: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+
+    let sending = app
+        .state::<VmcState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock VMC output: {}", e))?
+        .is_some();
+    if enabled && !sending {
+        return Err(format!("VMC output could not send to {}:{}", host.trim(), port));
+    }
+    Ok(())
+}
+
+/// Turn the avatar server on or off and choose its port; external renderers connect to
+/// ws://127.0.0.1:<port>.
+#[tauri::command]
+async fn set_avatar_server(enabled: bool, port: u16, app: AppHandle) -> Result<(), String> {
+    let (previous, updated) = config::update_config(serde_json::json!({ "avatar_server": { "enabled": enabled, "port": port } }))
+        .map_err(|e| format!("Failed to save avatar server settings: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+
+    let listening = app
+        .state::<AvatarServerState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock avatar server: {}", e))?
+        .as_ref()
+        .map(|server| server.port());
+    if enabled && listening != Some(port) {
+        return Err(format!("Avatar server could not listen on port {}", port));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn list_characters() -> Result<Vec<CharacterProfile>, String> {
+    character::profiles::list().map_err(|e| format!("Failed to list characters: {:#}", e))
+}
+
+#[tauri::command]
+fn create_character(profile: CharacterProfile) -> Result<CharacterProfile, String> {
+    character::profiles::create(profile).map_err(|e| format!("Failed to create character: {:#}", e))
+}
+
+/// Add the character in the .aichar package at `path` to the profiles.
+#[tauri::command]
+async fn import_character(path: String) -> Result<CharacterProfile, String> {
+    tauri::async_runtime::spawn_blocking(move || character::profiles::import(std::path::Path::new(&path)))
+        .await
+        .map_err(|e| format!("Failed to import character: {}", e))?
+        .map_err(|e| format!("Failed to import character: {:#}", e))
+}
+
+/// Save the character stored as `id` to `path` as a .aichar package to share.
+#[tauri::command]
+async fn export_character(id: String, path: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || character::profiles::export(&id, std::path::Path::new(&path)))
+        .await
+        .map_err(|e| format!("Failed to export character: {}", e))?
+        .map_err(|e| format!("Failed to export character: {:#}", e))
+}
+
+/// Become the character stored as `id`: its avatar, voice and persona replace the current
+/// ones, and the conversation continues under its system prompt.
+#[tauri::command]
+async fn switch_character(id: String, app: AppHandle) -> Result<CharacterProfile, String> {
+    let profile = character::profiles::load(id.trim()).map_err(|e| format!("Failed to load character: {:#}", e))?;
+
+    let mut patch = serde_json::json!({
+        "character": {
+            "profile": profile.id,
+            "facial_expressions": { "emotion_mapping": profile.emotion_mapping },
+        },
+    });
+    if !profile.avatar_url.is_empty() {
+        patch["character"]["avatar_url"] = serde_json::json!(profile.avatar_url);
+    }
+    if !profile.tts_provider.is_empty() {
+        patch["tts"]["provider"] = serde_json::json!(profile.tts_provider);
+    }
+    if !profile.voice.is_empty() {
+        patch["tts"]["voice"] = serde_json::json!(profile.voice);
+    }
+    if let Some(speed) = profile.speed {
+        patch["tts"]["speed"] = serde_json::json!(speed);
+    }
+    if let Some(pitch) = profile.pitch {
+        patch["tts"]["pitch"] = serde_json::json!(pitch);
+    }
+    if !profile.viseme_mapping.is_empty() {
+        patch["character"]["lip_sync"]["viseme_mapping"] = serde_json::json!(profile.viseme_mapping);
+    }
+    if let Some(animations) = &profile.animations {
+        patch["character"]["animations"] = serde_json::json!(animations);
+    }
+    if !profile.lexicon.is_empty() {
+        // The character's pronunciations win over the user's for the same words
+        let mut lexicon = config::try_get_config().ok_or("Configuration not initialized")?.tts.lexicon.clone();
+        lexicon.retain(|entry| !profile.lexicon.iter().any(|own| own.word.eq_ignore_ascii_case(&entry.word)));
+        lexicon.extend(profile.lexicon.iter().cloned());
+        patch["tts"]["lexicon"] = serde_json::json!(lexicon);
+    }
+    if !profile.system_prompt.is_empty() {
+        patch["llm"]["system_prompt"] = serde_json::json!(profile.system_prompt);
+        // The history opens with the old persona's prompt
+        if let Ok(mut history) = app.state::<ChatState>().history.lock() {
+            match history.first_mut() {
+                Some(first) if first.role == "system" => *first = ChatMessage::system(profile.system_prompt.clone()),
+                _ => history.insert(0, ChatMessage::system(profile.system_prompt.clone())),
+            }
+        }
+    }
+    let (previous, updated) = config::update_config(patch).map_err(|e| format!("Failed to switch character: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+    app.emit("character-switched", profile.clone())
+        .map_err(|e| format!("Failed to emit character switch: {}", e))?;
+    Ok(profile)
+}
+
+/// Set the avatar's expression to the emotion `reply` reads as, if emotion mapping is on.
+fn show_reply_emotion(app: &AppHandle, reply: &str) {
+    let Some(app_config) = config::try_get_config() else {
+        return;
+    };
+    let expressions = &app_config.character.facial_expressions;
+    if !expressions.enabled || !expressions.emotion_mapping {
+        return;
+    }
+    let app = app.clone();
+    let reply = reply.to_string();
+    let use_llm = expressions.emotion_source == "llm";
+    tauri::async_runtime::spawn(async move {
+        let emotion = if use_llm {
+            match character::emotion::infer_with_llm(&app_config.llm, &reply).await {
+                Ok(emotion) => emotion,
+                Err(e) => {
+                    eprintln!("Failed to read reply emotion, falling back to the lexicon: {:#}", e);
+                    character::emotion::infer_from_text(&reply)
+                }
+            }
+        } else {
+            character::emotion::infer_from_text(&reply)
+        };
+        if let Err(e) = change_character_emotion(emotion.to_string(), app).await {
+            eprintln!("Failed to show reply emotion: {}", e);
+        }
+    });
+}
+
+async fn deliver_reply(app: &AppHandle, turn: QueuedTurn, result: Result<String, String>, spoken: bool, interrupted: bool) {
+    let chat_state = app.state::<ChatState>();
+    let reply = match result {
+        Ok(reply) => reply,
+        Err(error) => {
+            eprintln!("{}", error);
+            track(app, |t| t.error("llm"));
+            // The unanswered turn stays out of the history, so it doesn't confuse the next request
+            let _ = app.emit("assistant-reply-error", AssistantReplyError { turn_id: turn.id, error });
+            return;
+        }
+    };
+    if interrupted && reply.trim().is_empty() {
+        // Stopped before it wrote anything: there's nothing to keep, so leave the turn out as above
+        let _ = app.emit("assistant-reply", AssistantReply {
+            turn_id: turn.id,
+            prompt: turn.text,
+            reply,
+            interrupted,
+        });
+        return;
+    }
+
+    if let Ok(mut history) = chat_state.history.lock() {
+        history.push(ChatMessage::user(turn.text.clone()));
+        history.push(ChatMessage::assistant(reply.clone()));
+        let max_history = config::try_get_config().map(|c| c.memory.max_history as usize).unwrap_or(50);
+        // 0 keeps every message; the context window still bounds what's sent
+        if max_history > 0 && history.len() > max_history.max(2) + 1 {
+            let max_history = max_history.max(2);
+            // Keep the system prompt and any summary of earlier turns, drop the oldest messages
+            let start = history.iter().take_while(|m| m.role == "system").count();
+            let excess = (history.len() - max_history - 1).min(history.len().saturating_sub(start + 1));
+            history.drain(start..start + excess);
+        }
+    }
+    match with_conversations(app, |store| {
+        store.append_turn("user", &turn.text)?;
+        store.append_turn_marked("assistant", &reply, interrupted)
+    }) {
+        Ok(recorded) => remember_exchange(app, recorded.conversation_id, &turn.text, &reply),
+        Err(e) => eprintln!("Failed to record conversation turn: {}", e),
+    }
+    maybe_tag_conversations(app);
+    track(app, |t| t.feature("chat"));
+
+    let event = AssistantReply {
+        turn_id: turn.id,
+        prompt: turn.text,
+        reply: reply.clone(),
+        interrupted,
+    };
+    if let Err(e) = app.emit("assistant-reply", event) {
+        eprintln!("Failed to emit assistant reply: {}", e);
+    }
+    send_avatar_frame(app, AvatarFrame::Transcript {
+        role: "assistant",
+        text: reply.clone(),
+        is_final: true,
+    });
+    show_reply_emotion(app, &reply);
+
+    // A stopped reply isn't picked back up by reading it out
+    if spoken || interrupted {
+        return;
+    }
+    // Speaking here, before the next reply is delivered, keeps spoken replies in order too
+    if let Ok(handle) = app.state::<AudioState>().handle() {
+        if let Err(e) = handle.speak(reply).await {
+            eprintln!("Failed to speak reply: {}", e);
+        }
+    }
+}
+
+fn queue_user_turn(app: &AppHandle, text: String) -> Result<TurnQueued, String> {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err("Nothing to send".to_string());
+    }
+    let chat_state = app.state::<ChatState>();
+    let queued = chat_state
+        .queue
+        .lock()
+        .map_err(|e| format!("Failed to lock turn queue: {}", e))?
+        .push(text)?;
+    let _ = chat_state.wake.send(());
+    app.emit("turn-queued", queued.clone())
+        .map_err(|e| format!("Failed to emit turn queued event: {}", e))?;
+    Ok(queued)
+}
+
+// Frames older than this no longer show what the user is talking about
+const VISION_FRAME_MAX_AGE: Duration = Duration::from_secs(10);
+
+/// Hand over what the camera sees now, as base64 with or without a data: URL prefix. With
+/// vision.enabled it goes along with the next turn, for providers that take images, shrunk to
+/// vision.input_resolution.
+#[tauri::command]
+async fn set_vision_frame(mime_type: String, data: String, vision_frame: State<'_, VisionFrameState>) -> Result<(), String> {
+    if !mime_type.starts_with("image/") {
+        return Err(format!("{} isn't an image type", mime_type));
+    }
+    if data.trim().is_empty() {
+        return Err("The frame is empty".to_string());
+    }
+    let frame = prepare_image(data).await?;
+    *vision_frame.0.lock().map_err(|e| format!("Failed to lock vision frame: {}", e))? =
+        Some((frame, std::time::Instant::now()));
+    Ok(())
+}
+
+/// `source` decoded and shrunk to vision.input_resolution, off the async runtime.
+async fn prepare_image(source: String) -> Result<ImageData, String> {
+    let resolution = config::try_get_config()
+        .map(|c| c.vision.input_resolution)
+        .ok_or("Configuration not initialized")?;
+    tauri::async_runtime::spawn_blocking(move || vision::prepare_image(&source, resolution))
+        .await
+        .map_err(|e| format!("Failed to prepare image: {}", e))?
+        .map_err(|e| format!("Failed to prepare image: {:#}", e))
+}
+
+/// Ask the LLM `question` about an image, given as a file path, a data: URL or base64, in the
+/// context of the current conversation. Only models that take images can see it (GPT-4o,
+/// Gemini, LLaVA on Ollama); the history keeps the question and answer as text.
+#[tauri::command]
+async fn ask_about_image(path_or_base64: String, question: String, app: AppHandle) -> Result<String, String> {
+    let question = question.trim().to_string();
+    if question.is_empty() {
+        return Err("Nothing to ask".to_string());
+    }
+    let image = prepare_image(path_or_base64).await?;
+    let (answer, _) = ask_llm_about_image(&app, image, &question).await?;
+    track(&app, |t| t.feature("vision"));
+    Ok(answer)
+}
+
+/// Ask the LLM `question` about `image` with the conversation so far, keeping the exchange in
+/// the history and the conversation as text. Returns the answer and, if saved, its turn.
+async fn ask_llm_about_image(app: &AppHandle, image: ImageData, question: &str) -> Result<(String, Option<ConversationTurn>), String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let mut history = app
+        .state::<ChatState>()
+        .history
+        .lock()
+        .map_err(|e| format!("Failed to lock chat history: {}", e))?
+        .clone();
+    history.push(ChatMessage::user(question));
+    let mut messages = build_context(app, &app_config.llm, history, question).await;
+    if let Some(newest) = messages.last_mut() {
+        newest.images.push(image);
+    }
+    let answer = llm::chat_completion(&app_config.llm, &messages, None)
+        .await
+        .map_err(|e| format!("Failed to ask about the image: {:#}", e))?;
+
+    if let Ok(mut history) = app.state::<ChatState>().history.lock() {
+        history.push(ChatMessage::user(question));
+        history.push(ChatMessage::assistant(answer.clone()));
+    }
+    let turn = match with_conversations(app, |store| {
+        store.append_turn("user", question)?;
+        store.append_turn("assistant", &answer)
+    }) {
+        Ok(turn) => Some(turn),
+        Err(e) => {
+            eprintln!("Failed to record conversation turn: {}", e);
+            None
+        }
+    };
+    Ok((answer, turn))
+}
+
+/// Read the text in an image, given as a file path, a data: URL or base64, with OCR. The text
+/// also goes into the history, so the next turn can ask about it.
+#[tauri::command]
+async fn ocr_image(path: String, app: AppHandle) -> Result<String, String> {
+    let ocr = config::try_get_config().ok_or("Configuration not initialized")?.vision.ocr.clone();
+    let source = std::path::Path::new(path.trim());
+    let name = match source.file_name() {
+        Some(name) if source.is_file() => name.to_string_lossy().into_owned(),
+        _ => "an image".to_string(),
+    };
+    let image = tauri::async_runtime::spawn_blocking(move || vision::load_image(&path))
+        .await
+        .map_err(|e| format!("Image loading task failed: {}", e))?
+        .map_err(|e| format!("Failed to load the image: {:#}", e))?;
+    let text = vision::ocr::read_text(&image, &ocr)
+        .await
+        .map_err(|e| format!("Failed to read text in the image: {:#}", e))?;
+    if !text.is_empty() {
+        if let Ok(mut history) = app.state::<ChatState>().history.lock() {
+            history.push(ChatMessage::system(format!("Text read from {}:
+{}", name, text)));
+        }
+    }
+    track(&app, |t| t.feature("ocr"));
+    Ok(text)
+}
+
+// What the history shows the user asking for a screen description
+const DESCRIBE_SCREEN_PROMPT: &str = "Describe what's on my screen.";
+
+/// The displays describe_screen can capture.
+#[tauri::command]
+async fn list_displays() -> Result<Vec<vision::screen::Display>, String> {
+    tauri::async_runtime::spawn_blocking(vision::screen::list_displays)
+        .await
+        .map_err(|e| format!("Display listing task failed: {}", e))?
+        .map_err(|e| format!("Failed to list displays: {:#}", e))
+}
+
+/// Capture display `monitor` (from list_displays; the primary one when left out), shrink it to
+/// vision.screen_resolution and have the LLM describe it. The description is added to the
+/// conversation and sent as "conversation-turn".
+#[tauri::command]
+async fn describe_screen(monitor: Option<u32>, app: AppHandle) -> Result<String, String> {
+    let resolution = config::try_get_config()
+        .map(|c| c.vision.screen_resolution)
+        .ok_or("Configuration not initialized")?;
+    let screenshot = tauri::async_runtime::spawn_blocking(move || {
+        vision::screen::capture(monitor).and_then(|screen| vision::to_jpeg(&vision::shrink(screen, resolution)))
+    })
+    .await
+    .map_err(|e| format!("Screen capture task failed: {}", e))?
+    .map_err(|e| format!("Failed to capture the screen: {:#}", e))?;
+
+    let (description, turn) = ask_llm_about_image(&app, screenshot, DESCRIBE_SCREEN_PROMPT).await?;
+    if let Some(turn) = turn {
+        if let Err(e) = app.emit("conversation-turn", turn) {
+            eprintln!("Failed to emit conversation turn: {}", e);
+        }
+    }
+    track(&app, |t| t.feature("describe_screen"));
+    Ok(description)
+}
+
+/// The frame handed over last, or else what the camera sees, while vision is on.
+fn latest_vision_frame(app: &AppHandle) -> Option<ImageData> {
+    let app_config = config::try_get_config().filter(|c| c.vision.enabled)?;
+    let frame = app.state::<VisionFrameState>().0.lock().ok()?.clone();
+    if let Some((image, _)) = frame.filter(|(_, at)| at.elapsed() < VISION_FRAME_MAX_AGE) {
+        return Some(image);
+    }
+    let frame = app.state::<CameraState>().0.lock().ok()?.as_ref()?.latest()?;
+    if frame.captured_at.elapsed() >= VISION_FRAME_MAX_AGE {
+        return None;
+    }
+    let image = vision::shrink(image::DynamicImage::ImageRgb8((*frame.image).clone()), app_config.vision.input_resolution);
+    match vision::to_jpeg(&image) {
+        Ok(image) => Some(image),
+        Err(e) => {
+            eprintln!("Failed to encode camera frame: {:#}", e);
+            None
+        }
+    }
+}
+
+// A running camera whose newest frame is older than this has stopped giving frames
+const CAMERA_FRAME_MAX_AGE: Duration = Duration::from_secs(2);
+
+/// Open the camera if vision is on, or release it if it's off, with the current settings, and
+/// tell the UI with "camera-state".
+async fn restart_camera(app: &AppHandle) -> Result<(), String> {
+    let result = reopen_camera(app).await;
+    let status = camera_status(app, result.as_ref().err().cloned());
+    if let Err(e) = app.emit("camera-state", status) {
+        eprintln!("Failed to emit camera state: {}", e);
+    }
+    result
+}
+
+async fn reopen_camera(app: &AppHandle) -> Result<(), String> {
+    let previous = app
+        .state::<CameraState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock camera: {}", e))?
+        .take();
+    if let Some(camera) = previous {
+        // Joining the capture thread waits for the frame being read
+        let _ = tauri::async_runtime::spawn_blocking(move || camera.stop()).await;
+    }
+    if let Ok(mut objects) = app.state::<ObjectsState>().0.lock() {
+        objects.clear();
+    }
+    let Some(app_config) = config::try_get_config().filter(|c| c.vision.enabled) else {
+        return Ok(());
+    };
+    let settings = app_config.vision.clone();
+    let watch_settings = settings.clone();
+    let track_gaze = app_config.character.facial_expressions.eye_tracking;
+    let camera = tauri::async_runtime::spawn_blocking(move || Camera::start(&settings))
+        .await
+        .map_err(|e| format!("Camera task failed: {}", e))?
+        .map_err(|e| format!("Failed to start the camera: {:#}", e))?;
+    if watch_settings.face_detection || watch_settings.emotion_recognition || watch_settings.object_detection || track_gaze {
+        let watch_app = app.clone();
+        // Capture goes on without it; only what's read from the frames is lost
+        let watched = vision::watch(&camera, &watch_settings, track_gaze, move |event| match event {
+            VisionEvent::Presence(event) => presence_changed(&watch_app, event),
+            VisionEvent::Expression(expression) => expression_changed(&watch_app, expression),
+            VisionEvent::Objects(objects) => objects_detected(&watch_app, objects),
+            VisionEvent::Gaze(gaze) => gaze_changed(&watch_app, gaze),
+        });
+        if let Err(e) = watched {
+            eprintln!("Failed to start watching the camera: {:#}", e);
+        }
+    }
+    *app.state::<CameraState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock camera: {}", e))? = Some(camera);
+    Ok(())
+}
+
+/// Tell the frontend someone sat down or left, so it can greet them, and start listening for a
+/// user who sat down with vision.auto_wake on.
+fn presence_changed(app: &AppHandle, event: PresenceEvent) {
+    if let Err(e) = app.emit("presence-changed", event.clone()) {
+        eprintln!("Failed to emit presence change: {}", e);
+    }
+    if !event.present {
+        if let Ok(mut expression) = app.state::<ExpressionState>().0.lock() {
+            *expression = None;
+        }
+    }
+    if !event.present || !config::try_get_config().is_some_and(|c| c.vision.auto_wake) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if app.state::<AssistantPausedState>().0.load(Ordering::SeqCst)
+            || quiet_hours_status(&app).is_ok_and(|status| status.active)
+        {
+            return;
+        }
+        // Only wake an idle assistant; one that's already listening or talking is left alone
+        let status = current_audio_status(&app.state::<AudioState>()).await;
+        if !status.initialized || status.mode != ProcessingMode::Idle {
+            return;
+        }
+        if let Err(e) = start_listening(app.clone(), app.state::<AudioState>()).await {
+            eprintln!("Failed to wake on presence: {}", e);
+        }
+    });
+}
+
+/// Keep what the camera sees for look_at_camera and send it as "objects-detected".
+fn objects_detected(app: &AppHandle, objects: Vec<DetectedObject>) {
+    if let Err(e) = app.emit("objects-detected", objects.clone()) {
+        eprintln!("Failed to emit detected objects: {}", e);
+    }
+    if let Ok(mut seen) = app.state::<ObjectsState>().0.lock() {
+        *seen = objects;
+    }
+}
+
+/// Turn the avatar's eyes and head toward the user at `gaze`, or back ahead once they're gone,
+/// with "look-at" and on external renderers.
+fn gaze_changed(app: &AppHandle, gaze: Option<Gaze>) {
+    if !config::try_get_config().is_some_and(|c| c.character.enabled && c.character.facial_expressions.eye_tracking) {
+        return;
+    }
+    let look = character::gaze::look_at(gaze.as_ref());
+    send_avatar_frame(app, AvatarFrame::LookAt(look));
+    if let Err(e) = app.emit("look-at", look) {
+        eprintln!("Failed to emit look-at: {}", e);
+    }
+}
+
+// Expressions read longer ago than this no longer say how the user looks
+const EXPRESSION_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Keep the user's new expression for the LLM, tell the frontend with "user-expression", and
+/// have an idle or listening avatar answer it.
+fn expression_changed(app: &AppHandle, expression: Expression) {
+    if let Ok(mut current) = app.state::<ExpressionState>().0.lock() {
+        *current = Some((expression.clone(), std::time::Instant::now()));
+    }
+    if let Err(e) = app.emit("user-expression", expression.clone()) {
+        eprintln!("Failed to emit user expression: {}", e);
+    }
+
+    let mapping = config::try_get_config()
+        .is_some_and(|c| c.character.facial_expressions.enabled && c.character.facial_expressions.emotion_mapping);
+    // A reply's own emotion wins while it's being thought up or spoken
+    let attentive = app
+        .state::<CharacterStateStore>()
+        .0
+        .lock()
+        .is_ok_and(|machine| matches!(machine.state(), CharacterState::Idle | CharacterState::Listening));
+    let Some(emotion) = character::emotion::empathize(expression.label).filter(|_| mapping && attentive) else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = change_character_emotion(emotion.to_string(), app).await {
+            eprintln!("Failed to show empathy: {}", e);
+        }
+    });
+}
+
+/// The user's recent expression as context for the LLM; a neutral face adds nothing.
+fn expression_context(app: &AppHandle) -> Option<ChatMessage> {
+    let (expression, at) = app.state::<ExpressionState>().0.lock().ok()?.clone()?;
+    (at.elapsed() < EXPRESSION_MAX_AGE && expression.label != "neutral")
+        .then(|| vision::emotion::context_message(&expression))
+}
+
+/// Whether the camera is capturing and from which device, with `error` as why it isn't.
+fn camera_status(app: &AppHandle, error: Option<String>) -> CameraStatus {
+    let device = app
+        .state::<CameraState>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|camera| camera.as_ref().filter(|camera| camera.is_running()).map(|camera| camera.name().to_string()));
+    CameraStatus {
+        on: device.is_some(),
+        device,
+        error,
+    }
+}
+
+/// Whether the camera is on, for a UI that opens after the last "camera-state".
+#[tauri::command]
+fn get_camera_state(app: AppHandle) -> CameraStatus {
+    camera_status(&app, None)
+}
+
+/// Opt in to the camera: turn vision on, which opens the camera and keeps it open across
+/// restarts until disable_camera.
+#[tauri::command]
+async fn enable_camera(app: AppHandle) -> Result<CameraStatus, String> {
+    let (previous, updated) = config::update_config(serde_json::json!({ "vision": { "enabled": true } }))
+        .map_err(|e| format!("Failed to save camera setting: {:#}", e))?;
+    let changed = section_changed(&previous.vision, &updated.vision);
+    apply_config_change(&app, &previous, &updated).await;
+    // Already on; make sure it's really capturing
+    if !changed && !camera_status(&app, None).on {
+        restart_camera(&app).await?;
+    }
+
+    let status = camera_status(&app, None);
+    if !status.on {
+        return Err("The camera could not be turned on".to_string());
+    }
+    track(&app, |t| t.feature("camera"));
+    Ok(status)
+}
+
+/// Turn the camera off: vision is turned off and the capture device released, not just left
+/// unread, so the camera's own light goes out too.
+#[tauri::command]
+async fn disable_camera(app: AppHandle) -> Result<CameraStatus, String> {
+    let (previous, updated) = config::update_config(serde_json::json!({ "vision": { "enabled": false } }))
+        .map_err(|e| format!("Failed to save camera setting: {:#}", e))?;
+    let changed = section_changed(&previous.vision, &updated.vision);
+    apply_config_change(&app, &previous, &updated).await;
+    // Off in the config already, but a camera could still be held
+    if !changed {
+        restart_camera(&app).await?;
+    }
+    Ok(camera_status(&app, None))
+}
+
+/// What the camera sees now, as a JPEG at the camera's own resolution. Needs the camera on;
+/// it's never opened behind the user's back.
+#[tauri::command]
+async fn capture_snapshot(app: AppHandle) -> Result<ImageData, String> {
+    let frame = app
+        .state::<CameraState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock camera: {}", e))?
+        .as_ref()
+        .ok_or("The camera is off")?
+        .latest()
+        .filter(|frame| frame.captured_at.elapsed() < CAMERA_FRAME_MAX_AGE)
+        .ok_or("The camera isn't giving any frames")?;
+    tauri::async_runtime::spawn_blocking(move || vision::to_jpeg(&image::DynamicImage::ImageRgb8((*frame.image).clone())))
+        .await
+        .map_err(|e| format!("Snapshot task failed: {}", e))?
+        .map_err(|e| format!("Failed to encode snapshot: {:#}", e))
+}
+
+#[tauri::command]
+async fn submit_user_turn(text: String, app: AppHandle) -> Result<TurnQueued, String> {
+    queue_user_turn(&app, text)
+}
+
+#[tauri::command]
+async fn list_llm_models() -> Result<Vec<String>, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    llm::provider_for(&app_config.llm)
+        .list_models()
+        .await
+        .map_err(|e| format!("Failed to list models: {:#}", e))
+}
+
+fn record_session_event(app: &AppHandle, event: SessionEvent, value: Option<&str>, confidence: Option<f64>, duration_ms: u64) {
+    if let Err(e) = with_conversations(app, |store| store.record_session_event(event, value, confidence, duration_ms)) {
+        eprintln!("Failed to record session event: {}", e);
+    }
+}
+
+fn current_session_mode(app: &AppHandle) -> Option<ProcessingMode> {
+    app.state::<SessionTimingState>().0.lock().ok().and_then(|timing| timing.map(|(mode, _)| mode))
+}
+
+/// Close out the time spent in the previous mode as talk or listen time.
+fn record_mode_change(app: &AppHandle, mode: ProcessingMode) {
+    let previous = match app.state::<SessionTimingState>().0.lock() {
+        Ok(mut timing) => timing.replace((mode, std::time::Instant::now())),
+        Err(_) => return,
+    };
+    let Some((previous_mode, since)) = previous else {
+        return;
+    };
+    let event = match previous_mode {
+        ProcessingMode::Speaking => SessionEvent::AssistantSpeech,
+        ProcessingMode::Listening => SessionEvent::Listening,
+        ProcessingMode::Idle => return,
+    };
+    record_session_event(app, event, None, None, since.elapsed().as_millis() as u64);
+}
+
+#[tauri::command]
+async fn record_user_emotion(emotion: String, confidence: Option<f64>, app: AppHandle) -> Result<String, String> {
+    let emotion = emotion.trim().to_lowercase();
+    if emotion.is_empty() {
+        return Err("Emotion must not be empty".to_string());
+    }
+    with_conversations(&app, |store| store.record_session_event(SessionEvent::Emotion, Some(&emotion), confidence, 0))?;
+    Ok(format!("Recorded emotion: {}", emotion))
+}
+
+#[tauri::command]
+async fn get_session_analytics(conversation_id: Option<i64>, app: AppHandle) -> Result<SessionAnalytics, String> {
+    with_conversations(&app, |store| store.session_analytics(conversation_id))
+}
+
+/// Tokens and cost of LLM requests over the last `days` days (30 by default), for keeping an
+/// eye on cloud spend.
+#[tauri::command]
+async fn get_usage_stats(days: Option<u32>, app: AppHandle) -> Result<UsageStats, String> {
+    with_conversations(&app, |store| store.usage_stats(days.unwrap_or(30).max(1)))
+}
+
+/// Tell the frontend, as "llm-fallback", when a request moves on to a backup backend.
+fn spawn_fallback_events(app: &AppHandle) {
+    let (events, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+    llm::fallback::set_sink(events);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = events_rx.recv().await {
+            if let Err(e) = app.emit("llm-fallback", event) {
+                eprintln!("Failed to emit LLM fallback: {}", e);
+            }
+        }
+    });
+}
+
+/// Store the usage of every LLM request as it finishes.
+fn spawn_usage_recording(app: &AppHandle) {
+    let (records, mut records_rx) = tokio::sync::mpsc::unbounded_channel();
+    llm::usage::set_sink(records);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(record) = records_rx.recv().await {
+            if let Err(e) = with_conversations(&app, |store| store.record_usage(&record)) {
+                eprintln!("Failed to record LLM usage: {}", e);
+            }
+        }
+    });
+}
+
+/// Whether past exchanges should be embedded and recalled, with the settings to do it.
+fn semantic_memory() -> Option<config::MemoryConfig> {
+    config::try_get_config()
+        .map(|c| c.memory.clone())
+        .filter(|memory| memory.enabled && memory.semantic_recall)
+}
+
+/// The `k` stored memories most similar to `query`, leaving out the current conversation.
+async fn recall(app: &AppHandle, query: &str, k: usize) -> Result<Vec<Memory>, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let memory = &app_config.memory;
+    let embedding = unless_shutdown(app, conversation::memory::embed(&app_config.llm, &memory.embedding_model, query))
+        .await?
+        .map_err(|e| format!("Failed to embed recall query: {:#}", e))?;
+    with_conversations(app, |store| {
+        let current = store.current_conversation()?;
+        store.search_memories(&memory.embedding_model, &embedding, k, memory.recall_min_score, current)
+    })
+}
+
+/// Add the memories relevant to `query` to the prompt, after the system prompt.
+async fn recalled_memories(app: &AppHandle, query: &str) -> Option<ChatMessage> {
+    let memory = semantic_memory().filter(|memory| memory.recall_k > 0)?;
+    match recall(app, query, memory.recall_k as usize).await {
+        Ok(memories) if !memories.is_empty() => Some(conversation::memory::context_message(&memories)),
+        Ok(_) => None,
+        // Answering without memories beats not answering
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+/// The request for the newest turn in `history`: the user's profile, recalled memories and the
+/// user's facial expression added and older turns trimmed to llm.context_window. Turns
+/// summarized to fit are swapped for their summary in the history.
+async fn build_context(app: &AppHandle, llm_config: &config::LlmConfig, history: Vec<ChatMessage>, query: &str) -> Vec<ChatMessage> {
+    let mut background: Vec<ChatMessage> = user_profile_context(app).into_iter().collect();
+    background.extend(recalled_memories(app, query).await);
+    let context = llm::context::build(llm_config, &history, background).await;
+    if context.dropped > 0 {
+        eprintln!("Left {} earlier messages out to fit the context window", context.dropped);
+    }
+    let mut messages = context.messages;
+    if let Some(expression) = expression_context(app) {
+        // Right before the newest turn, where it describes the user as they say it
+        messages.insert(messages.len().saturating_sub(1), expression);
+    }
+    if let Some(compaction) = context.compaction {
+        if let Ok(mut history) = app.state::<ChatState>().history.lock() {
+            let start = history.iter().take_while(|m| m.role == "system" && !llm::context::is_summary(m)).count();
+            let end = start + compaction.replaced.len();
+            // Other turns may have changed the history meanwhile; only swap what was summarized
+            let unchanged = history.get(start..end).is_some_and(|current| {
+                current.iter().zip(&compaction.replaced).all(|(a, b)| a.role == b.role && a.content == b.content)
+            });
+            if unchanged {
+                history.splice(start..end, [compaction.summary]);
+            }
+        }
+    }
+    messages
+}
+
+/// Embed a finished exchange in the background so later turns can recall it.
+fn remember_exchange(app: &AppHandle, conversation_id: i64, prompt: &str, reply: &str) {
+    if semantic_memory().is_none() {
+        return;
+    }
+    let app = app.clone();
+    let content = conversation::memory::exchange_text(prompt, reply);
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = store_memory(&app, MemoryKind::Exchange, Some(conversation_id), &content).await {
+            eprintln!("{}", e);
+        }
+    });
+}
+
+async fn store_memory(app: &AppHandle, kind: MemoryKind, conversation_id: Option<i64>, content: &str) -> Result<i64, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let model = &app_config.memory.embedding_model;
+    let embedding = unless_shutdown(app, conversation::memory::embed(&app_config.llm, model, content))
+        .await?
+        .map_err(|e| format!("Failed to embed memory: {:#}", e))?;
+    with_conversations(app, |store| store.add_memory(kind, conversation_id, content, model, &embedding))
+}
+
+#[tauri::command]
+async fn recall_memories(query: String, k: Option<u32>, app: AppHandle) -> Result<Vec<Memory>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Recall query must not be empty".to_string());
     }
-    if let Some(sidepanel_window) = app.get_webview_window("sidepanel") {
-        sidepanel_window.open_devtools();
+    let default_k = config::try_get_config().map(|c| c.memory.recall_k).unwrap_or(3);
+    recall(&app, query, k.unwrap_or(default_k).max(1) as usize).await
+}
+
+/// Keep a fact about the user for recall in any later conversation.
+#[tauri::command]
+async fn remember_fact(fact: String, app: AppHandle) -> Result<i64, String> {
+    let fact = fact.trim();
+    if fact.is_empty() {
+        return Err("Fact must not be empty".to_string());
     }
-    Ok("Developer tools opened".to_string())
+    store_memory(&app, MemoryKind::Fact, None, fact).await
+}
+
+/// Whether facts about the user are learned and added to the prompt.
+fn user_profile_enabled() -> bool {
+    config::try_get_config().is_some_and(|c| c.memory.enabled && c.memory.user_profile)
+}
+
+/// What's known about the user, for the system prompt.
+fn user_profile_context(app: &AppHandle) -> Option<ChatMessage> {
+    if !user_profile_enabled() {
+        return None;
+    }
+    match with_conversations(app, |store| store.user_facts()) {
+        Ok(facts) => conversation::profile::context_message(&facts),
+        Err(e) => {
+            eprintln!("Failed to load the user profile: {}", e);
+            None
+        }
+    }
+}
+
+/// Have the LLM pick up facts about the user from one conversation, sending
+/// "user-profile-updated" with the whole profile if any changed.
+async fn update_user_profile(app: &AppHandle, conversation_id: i64) -> Result<(), String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let (turns, facts) = with_conversations(app, |store| Ok((store.conversation_turns(conversation_id)?, store.user_facts()?)))?;
+    if turns.is_empty() {
+        return Ok(());
+    }
+
+    let update = unless_shutdown(app, conversation::profile::extract_facts(&app_config.llm, &facts, &turns))
+        .await?
+        .map_err(|e| format!("Failed to learn from conversation {}: {:#}", conversation_id, e))?;
+    let changed = with_conversations(app, |store| store.apply_profile_update(conversation_id, &update, turns.len() as u32))?;
+    if changed {
+        let facts = with_conversations(app, |store| store.user_facts())?;
+        app.emit("user-profile-updated", facts)
+            .map_err(|e| format!("Failed to emit user profile: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Learn about the user from conversations that have ended or gone quiet, in the background.
+fn maybe_update_user_profile(app: &AppHandle) {
+    if !user_profile_enabled() || app.state::<UserProfileState>().0.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let idle_secs = config::try_get_config().map_or(0, |c| c.memory.tag_idle_seconds);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match with_conversations(&app, |store| store.conversations_due_for_profile(idle_secs)) {
+            Ok(due) => {
+                for conversation_id in due {
+                    if let Err(e) = update_user_profile(&app, conversation_id).await {
+                        eprintln!("{}", e);
+                        break;
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to find conversations to learn from: {}", e),
+        }
+        app.state::<UserProfileState>().0.store(false, Ordering::SeqCst);
+    });
+}
+
+/// Everything known about the user, as the assistant sees it.
+#[tauri::command]
+async fn list_user_facts(app: AppHandle) -> Result<Vec<UserFact>, String> {
+    with_conversations(&app, |store| store.user_facts())
+}
+
+/// Tell the assistant something about yourself: `category` is name, preference, topic or detail.
+#[tauri::command]
+async fn add_user_fact(category: String, content: String, app: AppHandle) -> Result<UserFact, String> {
+    with_conversations(&app, |store| store.add_user_fact(&category, &content, FactSource::User, None))
+}
+
+/// Correct a fact; the assistant keeps the corrected one rather than relearning it.
+#[tauri::command]
+async fn update_user_fact(id: i64, content: String, category: Option<String>, app: AppHandle) -> Result<UserFact, String> {
+    with_conversations(&app, |store| store.update_user_fact(id, category.as_deref(), &content))
+}
+
+#[tauri::command]
+async fn delete_user_fact(id: i64, app: AppHandle) -> Result<String, String> {
+    with_conversations(&app, |store| store.delete_user_fact(id))?;
+    Ok(format!("Deleted fact {}", id))
+}
+
+/// Have the LLM title and tag one conversation from its turns.
+async fn tag_conversation_topics(app: &AppHandle, conversation_id: i64) -> Result<ConversationSummary, String> {
+    let app_config = config::try_get_config().ok_or("Configuration not initialized")?;
+    let turns = with_conversations(app, |store| store.conversation_turns(conversation_id))?;
+    if turns.is_empty() {
+        return Err(format!("Conversation {} has no turns to tag", conversation_id));
+    }
+
+    let topics = unless_shutdown(app, conversation::topics::generate_topics(&app_config.llm, &turns))
+        .await?
+        .map_err(|e| format!("Failed to tag conversation: {:#}", e))?;
+    let summary = with_conversations(app, |store| {
+        store.set_conversation_topics(conversation_id, &topics, turns.len() as u32)?;
+        store.conversation_summary(conversation_id)
+    })?;
+    app.emit("conversation-tagged", summary.clone())
+        .map_err(|e| format!("Failed to emit conversation tagged event: {}", e))?;
+    Ok(summary)
+}
+
+/// Tag finished conversations, and the current one every `tag_every_turns` turns or once it
+/// has gone quiet, in the background.
+fn maybe_tag_conversations(app: &AppHandle) {
+    let Some(memory) = config::try_get_config().map(|c| c.memory.clone()) else {
+        return;
+    };
+    if !memory.topic_tagging || app.state::<TopicTaggingState>().0.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let (every_turns, idle_secs) = (memory.tag_every_turns, memory.tag_idle_seconds);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match with_conversations(&app, |store| store.conversations_due_for_tagging(every_turns, idle_secs)) {
+            Ok(due) => {
+                for conversation_id in due {
+                    if let Err(e) = tag_conversation_topics(&app, conversation_id).await {
+                        eprintln!("{}", e);
+                        break;
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to find conversations to tag: {}", e),
+        }
+        app.state::<TopicTaggingState>().0.store(false, Ordering::SeqCst);
+    });
+}
+
+// How often conversations are checked for having gone quiet or ended without a new turn
+const IDLE_CONVERSATION_POLL: Duration = Duration::from_secs(60);
+
+/// Title, tag and learn about the user from conversations that go idle, which no new turn
+/// would otherwise prompt, until shutdown.
+fn spawn_idle_conversation_jobs(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut cancelled = app.state::<ShutdownState>().cancelled.subscribe();
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(IDLE_CONVERSATION_POLL) => {}
+                _ = cancelled.wait_for(|cancelled| *cancelled) => break,
+            }
+            clean_up_history(&app);
+            maybe_tag_conversations(&app);
+            maybe_update_user_profile(&app);
+        }
+    });
+}
+
+/// Close conversations gone quiet for memory.conversation_timeout, starting the live chat
+/// afresh if it was one of them, and delete the oldest beyond memory.max_conversations.
+fn clean_up_history(app: &AppHandle) {
+    let Some(config) = config::try_get_config() else {
+        return;
+    };
+    match with_conversations(app, |store| store.close_idle_conversations()) {
+        Ok(closed) if !closed.is_empty() => {
+            forget_live_chat(app);
+            for conversation_id in closed {
+                if let Err(e) = app.emit("conversation-ended", conversation_id) {
+                    eprintln!("Failed to emit conversation ended: {}", e);
+                }
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to close idle conversations: {}", e),
+    }
+    match with_conversations(app, |store| store.prune_conversations(config.memory.max_conversations)) {
+        Ok(0) => {}
+        Ok(pruned) => {
+            if let Err(e) = app.emit("history-pruned", pruned) {
+                eprintln!("Failed to emit history pruned: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to prune conversation history: {}", e),
+    }
+}
+
+/// Drop the turns of the live chat, keeping the system prompt, so the next turn starts a new
+/// conversation with the LLM too.
+fn forget_live_chat(app: &AppHandle) {
+    if let Ok(mut history) = app.state::<ChatState>().history.lock() {
+        let pinned = history.iter().take_while(|m| m.role == "system" && !llm::context::is_summary(m)).count();
+        history.truncate(pinned);
+    }
+}
+
+/// Delete conversations last active more than `older_than` days ago, or all of them with 0,
+/// returning how many were deleted.
+#[tauri::command]
+async fn purge_history(older_than: u32, app: AppHandle) -> Result<usize, String> {
+    let current = with_conversations(&app, |store| store.current_conversation())?;
+    let purged = with_conversations(&app, |store| store.purge_conversations(older_than))?;
+    // The live chat belonged to a conversation that's gone
+    if current.is_some() && with_conversations(&app, |store| store.current_conversation())? != current {
+        forget_live_chat(&app);
+    }
+    if purged > 0 {
+        if let Err(e) = app.emit("history-pruned", purged) {
+            eprintln!("Failed to emit history pruned: {}", e);
+        }
+    }
+    Ok(purged)
+}
+
+#[tauri::command]
+async fn tag_conversation(conversation_id: i64, app: AppHandle) -> Result<ConversationSummary, String> {
+    tag_conversation_topics(&app, conversation_id).await
+}
+
+#[tauri::command]
+async fn list_conversations(query: Option<String>, tag: Option<String>, app: AppHandle) -> Result<Vec<ConversationSummary>, String> {
+    with_conversations(&app, |store| store.list_conversations(query.as_deref(), tag.as_deref()))
+}
+
+/// Turns across all stored conversations that contain the words in `query`, best matches
+/// first, each with a snippet and its conversation's title and tags.
+#[tauri::command]
+async fn search_history(query: String, limit: Option<u32>, app: AppHandle) -> Result<Vec<HistoryMatch>, String> {
+    with_conversations(&app, |store| store.search_history(&query, limit))
+}
+
+// Carries out REST API requests the way the matching commands do
+struct AppApi(AppHandle);
+
+#[async_trait::async_trait]
+impl ApiHandler for AppApi {
+    async fn handle(&self, request: ApiRequest) -> Result<serde_json::Value, String> {
+        let app = &self.0;
+        let result = match request {
+            ApiRequest::Speak { text } => Ok(serde_json::json!({
+                "message": start_speaking(text, app.clone(), app.state::<AudioState>()).await?
+            })),
+            ApiRequest::Ask { text } => serde_json::to_value(queue_user_turn(app, text)?),
+            ApiRequest::StartListening => Ok(serde_json::json!({
+                "message": start_listening(app.clone(), app.state::<AudioState>()).await?
+            })),
+            ApiRequest::StopListening => Ok(serde_json::json!({
+                "message": stop_listening(app.clone(), app.state::<AudioState>()).await?
+            })),
+            ApiRequest::State => Ok(serde_json::json!({
+                "character": get_character_state(app.state::<CharacterStateStore>())?,
+                "audio": current_audio_status(&app.state::<AudioState>()).await,
+            })),
+            ApiRequest::Conversations { query, tag } => {
+                serde_json::to_value(list_conversations(query, tag, app.clone()).await?)
+            }
+        };
+        result.map_err(|e| format!("Failed to serialize response: {}", e))
+    }
+}
+
+/// Stop the REST API and, if rest_api.enabled is set, start it on the configured port,
+/// generating and saving a token first if there is none.
+async fn restart_rest_api(app: &AppHandle) -> Result<(), String> {
+    let running = app
+        .state::<RestApiState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock REST API: {}", e))?
+        .take();
+    if let Some(server) = running {
+        server.stop().await;
+    }
+
+    let Some(mut settings) = config::try_get_config().map(|c| c.rest_api.clone()) else {
+        return Ok(());
+    };
+    if !settings.enabled {
+        return Ok(());
+    }
+    if settings.token.is_empty() {
+        settings.token = api::generate_token().map_err(|e| format!("{:#}", e))?;
+        let (_, updated) = config::update_config(serde_json::json!({ "rest_api": { "token": settings.token } }))
+            .map_err(|e| format!("Failed to save REST API token: {:#}", e))?;
+        let _ = app.emit("config-changed", updated.clone());
+    }
+    let server = ApiServer::start(settings.port, settings.token, Arc::new(AppApi(app.clone())))
+        .map_err(|e| format!("Failed to start REST API: {:#}", e))?;
+    app.state::<RestApiState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock REST API: {}", e))?
+        .replace(server);
+    Ok(())
+}
+
+/// Turn the REST API on or off and choose its port. Returns the token requests must carry.
+#[tauri::command]
+async fn set_rest_api(enabled: bool, port: u16, app: AppHandle) -> Result<String, String> {
+    let (previous, updated) = config::update_config(serde_json::json!({ "rest_api": { "enabled": enabled, "port": port } }))
+        .map_err(|e| format!("Failed to save REST API settings: {:#}", e))?;
+    apply_config_change(&app, &previous, &updated).await;
+
+    let running = app
+        .state::<RestApiState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to lock REST API: {}", e))?
+        .is_some();
+    if enabled && !running {
+        return Err(format!("REST API could not listen on port {}", port));
+    }
+    Ok(config::try_get_config().map(|c| c.rest_api.token.clone()).unwrap_or_default())
+}
+
+/// Download a Whisper model, reporting progress as "model-download-progress" events.
+async fn download_whisper_model(app: &AppHandle, model: &str) -> Result<std::path::PathBuf, String> {
+    {
+        let download_state = app.state::<ModelDownloadState>();
+        let mut downloading = download_state.0.lock().map_err(|e| format!("Failed to lock model downloads: {}", e))?;
+        if !downloading.insert(model.to_string()) {
+            return Err(format!("Model {} is already downloading", model));
+        }
+    }
+
+    let progress_app = app.clone();
+    let on_progress = move |progress| {
+        let _ = progress_app.emit("model-download-progress", progress);
+    };
+    let result = unless_shutdown(app, models::download(model, on_progress))
+        .await
+        .and_then(|result| result.map_err(|e| format!("Failed to download model: {:#}", e)));
+
+    if let Ok(mut downloading) = app.state::<ModelDownloadState>().0.lock() {
+        downloading.remove(model);
+    }
+    let path = result?;
+    app.emit("model-downloaded", model.to_string())
+        .map_err(|e| format!("Failed to emit model downloaded event: {}", e))?;
+    Ok(path)
+}
+
+#[tauri::command]
+async fn list_available_models() -> Result<Vec<ModelInfo>, String> {
+    Ok(models::list_models())
+}
+
+#[tauri::command]
+async fn download_model(model: String, app: AppHandle) -> Result<String, String> {
+    let path = download_whisper_model(&app, &model).await?;
+    Ok(format!("Downloaded model {} to {}", model, path.display()))
+}
+
+#[tauri::command]
+async fn delete_model(model: String) -> Result<String, String> {
+    let in_use = config::try_get_config().is_some_and(|c| c.stt.model == model);
+    if in_use {
+        return Err(format!("Model {} is in use for speech recognition", model));
+    }
+    models::delete(&model).map_err(|e| format!("Failed to delete model: {:#}", e))?;
+    Ok(format!("Deleted model {}", model))
+}
+
+// Upper bound on the shutdown sequence before exiting regardless
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run `work` unless shutdown begins first, so in-flight LLM requests don't hold up exit.
+async fn unless_shutdown<T>(app: &AppHandle, work: impl Future<Output = T>) -> Result<T, String> {
+    let mut cancelled = app.state::<ShutdownState>().cancelled.subscribe();
+    tokio::select! {
+        output = work => Ok(output),
+        _ = cancelled.wait_for(|cancelled| *cancelled) => Err("Cancelled because the application is shutting down".to_string()),
+    }
+}
+
+/// Stop everything in order and then exit. Safe to call repeatedly; only the first call runs.
+fn request_shutdown(app: &AppHandle) {
+    if app.state::<ShutdownState>().started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, shutdown_sequence(&app)).await.is_err() {
+            eprintln!("Shutdown did not finish within {:?}, exiting anyway", SHUTDOWN_TIMEOUT);
+        }
+        app.state::<ShutdownState>().completed.store(true, Ordering::SeqCst);
+        app.exit(0);
+    });
+}
+
+async fn shutdown_sequence(app: &AppHandle) {
+    if let Err(e) = app.emit("app-shutting-down", ()) {
+        eprintln!("Failed to emit shutdown event: {}", e);
+    }
+
+    // Abort in-flight LLM work
+    app.state::<ShutdownState>().cancelled.send_replace(true);
+
+    // Stop producing new speech and keystrokes
+    if let Ok(mut queue) = app.state::<ChatState>().queue.lock() {
+        queue.clear();
+    }
+    if let Ok(mut session) = app.state::<ReadAloudState>().0.lock() {
+        session.stop();
+    }
+    if let Ok(mut session) = app.state::<DictationState>().0.lock() {
+        session.stop();
+    }
+
+    // Stop capture and playback, letting device streams close cleanly
+    if let Ok(handle) = app.state::<AudioState>().handle() {
+        if let Err(e) = handle.shutdown().await {
+            eprintln!("Failed to stop audio pipeline: {}", e);
+        }
+    }
+    let camera = app.state::<CameraState>().0.lock().ok().and_then(|mut camera| camera.take());
+    if let Some(camera) = camera {
+        let _ = tauri::async_runtime::spawn_blocking(move || camera.stop()).await;
+    }
+
+    // Keep counters recorded this session for the next upload
+    if let Ok(mut recorder) = app.state::<TelemetryState>().0.lock() {
+        if let Err(e) = recorder.save() {
+            eprintln!("Failed to save telemetry: {:#}", e);
+        }
+    }
+
+    // Finish a running meeting and hand the transcript to the frontend so it can be saved
+    let recorder = app
+        .state::<MeetingState>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|mut recorder| recorder.take());
+    if let Some(recorder) = recorder {
+        let duration_ms = recorder.elapsed_ms();
+        match tauri::async_runtime::spawn_blocking(move || recorder.stop()).await {
+            Ok(entries) => {
+                let transcript = MeetingTranscript {
+                    transcript: meeting::format_transcript(&entries),
+                    entries,
+                    summary: None,
+                    duration_ms,
+                };
+                if let Err(e) = app.emit("meeting-transcript-complete", transcript) {
+                    eprintln!("Failed to emit meeting transcript: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to stop meeting transcription: {}", e),
+        }
+    }
+}
+
+#[cfg(desktop)]
+fn register_global_shortcuts(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    // Register global shortcut for toggling sidepanel
+    let app_handle = app.handle().clone();
+    let shortcut = Shortcut::new(Some(Modifiers::CONTROL), Code::KeyO);
+    app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, event| {
+        // Only trigger on key press, not release
+        if event.state() == ShortcutState::Pressed {
+            let app_clone = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let sidepanel_state = app_clone.state::<SidepanelState>();
+                let app_clone2 = app_clone.clone();
+                if let Err(e) = show_sidepanel(app_clone2, sidepanel_state).await {
+                    eprintln!("Failed to show sidepanel: {}", e);
+                }
+            });
+        }
+    })?;
+    
+    // Register global shortcut for Ctrl+Q to quit the application
+    let app_handle_quit = app.handle().clone();
+    let quit_shortcut = Shortcut::new(Some(Modifiers::CONTROL), Code::KeyQ);
+    app.global_shortcut().on_shortcut(quit_shortcut, move |_app, _shortcut, event| {
+        if event.state() == ShortcutState::Pressed {
+            request_shutdown(&app_handle_quit);
+        }
+    })?;
+
+    // Register push-to-talk shortcut: key down starts capture, key up finalizes it
+    let ptt_config = config::try_get_config()
+        .map(|c| c.shortcuts.push_to_talk.clone())
+        .unwrap_or_default();
+    if ptt_config.enabled {
+        match ptt_config.shortcut.parse::<Shortcut>() {
+            Ok(ptt_shortcut) => {
+                let app_handle_ptt = app.handle().clone();
+                let hold = ptt_config.mode != "toggle";
+                app.global_shortcut().on_shortcut(ptt_shortcut, move |_app, _shortcut, event| {
+                    let app_clone = app_handle_ptt.clone();
+                    let shortcut_state = event.state();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = handle_push_to_talk(app_clone, shortcut_state, hold).await {
+                            eprintln!("Push-to-talk failed: {}", e);
+                        }
+                    });
+                })?;
+            }
+            Err(e) => eprintln!("Invalid push-to-talk shortcut '{}': {}", ptt_config.shortcut, e),
+        }
+    }
+
+    // Register dictation toggle shortcut
+    let dictation_shortcut = config::try_get_config()
+        .map(|c| c.shortcuts.dictation.clone())
+        .unwrap_or_default();
+    if !dictation_shortcut.is_empty() {
+        match dictation_shortcut.parse::<Shortcut>() {
+            Ok(shortcut) => {
+                let app_handle_dictation = app.handle().clone();
+                app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, event| {
+                    if event.state() == ShortcutState::Pressed {
+                        let app_clone = app_handle_dictation.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = toggle_dictation(app_clone).await {
+                                eprintln!("Failed to toggle dictation: {}", e);
+                            }
+                        });
+                    }
+                })?;
+            }
+            Err(e) => eprintln!("Invalid dictation shortcut '{}': {}", dictation_shortcut, e),
+        }
+    }
+
+    // Register read-aloud shortcut for the current selection
+    let read_aloud_shortcut = config::try_get_config()
+        .map(|c| c.shortcuts.read_aloud.clone())
+        .unwrap_or_default();
+    if !read_aloud_shortcut.is_empty() {
+        match read_aloud_shortcut.parse::<Shortcut>() {
+            Ok(shortcut) => {
+                let app_handle_read = app.handle().clone();
+                app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, event| {
+                    if event.state() == ShortcutState::Pressed {
+                        let app_clone = app_handle_read.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = read_aloud_selection(app_clone).await {
+                                eprintln!("Failed to read selection aloud: {}", e);
+                            }
+                        });
+                    }
+                })?;
+            }
+            Err(e) => eprintln!("Invalid read-aloud shortcut '{}': {}", read_aloud_shortcut, e),
+        }
+    }
+
+    // Register Esc key handler to prevent exiting fullscreen
+    let esc_shortcut = Shortcut::new(None, Code::Escape);
+    app.global_shortcut().on_shortcut(esc_shortcut, move |_app, _shortcut, event| {
+        if event.state() == ShortcutState::Pressed {
+            // Do nothing - prevent default Esc behavior
+        }
+    })?;
+
+    Ok(())
+}
+
+#[cfg(desktop)]
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(desktop)]
+async fn toggle_sidepanel(app: &AppHandle) -> Result<(), String> {
+    let visible = app
+        .get_webview_window("sidepanel")
+        .filter(|window| window.is_visible().unwrap_or(false));
+    match visible {
+        Some(window) => {
+            window.hide().map_err(|e| format!("Failed to hide sidepanel: {}", e))?;
+            *app.state::<SidepanelState>().0.lock().map_err(|e| format!("Failed to lock sidepanel state: {}", e))? = false;
+            Ok(())
+        }
+        None => show_sidepanel(app.clone(), app.state::<SidepanelState>()).await.map(|_| ()),
+    }
+}
+
+/// The tray icon and its menu, which keep the assistant reachable while the main window is hidden.
+#[cfg(desktop)]
+fn create_tray(app: &tauri::App) -> tauri::Result<()> {
+    use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+    use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+
+    let show = MenuItem::with_id(app, "show", i18n::tr("tray-show"), true, None::<&str>)?;
+    let sidepanel = MenuItem::with_id(app, "toggle_sidepanel", i18n::tr("tray-toggle-sidepanel"), true, None::<&str>)?;
+    let mute = CheckMenuItem::with_id(app, "mute", i18n::tr("tray-mute"), true, false, None::<&str>)?;
+    let pause = CheckMenuItem::with_id(app, "pause", i18n::tr("tray-pause"), true, false, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", i18n::tr("tray-quit"), true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &show,
+            &sidepanel,
+            &PredefinedMenuItem::separator(app)?,
+            &mute,
+            &pause,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut tray = TrayIconBuilder::with_id("main")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(move |app, event| {
+            let app = app.clone();
+            match event.id().as_ref() {
+                "show" => show_main_window(&app),
+                "toggle_sidepanel" => {
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = toggle_sidepanel(&app).await {
+                            eprintln!("{}", e);
+                        }
+                    });
+                }
+                "mute" => {
+                    // The item has already toggled itself
+                    let muted = mute.is_checked().unwrap_or(false);
+                    let mute = mute.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let result = if muted {
+                            stop_listening(app.clone(), app.state::<AudioState>()).await
+                        } else {
+                            start_listening(app.clone(), app.state::<AudioState>()).await
+                        };
+                        if let Err(e) = result {
+                            eprintln!("{}", e);
+                            let _ = mute.set_checked(!muted);
+                        }
+                    });
+                }
+                "pause" => {
+                    let paused = pause.is_checked().unwrap_or(false);
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = set_assistant_paused(paused, app).await {
+                            eprintln!("{}", e);
+                        }
+                    });
+                }
+                "quit" => request_shutdown(&app),
+                _ => {}
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    if let Some(app_config) = config::try_get_config() {
+        tray = tray.tooltip(&app_config.app.name);
+    }
+    tray.build(app)?;
+    Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -195,75 +4170,256 @@ pub fn run() {
         eprintln!("Failed to initialize config: {}", e);
     }
     
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
-        .manage(AudioState::new(false))
+    // --headless skips the webview entirely and runs the voice loop in the terminal
+    if headless::requested() {
+        env_logger::init();
+        if let Err(e) = headless::run() {
+            eprintln!("Headless mode failed: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
+    let builder = tauri::Builder::default().plugin(tauri_plugin_opener::init());
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_global_shortcut::Builder::new().build());
+
+    builder
+        .manage(AudioState::default())
+        .manage(AudioInitState::default())
+        .manage(ModelLoadState::default())
         .manage(SidepanelState::new(false))
+        .manage(PushToTalkState::default())
+        .manage(VoiceControlState::default())
+        .manage(TranslationState::default())
+        .manage(DictationState::default())
+        .manage(ReadAloudState::default())
+        .manage(MeetingState::default())
+        .manage(ShutdownState::default())
+        .manage(UpdateState::default())
+        .manage(SessionTimingState::default())
+        .manage(CharacterStateStore::default())
+        .manage(AvatarServerState::default())
+        .manage(VmcState::default())
+        .manage(RestApiState::default())
+        .manage(TopicTaggingState::default())
+        .manage(UserProfileState::default())
+        .manage(AssistantPausedState::default())
+        .manage(QuietHoursState::default())
+        .manage(ModelDownloadState::default())
+        .manage(ToolConfirmationState::default())
+        .manage(GenerationState::default())
+        .manage(VisionFrameState::default())
+        .manage(CameraState::default())
+        .manage(ExpressionState::default())
+        .manage(ObjectsState::default())
         .invoke_handler(tauri::generate_handler![
             greet,
             initialize_audio_system,
+            get_model_load_progress,
+            get_audio_status,
             start_listening,
             stop_listening,
             start_speaking,
             stop_speaking,
             synthesize_speech,
+            list_audio_input_devices,
+            list_audio_output_devices,
+            set_audio_device,
+            set_noise_suppression,
+            set_stt_language,
+            set_stt_task,
+            add_stt_vocabulary,
+            remove_stt_vocabulary,
+            transcribe_file,
+            test_audio_devices,
             show_sidepanel,
             change_character_emotion,
+            get_character_state,
+            set_avatar_server,
+            set_vmc_output,
+            set_rest_api,
+            answer_tool_confirmation,
+            abort_generation,
+            set_file_directories,
+            list_characters,
+            create_character,
+            switch_character,
+            import_character,
+            export_character,
             update_viewport_settings,
-            open_devtools
+            open_devtools,
+            handle_voice_command,
+            start_translation_mode,
+            stop_translation_mode,
+            translate_utterance,
+            start_dictation,
+            stop_dictation,
+            toggle_dictation,
+            dictate_transcript,
+            read_aloud_text,
+            read_aloud_selection,
+            pause_read_aloud,
+            resume_read_aloud,
+            stop_read_aloud,
+            set_read_aloud_speed,
+            start_meeting_transcription,
+            stop_meeting_transcription,
+            get_config,
+            update_config,
+            get_accessibility_settings,
+            record_conversation_turn,
+            bookmark_moment,
+            annotate_turn,
+            list_bookmarks,
+            delete_bookmark,
+            export_bookmarks,
+            export_conversation,
+            check_for_updates,
+            install_update,
+            get_telemetry_preview,
+            submit_user_turn,
+            set_vision_frame,
+            ask_about_image,
+            capture_snapshot,
+            get_camera_state,
+            enable_camera,
+            disable_camera,
+            list_displays,
+            describe_screen,
+            ocr_image,
+            list_llm_models,
+            record_user_emotion,
+            get_session_analytics,
+            get_usage_stats,
+            list_conversations,
+            search_history,
+            purge_history,
+            recall_memories,
+            remember_fact,
+            list_user_facts,
+            add_user_fact,
+            update_user_fact,
+            delete_user_fact,
+            tag_conversation,
+            get_onboarding_state,
+            onboarding_test_microphone,
+            onboarding_download_model,
+            onboarding_set_api_key,
+            list_tts_voices,
+            list_tts_providers,
+            set_tts_provider,
+            set_tts_voice,
+            list_viseme_mappings,
+            set_viseme_mapping,
+            export_visemes,
+            add_pronunciation,
+            remove_pronunciation,
+            preview_voice,
+            onboarding_select_voice,
+            onboarding_set_wake_word,
+            skip_onboarding_step,
+            finish_onboarding,
+            restart_onboarding,
+            get_quiet_hours_status,
+            set_do_not_disturb,
+            set_assistant_paused,
+            refresh_quiet_hours_calendar,
+            list_available_models,
+            download_model,
+            delete_model
         ])
         .setup(|app| {
-            // Register global shortcut for toggling sidepanel
-            let app_handle = app.handle().clone();
-            let shortcut = Shortcut::new(Some(Modifiers::CONTROL), Code::KeyO);
-            app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, event| {
-                // Only trigger on key press, not release
-                if event.state() == ShortcutState::Pressed {
-                    let app_clone = app_handle.clone();
-                    tauri::async_runtime::spawn(async move {
-                        let sidepanel_state = app_clone.state::<SidepanelState>();
-                        let app_clone2 = app_clone.clone();
-                        if let Err(e) = show_sidepanel(app_clone2, sidepanel_state).await {
-                            eprintln!("Failed to show sidepanel: {}", e);
-                        }
-                    });
+            app.manage(ConversationState(Mutex::new(open_conversation_store(app))));
+            spawn_usage_recording(app.handle());
+            spawn_fallback_events(app.handle());
+            app.manage(TelemetryState(Mutex::new(open_telemetry(app))));
+            spawn_telemetry_uploads(app.handle());
+            spawn_idle_animations(app.handle());
+            spawn_idle_conversation_jobs(app.handle());
+            app.manage(open_chat(app));
+            app.manage(OnboardingState(Mutex::new(open_onboarding(app))));
+            match app.path().app_data_dir() {
+                Ok(dir) => {
+                    models::init_models_dir(dir.join("models"));
+                    character::profiles::init_profiles_dir(dir.join("characters"));
                 }
-            })?;
+                Err(e) => eprintln!("Failed to resolve app data directory, using ./models for Whisper models: {}", e),
+            }
+            match app.path().app_cache_dir() {
+                Ok(dir) => audio::tts_cache::init_cache_dir(dir.join("tts")),
+                Err(e) => eprintln!("Failed to resolve app cache directory, synthesized speech won't be cached: {}", e),
+            }
+            
+            // Global shortcuts only exist on desktop platforms
+            #[cfg(desktop)]
+            register_global_shortcuts(app)?;
+            #[cfg(desktop)]
+            if let Err(e) = create_tray(app) {
+                eprintln!("Failed to create tray icon: {}", e);
+            }
             
-            // Register global shortcut for Ctrl+Q to quit the application
-            let app_handle_quit = app.handle().clone();
-            let quit_shortcut = Shortcut::new(Some(Modifiers::CONTROL), Code::KeyQ);
-            app.global_shortcut().on_shortcut(quit_shortcut, move |_app, _shortcut, event| {
-                if event.state() == ShortcutState::Pressed {
-                    app_handle_quit.exit(0);
+            #[cfg(desktop)]
+            spawn_update_checks(app.handle());
+            #[cfg(desktop)]
+            spawn_focus_monitor(app.handle());
+            #[cfg(desktop)]
+            spawn_config_watcher(app.handle());
+            spawn_quiet_hours_monitor(app.handle());
+            tauri::async_runtime::spawn(warm_up_models(app.handle().clone()));
+            if let Err(e) = restart_vmc_output(app.handle()) {
+                eprintln!("{}", e);
+            }
+            let camera_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = restart_camera(&camera_app).await {
+                    eprintln!("{}", e);
                 }
-            })?;
-
-            // Register Esc key handler to prevent exiting fullscreen
-            let esc_shortcut = Shortcut::new(None, Code::Escape);
-            app.global_shortcut().on_shortcut(esc_shortcut, move |_app, _shortcut, event| {
-                if event.state() == ShortcutState::Pressed {
-                    // Do nothing - prevent default Esc behavior
+            });
+            let server_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = restart_avatar_server(&server_app).await {
+                    eprintln!("{}", e);
+                }
+                if let Err(e) = restart_rest_api(&server_app).await {
+                    eprintln!("{}", e);
                 }
-            })?;
+            });
             
             // Handle main window events
             if let Some(main_window) = app.get_webview_window("main") {
                 let app_handle_close = app.handle().clone();
                 main_window.on_window_event(move |event| {
-                    match event {
-                        tauri::WindowEvent::CloseRequested { .. } => {
-                            // Close all windows and quit the application
-                            app_handle_close.exit(0);
+                    if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                        api.prevent_close();
+                        #[cfg(desktop)]
+                        {
+                            let to_tray = config::try_get_config().is_some_and(|c| c.app.window.close_to_tray);
+                            // Without a tray icon a hidden window could never be brought back
+                            if to_tray && app_handle_close.tray_by_id("main").is_some() {
+                                if let Some(window) = app_handle_close.get_webview_window("main") {
+                                    let _ = window.hide();
+                                }
+                                return;
+                            }
                         }
-                        _ => {}
+                        // Keep the window until everything has shut down, then quit the application
+                        request_shutdown(&app_handle_close);
                     }
                 });
             }
             
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Route OS-initiated exits (Cmd+Q, logout) through the same shutdown sequence
+            if let tauri::RunEvent::ExitRequested { api, .. } = event {
+                if !app.state::<ShutdownState>().completed.load(Ordering::SeqCst) {
+                    api.prevent_exit();
+                    request_shutdown(app);
+                }
+            }
+        });
 }
@@ -0,0 +1,140 @@
+use super::usage::count_message_tokens;
+use super::ChatMessage;
+use crate::config::LlmConfig;
+use anyhow::Result;
+
+// Marks the system message that stands in for turns summarized out of the history
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:\n";
+// Tokens seldom cover fewer characters than this, so text cut to this many per token fits
+const MIN_CHARS_PER_TOKEN: usize = 3;
+// The summary is asked to stay under this, so it can't crowd out the turns it makes room for
+const SUMMARY_MAX_TOKENS: usize = 400;
+
+/// Turns summarized out of the history: the caller swaps them for `summary` so they aren't
+/// summarized again on the next turn.
+pub struct Compaction {
+    // The history messages replaced, starting right after the system prompt; an earlier summary
+    // comes first when there was one
+    pub replaced: Vec<ChatMessage>,
+    pub summary: ChatMessage,
+}
+
+pub struct Context {
+    pub messages: Vec<ChatMessage>,
+    // Older turns left out, whether summarized or not
+    pub dropped: usize,
+    pub compaction: Option<Compaction>,
+}
+
+/// Assemble a request from `history` (system prompt first, the newest user turn last) and any
+/// `background`, such as the user's profile and recalled memories, within `llm.context_window`
+/// less the `llm.max_tokens` kept for the reply. The system prompt, background and newest turn
+/// always go in; older turns go in newest first until the budget runs out, and the rest are
+/// summarized with `llm.summarize_context`, or dropped.
+pub async fn build(config: &LlmConfig, history: &[ChatMessage], background: Vec<ChatMessage>) -> Context {
+    let budget = budget(config);
+    let pinned_count = history.iter().take_while(|m| m.role == "system" && !is_summary(m)).count();
+    let (pinned, rest) = history.split_at(pinned_count);
+    let (previous_summary, turns) = match rest.split_first() {
+        Some((first, turns)) if is_summary(first) => (Some(first), turns),
+        _ => (None, rest),
+    };
+
+    let mut fixed: Vec<ChatMessage> = pinned.to_vec();
+    fixed.extend(background);
+    let fixed_tokens = count_message_tokens(config, &fixed);
+    let summary_tokens = previous_summary.map_or(0, |summary| count_message_tokens(config, std::slice::from_ref(summary)));
+    let keep = turns_that_fit(config, turns, budget.saturating_sub(fixed_tokens + summary_tokens));
+    let dropped = turns.len() - keep;
+    if dropped == 0 {
+        return Context {
+            messages: assemble(fixed, previous_summary.cloned(), turns),
+            dropped,
+            compaction: None,
+        };
+    }
+
+    let (older, _) = turns.split_at(dropped);
+    if config.summarize_context {
+        match summarize(config, previous_summary, older).await {
+            Ok(summary) => {
+                // Make room for the summary if it's longer than the one it replaces
+                let summary_tokens = count_message_tokens(config, std::slice::from_ref(&summary));
+                let keep = turns_that_fit(config, &turns[dropped..], budget.saturating_sub(fixed_tokens + summary_tokens));
+                let dropped_after = turns.len() - keep;
+                let replaced = rest[..rest.len() - turns.len() + dropped].to_vec();
+                return Context {
+                    messages: assemble(fixed, Some(summary.clone()), &turns[dropped_after..]),
+                    dropped: dropped_after,
+                    compaction: Some(Compaction { replaced, summary }),
+                };
+            }
+            // Dropping turns still gets an answer
+            Err(e) => log::warn!("Failed to summarize earlier turns, dropping them instead: {:#}", e),
+        }
+    }
+    Context {
+        messages: assemble(fixed, previous_summary.cloned(), &turns[dropped..]),
+        dropped,
+        compaction: None,
+    }
+}
+
+fn budget(config: &LlmConfig) -> usize {
+    let window = config.context_window as usize;
+    // A reply reservation that eats the whole window would leave no room; split it instead
+    let reserved = (config.max_tokens as usize).min(window / 2);
+    window - reserved
+}
+
+/// Whether `message` is the summary `build` put in place of older turns.
+pub fn is_summary(message: &ChatMessage) -> bool {
+    message.role == "system" && message.content.starts_with(SUMMARY_PREFIX)
+}
+
+// How many of the newest `turns` fit in `budget`; the newest always does, being what's answered
+fn turns_that_fit(config: &LlmConfig, turns: &[ChatMessage], budget: usize) -> usize {
+    let mut used = 0;
+    let mut keep = 0;
+    for turn in turns.iter().rev() {
+        used += count_message_tokens(config, std::slice::from_ref(turn));
+        if used > budget && keep > 0 {
+            break;
+        }
+        keep += 1;
+    }
+    keep
+}
+
+fn assemble(fixed: Vec<ChatMessage>, summary: Option<ChatMessage>, turns: &[ChatMessage]) -> Vec<ChatMessage> {
+    let mut messages = fixed;
+    messages.extend(summary);
+    messages.extend_from_slice(turns);
+    messages
+}
+
+async fn summarize(config: &LlmConfig, previous: Option<&ChatMessage>, turns: &[ChatMessage]) -> Result<ChatMessage> {
+    let mut transcript = String::new();
+    if let Some(previous) = previous {
+        transcript.push_str(previous.content.trim_start_matches(SUMMARY_PREFIX));
+        transcript.push_str("\n\n");
+    }
+    for turn in turns.iter().filter(|turn| turn.role == "user" || turn.role == "assistant") {
+        transcript.push_str(&format!("{}: {}\n", turn.role, turn.content));
+    }
+    // The request has to fit the window too; its newest part matters most
+    let max_chars = budget(config).saturating_sub(SUMMARY_MAX_TOKENS) * MIN_CHARS_PER_TOKEN;
+    let skip = transcript.chars().count().saturating_sub(max_chars);
+    let transcript: String = transcript.chars().skip(skip).collect();
+
+    let messages = [
+        ChatMessage::system(format!(
+            "Summarize this conversation in under {} words so it can be continued without it. Keep \
+             names, facts about the user, decisions and open questions; leave out small talk.",
+            SUMMARY_MAX_TOKENS * 3 / 4
+        )),
+        ChatMessage::user(transcript),
+    ];
+    let summary = super::chat_completion(config, &messages, Some(0.2)).await?;
+    Ok(ChatMessage::system(format!("{}{}", SUMMARY_PREFIX, summary.trim())))
+}
@@ -0,0 +1,212 @@
+use super::tools::ToolDefinition;
+use super::{backend_for, Aborted, ChatMessage, LlmProvider};
+use crate::config::LlmConfig;
+use anyhow::Result;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// Backoff between retries never grows past this
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+// Where fallbacks are reported; unset until the app is ready to show them
+static SINK: Lazy<Mutex<Option<mpsc::UnboundedSender<FallbackEvent>>>> = Lazy::new(|| Mutex::new(None));
+
+/// A request that gave up on one backend and moved on to the next.
+#[derive(Debug, Clone, Serialize)]
+pub struct FallbackEvent {
+    // "provider/model"
+    pub from: String,
+    pub to: String,
+    pub error: String,
+}
+
+/// Send every fallback to `sink`.
+pub fn set_sink(sink: mpsc::UnboundedSender<FallbackEvent>) {
+    *SINK.lock().unwrap() = Some(sink);
+}
+
+/// The configured backend followed by `llm.fallback.providers`: each request is retried on a
+/// backend with exponential backoff, then handed to the next one. Requests that were aborted, or
+/// streams that already sent part of a reply, aren't retried.
+pub struct Fallback {
+    // Name and backend, in the order they're tried
+    chain: Vec<(String, Arc<dyn LlmProvider>)>,
+    retries: u32,
+    backoff: Duration,
+    timeout: Duration,
+}
+
+impl Fallback {
+    pub fn new(config: &LlmConfig) -> Self {
+        let fallback = &config.fallback;
+        let mut chain = vec![(backend_name(config), backend_for(config))];
+        for entry in &fallback.providers {
+            let backend = LlmConfig {
+                provider: entry.provider.clone(),
+                model: entry.model.clone(),
+                base_url: entry.base_url.clone(),
+                api_key: entry.api_key.clone(),
+                ..config.clone()
+            };
+            chain.push((backend_name(&backend), backend_for(&backend)));
+        }
+        Self {
+            chain,
+            retries: fallback.max_retries,
+            backoff: Duration::from_millis(fallback.initial_backoff_ms),
+            timeout: Duration::from_secs(fallback.timeout_seconds.max(1) as u64),
+        }
+    }
+
+    /// Run `request` down the chain until a backend answers. `request` is given the backend to
+    /// use and the time it has, and says whether a failure may still be retried.
+    async fn run<T, F, Fut>(&self, request: F) -> Result<T>
+    where
+        F: Fn(Arc<dyn LlmProvider>, Duration) -> Fut,
+        Fut: Future<Output = Attempt<T>>,
+    {
+        let mut last_error = None;
+        for (index, (name, backend)) in self.chain.iter().enumerate() {
+            if let Some(error) = &last_error {
+                report(&self.chain[index - 1].0, name, error);
+            }
+            let mut backoff = self.backoff;
+            for attempt in 0..=self.retries {
+                if attempt > 0 {
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                match request(backend.clone(), self.timeout).await {
+                    Attempt::Done(result) => return Ok(result),
+                    Attempt::Failed(e, retryable) => {
+                        if e.downcast_ref::<Aborted>().is_some() || !retryable {
+                            return Err(e);
+                        }
+                        log::warn!("LLM request to {} failed (attempt {}): {:#}", name, attempt + 1, e);
+                        let permanent = is_permanent(&e);
+                        last_error = Some(e);
+                        // The same request would be refused again; the next backend may take it
+                        if permanent {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No LLM backend is configured")))
+    }
+
+    fn primary(&self) -> &Arc<dyn LlmProvider> {
+        &self.chain[0].1
+    }
+}
+
+enum Attempt<T> {
+    Done(T),
+    // The error, and whether trying again could still give a whole reply
+    Failed(anyhow::Error, bool),
+}
+
+impl<T> From<Result<T>> for Attempt<T> {
+    fn from(result: Result<T>) -> Self {
+        match result {
+            Ok(value) => Attempt::Done(value),
+            Err(e) => Attempt::Failed(e, true),
+        }
+    }
+}
+
+/// `request`, failing once `timeout` has passed.
+async fn within<T>(timeout: Duration, request: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(timeout, request)
+        .await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("LLM request didn't finish within {} seconds", timeout.as_secs())))
+}
+
+fn backend_name(config: &LlmConfig) -> String {
+    format!("{}/{}", config.provider, config.model)
+}
+
+// Errors no retry will fix: bad keys, unknown models, malformed requests
+fn is_permanent(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .filter_map(reqwest::Error::status)
+        .any(|status| status.is_client_error() && status.as_u16() != 408 && status.as_u16() != 429)
+}
+
+fn report(from: &str, to: &str, error: &anyhow::Error) {
+    log::warn!("Falling back from {} to {}: {:#}", from, to, error);
+    if let Some(sink) = SINK.lock().unwrap().as_ref() {
+        let _ = sink.send(FallbackEvent {
+            from: from.to_string(),
+            to: to.to_string(),
+            error: format!("{:#}", error),
+        });
+    }
+}
+
+#[async_trait]
+impl LlmProvider for Fallback {
+    async fn chat(&self, messages: &[ChatMessage], temperature: Option<f32>) -> Result<String> {
+        self.run(|backend, timeout| async move { within(timeout, backend.chat(messages, temperature)).await.into() })
+            .await
+    }
+
+    async fn chat_with_tools(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>) -> Result<ChatMessage> {
+        self.run(|backend, timeout| async move { within(timeout, backend.chat_with_tools(messages, tools, temperature)).await.into() })
+            .await
+    }
+
+    async fn stream(&self, messages: &[ChatMessage], temperature: Option<f32>, deltas: mpsc::UnboundedSender<String>) -> Result<String> {
+        self.run(|backend, timeout| {
+            let deltas = deltas.clone();
+            async move {
+                // Pieces pass straight through; once one has, starting over would repeat it
+                let streamed = AtomicBool::new(false);
+                let (attempt_deltas, mut attempt_rx) = mpsc::unbounded_channel();
+                let forward = async {
+                    while let Some(delta) = attempt_rx.recv().await {
+                        streamed.store(true, Ordering::SeqCst);
+                        let _ = deltas.send(delta);
+                    }
+                };
+                let attempt = async {
+                    let (result, _) = tokio::join!(backend.stream(messages, temperature, attempt_deltas), forward);
+                    result
+                };
+                match within(timeout, attempt).await {
+                    Ok(reply) => Attempt::Done(reply),
+                    Err(e) => Attempt::Failed(e, !streamed.load(Ordering::SeqCst)),
+                }
+            }
+        })
+        .await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        self.primary().list_models().await
+    }
+
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        // Embeddings from another backend's model wouldn't match the ones already stored
+        self.primary().embed(model, texts).await
+    }
+
+    async fn preload(&self) -> Result<()> {
+        self.primary().preload().await
+    }
+
+    fn abort(&self) {
+        for (_, backend) in &self.chain {
+            backend.abort();
+        }
+    }
+}
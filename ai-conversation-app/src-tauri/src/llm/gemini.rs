@@ -0,0 +1,363 @@
+use super::tools::{ToolCall, ToolDefinition};
+use super::usage::{self, TokenUsage};
+use super::{rate_limit, with_system_prompt, AbortSignal, ChatMessage, LlmProvider};
+use crate::config::LlmConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+    #[serde(default)]
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Option<Content>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    function_call: Option<FunctionCall>,
+}
+
+#[derive(Deserialize)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    #[serde(default)]
+    block_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelEntry {
+    // "models/gemini-1.5-flash"
+    name: String,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    values: Vec<f32>,
+}
+
+impl GenerateResponse {
+    fn usage(&self) -> Option<TokenUsage> {
+        self.usage_metadata.as_ref().map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_token_count,
+            completion_tokens: usage.candidates_token_count,
+        })
+    }
+
+    /// The first candidate's parts, or why there is none.
+    fn into_parts(self) -> Result<Vec<Part>> {
+        if let Some(reason) = self.prompt_feedback.and_then(|feedback| feedback.block_reason) {
+            return Err(anyhow::anyhow!("Gemini blocked the request ({})", reason));
+        }
+        let Some(candidate) = self.candidates.into_iter().next() else {
+            return Ok(Vec::new());
+        };
+        if let Some(reason) = candidate.finish_reason.as_deref().filter(|reason| matches!(*reason, "SAFETY" | "RECITATION" | "BLOCKLIST")) {
+            return Err(anyhow::anyhow!("Gemini stopped the reply ({})", reason));
+        }
+        Ok(candidate.content.map(|content| content.parts).unwrap_or_default())
+    }
+}
+
+/// Google's Gemini models, through the Generative Language API. Images attached to messages are
+/// sent inline.
+pub struct Gemini {
+    config: LlmConfig,
+    client: reqwest::Client,
+    abort: AbortSignal,
+}
+
+impl Gemini {
+    pub fn new(config: LlmConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            abort: AbortSignal::default(),
+        }
+    }
+
+    fn model_path(&self, model: &str) -> String {
+        if model.starts_with("models/") {
+            model.to_string()
+        } else {
+            format!("models/{}", model)
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> Result<reqwest::RequestBuilder> {
+        let api_key = self
+            .config
+            .resolved_api_key()
+            .context("Gemini needs an API key in llm.api_key, the keyring or GEMINI_API_KEY")?;
+        Ok(self
+            .client
+            .request(method, format!("{}/{}", self.config.resolved_base_url(), path))
+            .header("x-goog-api-key", api_key))
+    }
+
+    async fn send(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>, stream: bool) -> Result<reqwest::Response> {
+        let messages = with_system_prompt(&self.config, messages);
+        let system: Vec<&str> = messages.iter().filter(|m| m.role == "system").map(|m| m.content.as_str()).collect();
+        let mut body = serde_json::json!({
+            "contents": wire_contents(&messages),
+            "generationConfig": {
+                "temperature": temperature.unwrap_or(self.config.temperature),
+                "topP": self.config.top_p,
+                "maxOutputTokens": self.config.max_tokens,
+            },
+        });
+        // Gemini takes one system instruction; memories and summaries join the prompt there
+        if !system.is_empty() {
+            body["systemInstruction"] = serde_json::json!({ "parts": [{ "text": system.join("\n\n") }] });
+        }
+        if !tools.is_empty() {
+            let declarations: Vec<serde_json::Value> = tools
+                .iter()
+                .map(|tool| serde_json::json!({ "name": tool.name, "description": tool.description, "parameters": tool.parameters }))
+                .collect();
+            body["tools"] = serde_json::json!([{ "functionDeclarations": declarations }]);
+        }
+
+        let path = if stream {
+            format!("{}:streamGenerateContent?alt=sse", self.model_path(&self.config.model))
+        } else {
+            format!("{}:generateContent", self.model_path(&self.config.model))
+        };
+        self.request(reqwest::Method::POST, &path)?
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to reach Gemini")?
+            .error_for_status()
+            .context("Gemini request failed")
+    }
+
+    async fn complete(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>) -> Result<ChatMessage> {
+        let response: GenerateResponse = self
+            .send(messages, tools, temperature, false)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse Gemini response")?;
+        let reported = response.usage();
+        let mut reply = ChatMessage::assistant(String::new());
+        for (index, part) in response.into_parts()?.into_iter().enumerate() {
+            if let Some(text) = part.text {
+                reply.content.push_str(&text);
+            }
+            if let Some(call) = part.function_call {
+                // Gemini has no call ids; results are matched to calls by name
+                reply.tool_calls.push(ToolCall {
+                    id: format!("call_{}", index),
+                    name: call.name,
+                    arguments: call.args,
+                });
+            }
+        }
+        reply.content = reply.content.trim().to_string();
+        usage::record(&self.config, messages, &reply.content, reported);
+        Ok(reply)
+    }
+}
+
+/// `messages` as Gemini's contents: "assistant" becomes "model", tool results go back as the
+/// user's function responses, and neighbouring messages from the same side are merged, as
+/// Gemini expects the two sides to take turns. System messages travel separately.
+fn wire_contents(messages: &[ChatMessage]) -> Vec<serde_json::Value> {
+    // Tool results carry the call's id, but Gemini wants the function's name
+    let call_names: HashMap<&str, &str> = messages
+        .iter()
+        .flat_map(|message| message.tool_calls.iter())
+        .map(|call| (call.id.as_str(), call.name.as_str()))
+        .collect();
+
+    let mut contents: Vec<(&str, Vec<serde_json::Value>)> = Vec::new();
+    for message in messages.iter().filter(|m| m.role != "system") {
+        let role = if message.role == "assistant" { "model" } else { "user" };
+        let mut parts = Vec::new();
+        if message.role == "tool" {
+            let name = message.tool_call_id.as_deref().and_then(|id| call_names.get(id)).copied().unwrap_or_default();
+            parts.push(serde_json::json!({
+                "functionResponse": { "name": name, "response": { "content": message.content } },
+            }));
+        } else if !message.content.is_empty() {
+            parts.push(serde_json::json!({ "text": message.content }));
+        }
+        for image in &message.images {
+            parts.push(serde_json::json!({ "inline_data": { "mime_type": image.mime_type, "data": image.data } }));
+        }
+        for call in &message.tool_calls {
+            parts.push(serde_json::json!({ "functionCall": { "name": call.name, "args": call.arguments } }));
+        }
+        if parts.is_empty() {
+            continue;
+        }
+        match contents.last_mut() {
+            Some((last_role, last_parts)) if *last_role == role => last_parts.extend(parts),
+            _ => contents.push((role, parts)),
+        }
+    }
+    contents
+        .into_iter()
+        .map(|(role, parts)| serde_json::json!({ "role": role, "parts": parts }))
+        .collect()
+}
+
+#[async_trait]
+impl LlmProvider for Gemini {
+    async fn chat(&self, messages: &[ChatMessage], temperature: Option<f32>) -> Result<String> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                Ok(self.complete(messages, &[], temperature).await?.content)
+            })
+            .await
+    }
+
+    async fn chat_with_tools(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>) -> Result<ChatMessage> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                self.complete(messages, tools, temperature).await
+            })
+            .await
+    }
+
+    async fn stream(&self, messages: &[ChatMessage], temperature: Option<f32>, deltas: mpsc::UnboundedSender<String>) -> Result<String> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                let mut response = self.send(messages, &[], temperature, true).await?;
+                let mut reply = String::new();
+                let mut reported = None;
+                // Server-sent events, each a whole GenerateContentResponse, split across chunks arbitrarily
+                let mut pending = Vec::new();
+                while let Some(chunk) = response.chunk().await.context("Gemini stream was interrupted")? {
+                    pending.extend_from_slice(&chunk);
+                    while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                        let line: Vec<u8> = pending.drain(..=end).collect();
+                        let line = String::from_utf8_lossy(&line);
+                        let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                            continue;
+                        };
+                        let event: GenerateResponse = serde_json::from_str(data).context("Failed to parse Gemini stream chunk")?;
+                        // Each event carries the usage so far; the last one has the totals
+                        if let Some(usage) = event.usage() {
+                            reported = Some(usage);
+                        }
+                        for delta in event.into_parts()?.into_iter().filter_map(|part| part.text) {
+                            if !delta.is_empty() {
+                                reply.push_str(&delta);
+                                let _ = deltas.send(delta);
+                            }
+                        }
+                    }
+                }
+                usage::record(&self.config, messages, &reply, reported);
+                Ok(reply.trim().to_string())
+            })
+            .await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let models: ModelList = self
+            .request(reqwest::Method::GET, "models?pageSize=1000")?
+            .send()
+            .await
+            .context("Failed to reach Gemini")?
+            .error_for_status()
+            .context("Listing Gemini models failed")?
+            .json()
+            .await
+            .context("Failed to parse Gemini model list")?;
+        Ok(models
+            .models
+            .into_iter()
+            .filter(|model| model.supported_generation_methods.iter().any(|method| method == "generateContent"))
+            .map(|model| model.name.trim_start_matches("models/").to_string())
+            .collect())
+    }
+
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+        let model = self.model_path(model);
+        let requests: Vec<serde_json::Value> = texts
+            .iter()
+            .map(|text| serde_json::json!({ "model": model, "content": { "parts": [{ "text": text }] } }))
+            .collect();
+        let response: EmbedResponse = self
+            .request(reqwest::Method::POST, &format!("{}:batchEmbedContents", model))?
+            .json(&serde_json::json!({ "requests": requests }))
+            .send()
+            .await
+            .context("Failed to reach Gemini")?
+            .error_for_status()
+            .context("Gemini embedding request failed")?
+            .json()
+            .await
+            .context("Failed to parse Gemini embeddings")?;
+        if response.embeddings.len() != texts.len() {
+            return Err(anyhow::anyhow!("Expected {} embeddings, got {}", texts.len(), response.embeddings.len()));
+        }
+        Ok(response.embeddings.into_iter().map(|embedding| embedding.values).collect())
+    }
+
+    fn abort(&self) {
+        self.abort.abort();
+    }
+}
@@ -0,0 +1,305 @@
+use super::tools::ToolDefinition;
+use super::usage::{self, TokenUsage};
+use super::{AbortSignal, ChatMessage, LlmProvider};
+use crate::config::LlmConfig;
+use crate::models;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::params::LlamaModelParams;
+use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+use llama_cpp_2::sampling::LlamaSampler;
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+// llama.cpp's global state; it may only be initialized once per process
+static BACKEND: OnceCell<LlamaBackend> = OnceCell::new();
+
+fn backend() -> Result<&'static LlamaBackend> {
+    BACKEND
+        .get_or_try_init(|| {
+            let mut backend = LlamaBackend::init()?;
+            // Its per-token logging would drown everything else out
+            backend.void_logs();
+            Ok::<_, anyhow::Error>(backend)
+        })
+        .context("Failed to initialize llama.cpp")
+}
+
+/// Runs GGUF models in-process with llama.cpp, so replies need no server at all. `llm.model` is
+/// a model name from the models directory or a path to a `.gguf` file. Tools aren't offered to
+/// these models; `chat_with_tools` answers directly.
+pub struct LlamaCpp {
+    config: LlmConfig,
+    abort: AbortSignal,
+    // Loaded models by file, the chat model and any embedding model; held while one loads so
+    // two requests don't load the same file twice
+    models: tokio::sync::Mutex<HashMap<PathBuf, Arc<LlamaModel>>>,
+    // One generation at a time; each takes its own context and KV cache
+    busy: Arc<Mutex<()>>,
+}
+
+// What a generation needs besides the model, owned so it can move to a blocking thread
+struct Generation {
+    // Role and content, in the model's chat template roles
+    messages: Vec<(String, String)>,
+    context_window: u32,
+    threads: i32,
+    max_tokens: u32,
+    temperature: f32,
+    top_p: f32,
+}
+
+// Stops the generation thread once the request waiting on it is dropped, e.g. when aborted
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl LlamaCpp {
+    pub fn new(config: LlmConfig) -> Self {
+        Self {
+            config,
+            abort: AbortSignal::default(),
+            models: tokio::sync::Mutex::new(HashMap::new()),
+            busy: Arc::new(Mutex::new(())),
+        }
+    }
+
+    fn threads(&self) -> i32 {
+        match self.config.llama_cpp.threads {
+            0 => std::thread::available_parallelism().map_or(4, |threads| threads.get() as i32),
+            threads => threads as i32,
+        }
+    }
+
+    async fn model(&self, name: &str) -> Result<Arc<LlamaModel>> {
+        let path = models::resolve_gguf(name)?;
+        let mut loaded = self.models.lock().await;
+        if let Some(model) = loaded.get(&path) {
+            return Ok(model.clone());
+        }
+        let gpu_layers = self.config.llama_cpp.gpu_layers;
+        let load_path = path.clone();
+        let model = tokio::task::spawn_blocking(move || {
+            let params = LlamaModelParams::default().with_n_gpu_layers(gpu_layers);
+            LlamaModel::load_from_file(backend()?, &load_path, &params)
+                .with_context(|| format!("Failed to load {}", load_path.display()))
+        })
+        .await??;
+        let model = Arc::new(model);
+        loaded.insert(path, model.clone());
+        Ok(model)
+    }
+
+    fn generation(&self, messages: &[ChatMessage], temperature: Option<f32>) -> Generation {
+        let messages = super::with_system_prompt(&self.config, messages)
+            .into_iter()
+            .filter(|message| !message.content.is_empty())
+            .map(|message| match message.role.as_str() {
+                // Chat templates seldom know a tool role
+                "tool" => ("user".to_string(), format!("Tool result: {}", message.content)),
+                _ => (message.role, message.content),
+            })
+            .collect();
+        Generation {
+            messages,
+            context_window: self.config.context_window,
+            threads: self.threads(),
+            max_tokens: self.config.max_tokens,
+            temperature: temperature.unwrap_or(self.config.temperature),
+            top_p: self.config.top_p,
+        }
+    }
+
+    /// Generate a reply on a blocking thread, sending each piece to `deltas` as it's decoded.
+    async fn run(&self, messages: &[ChatMessage], temperature: Option<f32>, deltas: Option<mpsc::UnboundedSender<String>>) -> Result<String> {
+        let model = self.model(&self.config.model).await?;
+        let generation = self.generation(messages, temperature);
+        let busy = self.busy.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let _stop_on_drop = StopOnDrop(stop.clone());
+        let (reply, usage) = tokio::task::spawn_blocking(move || {
+            let _busy = busy.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            generate(&model, &generation, &stop, |piece| {
+                if let Some(deltas) = &deltas {
+                    let _ = deltas.send(piece);
+                }
+            })
+        })
+        .await??;
+        usage::record(&self.config, messages, &reply, Some(usage));
+        Ok(reply.trim().to_string())
+    }
+}
+
+/// Run `generation` to the end of the reply, `max_tokens` or until `stop` is set.
+fn generate(model: &LlamaModel, generation: &Generation, stop: &AtomicBool, mut on_piece: impl FnMut(String)) -> Result<(String, TokenUsage)> {
+    let chat = generation
+        .messages
+        .iter()
+        .map(|(role, content)| LlamaChatMessage::new(role.clone(), content.clone()))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .context("Message can't be passed to llama.cpp")?;
+    let template = model.chat_template(None).context("The model has no chat template")?;
+    let prompt = model.apply_chat_template(&template, &chat, true).context("Failed to apply the chat template")?;
+    // The chat template writes its own begin-of-text marker
+    let tokens = model.str_to_token(&prompt, AddBos::Never).context("Failed to tokenize the prompt")?;
+    if tokens.len() + 1 >= generation.context_window as usize {
+        return Err(anyhow::anyhow!("The prompt ({} tokens) doesn't fit llm.context_window", tokens.len()));
+    }
+
+    let params = LlamaContextParams::default()
+        .with_n_ctx(NonZeroU32::new(generation.context_window))
+        .with_n_batch(generation.context_window)
+        .with_n_threads(generation.threads)
+        .with_n_threads_batch(generation.threads);
+    let mut context = model.new_context(backend()?, params).context("Failed to create a llama.cpp context")?;
+
+    let mut batch = LlamaBatch::new(tokens.len().max(512), 1);
+    let last = tokens.len() - 1;
+    for (position, token) in tokens.iter().enumerate() {
+        batch.add(*token, position as i32, &[0], position == last)?;
+    }
+    context.decode(&mut batch).context("Failed to read the prompt")?;
+
+    let mut sampler = if generation.temperature <= 0.0 {
+        LlamaSampler::greedy()
+    } else {
+        LlamaSampler::chain_simple([
+            LlamaSampler::temp(generation.temperature),
+            LlamaSampler::top_p(generation.top_p, 1),
+            LlamaSampler::dist(rand_seed()),
+        ])
+    };
+
+    let mut reply = String::new();
+    let mut pieces = Utf8Pieces::default();
+    let mut position = tokens.len() as i32;
+    let mut generated = 0;
+    while generated < generation.max_tokens && (position as u32) < generation.context_window && !stop.load(Ordering::SeqCst) {
+        let token = sampler.sample(&context, batch.n_tokens() - 1);
+        sampler.accept(token);
+        if model.is_eog_token(token) {
+            break;
+        }
+        generated += 1;
+        let piece = pieces.push(&model.token_to_bytes(token, Special::Tokenize)?);
+        if !piece.is_empty() {
+            reply.push_str(&piece);
+            on_piece(piece);
+        }
+
+        batch.clear();
+        batch.add(token, position, &[0], true)?;
+        position += 1;
+        context.decode(&mut batch).context("Failed to generate")?;
+    }
+    if stop.load(Ordering::SeqCst) {
+        return Err(anyhow::anyhow!("Generation was stopped"));
+    }
+    Ok((
+        reply,
+        TokenUsage {
+            prompt_tokens: tokens.len() as u32,
+            completion_tokens: generated,
+        },
+    ))
+}
+
+fn rand_seed() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_nanos())
+}
+
+/// Tokens can end partway through a character; this holds the bytes back until it's complete.
+#[derive(Default)]
+struct Utf8Pieces {
+    pending: Vec<u8>,
+}
+
+impl Utf8Pieces {
+    fn push(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // A broken sequence in the middle won't be fixed by more bytes; let it through mangled
+            Err(e) if e.error_len().is_some() => self.pending.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        let complete: Vec<u8> = self.pending.drain(..valid).collect();
+        String::from_utf8_lossy(&complete).into_owned()
+    }
+}
+
+/// Embedding vectors for `texts` from the GGUF embedding model `model`, on a blocking thread.
+fn embed_blocking(model: &LlamaModel, texts: &[String], threads: i32) -> Result<Vec<Vec<f32>>> {
+    let params = LlamaContextParams::default()
+        .with_embeddings(true)
+        .with_n_threads(threads)
+        .with_n_threads_batch(threads);
+    let mut context = model.new_context(backend()?, params).context("Failed to create a llama.cpp context")?;
+    let mut embeddings = Vec::with_capacity(texts.len());
+    for text in texts {
+        let tokens = model.str_to_token(text, AddBos::Always).context("Failed to tokenize text to embed")?;
+        let mut batch = LlamaBatch::new(tokens.len().max(1), 1);
+        let last = tokens.len().saturating_sub(1);
+        for (position, token) in tokens.iter().enumerate() {
+            batch.add(*token, position as i32, &[0], position == last)?;
+        }
+        context.clear_kv_cache();
+        context.decode(&mut batch).context("Failed to embed text")?;
+        embeddings.push(context.embeddings_seq_ith(0).context("The model gave no embedding")?.to_vec());
+    }
+    Ok(embeddings)
+}
+
+#[async_trait]
+impl LlmProvider for LlamaCpp {
+    async fn chat(&self, messages: &[ChatMessage], temperature: Option<f32>) -> Result<String> {
+        self.abort.run(self.run(messages, temperature, None)).await
+    }
+
+    async fn chat_with_tools(&self, messages: &[ChatMessage], _tools: &[ToolDefinition], temperature: Option<f32>) -> Result<ChatMessage> {
+        Ok(ChatMessage::assistant(self.chat(messages, temperature).await?))
+    }
+
+    async fn stream(&self, messages: &[ChatMessage], temperature: Option<f32>, deltas: mpsc::UnboundedSender<String>) -> Result<String> {
+        self.abort.run(self.run(messages, temperature, Some(deltas))).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        Ok(models::list_gguf_models())
+    }
+
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let model = self.model(model).await?;
+        let texts = texts.to_vec();
+        let threads = self.threads();
+        let busy = self.busy.clone();
+        tokio::task::spawn_blocking(move || {
+            let _busy = busy.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            embed_blocking(&model, &texts, threads)
+        })
+        .await?
+    }
+
+    async fn preload(&self) -> Result<()> {
+        self.model(&self.config.model).await.map(|_| ())
+    }
+
+    fn abort(&self) {
+        self.abort.abort();
+    }
+}
@@ -0,0 +1,237 @@
+use crate::config::LlmConfig;
+use anyhow::Result;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
+
+pub mod context;
+pub mod fallback;
+pub mod gemini;
+#[cfg(feature = "llama-cpp")]
+pub mod llama_cpp;
+pub mod ollama;
+pub mod openai;
+pub mod rate_limit;
+pub mod tools;
+pub mod turn_queue;
+pub mod usage;
+
+use tools::{ToolCall, ToolDefinition};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+    // An assistant message asking for tools to be run before it answers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    // A "tool" message: the call whose result it carries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    // What the camera saw; only providers that take images send them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageData>,
+}
+
+/// An image sent along with a message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageData {
+    // e.g. "image/jpeg"
+    pub mime_type: String,
+    // Base64, without a "data:" prefix
+    pub data: String,
+}
+
+impl ChatMessage {
+    fn new(role: &str, content: String) -> Self {
+        Self {
+            role: role.to_string(),
+            content,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            images: Vec::new(),
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new("system", content.into())
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content.into())
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new("assistant", content.into())
+    }
+
+    /// The result of running `call`, for the model to carry on with.
+    pub fn tool_result(call: &ToolCall, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(call.id.clone()),
+            ..Self::new("tool", content.into())
+        }
+    }
+}
+
+/// A chat model backend.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Complete `messages` in one response.
+    async fn chat(&self, messages: &[ChatMessage], temperature: Option<f32>) -> Result<String>;
+
+    /// Complete `messages`, sending each piece of the reply to `deltas` as it arrives.
+    /// Returns the whole reply.
+    async fn stream(&self, messages: &[ChatMessage], temperature: Option<f32>, deltas: mpsc::UnboundedSender<String>) -> Result<String>;
+
+    /// Complete `messages` with `tools` on offer. The reply is an assistant message holding either
+    /// the answer or the tool calls the model wants made first.
+    async fn chat_with_tools(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>) -> Result<ChatMessage>;
+
+    /// Models the backend can serve.
+    async fn list_models(&self) -> Result<Vec<String>>;
+
+    /// Embedding vectors for `texts` from `model`, one per text and in the same order.
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// Load the model ahead of the first request. Only backends that run models locally need to.
+    async fn preload(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Cancel every request in flight on this provider; they fail with an "aborted" error.
+    fn abort(&self);
+}
+
+/// The error a request fails with when `LlmProvider::abort` cancels it.
+#[derive(Debug)]
+pub struct Aborted;
+
+impl std::fmt::Display for Aborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LLM request was aborted")
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+/// Lets `LlmProvider::abort` cancel requests that are already running.
+pub struct AbortSignal {
+    generation: watch::Sender<u64>,
+}
+
+impl Default for AbortSignal {
+    fn default() -> Self {
+        Self {
+            generation: watch::channel(0).0,
+        }
+    }
+}
+
+impl AbortSignal {
+    pub fn abort(&self) {
+        self.generation.send_modify(|generation| *generation += 1);
+    }
+
+    /// Run `work` unless `abort` is called before it finishes.
+    pub async fn run<T>(&self, work: impl Future<Output = Result<T>>) -> Result<T> {
+        let mut aborted = self.generation.subscribe();
+        tokio::select! {
+            result = work => result,
+            _ = aborted.changed() => Err(Aborted.into()),
+        }
+    }
+}
+
+/// Stops one generation, unlike `AbortSignal` which stops everything a provider has in flight.
+/// Clones share the token, so whoever holds one can cancel the work watching another.
+#[derive(Clone)]
+pub struct CancellationToken(Arc<watch::Sender<bool>>);
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(false).0))
+    }
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once `cancel` has been called, straight away if it already was.
+    pub async fn cancelled(&self) {
+        let mut cancelled = self.0.subscribe();
+        let _ = cancelled.wait_for(|cancelled| *cancelled).await;
+    }
+
+    /// Run `work` to the end, or `None` if the token is cancelled first; `work` is dropped then,
+    /// which cancels the request it was making.
+    pub async fn run<T>(&self, work: impl Future<Output = T>) -> Option<T> {
+        tokio::select! {
+            result = work => Some(result),
+            _ = self.cancelled() => None,
+        }
+    }
+}
+
+/// Put the configured system prompt first unless the caller brought its own.
+pub fn with_system_prompt(config: &LlmConfig, messages: &[ChatMessage]) -> Vec<ChatMessage> {
+    let mut messages = messages.to_vec();
+    if messages.first().is_none_or(|m| m.role != "system") && !config.system_prompt.trim().is_empty() {
+        messages.insert(0, ChatMessage::system(config.system_prompt.clone()));
+    }
+    messages
+}
+
+// One provider per endpoint and model, shared so `abort` reaches every caller's requests
+static PROVIDERS: Lazy<Mutex<HashMap<String, Arc<dyn LlmProvider>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The provider for `config`, trying `llm.fallback` when it's set up.
+pub fn provider_for(config: &LlmConfig) -> Arc<dyn LlmProvider> {
+    if config.fallback.providers.is_empty() && config.fallback.max_retries == 0 {
+        return backend_for(config);
+    }
+    // Cheap to build: the backends in the chain come from the cache below
+    Arc::new(fallback::Fallback::new(config))
+}
+
+/// The one backend `config` names, without retries or fallbacks.
+fn backend_for(config: &LlmConfig) -> Arc<dyn LlmProvider> {
+    let key = format!("{}|{}|{}", config.provider, config.resolved_base_url(), config.model);
+    PROVIDERS
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_insert_with(|| -> Arc<dyn LlmProvider> {
+            match config.provider.as_str() {
+                "ollama" => Arc::new(ollama::Ollama::new(config.clone())),
+                "gemini" => Arc::new(gemini::Gemini::new(config.clone())),
+                #[cfg(feature = "llama-cpp")]
+                "llamacpp" => Arc::new(llama_cpp::LlamaCpp::new(config.clone())),
+                _ => Arc::new(openai::OpenAiCompatible::new(config.clone())),
+            }
+        })
+        .clone()
+}
+
+/// Drop the cached providers so the next request is built from the current configuration.
+/// Requests already running finish on the provider they started with.
+pub fn forget_providers() {
+    PROVIDERS.lock().unwrap().clear();
+}
+
+/// Single non-streaming request through the configured provider.
+///
+/// Waits for the provider's rate limiter, so bursts are spread out instead of hitting API quotas.
+pub async fn chat_completion(config: &LlmConfig, messages: &[ChatMessage], temperature: Option<f32>) -> Result<String> {
+    provider_for(config).chat(messages, temperature).await
+}
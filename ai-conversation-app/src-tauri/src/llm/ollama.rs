@@ -0,0 +1,295 @@
+use super::tools::{ToolCall, ToolDefinition};
+use super::usage::{self, TokenUsage};
+use super::{rate_limit, with_system_prompt, AbortSignal, ChatMessage, LlmProvider};
+use crate::config::LlmConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    #[serde(default)]
+    message: Option<ResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    error: Option<String>,
+    // Token counts from the model itself, on the final response
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+}
+
+impl ChatResponse {
+    fn usage(&self) -> Option<TokenUsage> {
+        // Ollama leaves out the prompt count when the prompt was cached from the last request
+        self.eval_count.map(|completion_tokens| TokenUsage {
+            prompt_tokens: self.prompt_eval_count.unwrap_or_default(),
+            completion_tokens,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<WireToolCall>,
+}
+
+#[derive(Deserialize)]
+struct WireToolCall {
+    function: WireFunction,
+}
+
+#[derive(Deserialize)]
+struct WireFunction {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct TagList {
+    models: Vec<TagEntry>,
+}
+
+#[derive(Deserialize)]
+struct TagEntry {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// A local Ollama server, through its native API rather than the OpenAI shim so `keep_alive`
+/// and model listing work.
+pub struct Ollama {
+    config: LlmConfig,
+    client: reqwest::Client,
+    abort: AbortSignal,
+}
+
+impl Ollama {
+    pub fn new(config: LlmConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            abort: AbortSignal::default(),
+        }
+    }
+
+    fn keep_alive(&self) -> serde_json::Value {
+        // Ollama takes durations as strings ("5m") and plain seconds as numbers ("-1", "0")
+        match self.config.keep_alive.trim().parse::<i64>() {
+            Ok(seconds) => serde_json::json!(seconds),
+            Err(_) => serde_json::json!(self.config.keep_alive.trim()),
+        }
+    }
+
+    async fn send(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>, stream: bool) -> Result<reqwest::Response> {
+        let messages: Vec<serde_json::Value> = with_system_prompt(&self.config, messages).iter().map(wire_message).collect();
+        let mut body = serde_json::json!({
+            "model": self.config.model,
+            "messages": messages,
+            "stream": stream,
+            "keep_alive": self.keep_alive(),
+            "options": {
+                "temperature": temperature.unwrap_or(self.config.temperature),
+                "top_p": self.config.top_p,
+                "num_predict": self.config.max_tokens,
+                "num_ctx": self.config.context_window,
+            },
+        });
+        if !tools.is_empty() {
+            body["tools"] = tools.iter().map(ToolDefinition::to_request).collect();
+        }
+
+        self.client
+            .post(format!("{}/api/chat", self.config.resolved_base_url()))
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to reach Ollama; is it running?")?
+            .error_for_status()
+            .context("Ollama request failed")
+    }
+}
+
+/// `message` as Ollama's chat API takes it. Ollama has no call ids; results follow their calls in order.
+fn wire_message(message: &ChatMessage) -> serde_json::Value {
+    let mut wire = serde_json::json!({ "role": message.role, "content": message.content });
+    // Vision models such as LLaVA take plain base64
+    if !message.images.is_empty() {
+        wire["images"] = message.images.iter().map(|image| serde_json::json!(image.data)).collect();
+    }
+    if !message.tool_calls.is_empty() {
+        wire["tool_calls"] = message
+            .tool_calls
+            .iter()
+            .map(|call| serde_json::json!({ "function": { "name": call.name, "arguments": call.arguments } }))
+            .collect();
+    }
+    wire
+}
+
+/// Parse one response line, turning an error reported in the body into an Err.
+fn parse_line(line: &str) -> Result<ChatResponse> {
+    let response: ChatResponse = serde_json::from_str(line).context("Failed to parse Ollama response")?;
+    if let Some(error) = &response.error {
+        return Err(anyhow::anyhow!("Ollama returned an error: {}", error));
+    }
+    Ok(response)
+}
+
+#[async_trait]
+impl LlmProvider for Ollama {
+    async fn chat(&self, messages: &[ChatMessage], temperature: Option<f32>) -> Result<String> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                let body = self
+                    .send(messages, &[], temperature, false)
+                    .await?
+                    .text()
+                    .await
+                    .context("Failed to read Ollama response")?;
+                let response = parse_line(&body)?;
+                let reply = response
+                    .message
+                    .as_ref()
+                    .map(|message| message.content.trim().to_string())
+                    .context("Ollama returned no message")?;
+                usage::record(&self.config, messages, &reply, response.usage());
+                Ok(reply)
+            })
+            .await
+    }
+
+    async fn chat_with_tools(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>) -> Result<ChatMessage> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                let body = self
+                    .send(messages, tools, temperature, false)
+                    .await?
+                    .text()
+                    .await
+                    .context("Failed to read Ollama response")?;
+                let response = parse_line(&body)?;
+                let reported = response.usage();
+                let message = response.message.context("Ollama returned no message")?;
+                usage::record(&self.config, messages, &message.content, reported);
+                let mut reply = ChatMessage::assistant(message.content.trim());
+                reply.tool_calls = message
+                    .tool_calls
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, call)| ToolCall {
+                        id: format!("call_{}", index),
+                        name: call.function.name,
+                        arguments: call.function.arguments,
+                    })
+                    .collect();
+                Ok(reply)
+            })
+            .await
+    }
+
+    async fn stream(&self, messages: &[ChatMessage], temperature: Option<f32>, deltas: mpsc::UnboundedSender<String>) -> Result<String> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                let mut response = self.send(messages, &[], temperature, true).await?;
+                let mut reply = String::new();
+                // One JSON object per line, split across chunks arbitrarily
+                let mut pending = Vec::new();
+                while let Some(chunk) = response.chunk().await.context("Ollama stream was interrupted")? {
+                    pending.extend_from_slice(&chunk);
+                    while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                        let line: Vec<u8> = pending.drain(..=end).collect();
+                        let line = String::from_utf8_lossy(&line);
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let response = parse_line(line.trim())?;
+                        let reported = response.usage();
+                        if let Some(delta) = response.message.map(|message| message.content) {
+                            if !delta.is_empty() {
+                                reply.push_str(&delta);
+                                let _ = deltas.send(delta);
+                            }
+                        }
+                        if response.done {
+                            usage::record(&self.config, messages, &reply, reported);
+                            return Ok(reply.trim().to_string());
+                        }
+                    }
+                }
+                usage::record(&self.config, messages, &reply, None);
+                Ok(reply.trim().to_string())
+            })
+            .await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let tags: TagList = self
+            .client
+            .get(format!("{}/api/tags", self.config.resolved_base_url()))
+            .send()
+            .await
+            .context("Failed to reach Ollama; is it running?")?
+            .error_for_status()
+            .context("Listing Ollama models failed")?
+            .json()
+            .await
+            .context("Failed to parse Ollama model list")?;
+        Ok(tags.models.into_iter().map(|model| model.name).collect())
+    }
+
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+        let response: EmbedResponse = self
+            .client
+            .post(format!("{}/api/embed", self.config.resolved_base_url()))
+            .json(&serde_json::json!({ "model": model, "input": texts }))
+            .send()
+            .await
+            .context("Failed to reach Ollama; is it running?")?
+            .error_for_status()
+            .context("Ollama embedding request failed")?
+            .json()
+            .await
+            .context("Failed to parse Ollama embeddings")?;
+        if response.embeddings.len() != texts.len() {
+            return Err(anyhow::anyhow!("Expected {} embeddings, got {}", texts.len(), response.embeddings.len()));
+        }
+        Ok(response.embeddings)
+    }
+
+    async fn preload(&self) -> Result<()> {
+        // A chat request without messages loads the model and returns without generating
+        self.client
+            .post(format!("{}/api/chat", self.config.resolved_base_url()))
+            .json(&serde_json::json!({
+                "model": self.config.model,
+                "messages": [],
+                "keep_alive": self.keep_alive(),
+            }))
+            .send()
+            .await
+            .context("Failed to reach Ollama; is it running?")?
+            .error_for_status()
+            .with_context(|| format!("Loading {} in Ollama failed", self.config.model))?;
+        Ok(())
+    }
+
+    fn abort(&self) {
+        self.abort.abort();
+    }
+}
@@ -0,0 +1,324 @@
+use super::tools::{ToolCall, ToolDefinition};
+use super::usage::{self, TokenUsage};
+use super::{rate_limit, with_system_prompt, AbortSignal, ChatMessage, LlmProvider};
+use crate::config::LlmConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+#[derive(Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatCompletionChoice>,
+    #[serde(default)]
+    usage: Option<WireUsage>,
+}
+
+#[derive(Deserialize)]
+struct WireUsage {
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+impl From<WireUsage> for TokenUsage {
+    fn from(usage: WireUsage) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChoice {
+    message: ResponseMessage,
+}
+
+// Content is null when the model calls tools, and some servers send null tool calls without
+#[derive(Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<WireToolCall>>,
+}
+
+#[derive(Deserialize)]
+struct WireToolCall {
+    id: String,
+    function: WireFunction,
+}
+
+#[derive(Deserialize)]
+struct WireFunction {
+    name: String,
+    // A JSON document in a string
+    arguments: String,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingEntry>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingEntry {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    // Empty in the closing chunk that carries the usage
+    #[serde(default)]
+    choices: Vec<ChatCompletionChunkChoice>,
+    #[serde(default)]
+    usage: Option<WireUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunkChoice {
+    #[serde(default)]
+    delta: ChatCompletionDelta,
+}
+
+#[derive(Default, Deserialize)]
+struct ChatCompletionDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// Any endpoint speaking the OpenAI chat completions API: OpenAI itself, llama.cpp's server,
+/// LM Studio, vLLM and the like.
+pub struct OpenAiCompatible {
+    config: LlmConfig,
+    client: reqwest::Client,
+    abort: AbortSignal,
+}
+
+impl OpenAiCompatible {
+    pub fn new(config: LlmConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            abort: AbortSignal::default(),
+        }
+    }
+
+    async fn send(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>, stream: bool) -> Result<reqwest::Response> {
+        let messages: Vec<serde_json::Value> = with_system_prompt(&self.config, messages).iter().map(wire_message).collect();
+        let mut body = serde_json::json!({
+            "model": self.config.model,
+            "temperature": temperature.unwrap_or(self.config.temperature),
+            "top_p": self.config.top_p,
+            "max_tokens": self.config.max_tokens,
+            "stream": stream,
+            "messages": messages,
+        });
+        if !tools.is_empty() {
+            body["tools"] = tools.iter().map(ToolDefinition::to_request).collect();
+        }
+        // Only OpenAI is known to take this; other servers may reject what they don't know
+        if stream && self.config.provider == "openai" {
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
+        let mut request = self
+            .client
+            .post(format!("{}/chat/completions", self.config.resolved_base_url()))
+            .json(&body);
+        if let Some(api_key) = self.config.resolved_api_key() {
+            request = request.bearer_auth(api_key);
+        }
+
+        request
+            .send()
+            .await
+            .context("Failed to reach LLM endpoint")?
+            .error_for_status()
+            .context("LLM request failed")
+    }
+
+    async fn complete(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>) -> Result<ResponseMessage> {
+        let response: ChatCompletionResponse = self
+            .send(messages, tools, temperature, false)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse LLM response")?;
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .context("LLM returned no choices")?;
+        let reply = message.content.as_deref().unwrap_or_default();
+        usage::record(&self.config, messages, reply, response.usage.map(TokenUsage::from));
+        Ok(message)
+    }
+}
+
+/// `message` as the chat completions API takes it, tool calls and images and all.
+fn wire_message(message: &ChatMessage) -> serde_json::Value {
+    let mut wire = serde_json::json!({ "role": message.role, "content": message.content });
+    if !message.images.is_empty() {
+        // Images turn the content into a list of parts
+        let mut parts = vec![serde_json::json!({ "type": "text", "text": message.content })];
+        parts.extend(message.images.iter().map(|image| {
+            serde_json::json!({
+                "type": "image_url",
+                "image_url": { "url": format!("data:{};base64,{}", image.mime_type, image.data) },
+            })
+        }));
+        wire["content"] = serde_json::json!(parts);
+    }
+    if !message.tool_calls.is_empty() {
+        wire["tool_calls"] = message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                serde_json::json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": { "name": call.name, "arguments": call.arguments.to_string() },
+                })
+            })
+            .collect();
+    }
+    if let Some(id) = &message.tool_call_id {
+        wire["tool_call_id"] = serde_json::json!(id);
+    }
+    wire
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatible {
+    async fn chat(&self, messages: &[ChatMessage], temperature: Option<f32>) -> Result<String> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                let message = self.complete(messages, &[], temperature).await?;
+                Ok(message.content.unwrap_or_default().trim().to_string())
+            })
+            .await
+    }
+
+    async fn chat_with_tools(&self, messages: &[ChatMessage], tools: &[ToolDefinition], temperature: Option<f32>) -> Result<ChatMessage> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                let message = self.complete(messages, tools, temperature).await?;
+                let mut reply = ChatMessage::assistant(message.content.unwrap_or_default().trim());
+                for call in message.tool_calls.unwrap_or_default() {
+                    // Models now and then send arguments that don't parse; the tool reports what's missing
+                    let arguments = serde_json::from_str(&call.function.arguments).unwrap_or(serde_json::Value::Null);
+                    reply.tool_calls.push(ToolCall {
+                        id: call.id,
+                        name: call.function.name,
+                        arguments,
+                    });
+                }
+                Ok(reply)
+            })
+            .await
+    }
+
+    async fn stream(&self, messages: &[ChatMessage], temperature: Option<f32>, deltas: mpsc::UnboundedSender<String>) -> Result<String> {
+        self.abort
+            .run(async {
+                let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+                let mut response = self.send(messages, &[], temperature, true).await?;
+                let mut reply = String::new();
+                let mut reported = None;
+                // Server-sent events arrive in arbitrary chunks; only act on complete lines
+                let mut pending = Vec::new();
+                while let Some(chunk) = response.chunk().await.context("LLM stream was interrupted")? {
+                    pending.extend_from_slice(&chunk);
+                    while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                        let line: Vec<u8> = pending.drain(..=end).collect();
+                        let line = String::from_utf8_lossy(&line);
+                        let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                            continue;
+                        };
+                        if data == "[DONE]" {
+                            usage::record(&self.config, messages, &reply, reported);
+                            return Ok(reply.trim().to_string());
+                        }
+                        let chunk: ChatCompletionChunk =
+                            serde_json::from_str(data).context("Failed to parse LLM stream chunk")?;
+                        if let Some(chunk_usage) = chunk.usage {
+                            reported = Some(TokenUsage::from(chunk_usage));
+                        }
+                        if let Some(delta) = chunk.choices.into_iter().next().and_then(|choice| choice.delta.content) {
+                            if !delta.is_empty() {
+                                reply.push_str(&delta);
+                                // The listener going away doesn't stop the reply from being collected
+                                let _ = deltas.send(delta);
+                            }
+                        }
+                    }
+                }
+                usage::record(&self.config, messages, &reply, reported);
+                Ok(reply.trim().to_string())
+            })
+            .await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let mut request = self.client.get(format!("{}/models", self.config.resolved_base_url()));
+        if let Some(api_key) = self.config.resolved_api_key() {
+            request = request.bearer_auth(api_key);
+        }
+        let models: ModelList = request
+            .send()
+            .await
+            .context("Failed to reach LLM endpoint")?
+            .error_for_status()
+            .context("Listing models failed")?
+            .json()
+            .await
+            .context("Failed to parse model list")?;
+        Ok(models.data.into_iter().map(|model| model.id).collect())
+    }
+
+    async fn embed(&self, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let _permit = rate_limit::limiter_for(&self.config).acquire().await;
+        let mut request = self
+            .client
+            .post(format!("{}/embeddings", self.config.resolved_base_url()))
+            .json(&serde_json::json!({ "model": model, "input": texts }));
+        if let Some(api_key) = self.config.resolved_api_key() {
+            request = request.bearer_auth(api_key);
+        }
+        let mut response: EmbeddingResponse = request
+            .send()
+            .await
+            .context("Failed to reach LLM endpoint")?
+            .error_for_status()
+            .context("Embedding request failed")?
+            .json()
+            .await
+            .context("Failed to parse embedding response")?;
+        if response.data.len() != texts.len() {
+            return Err(anyhow::anyhow!("Expected {} embeddings, got {}", texts.len(), response.data.len()));
+        }
+        // The API doesn't promise to keep the input order
+        response.data.sort_by_key(|entry| entry.index);
+        Ok(response.data.into_iter().map(|entry| entry.embedding).collect())
+    }
+
+    fn abort(&self) {
+        self.abort.abort();
+    }
+}
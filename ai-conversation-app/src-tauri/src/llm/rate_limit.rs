@@ -0,0 +1,91 @@
+use crate::config::LlmConfig;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Token bucket plus a concurrency cap for one provider endpoint.
+pub struct RateLimiter {
+    // Tokens per second; 0 disables the bucket
+    rate: f64,
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+    concurrency: Option<Arc<Semaphore>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Held for the duration of a request; dropping it frees a concurrency slot.
+pub struct RequestPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_minute: u32, max_concurrent: u32) -> Self {
+        let capacity = (requests_per_minute as f64 / 6.0).ceil().max(1.0);
+        Self {
+            rate: requests_per_minute as f64 / 60.0,
+            capacity,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+            concurrency: (max_concurrent > 0).then(|| Arc::new(Semaphore::new(max_concurrent as usize))),
+        }
+    }
+
+    /// Wait for a concurrency slot and a request token.
+    pub async fn acquire(&self) -> RequestPermit {
+        let permit = match &self.concurrency {
+            Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+
+        if self.rate > 0.0 {
+            while let Some(wait) = self.try_take() {
+                log::debug!("LLM rate limit reached, waiting {:?}", wait);
+                tokio::time::sleep(wait).await;
+            }
+        }
+
+        RequestPermit { _permit: permit }
+    }
+
+    /// Take a token, or return how long until one is available.
+    fn try_take(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+// One limiter per provider endpoint, shared by every caller (chat, translation, meeting summaries)
+static LIMITERS: Lazy<Mutex<HashMap<String, Arc<RateLimiter>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn limiter_for(config: &LlmConfig) -> Arc<RateLimiter> {
+    let key = format!("{}|{}", config.provider, config.resolved_base_url());
+    LIMITERS
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_insert_with(|| {
+            Arc::new(RateLimiter::new(
+                config.rate_limit.requests_per_minute,
+                config.rate_limit.max_concurrent,
+            ))
+        })
+        .clone()
+}
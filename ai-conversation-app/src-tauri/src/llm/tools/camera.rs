@@ -0,0 +1,40 @@
+use super::{Tool, ToolDefinition};
+use crate::vision::objects::{self, DetectedObject};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// The objects the camera sees now; None while the camera is off.
+pub type LookAtCamera = Arc<dyn Fn() -> Option<Vec<DetectedObject>> + Send + Sync>;
+
+/// Tells the model what the object detector sees on the camera.
+pub struct CameraObjects {
+    look: LookAtCamera,
+}
+
+impl CameraObjects {
+    pub fn new(look: LookAtCamera) -> Self {
+        Self { look }
+    }
+}
+
+#[async_trait]
+impl Tool for CameraObjects {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "look_at_camera".to_string(),
+            description: "List the objects the user's camera sees right now and where they are in view, e.g. \
+                          to tell what the user is holding up."
+                .to_string(),
+            parameters: serde_json::json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    async fn call(&self, _arguments: serde_json::Value) -> Result<String> {
+        match (self.look)() {
+            None => Ok("The camera is off".to_string()),
+            Some(objects) if objects.is_empty() => Ok("The camera sees nothing it recognizes".to_string()),
+            Some(objects) => Ok(format!("The camera sees:\n{}", objects::describe(&objects))),
+        }
+    }
+}
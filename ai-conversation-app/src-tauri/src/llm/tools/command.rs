@@ -0,0 +1,224 @@
+use super::{Confirm, Tool, ToolDefinition};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+// Output past this is still streamed to the frontend but not given to the model
+const MAX_RESULT_CHARS: usize = 16_000;
+
+/// Runs a program the user allowed, once they confirm the exact command line. Commands run
+/// without a shell, so pipes, redirects and `;` are plain arguments rather than more commands.
+pub struct RunCommand {
+    // Program names the command's first word must be
+    allowed: Vec<String>,
+    timeout: Duration,
+    confirm: Confirm,
+    // Each line of output as it's printed
+    on_output: Arc<dyn Fn(String) + Send + Sync>,
+}
+
+impl RunCommand {
+    pub fn new(allowed: Vec<String>, timeout: Duration, confirm: Confirm, on_output: Arc<dyn Fn(String) + Send + Sync>) -> Self {
+        Self {
+            allowed,
+            timeout,
+            confirm,
+            on_output,
+        }
+    }
+
+    fn is_allowed(&self, program: &str) -> bool {
+        // Only programs found on PATH; "./git" or "/tmp/git" could be anything
+        if program.contains(['/', '\\']) {
+            return false;
+        }
+        // "git" also allows "git.exe" on Windows
+        let name = program.strip_suffix(".exe").unwrap_or(program);
+        self.allowed.iter().any(|allowed| allowed.eq_ignore_ascii_case(name))
+    }
+}
+
+#[async_trait]
+impl Tool for RunCommand {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "run_command".to_string(),
+            description: format!(
+                "Run a command on the user's computer after they approve it, and get its output. \
+                 Runs without a shell from the home directory. Allowed programs: {}.",
+                self.allowed.join(", ")
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "The program and its arguments, e.g. \"git status\"" },
+                },
+                "required": ["command"],
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let command_line = arguments["command"].as_str().context("command is missing")?.trim().to_string();
+        let words = split_command(&command_line)?;
+        let Some((program, args)) = words.split_first() else {
+            return Err(anyhow::anyhow!("command is empty"));
+        };
+        if !self.is_allowed(program) {
+            return Err(anyhow::anyhow!("{} isn't an allowed program; allowed are {}", program, self.allowed.join(", ")));
+        }
+        if !(self.confirm)(command_line.clone()).await {
+            return Err(anyhow::anyhow!("The user declined to run {}", command_line));
+        }
+
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(dirs::home_dir().unwrap_or_else(|| ".".into()))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start {}", program))?;
+        let (lines, mut lines_rx) = mpsc::unbounded_channel();
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(forward_lines(stdout, lines.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(forward_lines(stderr, lines));
+        }
+
+        let run = async {
+            let mut output = String::new();
+            while let Some(line) = lines_rx.recv().await {
+                if output.len() < MAX_RESULT_CHARS {
+                    output.push_str(&line);
+                    output.push('\n');
+                }
+                (self.on_output)(line);
+            }
+            let status = child.wait().await.context("Failed to wait for the command")?;
+            Ok::<_, anyhow::Error>((status, output))
+        };
+        // A command that times out is killed when `child` is dropped on return
+        let (status, mut output) = tokio::time::timeout(self.timeout, run)
+            .await
+            .map_err(|_| anyhow::anyhow!("{} didn't finish within {} seconds", command_line, self.timeout.as_secs()))??;
+        if output.len() >= MAX_RESULT_CHARS {
+            output.push_str("[output truncated]\n");
+        }
+        Ok(format!("Exit status: {}\n{}", status.code().map_or("killed".to_string(), |code| code.to_string()), output))
+    }
+}
+
+async fn forward_lines(stream: impl AsyncRead + Unpin, lines: mpsc::UnboundedSender<String>) {
+    let mut reader = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = reader.next_line().await {
+        if lines.send(line).is_err() {
+            break;
+        }
+    }
+}
+
+/// Split a command line into words: whitespace separates them except inside single or double
+/// quotes, and outside single quotes a backslash escapes the next character (except on Windows,
+/// where it separates paths).
+fn split_command(command_line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command_line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some('\''), c) => word.push(c),
+            (_, '\\') if !cfg!(windows) => {
+                word.push(chars.next().context("command ends with a backslash")?);
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow::anyhow!("command has an unclosed quote"));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_command;
+
+    fn words(command_line: &str) -> Vec<String> {
+        split_command(command_line).unwrap()
+    }
+
+    #[test]
+    fn splits_on_any_run_of_whitespace() {
+        assert_eq!(words("  git\tstatus \n --short "), ["git", "status", "--short"]);
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn quotes_keep_whitespace_and_join_with_neighbours() {
+        assert_eq!(words(r#"echo "hello world" 'a  b'"#), ["echo", "hello world", "a  b"]);
+        assert_eq!(words(r#"--name="John Smith""#), ["--name=John Smith"]);
+        assert_eq!(words(r#"say "it's" 'a "quote"'"#), ["say", "it's", r#"a "quote""#]);
+    }
+
+    #[test]
+    fn empty_quotes_are_an_empty_word() {
+        assert_eq!(words(r#"grep "" file"#), ["grep", "", "file"]);
+        assert_eq!(words("echo ''"), ["echo", ""]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn backslash_escapes_outside_single_quotes() {
+        assert_eq!(words(r"touch my\ file"), ["touch", "my file"]);
+        assert_eq!(words(r#"echo "say \"hi\"""#), ["echo", r#"say "hi""#]);
+        assert_eq!(words(r"echo 'C:\dir'"), ["echo", r"C:\dir"]);
+        assert_eq!(words(r"echo \\"), ["echo", r"\"]);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn backslash_is_a_path_separator_on_windows() {
+        assert_eq!(words(r"type C:\dir\file.txt"), ["type", r"C:\dir\file.txt"]);
+    }
+
+    #[test]
+    fn unclosed_quote_is_an_error() {
+        assert!(split_command(r#"echo "hello"#).is_err());
+        assert!(split_command("echo 'hello").is_err());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn trailing_backslash_is_an_error() {
+        assert!(split_command(r"echo hello\").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Tool, ToolDefinition};
+use crate::config::LlmConfig;
+use crate::documents;
+use crate::llm::{self, ChatMessage};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+// Documents up to this long go to the model whole; longer ones are summarized piece by piece
+const MAX_DIRECT_CHARS: usize = 12_000;
+const CHUNK_CHARS: usize = 6_000;
+// Pieces summarized per document, so a book doesn't turn into hundreds of requests
+const MAX_CHUNKS: usize = 12;
+const MAX_LISTED_ENTRIES: usize = 200;
+
+/// Reads text, Markdown and PDF files in directories the user approved, summarizing long ones,
+/// and lists what those directories hold.
+pub struct ReadFile {
+    directories: Vec<PathBuf>,
+    // Summarizes documents too long to hand over whole
+    llm: LlmConfig,
+}
+
+impl ReadFile {
+    /// Directories that don't exist are left out.
+    pub fn new(directories: &[String], llm: LlmConfig) -> Self {
+        Self {
+            directories: approved_directories(directories),
+            llm,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directories.is_empty()
+    }
+
+    async fn summarize(&self, name: &str, text: &str, focus: Option<&str>) -> Result<String> {
+        let chunks = documents::chunk(text, CHUNK_CHARS);
+        let total = chunks.len();
+        let focus = focus
+            .filter(|focus| !focus.trim().is_empty())
+            .map(|focus| format!(" Focus on what bears on: {}.", focus.trim()))
+            .unwrap_or_default();
+
+        let mut summaries = Vec::new();
+        for (index, chunk) in chunks.iter().take(MAX_CHUNKS).enumerate() {
+            let messages = [
+                ChatMessage::system(format!(
+                    "Summarize part {} of {} of the document {} in a few sentences, keeping names, \
+                     numbers and conclusions.{}",
+                    index + 1,
+                    total,
+                    name,
+                    focus
+                )),
+                ChatMessage::user(chunk.clone()),
+            ];
+            let summary = llm::chat_completion(&self.llm, &messages, Some(0.2))
+                .await
+                .with_context(|| format!("Failed to summarize part {} of {}", index + 1, name))?;
+            summaries.push(format!("Part {}: {}", index + 1, summary));
+        }
+        if total > MAX_CHUNKS {
+            summaries.push(format!("(The last {} of {} parts weren't read.)", total - MAX_CHUNKS, total));
+        }
+        Ok(summaries.join("\n\n"))
+    }
+}
+
+#[async_trait]
+impl Tool for ReadFile {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "read_file".to_string(),
+            description: format!(
+                "Read a text, Markdown or PDF file, or list a directory, on the user's computer. Long \
+                 files come back summarized. Only these directories can be read: {}.",
+                self.directories
+                    .iter()
+                    .map(|directory| directory.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "A file or directory, absolute or relative to an allowed directory" },
+                    "focus": { "type": "string", "description": "What to look for when a long file is summarized" },
+                },
+                "required": ["path"],
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let path = resolve(&self.directories, arguments["path"].as_str().context("path is missing")?)?;
+        if path.is_dir() {
+            return list_directory(&path);
+        }
+
+        let extract_path = path.clone();
+        let text = tokio::task::spawn_blocking(move || documents::extract_text(&extract_path)).await??;
+        if text.trim().is_empty() {
+            return Ok(format!("{} has no text to read (it may be a scanned PDF)", path.display()));
+        }
+        if text.chars().count() <= MAX_DIRECT_CHARS {
+            return Ok(text);
+        }
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let summary = self.summarize(&name, &text, arguments["focus"].as_str()).await?;
+        Ok(format!("{} is long, so here is a summary of it:\n\n{}", path.display(), summary))
+    }
+}
+
+/// `directories` canonicalized, leaving out those that don't exist.
+pub(super) fn approved_directories(directories: &[String]) -> Vec<PathBuf> {
+    directories
+        .iter()
+        .filter_map(|directory| std::fs::canonicalize(directory.trim()).ok())
+        .filter(|directory| directory.is_dir())
+        .collect()
+}
+
+/// `path` resolved, if it's inside one of `directories`. Symlinks are followed first, so they
+/// can't lead out of the sandbox.
+pub(super) fn resolve(directories: &[PathBuf], path: &str) -> Result<PathBuf> {
+    let path = Path::new(path.trim());
+    let candidates: Vec<PathBuf> = if path.is_absolute() {
+        vec![path.to_path_buf()]
+    } else {
+        // Relative paths are tried against each approved directory in turn
+        directories.iter().map(|directory| directory.join(path)).collect()
+    };
+    candidates
+        .iter()
+        .filter_map(|candidate| std::fs::canonicalize(candidate).ok())
+        .find(|resolved| directories.iter().any(|directory| resolved.starts_with(directory)))
+        .with_context(|| format!("{} isn't in a directory you can read, or doesn't exist", path.display()))
+}
+
+/// The readable files and subdirectories in `directory`, one per line.
+fn list_directory(directory: &Path) -> Result<String> {
+    let mut entries: Vec<String> = std::fs::read_dir(directory)
+        .with_context(|| format!("Failed to list {}", directory.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() || documents::is_supported(path))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                return None;
+            }
+            Some(if path.is_dir() { format!("{}/", name) } else { name })
+        })
+        .collect();
+    entries.sort();
+    if entries.is_empty() {
+        return Ok(format!("{} has no readable files", directory.display()));
+    }
+    let total = entries.len();
+    entries.truncate(MAX_LISTED_ENTRIES);
+    let mut listing = format!("{}:\n{}", directory.display(), entries.join("\n"));
+    if total > MAX_LISTED_ENTRIES {
+        listing.push_str(&format!("\n(and {} more)", total - MAX_LISTED_ENTRIES));
+    }
+    Ok(listing)
+}
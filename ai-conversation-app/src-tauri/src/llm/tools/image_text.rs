@@ -0,0 +1,67 @@
+use super::files::{approved_directories, resolve};
+use super::{Tool, ToolDefinition};
+use crate::config::OcrConfig;
+use crate::vision;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+// Screens full of text are cut here rather than filling the context
+const MAX_CHARS: usize = 12_000;
+
+/// Reads the text on the user's screen, or in an image in a directory read_file may read, with
+/// OCR.
+pub struct ReadImageText {
+    directories: Vec<PathBuf>,
+    ocr: OcrConfig,
+}
+
+impl ReadImageText {
+    pub fn new(directories: &[String], ocr: OcrConfig) -> Self {
+        Self {
+            directories: approved_directories(directories),
+            ocr,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for ReadImageText {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "read_image_text".to_string(),
+            description: "Read the text on the user's screen, or in an image file, with OCR. Without a path \
+                          it reads what the screen shows now."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "An image file in a directory read_file can read" },
+                    "display": { "type": "integer", "description": "Which display to read when there are several; the primary one by default" },
+                },
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let image = match arguments["path"].as_str().filter(|path| !path.trim().is_empty()) {
+            Some(path) => {
+                let path = resolve(&self.directories, path)?;
+                tokio::task::spawn_blocking(move || vision::load_image(&path.to_string_lossy())).await??
+            }
+            None => {
+                let display = arguments["display"].as_u64().map(|display| display as u32);
+                tokio::task::spawn_blocking(move || vision::screen::capture(display)).await??
+            }
+        };
+        let text = vision::ocr::read_text(&image, &self.ocr).await?;
+        if text.is_empty() {
+            return Ok("No text could be read".to_string());
+        }
+        if text.chars().count() > MAX_CHARS {
+            let cut: String = text.chars().take(MAX_CHARS).collect();
+            return Ok(format!("{}\n(The rest of the text was left out.)", cut));
+        }
+        Ok(text)
+    }
+}
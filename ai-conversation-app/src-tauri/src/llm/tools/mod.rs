@@ -0,0 +1,196 @@
+use super::{ChatMessage, LlmProvider};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub mod camera;
+pub mod command;
+pub mod files;
+pub mod image_text;
+pub mod web_search;
+
+/// Asks the user whether a tool may go ahead with what's described; resolves to their answer.
+pub type Confirm = Arc<dyn Fn(String) -> BoxFuture<'static, bool> + Send + Sync>;
+
+/// A function the model asked to have run, with its arguments as the tool's schema describes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    // Ties the result to the call; Ollama doesn't send one, so it's made up for it
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+/// What the model is told about a tool: its name, what it's for and a JSON schema of its arguments.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+impl ToolDefinition {
+    /// As both OpenAI-compatible servers and Ollama take it in a request's "tools".
+    pub fn to_request(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            },
+        })
+    }
+}
+
+/// Something the assistant can do besides talk.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn definition(&self) -> ToolDefinition;
+
+    /// Run the tool; the result, or the error, is given to the model as text.
+    async fn call(&self, arguments: serde_json::Value) -> Result<String>;
+}
+
+/// Sent as "tool-call" once a tool has run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallEvent {
+    pub name: String,
+    pub arguments: serde_json::Value,
+    pub result: String,
+    pub success: bool,
+}
+
+/// The tools on offer for a turn.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    /// Offer `tool`, replacing one of the same name.
+    pub fn register(&mut self, tool: Arc<dyn Tool>) {
+        let name = tool.definition().name;
+        self.tools.retain(|existing| existing.definition().name != name);
+        self.tools.push(tool);
+    }
+
+    /// Keep only the tools named in `allowed`.
+    pub fn retain(&mut self, allowed: &[String]) {
+        self.tools.retain(|tool| allowed.contains(&tool.definition().name));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|tool| tool.definition()).collect()
+    }
+
+    /// Run `call`, describing a failure rather than returning it, so the model can recover.
+    pub async fn call(&self, call: &ToolCall) -> (String, bool) {
+        let Some(tool) = self.tools.iter().find(|tool| tool.definition().name == call.name) else {
+            return (format!("Error: there is no tool named {}", call.name), false);
+        };
+        match tool.call(call.arguments.clone()).await {
+            Ok(result) => (result, true),
+            Err(e) => (format!("Error: {:#}", e), false),
+        }
+    }
+}
+
+/// Complete `messages`, running the tools the model calls and giving it their results until it
+/// answers, after at most `max_rounds` rounds of calls. `on_call` hears about every call made.
+pub async fn complete_with_tools(
+    provider: &dyn LlmProvider,
+    registry: &ToolRegistry,
+    messages: &[ChatMessage],
+    max_rounds: u32,
+    on_call: impl Fn(ToolCallEvent) + Send,
+) -> Result<String> {
+    let definitions = registry.definitions();
+    let mut messages = messages.to_vec();
+    // The last round is for answering with what the calls before it found
+    for round in 0..=max_rounds {
+        let reply = provider.chat_with_tools(&messages, &definitions, None).await?;
+        if reply.tool_calls.is_empty() {
+            return Ok(reply.content);
+        }
+        if round == max_rounds {
+            break;
+        }
+        let calls = reply.tool_calls.clone();
+        messages.push(reply);
+        for call in calls {
+            log::info!("Running tool {}", call.name);
+            let (result, success) = registry.call(&call).await;
+            on_call(ToolCallEvent {
+                name: call.name.clone(),
+                arguments: call.arguments.clone(),
+                result: result.clone(),
+                success,
+            });
+            messages.push(ChatMessage::tool_result(&call, result));
+        }
+    }
+    Err(anyhow::anyhow!("LLM made more than {} rounds of tool calls without answering", max_rounds))
+}
+
+/// The local date and time.
+pub struct CurrentTime;
+
+#[async_trait]
+impl Tool for CurrentTime {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "get_current_time".to_string(),
+            description: "Get the current local date, time, weekday and time zone offset.".to_string(),
+            parameters: serde_json::json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    async fn call(&self, _arguments: serde_json::Value) -> Result<String> {
+        Ok(chrono::Local::now().format("%A %Y-%m-%d %H:%M:%S (UTC%:z)").to_string())
+    }
+}
+
+/// Opens a web page in the user's browser.
+pub struct OpenUrl;
+
+#[async_trait]
+impl Tool for OpenUrl {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "open_url".to_string(),
+            description: "Open a web page in the user's default browser.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "description": "An http or https URL" },
+                },
+                "required": ["url"],
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let url = arguments["url"].as_str().context("url is missing")?.trim();
+        // Nothing but web pages: no file://, no custom schemes that launch other apps
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(anyhow::anyhow!("Only http and https URLs can be opened"));
+        }
+        tauri_plugin_opener::open_url(url, None::<&str>).with_context(|| format!("Failed to open {}", url))?;
+        Ok(format!("Opened {}", url))
+    }
+}
+
+/// The tools that come with the app.
+pub fn builtin() -> ToolRegistry {
+    let mut registry = ToolRegistry::default();
+    registry.register(Arc::new(CurrentTime));
+    registry.register(Arc::new(OpenUrl));
+    registry
+}
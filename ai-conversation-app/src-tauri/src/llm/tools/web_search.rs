@@ -0,0 +1,55 @@
+use super::{Tool, ToolDefinition};
+use crate::search::SearchBackend;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+/// Searches the web so answers about current or niche topics can rest on sources.
+pub struct WebSearch {
+    backend: Box<dyn SearchBackend>,
+    max_results: usize,
+}
+
+impl WebSearch {
+    pub fn new(backend: Box<dyn SearchBackend>, max_results: usize) -> Self {
+        Self {
+            backend,
+            max_results: max_results.max(1),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for WebSearch {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "web_search".to_string(),
+            description: "Search the web for current information. Returns titles, URLs and snippets; \
+                          mention the sources you use."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "What to search for" },
+                },
+                "required": ["query"],
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let query = arguments["query"].as_str().context("query is missing")?.trim();
+        if query.is_empty() {
+            return Err(anyhow::anyhow!("query is empty"));
+        }
+        let results = self.backend.search(query, self.max_results).await?;
+        if results.is_empty() {
+            return Ok(format!("No results for {}", query));
+        }
+        Ok(results
+            .iter()
+            .enumerate()
+            .map(|(index, result)| format!("{}. {}\n   {}\n   {}", index + 1, result.title, result.url, result.snippet))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
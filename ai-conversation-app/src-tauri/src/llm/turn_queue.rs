@@ -0,0 +1,166 @@
+use crate::config::TurnQueueConfig;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueuePolicy {
+    // Reject new turns while the queue is full
+    Queue,
+    // Make room by discarding the oldest pending turn
+    DropOldest,
+    // Merge a new turn into the one still waiting, so a burst becomes one request
+    Coalesce,
+}
+
+impl QueuePolicy {
+    pub fn from_config(policy: &str) -> Self {
+        match policy {
+            "drop_oldest" => QueuePolicy::DropOldest,
+            "coalesce" => QueuePolicy::Coalesce,
+            "queue" => QueuePolicy::Queue,
+            other => {
+                log::warn!("Unknown turn queue policy {}, using coalesce", other);
+                QueuePolicy::Coalesce
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedTurn {
+    pub id: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum TurnQueued {
+    Queued { id: u64 },
+    Coalesced { id: u64 },
+    DroppedOldest { id: u64, dropped: u64 },
+}
+
+/// User turns waiting for the LLM, in the order they were spoken.
+pub struct TurnQueue {
+    pending: VecDeque<QueuedTurn>,
+    next_id: u64,
+    policy: QueuePolicy,
+    max_pending: usize,
+}
+
+impl TurnQueue {
+    pub fn new(config: &TurnQueueConfig) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            next_id: 1,
+            policy: QueuePolicy::from_config(&config.policy),
+            max_pending: config.max_pending.max(1),
+        }
+    }
+
+    pub fn push(&mut self, text: String) -> Result<TurnQueued, String> {
+        if self.policy == QueuePolicy::Coalesce {
+            if let Some(last) = self.pending.back_mut() {
+                last.text = format!("{} {}", last.text, text);
+                return Ok(TurnQueued::Coalesced { id: last.id });
+            }
+        }
+
+        let mut dropped = None;
+        if self.pending.len() >= self.max_pending {
+            match self.policy {
+                QueuePolicy::DropOldest => dropped = self.pending.pop_front().map(|turn| turn.id),
+                _ => return Err(format!("Too many pending turns ({})", self.pending.len())),
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.push_back(QueuedTurn { id, text });
+        Ok(match dropped {
+            Some(dropped) => TurnQueued::DroppedOldest { id, dropped },
+            None => TurnQueued::Queued { id },
+        })
+    }
+
+    pub fn pop(&mut self) -> Option<QueuedTurn> {
+        self.pending.pop_front()
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ChatMessage;
+    use futures::stream::{FuturesOrdered, StreamExt};
+    use std::time::Duration;
+
+    fn queue(policy: &str, max_pending: usize) -> TurnQueue {
+        TurnQueue::new(&TurnQueueConfig {
+            policy: policy.to_string(),
+            max_pending,
+            ..TurnQueueConfig::default()
+        })
+    }
+
+    #[test]
+    fn coalesces_turns_waiting_together() {
+        let mut queue = queue("coalesce", 4);
+        assert!(matches!(queue.push("what's the".to_string()), Ok(TurnQueued::Queued { id: 1 })));
+        assert!(matches!(queue.push("weather".to_string()), Ok(TurnQueued::Coalesced { id: 1 })));
+        assert_eq!(queue.pop().map(|turn| turn.text).as_deref(), Some("what's the weather"));
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn drops_the_oldest_turn_when_full() {
+        let mut queue = queue("drop_oldest", 1);
+        queue.push("first".to_string()).unwrap();
+        assert!(matches!(queue.push("second".to_string()), Ok(TurnQueued::DroppedOldest { id: 2, dropped: 1 })));
+        assert_eq!(queue.pop().map(|turn| turn.text).as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn rejects_turns_when_full() {
+        let mut queue = queue("queue", 1);
+        queue.push("first".to_string()).unwrap();
+        assert!(queue.push("second".to_string()).is_err());
+    }
+
+    // Mirrors the turn worker: each request sees the history plus its own turn, and the turn
+    // joins the history with its reply once replies come back in submission order
+    #[tokio::test]
+    async fn concurrent_turns_stay_paired_with_their_replies() {
+        let mut queue = queue("queue", 4);
+        queue.push("first".to_string()).unwrap();
+        queue.push("second".to_string()).unwrap();
+        let mut history = vec![ChatMessage::system("prompt")];
+
+        let mut in_flight = FuturesOrdered::new();
+        for delay in [30, 0] {
+            let turn = queue.pop().unwrap();
+            let mut messages = history.clone();
+            messages.push(ChatMessage::user(turn.text.clone()));
+            in_flight.push_back(async move {
+                // The first turn answers last
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                let seen: Vec<_> = messages.iter().map(|m| m.content.clone()).collect();
+                (turn, format!("reply to {}", seen.join(" + ")))
+            });
+        }
+        while let Some((turn, reply)) = in_flight.next().await {
+            history.push(ChatMessage::user(turn.text));
+            history.push(ChatMessage::assistant(reply));
+        }
+
+        let contents: Vec<_> = history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            ["prompt", "first", "reply to prompt + first", "second", "reply to prompt + second"]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use super::ChatMessage;
+use crate::config::LlmConfig;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+use tiktoken_rs::CoreBPE;
+use tokio::sync::mpsc;
+
+// Rough English average for models whose tokenizer isn't known here
+const CHARS_PER_TOKEN: usize = 4;
+// Role markers and separators each message costs on top of its text
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+// Built on first use; loading a vocabulary takes a moment
+static CL100K: Lazy<Option<CoreBPE>> = Lazy::new(|| tiktoken_rs::cl100k_base().ok());
+static O200K: Lazy<Option<CoreBPE>> = Lazy::new(|| tiktoken_rs::o200k_base().ok());
+
+// Where finished requests are reported; unset until the app is ready to store them
+static SINK: Lazy<Mutex<Option<mpsc::UnboundedSender<UsageRecord>>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+/// One finished chat request.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageRecord {
+    pub provider: String,
+    pub model: String,
+    pub usage: TokenUsage,
+    // Counted here because the server didn't say
+    pub estimated: bool,
+    // In the currency llm.pricing is given in
+    pub cost: f64,
+}
+
+/// Send every finished chat request to `sink`.
+pub fn set_sink(sink: mpsc::UnboundedSender<UsageRecord>) {
+    *SINK.lock().unwrap() = Some(sink);
+}
+
+// OpenAI's vocabularies, by model family; other providers' models use their own
+fn bpe_for(config: &LlmConfig) -> Option<&'static CoreBPE> {
+    if config.provider != "openai" {
+        return None;
+    }
+    let model = config.model.to_lowercase();
+    if ["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"].iter().any(|family| model.starts_with(family)) {
+        O200K.as_ref()
+    } else {
+        CL100K.as_ref()
+    }
+}
+
+/// Tokens `text` takes up for the configured model: exact for OpenAI's models, estimated otherwise.
+pub fn count_tokens(config: &LlmConfig, text: &str) -> usize {
+    match bpe_for(config) {
+        Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+        None => text.chars().count().div_ceil(CHARS_PER_TOKEN),
+    }
+}
+
+/// Tokens `messages` take up in a request, tool calls included.
+pub fn count_message_tokens(config: &LlmConfig, messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|message| {
+            let calls: usize = message
+                .tool_calls
+                .iter()
+                .map(|call| count_tokens(config, &call.name) + count_tokens(config, &call.arguments.to_string()))
+                .sum();
+            MESSAGE_OVERHEAD_TOKENS + count_tokens(config, &message.content) + calls
+        })
+        .sum()
+}
+
+/// Report a finished request. `reported` is the usage the server sent, if it sent any; without it
+/// the request and reply are counted here.
+pub(super) fn record(config: &LlmConfig, messages: &[ChatMessage], reply: &str, reported: Option<TokenUsage>) {
+    let Some(sink) = SINK.lock().unwrap().clone() else {
+        return;
+    };
+    let estimated = reported.is_none();
+    let usage = reported.unwrap_or_else(|| TokenUsage {
+        prompt_tokens: count_message_tokens(config, &super::with_system_prompt(config, messages)) as u32,
+        completion_tokens: count_tokens(config, reply) as u32,
+    });
+    let cost = usage.prompt_tokens as f64 * config.pricing.input_per_million / 1_000_000.0
+        + usage.completion_tokens as f64 * config.pricing.output_per_million / 1_000_000.0;
+    let _ = sink.send(UsageRecord {
+        provider: config.provider.clone(),
+        model: config.model.clone(),
+        usage,
+        estimated,
+        cost,
+    });
+}
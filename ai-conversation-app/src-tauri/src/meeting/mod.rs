@@ -0,0 +1,402 @@
+use crate::config::{AppConfig, LlmConfig};
+use crate::llm::{self, ChatMessage};
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeetingSource {
+    /// The local microphone
+    Me,
+    /// System loopback: everyone else on the call
+    Others,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptEntry {
+    pub source: MeetingSource,
+    pub speaker: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MeetingTranscript {
+    pub entries: Vec<TranscriptEntry>,
+    pub transcript: String,
+    pub summary: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Samples captured from one source since the last transcription pass
+struct SourceBuffer {
+    source: MeetingSource,
+    samples: Vec<f32>,
+    sample_rate: u32,
+    // Offset of the first buffered sample from the start of the meeting
+    start_ms: u64,
+    // Speaker turns seen so far, used to number the "others" speakers
+    speaker_index: usize,
+}
+
+pub struct MeetingRecorder {
+    running: Arc<AtomicBool>,
+    entries: Arc<Mutex<Vec<TranscriptEntry>>>,
+    started_at: Instant,
+    capture_thread: Option<JoinHandle<()>>,
+    transcription_thread: Option<JoinHandle<()>>,
+}
+
+impl MeetingRecorder {
+    /// Start capturing mic and system loopback and transcribing them in chunks.
+    /// `on_entry` is called from the transcription thread for every new transcript line.
+    pub fn start(config: &AppConfig, on_entry: impl Fn(TranscriptEntry) + Send + 'static) -> Result<Self> {
+        crate::platform::configure_audio_session()?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        let started_at = Instant::now();
+
+        let buffers = Arc::new(Mutex::new(vec![
+            SourceBuffer { source: MeetingSource::Me, samples: Vec::new(), sample_rate: WHISPER_SAMPLE_RATE, start_ms: 0, speaker_index: 0 },
+            SourceBuffer { source: MeetingSource::Others, samples: Vec::new(), sample_rate: WHISPER_SAMPLE_RATE, start_ms: 0, speaker_index: 0 },
+        ]));
+
+        // cpal streams are not Send, so they live and die on their own thread
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<()>>();
+        let capture_running = running.clone();
+        let capture_buffers = buffers.clone();
+        let input_device = config.audio.input.device.clone();
+        let loopback_device = config.meeting.loopback_device.clone();
+        let capture_thread = std::thread::spawn(move || {
+            let streams = (|| -> Result<Vec<cpal::Stream>> {
+                let host = cpal::default_host();
+                let mic = find_input_device(&host, &input_device)?;
+                let loopback = find_loopback_device(&host, &loopback_device)?;
+                Ok(vec![
+                    build_capture_stream(&mic, false, 0, capture_buffers.clone(), started_at)?,
+                    build_capture_stream(&loopback, true, 1, capture_buffers.clone(), started_at)?,
+                ])
+            })();
+
+            match streams {
+                Ok(streams) => {
+                    let _ = ready_tx.send(Ok(()));
+                    while capture_running.load(Ordering::SeqCst) {
+                        std::thread::sleep(Duration::from_millis(50));
+                    }
+                    drop(streams);
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                }
+            }
+        });
+
+        ready_rx
+            .recv()
+            .context("Meeting capture thread exited unexpectedly")??;
+
+        let model_path = crate::models::resolve(&config.stt.model)?;
+        let whisper_ctx = WhisperContext::new_with_params(&model_path.to_string_lossy(), WhisperContextParameters::default())
+            .context("Failed to load Whisper model for meeting transcription")?;
+        let chunk = Duration::from_secs_f32(config.meeting.chunk_seconds.max(2.0));
+        let diarization = config.meeting.diarization;
+        let language = config.stt.language.clone();
+
+        let transcription_running = running.clone();
+        let transcription_entries = entries.clone();
+        let transcription_thread = std::thread::spawn(move || {
+            let mut last_pass = Instant::now();
+            loop {
+                let stopping = !transcription_running.load(Ordering::SeqCst);
+                if stopping || last_pass.elapsed() >= chunk {
+                    last_pass = Instant::now();
+                    let mut pending = {
+                        let mut buffers = buffers.lock().unwrap();
+                        buffers
+                            .iter_mut()
+                            .map(|buffer| {
+                                let samples = std::mem::take(&mut buffer.samples);
+                                let start_ms = buffer.start_ms;
+                                buffer.start_ms = started_at.elapsed().as_millis() as u64;
+                                (buffer.source, samples, buffer.sample_rate, start_ms, buffer.speaker_index)
+                            })
+                            .collect::<Vec<_>>()
+                    };
+
+                    for (source, samples, sample_rate, start_ms, speaker_index) in pending.drain(..) {
+                        if samples.len() < sample_rate as usize / 2 {
+                            continue;
+                        }
+                        let samples = resample_linear(&samples, sample_rate, WHISPER_SAMPLE_RATE);
+                        let diarize = diarization && source == MeetingSource::Others;
+                        match transcribe_chunk(&whisper_ctx, &samples, &language, diarize) {
+                            Ok(segments) => {
+                                let mut speaker = speaker_index;
+                                for segment in segments {
+                                    let entry = TranscriptEntry {
+                                        source,
+                                        speaker: speaker_label(source, speaker),
+                                        start_ms: start_ms + segment.start_ms,
+                                        end_ms: start_ms + segment.end_ms,
+                                        text: segment.text,
+                                    };
+                                    if segment.speaker_turn_next {
+                                        speaker += 1;
+                                    }
+                                    transcription_entries.lock().unwrap().push(entry.clone());
+                                    on_entry(entry);
+                                }
+                                let mut buffers = buffers.lock().unwrap();
+                                if let Some(buffer) = buffers.iter_mut().find(|b| b.source == source) {
+                                    buffer.speaker_index = speaker;
+                                }
+                            }
+                            Err(e) => log::error!("Meeting transcription failed: {}", e),
+                        }
+                    }
+
+                    if stopping {
+                        break;
+                    }
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        log::info!("Meeting transcription started");
+        Ok(Self {
+            running,
+            entries,
+            started_at,
+            capture_thread: Some(capture_thread),
+            transcription_thread: Some(transcription_thread),
+        })
+    }
+
+    /// Stop capture, transcribe whatever is still buffered and return the merged transcript.
+    pub fn stop(mut self) -> Vec<TranscriptEntry> {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.capture_thread.take() {
+            let _ = handle.join();
+        }
+        if let Some(handle) = self.transcription_thread.take() {
+            let _ = handle.join();
+        }
+
+        let mut entries = self.entries.lock().unwrap().clone();
+        entries.sort_by_key(|entry| entry.start_ms);
+        log::info!("Meeting transcription stopped with {} entries", entries.len());
+        entries
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.started_at.elapsed().as_millis() as u64
+    }
+}
+
+impl Drop for MeetingRecorder {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+fn speaker_label(source: MeetingSource, speaker_index: usize) -> String {
+    match source {
+        MeetingSource::Me => "Me".to_string(),
+        MeetingSource::Others => format!("Speaker {}", speaker_index + 1),
+    }
+}
+
+pub(crate) fn find_input_device(host: &cpal::Host, name: &str) -> Result<cpal::Device> {
+    if name == "default" {
+        return host.default_input_device().context("No default input device available");
+    }
+    host.input_devices()?
+        .find(|device| device.name().map(|n| n.contains(name)).unwrap_or(false))
+        .with_context(|| format!("Input device '{}' not found", name))
+}
+
+fn find_loopback_device(host: &cpal::Host, name: &str) -> Result<cpal::Device> {
+    if name != "default" {
+        return find_input_device(host, name);
+    }
+
+    // WASAPI can open an input stream on an output device, which captures what it plays
+    #[cfg(target_os = "windows")]
+    {
+        host.default_output_device().context("No default output device available for loopback")
+    }
+
+    // Mobile platforms don't expose other apps' audio
+    #[cfg(mobile)]
+    {
+        Err(crate::platform::desktop_only("System loopback capture"))
+    }
+
+    // Elsewhere loopback needs a monitor source (PulseAudio/PipeWire) or a virtual device (BlackHole)
+    #[cfg(all(desktop, not(target_os = "windows")))]
+    {
+        host.input_devices()?
+            .find(|device| {
+                device
+                    .name()
+                    .map(|n| {
+                        let n = n.to_lowercase();
+                        n.contains("monitor") || n.contains("blackhole") || n.contains("loopback")
+                    })
+                    .unwrap_or(false)
+            })
+            .context("No system loopback device found; set meeting.loopback_device to a monitor or virtual device")
+    }
+}
+
+fn build_capture_stream(
+    device: &cpal::Device,
+    loopback: bool,
+    buffer_index: usize,
+    buffers: Arc<Mutex<Vec<SourceBuffer>>>,
+    started_at: Instant,
+) -> Result<cpal::Stream> {
+    let supported = if loopback && cfg!(target_os = "windows") {
+        device.default_output_config()?
+    } else {
+        device.default_input_config()?
+    };
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate.0;
+
+    {
+        let mut buffers = buffers.lock().unwrap();
+        buffers[buffer_index].sample_rate = sample_rate;
+        buffers[buffer_index].start_ms = started_at.elapsed().as_millis() as u64;
+    }
+
+    let push = move |mono: Vec<f32>| {
+        if let Ok(mut buffers) = buffers.lock() {
+            buffers[buffer_index].samples.extend(mono);
+        }
+    };
+    let on_error = |err| log::error!("Meeting capture stream error: {}", err);
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| push(downmix(data, channels)),
+            on_error,
+            None,
+        )?,
+        cpal::SampleFormat::I16 => device.build_input_stream(
+            &config,
+            move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                let samples: Vec<f32> = data.iter().map(|&s| s as f32 / i16::MAX as f32).collect();
+                push(downmix(&samples, channels))
+            },
+            on_error,
+            None,
+        )?,
+        other => return Err(anyhow::anyhow!("Unsupported capture sample format: {:?}", other)),
+    };
+
+    stream.play()?;
+    Ok(stream)
+}
+
+pub(crate) fn downmix(data: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return data.to_vec();
+    }
+    data.chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// Linear-interpolation resampler; good enough for speech recognition input.
+pub fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = from_rate as f64 / to_rate as f64;
+    let output_len = (samples.len() as f64 / ratio) as usize;
+    (0..output_len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index];
+            let next = samples.get(index + 1).copied().unwrap_or(current);
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+struct TranscribedSegment {
+    start_ms: u64,
+    end_ms: u64,
+    text: String,
+    speaker_turn_next: bool,
+}
+
+fn transcribe_chunk(ctx: &WhisperContext, samples: &[f32], language: &str, diarize: bool) -> Result<Vec<TranscribedSegment>> {
+    let mut state = ctx.create_state().context("Failed to create Whisper state")?;
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_language(Some(language));
+    // tinydiarize marks speaker changes when a tdrz-capable model is loaded
+    params.set_tdrz_enable(diarize);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_special(false);
+    params.set_print_timestamps(false);
+
+    state.full(params, samples).context("Whisper inference failed")?;
+
+    let mut segments = Vec::new();
+    for i in 0..state.full_n_segments()? {
+        let text = state.full_get_segment_text_lossy(i)?.trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        // Whisper timestamps are in centiseconds
+        let start_ms = state.full_get_segment_t0(i)?.max(0) as u64 * 10;
+        let end_ms = state.full_get_segment_t1(i)?.max(0) as u64 * 10;
+        let speaker_turn_next = diarize && state.full_get_segment_speaker_turn_next(i);
+        segments.push(TranscribedSegment { start_ms, end_ms, text, speaker_turn_next });
+    }
+    Ok(segments)
+}
+
+fn format_timestamp(ms: u64) -> String {
+    let seconds = ms / 1000;
+    format!("{:02}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60)
+}
+
+pub fn format_transcript(entries: &[TranscriptEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| format!("[{}] {}: {}", format_timestamp(entry.start_ms), entry.speaker, entry.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub async fn summarize(config: &LlmConfig, transcript: &str) -> Result<String> {
+    let messages = [
+        ChatMessage::system(
+            "You summarize meeting transcripts. Lines from \"Me\" are the user; other speakers are remote participants. \
+             Produce a short summary, then bullet lists of decisions and action items with owners when known.",
+        ),
+        ChatMessage::user(transcript),
+    ];
+    llm::chat_completion(config, &messages, Some(0.3)).await
+}
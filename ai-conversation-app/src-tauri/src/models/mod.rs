@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const MODEL_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+// Where models were placed by hand before the manager existed, relative to the working directory
+const LEGACY_MODELS_DIR: &str = "models";
+
+/// A whisper.cpp model that can be downloaded.
+struct ModelSpec {
+    name: &'static str,
+    size_mb: u32,
+    english_only: bool,
+}
+
+const CATALOG: &[ModelSpec] = &[
+    ModelSpec { name: "tiny", size_mb: 75, english_only: false },
+    ModelSpec { name: "tiny.en", size_mb: 75, english_only: true },
+    ModelSpec { name: "base", size_mb: 142, english_only: false },
+    ModelSpec { name: "base.en", size_mb: 142, english_only: true },
+    ModelSpec { name: "small", size_mb: 466, english_only: false },
+    ModelSpec { name: "small.en", size_mb: 466, english_only: true },
+    ModelSpec { name: "medium", size_mb: 1500, english_only: false },
+    ModelSpec { name: "medium.en", size_mb: 1500, english_only: true },
+    ModelSpec { name: "large-v3-turbo", size_mb: 1620, english_only: false },
+    ModelSpec { name: "large-v3", size_mb: 3100, english_only: false },
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    // Approximate download size; None for models that aren't in the catalog
+    pub download_size_mb: Option<u32>,
+    pub english_only: bool,
+    pub installed: bool,
+    pub path: Option<String>,
+    pub size_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDownloadProgress {
+    pub model: String,
+    pub downloaded: u64,
+    // None when the server doesn't send a content length
+    pub total: Option<u64>,
+}
+
+static MODELS_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Keep downloaded models in `dir` (the app data directory). Without this, as in headless
+/// mode, only the legacy `models/` directory is used.
+pub fn init_models_dir(dir: PathBuf) {
+    if MODELS_DIR.set(dir).is_err() {
+        log::warn!("Models directory already initialized");
+    }
+}
+
+fn models_dir() -> PathBuf {
+    MODELS_DIR.get().cloned().unwrap_or_else(|| PathBuf::from(LEGACY_MODELS_DIR))
+}
+
+fn model_file(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.bin", name))
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    // Model names become file names and URL segments
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_') || name.starts_with('.') {
+        return Err(anyhow::anyhow!("Invalid model name: {}", name));
+    }
+    Ok(())
+}
+
+/// Where an installed model lives, preferring the managed directory over `models/`.
+pub fn resolve(name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+    [models_dir(), PathBuf::from(LEGACY_MODELS_DIR)]
+        .iter()
+        .map(|dir| model_file(dir, name))
+        .find(|path| path.is_file())
+        .with_context(|| format!("Whisper model {} is not installed; download it from the model settings", name))
+}
+
+/// Where a model file that isn't a Whisper model lives, e.g. "silero_vad.onnx".
+pub fn resolve_asset(file: &str) -> Result<PathBuf> {
+    validate_name(file)?;
+    [models_dir(), PathBuf::from(LEGACY_MODELS_DIR)]
+        .iter()
+        .map(|dir| dir.join(file))
+        .find(|path| path.is_file())
+        .with_context(|| format!("{} not found; put it in {}", file, models_dir().display()))
+}
+
+/// Where a Piper voice lives: `voice` is either a path to an `.onnx` file or a voice name
+/// such as "en_US-lessac-medium", looked up in the `piper` folder of the models directory.
+pub fn resolve_piper_voice(voice: &str) -> Result<PathBuf> {
+    if voice.ends_with(".onnx") {
+        let path = PathBuf::from(voice);
+        return if path.is_file() {
+            Ok(path)
+        } else {
+            Err(anyhow::anyhow!("Piper voice {} does not exist", path.display()))
+        };
+    }
+    validate_name(voice)?;
+    [models_dir(), PathBuf::from(LEGACY_MODELS_DIR)]
+        .iter()
+        .map(|dir| dir.join("piper").join(format!("{}.onnx", voice)))
+        .find(|path| path.is_file())
+        .with_context(|| format!("Piper voice {} is not installed; put {}.onnx and {}.onnx.json in {}", voice, voice, voice, models_dir().join("piper").display()))
+}
+
+/// Names of the Piper voices installed in the models directories.
+pub fn list_piper_voices() -> Vec<String> {
+    let mut voices = Vec::new();
+    for dir in [models_dir(), PathBuf::from(LEGACY_MODELS_DIR)] {
+        let Ok(entries) = std::fs::read_dir(dir.join("piper")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("onnx") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                if !voices.iter().any(|voice| voice == name) {
+                    voices.push(name.to_string());
+                }
+            }
+        }
+    }
+    voices.sort();
+    voices
+}
+
+/// Where a GGUF language model lives: `model` is either a path to a `.gguf` file or a name such
+/// as "llama-3.2-3b-instruct-q4_k_m", looked up in the `llm` folder of the models directory.
+#[cfg(feature = "llama-cpp")]
+pub fn resolve_gguf(model: &str) -> Result<PathBuf> {
+    if model.ends_with(".gguf") {
+        let path = PathBuf::from(model);
+        return if path.is_file() {
+            Ok(path)
+        } else {
+            Err(anyhow::anyhow!("Language model {} does not exist", path.display()))
+        };
+    }
+    validate_name(model)?;
+    [models_dir(), PathBuf::from(LEGACY_MODELS_DIR)]
+        .iter()
+        .map(|dir| dir.join("llm").join(format!("{}.gguf", model)))
+        .find(|path| path.is_file())
+        .with_context(|| format!("Language model {} is not installed; put {}.gguf in {}", model, model, models_dir().join("llm").display()))
+}
+
+/// Names of the GGUF language models installed in the models directories.
+#[cfg(feature = "llama-cpp")]
+pub fn list_gguf_models() -> Vec<String> {
+    let mut models = Vec::new();
+    for dir in [models_dir(), PathBuf::from(LEGACY_MODELS_DIR)] {
+        let Ok(entries) = std::fs::read_dir(dir.join("llm")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("gguf") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                if !models.iter().any(|model| model == name) {
+                    models.push(name.to_string());
+                }
+            }
+        }
+    }
+    models.sort();
+    models
+}
+
+/// Catalog models plus any other model files found on disk.
+pub fn list_models() -> Vec<ModelInfo> {
+    let mut models: Vec<ModelInfo> = CATALOG
+        .iter()
+        .map(|spec| model_info(spec.name, Some(spec.size_mb), spec.english_only))
+        .collect();
+
+    for dir in [models_dir(), PathBuf::from(LEGACY_MODELS_DIR)] {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("bin") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if !models.iter().any(|model| model.name == name) {
+                models.push(model_info(name, None, name.ends_with(".en")));
+            }
+        }
+    }
+    models
+}
+
+fn model_info(name: &str, download_size_mb: Option<u32>, english_only: bool) -> ModelInfo {
+    let path = resolve(name).ok();
+    ModelInfo {
+        name: name.to_string(),
+        download_size_mb,
+        english_only,
+        installed: path.is_some(),
+        size_bytes: path.as_ref().and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len()),
+        path: path.map(|p| p.display().to_string()),
+    }
+}
+
+/// Download a whisper.cpp model into the managed directory.
+pub async fn download(name: &str, on_progress: impl Fn(ModelDownloadProgress)) -> Result<PathBuf> {
+    validate_name(name)?;
+    if let Ok(path) = resolve(name) {
+        return Ok(path);
+    }
+    let dir = models_dir();
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create model directory {}", dir.display()))?;
+    let path = model_file(&dir, name);
+
+    let url = format!("{}/ggml-{}.bin", MODEL_BASE_URL, name);
+    let mut response = reqwest::Client::new()
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {}", url))?
+        .error_for_status()
+        .context("Model download returned an error")?;
+    let total = response.content_length();
+
+    // Write to a partial file so an interrupted download is never loaded as a model
+    let partial = path.with_extension("part");
+    let mut file = std::fs::File::create(&partial)
+        .with_context(|| format!("Failed to create {}", partial.display()))?;
+    let mut downloaded = 0u64;
+    while let Some(chunk) = response.chunk().await.context("Model download was interrupted")? {
+        file.write_all(&chunk).context("Failed to write model file")?;
+        downloaded += chunk.len() as u64;
+        on_progress(ModelDownloadProgress {
+            model: name.to_string(),
+            downloaded,
+            total,
+        });
+    }
+    file.flush().context("Failed to write model file")?;
+    drop(file);
+
+    if total.is_some_and(|total| total != downloaded) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(anyhow::anyhow!("Model download for {} was incomplete", name));
+    }
+    std::fs::rename(&partial, &path).context("Failed to finalize model file")?;
+    Ok(path)
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    let path = resolve(name)?;
+    std::fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))
+}
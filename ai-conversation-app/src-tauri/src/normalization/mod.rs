@@ -0,0 +1,201 @@
+use crate::i18n::{self, DateOrder, LocaleFormat};
+
+// Abbreviations expanded before sentence splitting so their periods don't end a sentence.
+// English only; other locales keep their abbreviations as written.
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("etc.", "et cetera"),
+    ("vs.", "versus"),
+    ("approx.", "approximately"),
+    ("Dr.", "Doctor"),
+    ("Mr.", "Mister"),
+    ("Mrs.", "Missus"),
+    ("Ms.", "Miz"),
+    ("Prof.", "Professor"),
+    ("St.", "Street"),
+    ("No.", "Number"),
+];
+
+// Symbol -> message key of its spoken form
+const SYMBOLS: &[(&str, &str)] = &[
+    ("&", "symbol-and"),
+    ("%", "symbol-percent"),
+    ("+", "symbol-plus"),
+    ("=", "symbol-equals"),
+    ("@", "symbol-at"),
+    ("~", "symbol-about"),
+];
+
+// Punctuation that may trail a date or number without being part of it
+const TRAILING_PUNCTUATION: &[char] = &[',', '.', ';', ':', '!', '?', ')'];
+
+/// Clean up text copied from documents or web pages so a TTS engine reads it naturally.
+///
+/// Dates and digit grouping are interpreted using the configured locale.
+pub fn normalize_for_speech(text: &str) -> String {
+    let format = i18n::locale_format();
+    let link = i18n::tr("speech-link");
+
+    // Re-join words hyphenated across line breaks ("exam-\nple")
+    let mut result = text.replace("-\r\n", "").replace("-\n", "");
+
+    result = result
+        .lines()
+        .map(strip_markdown_line)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    result = result
+        .split_whitespace()
+        .map(|word| {
+            if word.starts_with("http://") || word.starts_with("https://") || word.starts_with("www.") {
+                link.clone()
+            } else {
+                expand_date_or_number(word, format)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if format.language == "en" {
+        for (abbreviation, expansion) in ABBREVIATIONS {
+            result = replace_word(&result, abbreviation, expansion);
+        }
+    }
+    for (symbol, key) in SYMBOLS {
+        if result.contains(symbol) {
+            result = result.replace(symbol, &format!(" {} ", i18n::tr(key)));
+        }
+    }
+
+    result.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn expand_date_or_number(word: &str, format: &LocaleFormat) -> String {
+    let core = word.trim_end_matches(TRAILING_PUNCTUATION);
+    let suffix = &word[core.len()..];
+    if let Some(date) = spoken_date(core, format) {
+        return format!("{}{}", date, suffix);
+    }
+    if let Some(number) = strip_digit_grouping(core, format) {
+        return format!("{}{}", number, suffix);
+    }
+    word.to_string()
+}
+
+/// "03/04/2024" -> "March 4, 2024" (en-US) or "3 de abril de 2024" (es-ES). ISO dates are always year first.
+fn spoken_date(token: &str, format: &LocaleFormat) -> Option<String> {
+    let separator = token.chars().find(|c| matches!(c, '/' | '-' | '.'))?;
+    let parts: Vec<&str> = token.split(separator).collect();
+    if parts.len() != 3 || parts.iter().any(|p| p.is_empty() || !p.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+
+    let order = if parts[0].len() == 4 { DateOrder::YearMonthDay } else { format.date_order };
+    let (year, month, day) = match order {
+        DateOrder::YearMonthDay => (parts[0], parts[1], parts[2]),
+        DateOrder::MonthDayYear => (parts[2], parts[0], parts[1]),
+        DateOrder::DayMonthYear => (parts[2], parts[1], parts[0]),
+    };
+    let month: u32 = month.parse().ok()?;
+    let day: u32 = day.parse().ok()?;
+    // Two- or four-digit years only, so version numbers like 1.2.3 stay untouched
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || !matches!(year.len(), 2 | 4) {
+        return None;
+    }
+
+    Some(i18n::tr_with(
+        "spoken-date",
+        &[
+            ("month", i18n::tr(&format!("month-{}", month)).as_str()),
+            ("day", day.to_string().as_str()),
+            ("year", year),
+        ],
+    ))
+}
+
+/// "1,234,567.5" -> "1234567.5" in English, "1.234,5" -> "1234,5" in German, so group
+/// separators aren't read out as "comma" or "point".
+fn strip_digit_grouping(token: &str, format: &LocaleFormat) -> Option<String> {
+    if !token.contains(format.group_separator) {
+        return None;
+    }
+    let (integer, fraction) = match token.split_once(format.decimal_separator) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (token, None),
+    };
+    let groups: Vec<&str> = integer.split(format.group_separator).collect();
+    let valid_groups = groups.len() > 1
+        && (1..=3).contains(&groups[0].len())
+        && groups[1..].iter().all(|g| g.len() == 3)
+        && groups.iter().all(|g| g.chars().all(|c| c.is_ascii_digit()));
+    let valid_fraction = fraction.is_none_or(|f| !f.is_empty() && f.chars().all(|c| c.is_ascii_digit()));
+    if !valid_groups || !valid_fraction {
+        return None;
+    }
+
+    let mut number = groups.concat();
+    if let Some(fraction) = fraction {
+        number.push(format.decimal_separator);
+        number.push_str(fraction);
+    }
+    Some(number)
+}
+
+fn strip_markdown_line(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let without_marker = trimmed
+        .trim_start_matches('#')
+        .trim_start_matches("> ")
+        .trim_start_matches("- ")
+        .trim_start_matches("* ");
+    let mut cleaned = without_marker.replace("**", "").replace("__", "").replace('`', "");
+
+    // A heading or bullet without closing punctuation still deserves a pause
+    if without_marker.len() != trimmed.len() && !cleaned.trim_end().ends_with(['.', '!', '?', ':']) && !cleaned.trim().is_empty() {
+        cleaned.push('.');
+    }
+    cleaned
+}
+
+fn replace_word(text: &str, word: &str, replacement: &str) -> String {
+    text.split(' ')
+        .map(|token| {
+            if token.eq_ignore_ascii_case(word) {
+                replacement.to_string()
+            } else {
+                token.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Split normalized text into sentences, keeping the terminating punctuation.
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let chars: Vec<char> = text.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        current.push(c);
+        if matches!(c, '.' | '!' | '?' | '\n') {
+            let next = chars.get(i + 1).copied();
+            // Don't split decimals like "3.14"
+            if next.is_none() || next.is_some_and(char::is_whitespace) {
+                let sentence = current.trim().to_string();
+                if !sentence.is_empty() {
+                    sentences.push(sentence);
+                }
+                current.clear();
+            }
+        }
+    }
+
+    let rest = current.trim();
+    if !rest.is_empty() {
+        sentences.push(rest.to_string());
+    }
+    sentences
+}
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const MAX_WAKE_WORD_WORDS: usize = 4;
+
+/// Wizard steps, in the order they are presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    MicTest,
+    ModelDownload,
+    ApiKey,
+    Voice,
+    WakeWord,
+}
+
+impl OnboardingStep {
+    pub const ALL: [OnboardingStep; 5] = [
+        OnboardingStep::MicTest,
+        OnboardingStep::ModelDownload,
+        OnboardingStep::ApiKey,
+        OnboardingStep::Voice,
+        OnboardingStep::WakeWord,
+    ];
+}
+
+/// What the user picked along the way.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnboardingChoices {
+    pub input_device: Option<String>,
+    pub output_device: Option<String>,
+    pub stt_model: Option<String>,
+    pub voice: Option<String>,
+    pub wake_word: Option<String>,
+    // The key itself lives in the OS keyring, never in this file
+    pub api_key_stored: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnboardingProgress {
+    pub completed: bool,
+    pub completed_at: Option<u64>,
+    pub completed_steps: Vec<OnboardingStep>,
+    pub skipped_steps: Vec<OnboardingStep>,
+    // First step neither completed nor skipped; None once all are done
+    pub current_step: Option<OnboardingStep>,
+    pub choices: OnboardingChoices,
+}
+
+/// First-run wizard progress, persisted so the wizard only appears once.
+pub struct Onboarding {
+    progress: OnboardingProgress,
+    path: Option<PathBuf>,
+}
+
+impl Onboarding {
+    /// Load progress from `path`, starting at the first step if it's missing or unreadable.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let progress = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<OnboardingProgress>(&content).ok())
+            .unwrap_or_default();
+        let mut onboarding = Self {
+            progress,
+            path: Some(path),
+        };
+        onboarding.update_current_step();
+        onboarding
+    }
+
+    pub fn in_memory() -> Self {
+        let mut onboarding = Self {
+            progress: OnboardingProgress::default(),
+            path: None,
+        };
+        onboarding.update_current_step();
+        onboarding
+    }
+
+    pub fn progress(&self) -> &OnboardingProgress {
+        &self.progress
+    }
+
+    pub fn choices_mut(&mut self) -> &mut OnboardingChoices {
+        &mut self.progress.choices
+    }
+
+    pub fn complete_step(&mut self, step: OnboardingStep) {
+        self.progress.skipped_steps.retain(|s| *s != step);
+        if !self.progress.completed_steps.contains(&step) {
+            self.progress.completed_steps.push(step);
+        }
+        self.update_current_step();
+    }
+
+    pub fn skip_step(&mut self, step: OnboardingStep) {
+        if !self.progress.completed_steps.contains(&step) && !self.progress.skipped_steps.contains(&step) {
+            self.progress.skipped_steps.push(step);
+        }
+        self.update_current_step();
+    }
+
+    /// Mark the wizard done, skipping whatever steps are left.
+    pub fn finish(&mut self, now_ms: u64) {
+        for step in OnboardingStep::ALL {
+            self.skip_step(step);
+        }
+        self.progress.completed = true;
+        self.progress.completed_at = Some(now_ms);
+    }
+
+    /// Start over from the first step, e.g. when re-run from settings. Earlier choices are kept
+    /// as defaults for the new run.
+    pub fn restart(&mut self) {
+        self.progress.completed = false;
+        self.progress.completed_at = None;
+        self.progress.completed_steps.clear();
+        self.progress.skipped_steps.clear();
+        self.update_current_step();
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create data directory {}", parent.display()))?;
+        }
+        let content = serde_json::to_string_pretty(&self.progress)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write onboarding state {}", path.display()))
+    }
+
+    fn update_current_step(&mut self) {
+        self.progress.current_step = OnboardingStep::ALL.into_iter().find(|step| {
+            !self.progress.completed_steps.contains(step) && !self.progress.skipped_steps.contains(step)
+        });
+    }
+}
+
+/// Normalize a wake word choice: a short phrase of plain words.
+pub fn validate_wake_word(wake_word: &str) -> Result<String> {
+    let words: Vec<String> = wake_word
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.is_empty() {
+        return Err(anyhow::anyhow!("The wake word must not be empty"));
+    }
+    if words.len() > MAX_WAKE_WORD_WORDS {
+        return Err(anyhow::anyhow!("The wake word must be at most {} words", MAX_WAKE_WORD_WORDS));
+    }
+    Ok(words.join(" "))
+}
@@ -0,0 +1,84 @@
+use anyhow::Result;
+
+/// Error for features that only exist on desktop (global shortcuts, keystroke
+/// injection, system loopback capture, multi-window management).
+pub fn desktop_only(feature: &str) -> anyhow::Error {
+    anyhow::anyhow!("{} is only available on desktop platforms", feature)
+}
+
+/// Prepare the OS audio session for simultaneous capture and playback.
+///
+/// Desktop hosts need no setup. On iOS the shared AVAudioSession must be put
+/// into play-and-record mode before cpal can open the microphone; on Android
+/// cpal talks to AAudio through oboe and only needs the RECORD_AUDIO permission.
+pub fn configure_audio_session() -> Result<()> {
+    #[cfg(target_os = "ios")]
+    {
+        use objc2_avf_audio::{
+            AVAudioSession, AVAudioSessionCategoryOptions, AVAudioSessionCategoryPlayAndRecord,
+            AVAudioSessionModeVoiceChat,
+        };
+
+        unsafe {
+            let session = AVAudioSession::sharedInstance();
+            let category = AVAudioSessionCategoryPlayAndRecord
+                .ok_or_else(|| anyhow::anyhow!("AVAudioSessionCategoryPlayAndRecord unavailable"))?;
+            let mode = AVAudioSessionModeVoiceChat
+                .ok_or_else(|| anyhow::anyhow!("AVAudioSessionModeVoiceChat unavailable"))?;
+            // Voice chat mode enables the hardware echo canceller, so the assistant doesn't hear itself
+            session
+                .setCategory_mode_options_error(
+                    category,
+                    mode,
+                    AVAudioSessionCategoryOptions::DefaultToSpeaker | AVAudioSessionCategoryOptions::AllowBluetooth,
+                )
+                .map_err(|e| anyhow::anyhow!("Failed to set audio session category: {:?}", e))?;
+            session
+                .setActive_error(true)
+                .map_err(|e| anyhow::anyhow!("Failed to activate audio session: {:?}", e))?;
+        }
+        log::info!("iOS audio session configured for play-and-record");
+    }
+
+    #[cfg(target_os = "android")]
+    {
+        log::info!("Using AAudio backend for capture and playback");
+    }
+
+    Ok(())
+}
+
+// Keyring service name secrets are stored under
+#[cfg(desktop)]
+const KEYRING_SERVICE: &str = "ai-conversation-app";
+
+/// Store a secret in the OS keyring (Keychain, Credential Manager or Secret Service).
+#[cfg(desktop)]
+pub fn store_secret(name: &str, value: &str) -> Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .and_then(|entry| entry.set_password(value))
+        .map_err(|e| anyhow::anyhow!("Failed to store {} in the keyring: {}", name, e))
+}
+
+#[cfg(mobile)]
+pub fn store_secret(_name: &str, _value: &str) -> Result<()> {
+    Err(desktop_only("Storing secrets in the keyring"))
+}
+
+/// Read a secret from the OS keyring; None if it was never stored or the keyring is unavailable.
+#[cfg(desktop)]
+pub fn load_secret(name: &str) -> Option<String> {
+    match keyring::Entry::new(KEYRING_SERVICE, name).and_then(|entry| entry.get_password()) {
+        Ok(value) => Some(value),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            log::warn!("Failed to read {} from the keyring: {}", name, e);
+            None
+        }
+    }
+}
+
+#[cfg(mobile)]
+pub fn load_secret(_name: &str) -> Option<String> {
+    None
+}
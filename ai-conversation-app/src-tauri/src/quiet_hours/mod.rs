@@ -0,0 +1,529 @@
+use crate::config::{QuietHoursConfig, QuietSchedule};
+#[cfg(desktop)]
+use crate::focus::{FocusKind, FocusSuppression};
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use serde::Serialize;
+
+// Messages held back during quiet hours; the oldest are dropped beyond this
+const MAX_DEFERRED: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QuietReason {
+    // Do not disturb was switched on by hand
+    Manual,
+    Schedule { name: String },
+    Meeting { summary: String },
+    // A call, presentation or fullscreen app is in front
+    #[cfg(desktop)]
+    FocusApp { app: String, activity: FocusKind },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuietStatus {
+    pub active: bool,
+    pub reason: Option<QuietReason>,
+    // When the current quiet period ends, in ms since the epoch; None if open-ended
+    pub until: Option<u64>,
+    pub deferred: usize,
+}
+
+/// A busy event from the calendar feed, in local time.
+#[derive(Debug, Clone)]
+pub struct CalendarEvent {
+    pub summary: String,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    recurrence: Option<Recurrence>,
+}
+
+#[derive(Debug, Clone)]
+struct Recurrence {
+    weekly: bool,
+    interval: u32,
+    until: Option<NaiveDateTime>,
+    days: Vec<Weekday>,
+}
+
+/// Do-not-disturb state: a manual switch, recurring schedules and calendar meetings.
+#[derive(Default)]
+pub struct QuietHours {
+    // Some(None) means on until switched off
+    manual: Option<Option<NaiveDateTime>>,
+    events: Vec<CalendarEvent>,
+    #[cfg(desktop)]
+    focus: Option<FocusSuppression>,
+    deferred: Vec<String>,
+}
+
+impl QuietHours {
+    pub fn set_manual(&mut self, enabled: bool, minutes: Option<u32>) {
+        self.manual = enabled.then(|| minutes.map(|minutes| Local::now().naive_local() + Duration::minutes(minutes as i64)));
+    }
+
+    pub fn set_calendar(&mut self, events: Vec<CalendarEvent>) {
+        self.events = events;
+    }
+
+    #[cfg(desktop)]
+    pub fn set_focus(&mut self, focus: Option<FocusSuppression>) {
+        self.focus = focus;
+    }
+
+    pub fn status(&self, config: &QuietHoursConfig) -> QuietStatus {
+        let now = Local::now().naive_local();
+        let (reason, until) = match self.reason_at(config, now) {
+            Some((reason, until)) => (Some(reason), until),
+            None => (None, None),
+        };
+        QuietStatus {
+            active: reason.is_some(),
+            reason,
+            until: until.and_then(to_epoch_ms),
+            deferred: self.deferred.len(),
+        }
+    }
+
+    /// Hold a message back until quiet hours end.
+    pub fn defer(&mut self, message: String) {
+        if self.deferred.len() >= MAX_DEFERRED {
+            self.deferred.remove(0);
+        }
+        self.deferred.push(message);
+    }
+
+    pub fn take_deferred(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.deferred)
+    }
+
+    fn reason_at(&self, config: &QuietHoursConfig, now: NaiveDateTime) -> Option<(QuietReason, Option<NaiveDateTime>)> {
+        match self.manual {
+            Some(None) => return Some((QuietReason::Manual, None)),
+            Some(Some(until)) if now < until => return Some((QuietReason::Manual, Some(until))),
+            _ => {}
+        }
+        // Focus-app detection has its own switch, so it applies even without quiet hours
+        #[cfg(desktop)]
+        if let Some(focus) = &self.focus {
+            let reason = QuietReason::FocusApp {
+                app: focus.app.clone(),
+                activity: focus.kind,
+            };
+            return Some((reason, None));
+        }
+        if !config.enabled {
+            return None;
+        }
+        if let Some((event, until)) = self.events.iter().find_map(|event| event.occurrence_end(now).map(|end| (event, end))) {
+            return Some((QuietReason::Meeting { summary: event.summary.clone() }, Some(until)));
+        }
+        config.schedules.iter().find_map(|schedule| {
+            schedule_end(schedule, now).map(|until| (QuietReason::Schedule { name: schedule.name.clone() }, Some(until)))
+        })
+    }
+}
+
+/// When `schedule` stops being active, if it is active at `now`.
+fn schedule_end(schedule: &QuietSchedule, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let start = parse_clock(&schedule.start)?;
+    let end = parse_clock(&schedule.end)?;
+    let days: Vec<Weekday> = schedule.days.iter().filter_map(|day| day.trim().parse().ok()).collect();
+    let on_day = |date: NaiveDate| days.is_empty() || days.contains(&date.weekday());
+    let (today, time) = (now.date(), now.time());
+
+    if start <= end {
+        (on_day(today) && start <= time && time < end).then(|| today.and_time(end))
+    } else if time >= start && on_day(today) {
+        // Overnight: runs from `start` on a listed day into the next morning
+        Some(today.succ_opt()?.and_time(end))
+    } else if time < end && on_day(today.pred_opt()?) {
+        Some(today.and_time(end))
+    } else {
+        None
+    }
+}
+
+fn parse_clock(value: &str) -> Option<NaiveTime> {
+    match NaiveTime::parse_from_str(value.trim(), "%H:%M") {
+        Ok(time) => Some(time),
+        Err(_) => {
+            log::warn!("Invalid quiet hours time {:?}, expected HH:MM", value);
+            None
+        }
+    }
+}
+
+fn to_epoch_ms(time: NaiveDateTime) -> Option<u64> {
+    Local.from_local_datetime(&time).earliest().map(|time| time.timestamp_millis() as u64)
+}
+
+impl CalendarEvent {
+    /// End of the occurrence in progress at `now`, if any.
+    fn occurrence_end(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let duration = self.end - self.start;
+        let Some(rule) = &self.recurrence else {
+            return (self.start <= now && now < self.end).then_some(self.end);
+        };
+
+        // An occurrence in progress started today or, for long events, a few days back
+        (0..=duration.num_days() + 1)
+            .filter_map(|days_back| now.date().checked_sub_signed(Duration::days(days_back)))
+            .filter(|date| *date >= self.start.date() && rule.occurs_on(self.start.date(), *date))
+            .map(|date| date.and_time(self.start.time()))
+            .filter(|start| rule.until.is_none_or(|until| *start <= until))
+            .find(|start| *start <= now && now < *start + duration)
+            .map(|start| start + duration)
+    }
+}
+
+impl Recurrence {
+    fn occurs_on(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        let interval = self.interval.max(1) as i64;
+        if !self.weekly {
+            return (date - first).num_days() % interval == 0;
+        }
+        let week_start = |date: NaiveDate| date - Duration::days(date.weekday().num_days_from_monday() as i64);
+        let weeks = (week_start(date) - week_start(first)).num_days() / 7;
+        weeks % interval == 0 && self.days.contains(&date.weekday())
+    }
+}
+
+/// Download an ICS feed and keep the events that mark the user as busy.
+pub async fn fetch_calendar(url: &str) -> Result<Vec<CalendarEvent>> {
+    // Calendar apps hand out webcal:// links for subscriptions
+    let url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    let body = reqwest::Client::new()
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch calendar {}", url))?
+        .error_for_status()
+        .context("Calendar feed returned an error")?
+        .text()
+        .await
+        .context("Failed to read calendar feed")?;
+    Ok(parse_ics(&body))
+}
+
+/// Timed VEVENTs from an iCalendar document. All-day, cancelled and free events are skipped,
+/// and only daily and weekly recurrence rules are understood.
+pub fn parse_ics(content: &str) -> Vec<CalendarEvent> {
+    // Long lines are folded onto continuation lines that start with whitespace
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match line.strip_prefix([' ', '\t']) {
+            Some(continuation) if !lines.is_empty() => lines.last_mut().unwrap().push_str(continuation),
+            _ => lines.push(line.trim_end_matches('\r').to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut properties: Option<Vec<(String, String, String)>> = None;
+    for line in &lines {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = key.split_once(';').unwrap_or((key, ""));
+        match (name.to_uppercase().as_str(), value) {
+            ("BEGIN", "VEVENT") => properties = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                if let Some(event) = properties.take().and_then(|properties| event_from_properties(&properties)) {
+                    events.push(event);
+                }
+            }
+            (name, value) => {
+                if let Some(properties) = properties.as_mut() {
+                    properties.push((name.to_string(), params.to_uppercase(), value.to_string()));
+                }
+            }
+        }
+    }
+    events
+}
+
+fn event_from_properties(properties: &[(String, String, String)]) -> Option<CalendarEvent> {
+    let get = |name: &str| properties.iter().find(|(n, _, _)| n == name);
+    if get("STATUS").is_some_and(|(_, _, value)| value.eq_ignore_ascii_case("CANCELLED"))
+        || get("TRANSP").is_some_and(|(_, _, value)| value.eq_ignore_ascii_case("TRANSPARENT"))
+    {
+        return None;
+    }
+
+    let (_, start_params, start_value) = get("DTSTART")?;
+    // All-day events (holidays, out of office) shouldn't silence the whole day
+    if start_params.contains("VALUE=DATE") && !start_params.contains("VALUE=DATE-TIME") {
+        return None;
+    }
+    let start = parse_ics_time(start_value)?;
+    let end = match get("DTEND") {
+        Some((_, _, value)) => parse_ics_time(value)?,
+        None => start + get("DURATION").and_then(|(_, _, value)| parse_ics_duration(value))?,
+    };
+    if end <= start {
+        return None;
+    }
+
+    let summary = get("SUMMARY").map(|(_, _, value)| unescape_ics(value)).unwrap_or_default();
+    let recurrence = match get("RRULE") {
+        Some((_, _, rule)) => Some(parse_rrule(rule, start)?),
+        None => None,
+    };
+    Some(CalendarEvent {
+        summary,
+        start,
+        end,
+        recurrence,
+    })
+}
+
+/// `20240105T093000Z` (UTC) or `20240105T093000` (floating or TZID). There is no timezone
+/// database here, so TZID times are taken as local time.
+fn parse_ics_time(value: &str) -> Option<NaiveDateTime> {
+    match value.strip_suffix('Z') {
+        Some(utc) => {
+            let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            Some(Utc.from_utc_datetime(&time).with_timezone(&Local).naive_local())
+        }
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok(),
+    }
+}
+
+/// `PT1H30M`, `P1D` and the like.
+fn parse_ics_duration(value: &str) -> Option<Duration> {
+    let value = value.strip_prefix('+').unwrap_or(value).strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            'T' => {}
+            '0'..='9' => number.push(c),
+            unit => {
+                let amount: i64 = number.parse().ok()?;
+                number.clear();
+                total += match unit {
+                    'W' => Duration::weeks(amount),
+                    'D' => Duration::days(amount),
+                    'H' => Duration::hours(amount),
+                    'M' => Duration::minutes(amount),
+                    'S' => Duration::seconds(amount),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(total)
+}
+
+/// Daily and weekly rules with INTERVAL, BYDAY, UNTIL and COUNT; None for anything else.
+fn parse_rrule(rule: &str, start: NaiveDateTime) -> Option<Recurrence> {
+    let parts: Vec<(&str, &str)> = rule.split(';').filter_map(|part| part.split_once('=')).collect();
+    let get = |name: &str| parts.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| *value);
+
+    let weekly = match get("FREQ")?.to_uppercase().as_str() {
+        "DAILY" => false,
+        "WEEKLY" => true,
+        other => {
+            log::debug!("Ignoring calendar event with unsupported recurrence {}", other);
+            return None;
+        }
+    };
+    let interval = get("INTERVAL").and_then(|value| value.parse().ok()).unwrap_or(1u32).max(1);
+    let mut days: Vec<Weekday> = get("BYDAY")
+        .map(|days| days.split(',').filter_map(parse_ics_weekday).collect())
+        .unwrap_or_default();
+    if days.is_empty() {
+        days.push(start.weekday());
+    }
+
+    let until = match (get("UNTIL"), get("COUNT").and_then(|count| count.parse::<i64>().ok())) {
+        (Some(until), _) => parse_ics_time(until)
+            .or_else(|| NaiveDate::parse_from_str(until, "%Y%m%d").ok().and_then(|date| date.and_hms_opt(23, 59, 59))),
+        (None, Some(count)) if weekly => {
+            // Close enough for BYDAY rules: the week the last occurrence falls in
+            let weeks = (count.max(1) - 1) / days.len() as i64 * interval as i64;
+            Some(start + Duration::weeks(weeks) + Duration::days(6))
+        }
+        (None, Some(count)) => Some(start + Duration::days((count.max(1) - 1) * interval as i64)),
+        (None, None) => None,
+    };
+
+    Some(Recurrence {
+        weekly,
+        interval,
+        until,
+        days,
+    })
+}
+
+fn parse_ics_weekday(day: &str) -> Option<Weekday> {
+    // BYDAY entries may carry an ordinal, as in 1MO or -1FR
+    match day.trim().trim_start_matches(|c: char| c == '-' || c == '+' || c.is_ascii_digit()) {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn unescape_ics(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn schedule(days: &[&str], start: &str, end: &str) -> QuietSchedule {
+        QuietSchedule {
+            name: "test".to_string(),
+            days: days.iter().map(|day| day.to_string()).collect(),
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    fn calendar(events: &str) -> String {
+        format!("BEGIN:VCALENDAR\r\n{}END:VCALENDAR\r\n", events)
+    }
+
+    #[test]
+    fn daytime_schedule_only_on_listed_days() {
+        // 2024-01-08 is a Monday
+        let lunch = schedule(&["mon"], "12:00", "13:00");
+        assert_eq!(schedule_end(&lunch, at("2024-01-08 12:30")), Some(at("2024-01-08 13:00")));
+        assert_eq!(schedule_end(&lunch, at("2024-01-08 13:00")), None);
+        assert_eq!(schedule_end(&lunch, at("2024-01-09 12:30")), None);
+    }
+
+    #[test]
+    fn overnight_schedule_runs_into_the_next_morning() {
+        let sleep = schedule(&["fri"], "22:00", "07:00");
+        // Friday night and the Saturday morning after it
+        assert_eq!(schedule_end(&sleep, at("2024-01-12 23:00")), Some(at("2024-01-13 07:00")));
+        assert_eq!(schedule_end(&sleep, at("2024-01-13 06:00")), Some(at("2024-01-13 07:00")));
+        // Friday morning belongs to Thursday night, which isn't listed
+        assert_eq!(schedule_end(&sleep, at("2024-01-12 06:00")), None);
+    }
+
+    #[test]
+    fn invalid_clock_times_never_match() {
+        assert_eq!(schedule_end(&schedule(&[], "10pm", "07:00"), at("2024-01-12 23:00")), None);
+    }
+
+    #[test]
+    fn parses_timed_events_and_unfolds_lines() {
+        let ics = calendar(concat!(
+            "BEGIN:VEVENT\r\n",
+            "SUMMARY:Design review\\, round\r\n",
+            "  two\r\n",
+            "DTSTART;TZID=Europe/Berlin:20240108T100000\r\n",
+            "DURATION:PT1H30M\r\n",
+            "END:VEVENT\r\n",
+        ));
+        let events = parse_ics(&ics);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Design review, round two");
+        assert_eq!(events[0].start, at("2024-01-08 10:00"));
+        assert_eq!(events[0].end, at("2024-01-08 11:30"));
+        assert!(events[0].recurrence.is_none());
+    }
+
+    #[test]
+    fn skips_cancelled_free_and_all_day_events() {
+        let ics = calendar(concat!(
+            "BEGIN:VEVENT\r\nSTATUS:CANCELLED\r\nDTSTART:20240108T100000\r\nDTEND:20240108T110000\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nTRANSP:TRANSPARENT\r\nDTSTART:20240108T100000\r\nDTEND:20240108T110000\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20240108\r\nDTEND;VALUE=DATE:20240109\r\nEND:VEVENT\r\n",
+            "BEGIN:VEVENT\r\nDTSTART:20240108T100000\r\nDTEND:20240108T090000\r\nEND:VEVENT\r\n",
+        ));
+        assert!(parse_ics(&ics).is_empty());
+    }
+
+    #[test]
+    fn weekly_recurrence_respects_interval_days_and_until() {
+        let ics = calendar(concat!(
+            "BEGIN:VEVENT\r\n",
+            "SUMMARY:Standup\r\n",
+            "DTSTART:20240108T093000\r\n",
+            "DTEND:20240108T094500\r\n",
+            "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;UNTIL=20240131\r\n",
+            "END:VEVENT\r\n",
+        ));
+        let events = parse_ics(&ics);
+        let standup = &events[0];
+        assert_eq!(standup.occurrence_end(at("2024-01-10 09:40")), Some(at("2024-01-10 09:45")));
+        assert_eq!(standup.occurrence_end(at("2024-01-22 09:40")), Some(at("2024-01-22 09:45")));
+        // Off weeks, other days, outside the slot and after UNTIL
+        assert_eq!(standup.occurrence_end(at("2024-01-15 09:40")), None);
+        assert_eq!(standup.occurrence_end(at("2024-01-09 09:40")), None);
+        assert_eq!(standup.occurrence_end(at("2024-01-08 09:50")), None);
+        assert_eq!(standup.occurrence_end(at("2024-02-05 09:40")), None);
+    }
+
+    #[test]
+    fn unsupported_recurrence_drops_the_event() {
+        let ics = calendar("BEGIN:VEVENT\r\nDTSTART:20240108T093000\r\nDTEND:20240108T100000\r\nRRULE:FREQ=MONTHLY\r\nEND:VEVENT\r\n");
+        assert!(parse_ics(&ics).is_empty());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_ics_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_ics_duration("P1DT2H"), Some(Duration::hours(26)));
+        assert_eq!(parse_ics_duration("P1W"), Some(Duration::weeks(1)));
+        assert_eq!(parse_ics_duration("1H"), None);
+    }
+
+    #[test]
+    fn meetings_take_priority_over_schedules_and_manual_over_both() {
+        let config = QuietHoursConfig {
+            schedules: vec![schedule(&[], "09:00", "17:00")],
+            ..QuietHoursConfig::default()
+        };
+        let mut quiet = QuietHours::default();
+        quiet.set_calendar(parse_ics(&calendar(
+            "BEGIN:VEVENT\r\nSUMMARY:Planning\r\nDTSTART:20240108T100000\r\nDTEND:20240108T110000\r\nEND:VEVENT\r\n",
+        )));
+
+        let reason = |quiet: &QuietHours, now| quiet.reason_at(&config, at(now)).map(|(reason, _)| reason);
+        assert_eq!(reason(&quiet, "2024-01-08 09:30"), Some(QuietReason::Schedule { name: "test".to_string() }));
+        assert_eq!(reason(&quiet, "2024-01-08 10:30"), Some(QuietReason::Meeting { summary: "Planning".to_string() }));
+        assert_eq!(reason(&quiet, "2024-01-08 18:00"), None);
+
+        quiet.set_manual(true, None);
+        assert_eq!(reason(&quiet, "2024-01-08 10:30"), Some(QuietReason::Manual));
+        quiet.set_manual(false, None);
+
+        let disabled = QuietHoursConfig { enabled: false, ..config.clone() };
+        assert_eq!(quiet.reason_at(&disabled, at("2024-01-08 10:30")), None);
+    }
+
+    #[test]
+    fn deferred_messages_are_capped() {
+        let mut quiet = QuietHours::default();
+        for i in 0..MAX_DEFERRED + 2 {
+            quiet.defer(format!("message {}", i));
+        }
+        let deferred = quiet.take_deferred();
+        assert_eq!(deferred.len(), MAX_DEFERRED);
+        assert_eq!(deferred[0], "message 2");
+        assert!(quiet.take_deferred().is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::normalization;
+use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadAloudStatus {
+    Stopped,
+    Playing,
+    Paused,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadAloudProgress {
+    pub status: ReadAloudStatus,
+    pub sentence_index: usize,
+    pub sentence_count: usize,
+    pub sentence: Option<String>,
+    pub speed: f32,
+}
+
+pub struct ReadAloudSession {
+    pub status: ReadAloudStatus,
+    pub sentences: Vec<String>,
+    pub position: usize,
+    pub speed: f32,
+    // Bumped on every new reading so a superseded playback task stops itself
+    pub generation: u64,
+}
+
+impl ReadAloudSession {
+    pub fn new() -> Self {
+        Self {
+            status: ReadAloudStatus::Stopped,
+            sentences: Vec::new(),
+            position: 0,
+            speed: 1.0,
+            generation: 0,
+        }
+    }
+
+    pub fn load(&mut self, text: &str) -> u64 {
+        let normalized = normalization::normalize_for_speech(text);
+        self.sentences = normalization::split_sentences(&normalized);
+        self.position = 0;
+        self.status = ReadAloudStatus::Playing;
+        self.generation += 1;
+        self.generation
+    }
+
+    pub fn stop(&mut self) {
+        self.status = ReadAloudStatus::Stopped;
+        self.sentences.clear();
+        self.position = 0;
+        self.generation += 1;
+    }
+
+    pub fn progress(&self) -> ReadAloudProgress {
+        ReadAloudProgress {
+            status: self.status,
+            sentence_index: self.position,
+            sentence_count: self.sentences.len(),
+            sentence: self.sentences.get(self.position).cloned(),
+            speed: self.speed,
+        }
+    }
+}
+
+impl Default for ReadAloudSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Grab the currently selected text, falling back to the clipboard contents.
+#[cfg(desktop)]
+pub fn grab_selection() -> Result<String> {
+    use anyhow::Context;
+    use arboard::Clipboard;
+
+    let mut clipboard = Clipboard::new().context("Failed to access clipboard")?;
+
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+        // X11/Wayland keep the current selection in the PRIMARY buffer
+        if let Ok(selection) = clipboard.get().clipboard(LinuxClipboardKind::Primary).text() {
+            if !selection.trim().is_empty() {
+                return Ok(selection);
+            }
+        }
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    {
+        if let Some(selection) = copy_selection(&mut clipboard) {
+            return Ok(selection);
+        }
+    }
+
+    let text = clipboard.get_text().context("No selected text or clipboard text available")?;
+    if text.trim().is_empty() {
+        return Err(anyhow::anyhow!("No selected text or clipboard text available"));
+    }
+    Ok(text)
+}
+
+#[cfg(mobile)]
+pub fn grab_selection() -> Result<String> {
+    Err(crate::platform::desktop_only("Reading the current selection"))
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn copy_selection(clipboard: &mut arboard::Clipboard) -> Option<String> {
+    use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+    use std::time::Duration;
+
+    let previous = clipboard.get_text().ok();
+    let mut enigo = Enigo::new(&Settings::default()).ok()?;
+
+    #[cfg(target_os = "macos")]
+    let modifier = Key::Meta;
+    #[cfg(target_os = "windows")]
+    let modifier = Key::Control;
+
+    // Give the user time to release the shortcut keys before simulating the copy
+    std::thread::sleep(Duration::from_millis(150));
+    enigo.key(modifier, Direction::Press).ok()?;
+    let copied = enigo.key(Key::Unicode('c'), Direction::Click);
+    enigo.key(modifier, Direction::Release).ok()?;
+    copied.ok()?;
+    std::thread::sleep(Duration::from_millis(150));
+
+    let selection = clipboard.get_text().ok()?;
+    // Put the user's clipboard back the way we found it
+    if let Some(previous) = previous {
+        let _ = clipboard.set_text(previous);
+    }
+    (!selection.trim().is_empty()).then_some(selection)
+}
@@ -0,0 +1,261 @@
+use crate::config::WebSearchConfig;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Search backends, by the name `llm.tools.web_search.backend` takes.
+pub const BACKENDS: &[&str] = &["duckduckgo", "searxng", "brave"];
+
+// Snippets are cut to this many characters; the model needs the gist, not the page
+const MAX_SNIPPET_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    /// Up to `count` results for `query`, best first.
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<SearchResult>>;
+}
+
+/// The backend `config` names.
+pub fn backend_for(config: &WebSearchConfig) -> Result<Box<dyn SearchBackend>> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .user_agent(concat!("ai-conversation-app/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    match config.backend.trim().to_lowercase().as_str() {
+        "duckduckgo" => Ok(Box::new(DuckDuckGo { client })),
+        "searxng" => {
+            let base_url = config.base_url.trim().trim_end_matches('/').to_string();
+            if base_url.is_empty() {
+                return Err(anyhow::anyhow!("SearxNG needs llm.tools.web_search.base_url"));
+            }
+            Ok(Box::new(SearxNg { client, base_url }))
+        }
+        "brave" => {
+            let api_key = config
+                .resolved_api_key()
+                .context("Brave Search needs an API key in llm.tools.web_search.api_key, the keyring or BRAVE_SEARCH_API_KEY")?;
+            Ok(Box::new(Brave { client, api_key }))
+        }
+        other => Err(anyhow::anyhow!("Unknown search backend {}; use one of {}", other, BACKENDS.join(", "))),
+    }
+}
+
+#[derive(Deserialize)]
+struct SearxNgResponse {
+    #[serde(default)]
+    results: Vec<SearxNgResult>,
+}
+
+#[derive(Deserialize)]
+struct SearxNgResult {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    content: String,
+}
+
+/// A SearxNG instance, through its JSON API; the instance must have the json format enabled.
+pub struct SearxNg {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[async_trait]
+impl SearchBackend for SearxNg {
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<SearchResult>> {
+        let response: SearxNgResponse = self
+            .client
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query), ("format", "json")])
+            .send()
+            .await
+            .context("Failed to reach SearxNG")?
+            .error_for_status()
+            .context("SearxNG search failed; is its json format enabled?")?
+            .json()
+            .await
+            .context("Failed to parse SearxNG results")?;
+        Ok(response
+            .results
+            .into_iter()
+            .take(count)
+            .map(|result| SearchResult {
+                title: clean_snippet(&result.title),
+                url: result.url,
+                snippet: clean_snippet(&result.content),
+            })
+            .collect())
+    }
+}
+
+#[derive(Deserialize)]
+struct BraveResponse {
+    #[serde(default)]
+    web: Option<BraveWeb>,
+}
+
+#[derive(Deserialize)]
+struct BraveWeb {
+    #[serde(default)]
+    results: Vec<BraveResult>,
+}
+
+#[derive(Deserialize)]
+struct BraveResult {
+    #[serde(default)]
+    title: String,
+    url: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// The Brave Search API.
+pub struct Brave {
+    client: reqwest::Client,
+    api_key: String,
+}
+
+#[async_trait]
+impl SearchBackend for Brave {
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<SearchResult>> {
+        let response: BraveResponse = self
+            .client
+            .get("https://api.search.brave.com/res/v1/web/search")
+            .query(&[("q", query), ("count", &count.clamp(1, 20).to_string())])
+            .header("X-Subscription-Token", &self.api_key)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .context("Failed to reach Brave Search")?
+            .error_for_status()
+            .context("Brave Search failed")?
+            .json()
+            .await
+            .context("Failed to parse Brave Search results")?;
+        Ok(response
+            .web
+            .map(|web| web.results)
+            .unwrap_or_default()
+            .into_iter()
+            .take(count)
+            .map(|result| SearchResult {
+                title: clean_snippet(&result.title),
+                url: result.url,
+                snippet: clean_snippet(&result.description),
+            })
+            .collect())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DuckDuckGoResponse {
+    #[serde(default)]
+    heading: String,
+    #[serde(default)]
+    abstract_text: String,
+    #[serde(rename = "AbstractURL", default)]
+    abstract_url: String,
+    #[serde(default)]
+    related_topics: Vec<DuckDuckGoTopic>,
+}
+
+// Either a topic or a named group of them
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DuckDuckGoTopic {
+    #[serde(default)]
+    text: String,
+    #[serde(rename = "FirstURL", default)]
+    first_url: String,
+    #[serde(default)]
+    topics: Vec<DuckDuckGoTopic>,
+}
+
+/// DuckDuckGo's Instant Answer API: no key needed, but it answers with encyclopedia-style
+/// abstracts and related topics rather than a full list of web results.
+pub struct DuckDuckGo {
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl SearchBackend for DuckDuckGo {
+    async fn search(&self, query: &str, count: usize) -> Result<Vec<SearchResult>> {
+        let response: DuckDuckGoResponse = self
+            .client
+            .get("https://api.duckduckgo.com/")
+            .query(&[("q", query), ("format", "json"), ("no_html", "1"), ("skip_disambig", "1")])
+            .send()
+            .await
+            .context("Failed to reach DuckDuckGo")?
+            .error_for_status()
+            .context("DuckDuckGo search failed")?
+            .json()
+            .await
+            .context("Failed to parse DuckDuckGo results")?;
+
+        let mut results = Vec::new();
+        if !response.abstract_text.is_empty() {
+            results.push(SearchResult {
+                title: response.heading.clone(),
+                url: response.abstract_url.clone(),
+                snippet: clean_snippet(&response.abstract_text),
+            });
+        }
+        let topics = response
+            .related_topics
+            .iter()
+            .flat_map(|topic| if topic.topics.is_empty() { std::slice::from_ref(topic) } else { &topic.topics[..] });
+        for topic in topics {
+            if topic.text.is_empty() || topic.first_url.is_empty() {
+                continue;
+            }
+            // A topic's text starts with its title, then " - " and the description
+            let (title, snippet) = topic.text.split_once(" - ").unwrap_or((&topic.text, ""));
+            results.push(SearchResult {
+                title: title.to_string(),
+                url: topic.first_url.clone(),
+                snippet: clean_snippet(snippet),
+            });
+        }
+        results.truncate(count);
+        Ok(results)
+    }
+}
+
+/// `text` without HTML tags or common entities, whitespace collapsed, cut at a word boundary
+/// to at most `MAX_SNIPPET_CHARS` characters.
+pub fn clean_snippet(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+    let plain = plain
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ");
+    let plain = plain.split_whitespace().collect::<Vec<_>>().join(" ");
+    if plain.chars().count() <= MAX_SNIPPET_CHARS {
+        return plain;
+    }
+    let cut: String = plain.chars().take(MAX_SNIPPET_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(head, _)| head);
+    format!("{}…", cut.trim_end_matches([',', ';', ':', '.']))
+}
@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SCHEMA_VERSION: u32 = 1;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+// Upper bounds of the latency buckets in milliseconds; anything slower lands in "slow"
+const TIMING_BUCKETS: [(u64, &str); 6] = [
+    (100, "<100ms"),
+    (250, "<250ms"),
+    (500, "<500ms"),
+    (1000, "<1s"),
+    (2000, "<2s"),
+    (5000, "<5s"),
+];
+
+/// Exactly what an upload contains: counts only, with no identifiers, text or timestamps finer than a day.
+///
+/// Keys are `&'static str` at every recording site so user content can never end up in a batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryBatch {
+    pub schema_version: u32,
+    pub app_version: String,
+    // Operating system family only, e.g. "windows"
+    pub platform: String,
+    // Start of the UTC day collection began, in milliseconds
+    pub collected_since: u64,
+    pub features: BTreeMap<String, u64>,
+    pub errors: BTreeMap<String, u64>,
+    // Metric -> latency bucket -> count
+    pub performance: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl TelemetryBatch {
+    fn new(app_version: &str) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            app_version: app_version.to_string(),
+            platform: std::env::consts::OS.to_string(),
+            collected_since: today_ms(),
+            features: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            performance: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty() && self.errors.is_empty() && self.performance.is_empty()
+    }
+
+    /// Add the counts of `other`, e.g. a batch that failed to upload, to this one.
+    fn merge(&mut self, other: TelemetryBatch) {
+        self.collected_since = self.collected_since.min(other.collected_since);
+        for (name, count) in other.features {
+            *self.features.entry(name).or_default() += count;
+        }
+        for (category, count) in other.errors {
+            *self.errors.entry(category).or_default() += count;
+        }
+        for (metric, buckets) in other.performance {
+            let merged = self.performance.entry(metric).or_default();
+            for (bucket, count) in buckets {
+                *merged.entry(bucket).or_default() += count;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryPreview {
+    // Counters are only collected while telemetry is enabled
+    pub enabled: bool,
+    // False in offline mode, even when telemetry is enabled
+    pub uploads: bool,
+    pub endpoint: String,
+    pub batch: TelemetryBatch,
+}
+
+/// Local batch of usage counters, persisted between runs until it is uploaded.
+pub struct TelemetryRecorder {
+    batch: TelemetryBatch,
+    path: Option<PathBuf>,
+    dirty: bool,
+}
+
+impl TelemetryRecorder {
+    /// Load the pending batch from `path`, starting fresh if it's missing, unreadable or from another version.
+    pub fn load(path: impl AsRef<Path>, app_version: &str) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let batch = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<TelemetryBatch>(&content).ok())
+            .filter(|batch| batch.schema_version == SCHEMA_VERSION && batch.app_version == app_version)
+            .unwrap_or_else(|| TelemetryBatch::new(app_version));
+        Self {
+            batch,
+            path: Some(path),
+            dirty: false,
+        }
+    }
+
+    pub fn in_memory(app_version: &str) -> Self {
+        Self {
+            batch: TelemetryBatch::new(app_version),
+            path: None,
+            dirty: false,
+        }
+    }
+
+    pub fn feature(&mut self, name: &'static str) {
+        *self.batch.features.entry(name.to_string()).or_default() += 1;
+        self.dirty = true;
+    }
+
+    pub fn error(&mut self, category: &'static str) {
+        *self.batch.errors.entry(category.to_string()).or_default() += 1;
+        self.dirty = true;
+    }
+
+    pub fn timing(&mut self, metric: &'static str, elapsed: Duration) {
+        *self
+            .batch
+            .performance
+            .entry(metric.to_string())
+            .or_default()
+            .entry(timing_bucket(elapsed).to_string())
+            .or_default() += 1;
+        self.dirty = true;
+    }
+
+    pub fn batch(&self) -> &TelemetryBatch {
+        &self.batch
+    }
+
+    /// Hand over everything recorded so far for uploading and start a new batch. Counts
+    /// recorded while the upload runs go into the new one.
+    pub fn take_batch(&mut self) -> TelemetryBatch {
+        self.dirty = true;
+        let fresh = TelemetryBatch::new(&self.batch.app_version);
+        std::mem::replace(&mut self.batch, fresh)
+    }
+
+    /// Put back a batch from `take_batch` that couldn't be uploaded, so it's tried again.
+    pub fn restore_batch(&mut self, batch: TelemetryBatch) {
+        self.batch.merge(batch);
+        self.dirty = true;
+    }
+
+    /// Write the batch to disk if anything changed since the last save.
+    pub fn save(&mut self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create data directory {}", parent.display()))?;
+        }
+        let content = serde_json::to_string_pretty(&self.batch)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write telemetry batch {}", path.display()))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+pub async fn upload(endpoint: &str, batch: &TelemetryBatch) -> Result<()> {
+    reqwest::Client::new()
+        .post(endpoint)
+        .json(batch)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach telemetry endpoint {}", endpoint))?
+        .error_for_status()
+        .context("Telemetry endpoint rejected the batch")?;
+    Ok(())
+}
+
+fn timing_bucket(elapsed: Duration) -> &'static str {
+    let ms = elapsed.as_millis() as u64;
+    TIMING_BUCKETS
+        .iter()
+        .find(|(limit, _)| ms < *limit)
+        .map(|(_, label)| *label)
+        .unwrap_or("slow")
+}
+
+fn today_ms() -> u64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    now - now % DAY_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_recorded_during_an_upload_are_kept() {
+        let mut recorder = TelemetryRecorder::in_memory("1.0.0");
+        recorder.feature("chat");
+        recorder.timing("reply", Duration::from_millis(300));
+
+        let uploading = recorder.take_batch();
+        assert!(recorder.batch().is_empty());
+        recorder.feature("chat");
+        recorder.error("llm");
+        // The upload failed
+        recorder.restore_batch(uploading);
+
+        let batch = recorder.batch();
+        assert_eq!(batch.features["chat"], 2);
+        assert_eq!(batch.errors["llm"], 1);
+        assert_eq!(batch.performance["reply"]["<500ms"], 1);
+    }
+
+    #[test]
+    fn timings_land_in_the_first_bucket_they_fit() {
+        assert_eq!(timing_bucket(Duration::from_millis(99)), "<100ms");
+        assert_eq!(timing_bucket(Duration::from_millis(100)), "<250ms");
+        assert_eq!(timing_bucket(Duration::from_secs(10)), "slow");
+    }
+}
@@ -0,0 +1,169 @@
+use crate::config::{LlmConfig, TranslationConfig};
+use crate::llm::{self, ChatMessage};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptionEvent {
+    pub utterance_id: u64,
+    // "source" for the recognized speech, "translated" for the target-language text
+    pub kind: String,
+    pub text: String,
+    pub language: String,
+    pub is_final: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranslationResult {
+    pub utterance_id: u64,
+    pub source_text: String,
+    pub translated_text: String,
+    pub source_language: String,
+    pub target_language: String,
+    pub voice: Option<String>,
+}
+
+#[async_trait]
+pub trait Translator: Send + Sync {
+    async fn translate(&self, text: &str, source_language: &str, target_language: &str) -> Result<String>;
+}
+
+pub struct LlmTranslator {
+    config: LlmConfig,
+}
+
+impl LlmTranslator {
+    pub fn new(config: LlmConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Translator for LlmTranslator {
+    async fn translate(&self, text: &str, source_language: &str, target_language: &str) -> Result<String> {
+        let source = if source_language == "auto" {
+            "the speaker's language".to_string()
+        } else {
+            source_language.to_string()
+        };
+        let instructions = format!(
+            "You are a live interpreter. Translate the user's message from {} to {}. \
+             Reply with the translation only, without quotes, notes, or explanations.",
+            source, target_language
+        );
+
+        let messages = [ChatMessage::system(instructions), ChatMessage::user(text)];
+        llm::chat_completion(&self.config, &messages, Some(0.2))
+            .await
+            .context("LLM translation failed")
+    }
+}
+
+pub struct LibreTranslateTranslator {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl LibreTranslateTranslator {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibreTranslateResponse {
+    translated_text: String,
+}
+
+#[async_trait]
+impl Translator for LibreTranslateTranslator {
+    async fn translate(&self, text: &str, source_language: &str, target_language: &str) -> Result<String> {
+        let body = serde_json::json!({
+            "q": text,
+            "source": source_language,
+            "target": target_language,
+            "format": "text",
+        });
+
+        let response: LibreTranslateResponse = self
+            .client
+            .post(format!("{}/translate", self.endpoint))
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to reach translation server")?
+            .error_for_status()
+            .context("Translation request failed")?
+            .json()
+            .await
+            .context("Failed to parse translation response")?;
+
+        Ok(response.translated_text)
+    }
+}
+
+pub fn create_translator(config: &TranslationConfig, llm_config: &LlmConfig) -> Result<Box<dyn Translator>> {
+    match config.provider.as_str() {
+        "llm" => Ok(Box::new(LlmTranslator::new(llm_config.clone()))),
+        "libretranslate" => Ok(Box::new(LibreTranslateTranslator::new(&config.endpoint))),
+        other => Err(anyhow::anyhow!("Unknown translation provider: {}", other)),
+    }
+}
+
+/// State of the live speech-to-speech translation mode
+pub struct TranslationSession {
+    pub active: bool,
+    pub source_language: String,
+    pub target_language: String,
+    next_utterance_id: u64,
+}
+
+impl TranslationSession {
+    pub fn new() -> Self {
+        Self {
+            active: false,
+            source_language: "auto".to_string(),
+            target_language: "en".to_string(),
+            next_utterance_id: 0,
+        }
+    }
+
+    pub fn start(&mut self, source_language: String, target_language: String) {
+        self.active = true;
+        self.source_language = source_language;
+        self.target_language = target_language;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+    }
+
+    pub fn next_utterance_id(&mut self) -> u64 {
+        self.next_utterance_id += 1;
+        self.next_utterance_id
+    }
+}
+
+impl Default for TranslationSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pick the TTS voice configured for the target language, if any
+pub fn voice_for_language(config: &TranslationConfig, language: &str) -> Option<String> {
+    config.voices.get(language).cloned().or_else(|| {
+        // Allow "pt" to match a "pt-BR" entry and vice versa
+        let base = language.split('-').next().unwrap_or(language);
+        config
+            .voices
+            .iter()
+            .find(|(lang, _)| lang.split('-').next() == Some(base))
+            .map(|(_, voice)| voice.clone())
+    })
+}
@@ -0,0 +1,280 @@
+use crate::config::UpdateConfig;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One channel's entry in the release feed.
+///
+/// The feed is fetched from `feed_url` with `{channel}` replaced by the channel name:
+///
+/// ```json
+/// {
+///   "version": "0.2.0-beta.1",
+///   "notes": "Faster wake word detection",
+///   "pub_date": "2026-10-01T12:00:00Z",
+///   "platforms": {
+///     "windows-x86_64": { "url": "https://.../app_0.2.0_x64.msi", "sha256": "..." }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseFeed {
+    version: String,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    pub_date: Option<String>,
+    platforms: HashMap<String, PlatformRelease>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PlatformRelease {
+    url: String,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub version: String,
+    pub channel: String,
+    pub notes: Option<String>,
+    pub pub_date: Option<String>,
+    pub url: String,
+    pub sha256: String,
+}
+
+/// What the app knows about the newest release between checks.
+#[derive(Default)]
+pub struct UpdateSession {
+    pub available: Option<UpdateInfo>,
+    // Verified package for `available`, ready to install
+    pub downloaded: Option<PathBuf>,
+    pub downloading: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateProgress {
+    pub version: String,
+    pub downloaded: u64,
+    // None when the server doesn't send a content length
+    pub total: Option<u64>,
+}
+
+/// Key of this build in the feed's `platforms` map, e.g. "darwin-aarch64".
+pub fn platform_key() -> String {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        other => other,
+    };
+    format!("{}-{}", os, std::env::consts::ARCH)
+}
+
+/// Fail unless the app may contact the release server.
+pub fn ensure_allowed(offline_mode: bool, config: &UpdateConfig) -> Result<()> {
+    if offline_mode {
+        return Err(anyhow::anyhow!("Update checks are disabled in offline mode"));
+    }
+    if config.feed_url.trim().is_empty() {
+        return Err(anyhow::anyhow!("No release feed is configured"));
+    }
+    Ok(())
+}
+
+/// Fetch the feed for the configured channel and return the release if it's newer than `current_version`.
+pub async fn check(current_version: &str, config: &UpdateConfig) -> Result<Option<UpdateInfo>> {
+    let channel = match config.channel.as_str() {
+        "stable" | "beta" => config.channel.as_str(),
+        other => return Err(anyhow::anyhow!("Unknown release channel: {}", other)),
+    };
+    let url = config.feed_url.replace("{channel}", channel);
+
+    let feed: ReleaseFeed = reqwest::Client::new()
+        .get(&url)
+        .header("User-Agent", format!("ai-conversation-app/{}", current_version))
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach release feed {}", url))?
+        .error_for_status()
+        .context("Release feed returned an error")?
+        .json()
+        .await
+        .context("Failed to parse release feed")?;
+
+    let current = semver::Version::parse(current_version)
+        .with_context(|| format!("Invalid current version {}", current_version))?;
+    let latest = semver::Version::parse(&feed.version)
+        .with_context(|| format!("Invalid version {} in release feed", feed.version))?;
+    // The stable channel never offers prereleases, even if the feed lists one
+    if latest <= current || (channel == "stable" && !latest.pre.is_empty()) {
+        return Ok(None);
+    }
+
+    let key = platform_key();
+    let Some(release) = feed.platforms.get(&key) else {
+        log::info!("Version {} has no build for {}", feed.version, key);
+        return Ok(None);
+    };
+
+    Ok(Some(UpdateInfo {
+        current_version: current_version.to_string(),
+        version: feed.version,
+        channel: channel.to_string(),
+        notes: feed.notes,
+        pub_date: feed.pub_date,
+        url: release.url.clone(),
+        sha256: release.sha256.to_lowercase(),
+    }))
+}
+
+/// Download the update into `dir`, verifying its checksum. Reuses an earlier verified download.
+pub async fn download(info: &UpdateInfo, dir: &Path, on_progress: impl Fn(UpdateProgress)) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create update directory {}", dir.display()))?;
+    let path = dir.join(download_file_name(info));
+
+    if path.exists() && file_sha256(&path).map(|hash| hash == info.sha256).unwrap_or(false) {
+        return Ok(path);
+    }
+
+    let mut response = reqwest::Client::new()
+        .get(&info.url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {}", info.url))?
+        .error_for_status()
+        .context("Update download returned an error")?;
+    let total = response.content_length();
+
+    // Write to a partial file so an interrupted download is never mistaken for a finished one
+    let partial = path.with_extension("part");
+    let mut file = std::fs::File::create(&partial)
+        .with_context(|| format!("Failed to create {}", partial.display()))?;
+    let mut hasher = Sha256::new();
+    let mut downloaded = 0u64;
+    while let Some(chunk) = response.chunk().await.context("Update download was interrupted")? {
+        file.write_all(&chunk).context("Failed to write update file")?;
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+        on_progress(UpdateProgress {
+            version: info.version.clone(),
+            downloaded,
+            total,
+        });
+    }
+    file.flush().context("Failed to write update file")?;
+    drop(file);
+
+    let hash = to_hex(&hasher.finalize());
+    if hash != info.sha256 {
+        let _ = std::fs::remove_file(&partial);
+        return Err(anyhow::anyhow!("Checksum mismatch for update {}", info.version));
+    }
+    std::fs::rename(&partial, &path).context("Failed to finalize update file")?;
+    Ok(path)
+}
+
+/// Hand the downloaded package to the platform installer. The caller should quit afterwards.
+pub fn launch_installer(path: &Path) -> Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    let mut command = match (std::env::consts::OS, extension.as_str()) {
+        // Mobile builds update through the app stores
+        ("android" | "ios", _) => return Err(crate::platform::desktop_only("Installing updates")),
+        ("windows", "msi") => {
+            let mut command = std::process::Command::new("msiexec");
+            command.arg("/i").arg(path).arg("/passive");
+            command
+        }
+        ("windows", _) => std::process::Command::new(path),
+        ("macos", _) => {
+            let mut command = std::process::Command::new("open");
+            command.arg(path);
+            command
+        }
+        ("linux", "appimage") => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+                    .context("Failed to make the AppImage executable")?;
+            }
+            std::process::Command::new(path)
+        }
+        _ => {
+            let mut command = std::process::Command::new("xdg-open");
+            command.arg(path);
+            command
+        }
+    };
+    command
+        .spawn()
+        .with_context(|| format!("Failed to launch installer {}", path.display()))?;
+    Ok(())
+}
+
+/// The installer's name from the last segment of its URL, which the platform may need to
+/// recognize it, or `update-<version>` when that isn't a plain file name. Neither can name a
+/// file outside the update directory.
+fn download_file_name(info: &UpdateInfo) -> String {
+    let from_url = reqwest::Url::parse(&info.url)
+        .ok()
+        .and_then(|url| url.path_segments()?.next_back().map(str::to_string));
+    match from_url {
+        Some(name) if is_plain_file_name(&name) => name,
+        _ => {
+            let version: String = info.version.chars().filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')).collect();
+            format!("update-{}", version)
+        }
+    }
+}
+
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.chars().any(|c| matches!(c, '/' | '\\' | ':') || c.is_control())
+}
+
+fn file_sha256(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_name(url: &str, version: &str) -> String {
+        download_file_name(&UpdateInfo {
+            current_version: "0.1.0".to_string(),
+            version: version.to_string(),
+            channel: "stable".to_string(),
+            notes: None,
+            pub_date: None,
+            url: url.to_string(),
+            sha256: String::new(),
+        })
+    }
+
+    #[test]
+    fn keeps_the_installer_name_from_the_url() {
+        assert_eq!(file_name("https://example.com/releases/App_1.2.0_x64.msi?sig=abc", "1.2.0"), "App_1.2.0_x64.msi");
+    }
+
+    #[test]
+    fn never_names_a_file_outside_the_update_directory() {
+        assert_eq!(file_name("https://example.com/releases/..", "1.2.0"), "update-1.2.0");
+        assert_eq!(file_name("https://example.com/releases/", "1.2.0"), "update-1.2.0");
+        assert_eq!(file_name("https://example.com/releases/%2E%2E", "1.2.0"), "update-1.2.0");
+        assert_eq!(file_name("not a url", "../../1.2.0"), "update-....1.2.0");
+    }
+}
@@ -0,0 +1,187 @@
+use crate::config::VisionConfig;
+use anyhow::{Context, Result};
+use image::RgbImage;
+use nokhwa::pixel_format::RgbFormat;
+use nokhwa::utils::{CameraIndex, RequestedFormat, RequestedFormatType};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+// Subscribers that fall further behind than this skip ahead to the newest frames
+const FRAME_CHANNEL_CAPACITY: usize = 4;
+// Reads that fail in a row before the camera is taken to be gone
+const MAX_FAILED_READS: u32 = 50;
+
+/// A decoded camera frame. Cloning it shares the pixels.
+#[derive(Clone)]
+pub struct Frame {
+    pub image: Arc<RgbImage>,
+    pub captured_at: Instant,
+}
+
+/// Whether the camera is capturing, sent as "camera-state" for the UI's indicator.
+#[derive(Debug, Clone, Serialize)]
+pub struct CameraStatus {
+    pub on: bool,
+    // The camera's name while it's on
+    pub device: Option<String>,
+    // Why the camera couldn't be turned on, when it couldn't
+    pub error: Option<String>,
+}
+
+/// Captures the default camera on its own thread, at `vision.fps`, and hands each frame to
+/// everyone subscribed.
+pub struct Camera {
+    running: Arc<AtomicBool>,
+    frames: broadcast::Sender<Frame>,
+    latest: Arc<Mutex<Option<Frame>>>,
+    name: String,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Camera {
+    /// Open the default camera and start capturing. Blocks until the camera is streaming.
+    pub fn start(config: &VisionConfig) -> Result<Self> {
+        let running = Arc::new(AtomicBool::new(true));
+        let (frames, _) = broadcast::channel(FRAME_CHANNEL_CAPACITY);
+        let latest = Arc::new(Mutex::new(None));
+        let interval = Duration::from_secs_f64(1.0 / config.fps.max(1) as f64);
+
+        // Some camera backends aren't Send, so the camera lives and dies on its own thread
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<String>>();
+        let thread_running = running.clone();
+        let thread_frames = frames.clone();
+        let thread_latest = latest.clone();
+        let thread = std::thread::spawn(move || {
+            let mut camera = match open_default_camera() {
+                Ok(camera) => {
+                    let _ = ready_tx.send(Ok(camera.info().human_name()));
+                    camera
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            let mut next = Instant::now();
+            let mut failed_reads = 0;
+            while thread_running.load(Ordering::SeqCst) {
+                let buffer = match camera.frame() {
+                    Ok(buffer) => {
+                        failed_reads = 0;
+                        buffer
+                    }
+                    Err(e) => {
+                        failed_reads += 1;
+                        if failed_reads >= MAX_FAILED_READS {
+                            log::error!("Stopped capturing, the camera stopped giving frames: {}", e);
+                            break;
+                        }
+                        log::warn!("Failed to read a camera frame: {}", e);
+                        std::thread::sleep(interval);
+                        continue;
+                    }
+                };
+                // Frames between ticks are still read, so the next one sent isn't a stale one
+                let now = Instant::now();
+                if now < next {
+                    continue;
+                }
+                // Keep to the tick schedule, unless it has fallen a whole tick behind
+                next = if next + interval > now { next + interval } else { now + interval };
+
+                match decode(&buffer) {
+                    Ok(image) => {
+                        let frame = Frame {
+                            image: Arc::new(image),
+                            captured_at: now,
+                        };
+                        *thread_latest.lock().unwrap() = Some(frame.clone());
+                        // Nobody may be listening; that's fine
+                        let _ = thread_frames.send(frame);
+                    }
+                    Err(e) => log::warn!("Failed to decode a camera frame: {:#}", e),
+                }
+            }
+            if let Err(e) = camera.stop_stream() {
+                log::warn!("Failed to stop the camera stream: {}", e);
+            }
+            // The device is released as the camera drops here, whether stopped or gone
+            thread_running.store(false, Ordering::SeqCst);
+        });
+
+        let name = ready_rx.recv().context("Camera thread exited unexpectedly")??;
+        log::info!("Capturing from {} at {} fps", name, config.fps.max(1));
+        Ok(Self {
+            running,
+            frames,
+            latest,
+            name,
+            thread: Some(thread),
+        })
+    }
+
+    /// Whether the camera is still capturing; false once it has stopped giving frames.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Frames from now on. A receiver that lags behind loses the oldest ones.
+    pub fn subscribe(&self) -> broadcast::Receiver<Frame> {
+        self.frames.subscribe()
+    }
+
+    /// The most recent frame, if one has come in yet.
+    pub fn latest(&self) -> Option<Frame> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    /// Stop capturing and release the camera. Blocks until it has.
+    pub fn stop(mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+        log::info!("Stopped capturing from {}", self.name);
+    }
+}
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+fn open_default_camera() -> Result<nokhwa::Camera> {
+    // macOS asks the user for camera access first
+    #[cfg(target_os = "macos")]
+    {
+        let (granted_tx, granted_rx) = std::sync::mpsc::channel();
+        nokhwa::nokhwa_initialize(move |granted| {
+            let _ = granted_tx.send(granted);
+        });
+        if !granted_rx.recv().unwrap_or(false) {
+            return Err(anyhow::anyhow!("Camera access was not granted"));
+        }
+    }
+
+    let format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
+    let mut camera = nokhwa::Camera::new(CameraIndex::Index(0), format).context("Failed to open the camera")?;
+    camera.open_stream().context("Failed to start the camera stream")?;
+    Ok(camera)
+}
+
+fn decode(buffer: &nokhwa::Buffer) -> Result<RgbImage> {
+    let decoded = buffer.decode_image::<RgbFormat>()?;
+    let (width, height) = (decoded.width(), decoded.height());
+    // nokhwa may build against another version of the image crate; the raw pixels carry over
+    RgbImage::from_raw(width, height, decoded.into_raw()).context("Camera frame has the wrong size")
+}
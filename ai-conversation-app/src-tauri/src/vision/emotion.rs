@@ -0,0 +1,131 @@
+use super::face::Face;
+use crate::llm::ChatMessage;
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::RgbImage;
+use ort::session::Session;
+use ort::value::Tensor;
+use serde::Serialize;
+
+// FER+ from the ONNX model zoo
+const EMOTION_MODEL: &str = "emotion-ferplus-8.onnx";
+const INPUT_SIZE: u32 = 64;
+// In the order the model scores them
+const EXPRESSIONS: [&str; 8] = ["neutral", "happy", "surprised", "sad", "angry", "disgusted", "fearful", "contemptuous"];
+// Faces are cropped a little wider than their box, the way the training faces were
+const CROP_MARGIN: f32 = 0.1;
+// Weight of each new reading in the running average; lower is steadier but slower to follow
+const SMOOTHING: f32 = 0.3;
+// Below this averaged probability no expression is reported as a change
+const MIN_CONFIDENCE: f32 = 0.4;
+
+/// The expression on the user's face.
+#[derive(Debug, Clone, Serialize)]
+pub struct Expression {
+    pub label: &'static str,
+    pub confidence: f32,
+}
+
+/// Reads facial expressions with FER+, run through ONNX Runtime.
+pub struct EmotionClassifier {
+    session: Session,
+}
+
+impl EmotionClassifier {
+    pub fn load() -> Result<Self> {
+        let path = crate::models::resolve_asset(EMOTION_MODEL)?;
+        let session = Session::builder()
+            .and_then(|builder| builder.with_intra_threads(1))
+            .and_then(|builder| builder.commit_from_file(&path))
+            .with_context(|| format!("Failed to load the emotion classifier from {}", path.display()))?;
+        log::info!("Emotion classifier loaded from {}", path.display());
+        Ok(Self { session })
+    }
+
+    /// The probability of each of EXPRESSIONS for `face` in `frame`.
+    pub fn classify(&mut self, frame: &RgbImage, face: &Face) -> Result<[f32; 8]> {
+        let (frame_width, frame_height) = (frame.width() as f32, frame.height() as f32);
+        let left = ((face.x - face.width * CROP_MARGIN) * frame_width).max(0.0) as u32;
+        let top = ((face.y - face.height * CROP_MARGIN) * frame_height).max(0.0) as u32;
+        let right = (((face.x + face.width * (1.0 + CROP_MARGIN)) * frame_width) as u32).min(frame.width());
+        let bottom = (((face.y + face.height * (1.0 + CROP_MARGIN)) * frame_height) as u32).min(frame.height());
+        if right <= left || bottom <= top {
+            return Err(anyhow::anyhow!("The face box is empty"));
+        }
+
+        let crop = image::imageops::crop_imm(frame, left, top, right - left, bottom - top).to_image();
+        let gray = image::DynamicImage::ImageRgb8(crop).to_luma8();
+        let resized = image::imageops::resize(&gray, INPUT_SIZE, INPUT_SIZE, FilterType::Triangle);
+        // Grayscale 0-255, not normalized
+        let input: Vec<f32> = resized.pixels().map(|pixel| pixel[0] as f32).collect();
+
+        let outputs = self.session.run(ort::inputs![
+            "Input3" => Tensor::from_array(([1usize, 1, INPUT_SIZE as usize, INPUT_SIZE as usize], input))?,
+        ]?)?;
+        let (_, logits) = outputs["Plus692_Output_0"].try_extract_raw_tensor::<f32>()?;
+        if logits.len() != EXPRESSIONS.len() {
+            return Err(anyhow::anyhow!("The emotion classifier gave {} scores", logits.len()));
+        }
+        Ok(softmax(logits))
+    }
+}
+
+fn softmax(logits: &[f32]) -> [f32; 8] {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mut probabilities = [0.0; 8];
+    for (probability, logit) in probabilities.iter_mut().zip(logits) {
+        *probability = (logit - max).exp();
+    }
+    let sum: f32 = probabilities.iter().sum();
+    probabilities.iter_mut().for_each(|probability| *probability /= sum);
+    probabilities
+}
+
+/// The user's expression, averaged over readings so a blink or a passing frown doesn't count.
+#[derive(Default)]
+pub struct ExpressionTracker {
+    averaged: Option<[f32; 8]>,
+    current: Option<usize>,
+}
+
+impl ExpressionTracker {
+    /// Take in one reading, returning the expression if it changed.
+    pub fn update(&mut self, probabilities: [f32; 8]) -> Option<Expression> {
+        let averaged = match self.averaged.as_mut() {
+            Some(averaged) => {
+                for (average, probability) in averaged.iter_mut().zip(probabilities) {
+                    *average += SMOOTHING * (probability - *average);
+                }
+                *averaged
+            }
+            None => *self.averaged.insert(probabilities),
+        };
+        let (top, confidence) = averaged
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if confidence < MIN_CONFIDENCE || self.current == Some(top) {
+            return None;
+        }
+        self.current = Some(top);
+        Some(Expression {
+            label: EXPRESSIONS[top],
+            confidence,
+        })
+    }
+
+    /// Start over, e.g. once the user has left.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// A system message telling the LLM how the user looks, so it can respond with some empathy.
+pub fn context_message(expression: &Expression) -> ChatMessage {
+    ChatMessage::system(format!(
+        "From the camera, the user looks {} right now. Let that inform your tone where it fits, \
+         without commenting on it every time.",
+        expression.label
+    ))
+}
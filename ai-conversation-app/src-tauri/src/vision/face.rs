@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::RgbImage;
+use ort::session::Session;
+use ort::value::Tensor;
+use serde::Serialize;
+
+// UltraFace (RFB-320) from the ONNX model zoo
+const FACE_MODEL: &str = "version-RFB-320.onnx";
+const INPUT_WIDTH: u32 = 320;
+const INPUT_HEIGHT: u32 = 240;
+// Overlapping boxes above this are taken to be the same face
+const NMS_IOU: f32 = 0.3;
+
+/// A face found in a frame. The box is in fractions of the frame's width and height.
+#[derive(Debug, Clone, Serialize)]
+pub struct Face {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub confidence: f32,
+}
+
+impl Face {
+    fn area(&self) -> f32 {
+        self.width * self.height
+    }
+
+    fn iou(&self, other: &Face) -> f32 {
+        let overlap_width = (self.x + self.width).min(other.x + other.width) - self.x.max(other.x);
+        let overlap_height = (self.y + self.height).min(other.y + other.height) - self.y.max(other.y);
+        if overlap_width <= 0.0 || overlap_height <= 0.0 {
+            return 0.0;
+        }
+        let overlap = overlap_width * overlap_height;
+        overlap / (self.area() + other.area() - overlap)
+    }
+}
+
+/// Finds faces in camera frames with UltraFace, run through ONNX Runtime.
+pub struct FaceDetector {
+    session: Session,
+    threshold: f32,
+}
+
+impl FaceDetector {
+    pub fn load(threshold: f32) -> Result<Self> {
+        let path = crate::models::resolve_asset(FACE_MODEL)?;
+        let session = Session::builder()
+            .and_then(|builder| builder.with_intra_threads(1))
+            .and_then(|builder| builder.commit_from_file(&path))
+            .with_context(|| format!("Failed to load the face detector from {}", path.display()))?;
+        log::info!("Face detector loaded from {}", path.display());
+        Ok(Self { session, threshold })
+    }
+
+    /// The faces in `frame`, most confident first.
+    pub fn detect(&mut self, frame: &RgbImage) -> Result<Vec<Face>> {
+        let resized = image::imageops::resize(frame, INPUT_WIDTH, INPUT_HEIGHT, FilterType::Triangle);
+        // Planar RGB, normalized the way the model was trained
+        let plane = (INPUT_WIDTH * INPUT_HEIGHT) as usize;
+        let mut input = vec![0.0f32; 3 * plane];
+        for (index, pixel) in resized.pixels().enumerate() {
+            for channel in 0..3 {
+                input[channel * plane + index] = (pixel[channel] as f32 - 127.0) / 128.0;
+            }
+        }
+
+        let outputs = self.session.run(ort::inputs![
+            "input" => Tensor::from_array(([1usize, 3, INPUT_HEIGHT as usize, INPUT_WIDTH as usize], input))?,
+        ]?)?;
+        // Per candidate box: [background, face] scores and [x1, y1, x2, y2] corners
+        let (_, scores) = outputs["scores"].try_extract_raw_tensor::<f32>()?;
+        let (_, boxes) = outputs["boxes"].try_extract_raw_tensor::<f32>()?;
+
+        let mut candidates: Vec<Face> = scores
+            .chunks_exact(2)
+            .zip(boxes.chunks_exact(4))
+            .filter(|(score, _)| score[1] > self.threshold)
+            .map(|(score, corners)| Face {
+                x: corners[0].clamp(0.0, 1.0),
+                y: corners[1].clamp(0.0, 1.0),
+                width: (corners[2] - corners[0]).clamp(0.0, 1.0),
+                height: (corners[3] - corners[1]).clamp(0.0, 1.0),
+                confidence: score[1],
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        let mut faces: Vec<Face> = Vec::new();
+        for candidate in candidates {
+            if faces.iter().all(|face| face.iou(&candidate) < NMS_IOU) {
+                faces.push(candidate);
+            }
+        }
+        Ok(faces)
+    }
+}
@@ -0,0 +1,182 @@
+use super::face::Face;
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::RgbImage;
+use ort::session::Session;
+use ort::value::Tensor;
+use serde::Serialize;
+
+// Hopenet (lite), which scores yaw, pitch and roll in 3-degree bins from -99 to 99
+const HEAD_POSE_MODEL: &str = "hopenet_lite.onnx";
+const INPUT_SIZE: u32 = 224;
+const BINS: usize = 66;
+const BIN_DEGREES: f32 = 3.0;
+const MIN_DEGREES: f32 = -99.0;
+// ImageNet statistics, which the model was trained with
+const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const STD: [f32; 3] = [0.229, 0.224, 0.225];
+// The model wants the whole head, not just the face box
+const CROP_MARGIN: f32 = 0.2;
+// Weight of each new reading in the running average; lower is steadier but slower to follow
+const SMOOTHING: f32 = 0.4;
+// Smaller moves than these aren't reported, so a still user doesn't send a stream of events
+const MIN_MOVE: f32 = 0.03;
+const MIN_TURN_DEGREES: f32 = 3.0;
+// A head turned less than this is taken to be looking at the screen
+const FACING_YAW: f32 = 20.0;
+const FACING_PITCH: f32 = 15.0;
+
+/// Which way the user's head is turned, in degrees.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct HeadPose {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+/// Where the user is in front of the camera and roughly where they're looking.
+#[derive(Debug, Clone, Serialize)]
+pub struct Gaze {
+    // Centre of the face from -1 to 1, left to right and bottom to top, as the camera sees it
+    pub x: f32,
+    pub y: f32,
+    // None when the head pose model isn't available
+    pub head: Option<HeadPose>,
+    // Whether the user seems to be looking at the screen
+    pub facing: bool,
+}
+
+/// Estimates head pose with Hopenet, run through ONNX Runtime.
+pub struct HeadPoseEstimator {
+    session: Session,
+    input: String,
+    outputs: [String; 3],
+}
+
+impl HeadPoseEstimator {
+    pub fn load() -> Result<Self> {
+        let path = crate::models::resolve_asset(HEAD_POSE_MODEL)?;
+        let session = Session::builder()
+            .and_then(|builder| builder.with_intra_threads(1))
+            .and_then(|builder| builder.commit_from_file(&path))
+            .with_context(|| format!("Failed to load the head pose model from {}", path.display()))?;
+        // Exports name their tensors differently; the order is always yaw, pitch, roll
+        let input = session.inputs.first().context("The head pose model has no input")?.name.clone();
+        let outputs = match session.outputs.as_slice() {
+            [yaw, pitch, roll, ..] => [yaw.name.clone(), pitch.name.clone(), roll.name.clone()],
+            _ => return Err(anyhow::anyhow!("The head pose model should give yaw, pitch and roll")),
+        };
+        log::info!("Head pose model loaded from {}", path.display());
+        Ok(Self { session, input, outputs })
+    }
+
+    /// How the head of `face` in `frame` is turned.
+    pub fn estimate(&mut self, frame: &RgbImage, face: &Face) -> Result<HeadPose> {
+        let (frame_width, frame_height) = (frame.width() as f32, frame.height() as f32);
+        let left = ((face.x - face.width * CROP_MARGIN) * frame_width).max(0.0) as u32;
+        let top = ((face.y - face.height * CROP_MARGIN) * frame_height).max(0.0) as u32;
+        let right = (((face.x + face.width * (1.0 + CROP_MARGIN)) * frame_width) as u32).min(frame.width());
+        let bottom = (((face.y + face.height * (1.0 + CROP_MARGIN)) * frame_height) as u32).min(frame.height());
+        if right <= left || bottom <= top {
+            return Err(anyhow::anyhow!("The face box is empty"));
+        }
+
+        let crop = image::imageops::crop_imm(frame, left, top, right - left, bottom - top).to_image();
+        let resized = image::imageops::resize(&crop, INPUT_SIZE, INPUT_SIZE, FilterType::Triangle);
+        let plane = (INPUT_SIZE * INPUT_SIZE) as usize;
+        let mut input = vec![0.0f32; 3 * plane];
+        for (index, pixel) in resized.pixels().enumerate() {
+            for channel in 0..3 {
+                input[channel * plane + index] = (pixel[channel] as f32 / 255.0 - MEAN[channel]) / STD[channel];
+            }
+        }
+
+        let outputs = self.session.run(ort::inputs![
+            self.input.as_str() => Tensor::from_array(([1usize, 3, INPUT_SIZE as usize, INPUT_SIZE as usize], input))?,
+        ]?)?;
+        let mut angles = [0.0f32; 3];
+        for (angle, name) in angles.iter_mut().zip(&self.outputs) {
+            let (_, scores) = outputs[name.as_str()].try_extract_raw_tensor::<f32>()?;
+            if scores.len() != BINS {
+                return Err(anyhow::anyhow!("The head pose model gave {} bins", scores.len()));
+            }
+            *angle = expected_degrees(scores);
+        }
+        Ok(HeadPose {
+            yaw: angles[0],
+            pitch: angles[1],
+            roll: angles[2],
+        })
+    }
+}
+
+// The angle the bin scores average out to, which is finer than any one bin
+fn expected_degrees(scores: &[f32]) -> f32 {
+    let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let weights: Vec<f32> = scores.iter().map(|score| (score - max).exp()).collect();
+    let sum: f32 = weights.iter().sum();
+    let bin: f32 = weights.iter().enumerate().map(|(index, weight)| index as f32 * weight).sum::<f32>() / sum;
+    bin * BIN_DEGREES + MIN_DEGREES
+}
+
+/// The user's gaze, averaged over readings so the avatar's eyes don't jitter with every frame.
+#[derive(Default)]
+pub struct GazeTracker {
+    averaged: Option<Gaze>,
+    reported: Option<Gaze>,
+}
+
+impl GazeTracker {
+    /// Take in the user's face and, if it could be read, head pose, returning the gaze if it
+    /// moved enough to be worth reporting.
+    pub fn update(&mut self, face: &Face, head: Option<HeadPose>) -> Option<Gaze> {
+        let x = (face.x + face.width / 2.0) * 2.0 - 1.0;
+        let y = 1.0 - (face.y + face.height / 2.0) * 2.0;
+        let gaze = match self.averaged.take() {
+            Some(mut averaged) => {
+                averaged.x += SMOOTHING * (x - averaged.x);
+                averaged.y += SMOOTHING * (y - averaged.y);
+                averaged.head = match (averaged.head, head) {
+                    (Some(mut average), Some(head)) => {
+                        average.yaw += SMOOTHING * (head.yaw - average.yaw);
+                        average.pitch += SMOOTHING * (head.pitch - average.pitch);
+                        average.roll += SMOOTHING * (head.roll - average.roll);
+                        Some(average)
+                    }
+                    (_, head) => head,
+                };
+                averaged
+            }
+            None => Gaze { x, y, head, facing: true },
+        };
+        let gaze = Gaze {
+            facing: gaze.head.is_none_or(|head| head.yaw.abs() < FACING_YAW && head.pitch.abs() < FACING_PITCH),
+            ..gaze
+        };
+        self.averaged = Some(gaze.clone());
+
+        let moved = self.reported.as_ref().is_none_or(|reported| {
+            (gaze.x - reported.x).abs() >= MIN_MOVE
+                || (gaze.y - reported.y).abs() >= MIN_MOVE
+                || gaze.facing != reported.facing
+                || match (gaze.head, reported.head) {
+                    (Some(head), Some(last)) => {
+                        (head.yaw - last.yaw).abs() >= MIN_TURN_DEGREES || (head.pitch - last.pitch).abs() >= MIN_TURN_DEGREES
+                    }
+                    (head, last) => head.is_some() != last.is_some(),
+                }
+        });
+        if !moved {
+            return None;
+        }
+        self.reported = Some(gaze.clone());
+        Some(gaze)
+    }
+
+    /// Start over once the face is lost, returning true if a gaze had been reported.
+    pub fn reset(&mut self) -> bool {
+        let reported = self.reported.is_some();
+        *self = Self::default();
+        reported
+    }
+}
@@ -0,0 +1,242 @@
+use crate::config::VisionConfig;
+use crate::llm::ImageData;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use camera::Camera;
+use emotion::{EmotionClassifier, Expression, ExpressionTracker};
+use face::FaceDetector;
+use head_pose::{Gaze, GazeTracker, HeadPoseEstimator};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use objects::{DetectedObject, ObjectDetector};
+use presence::{Presence, PresenceEvent};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+
+pub mod camera;
+pub mod emotion;
+pub mod face;
+pub mod head_pose;
+pub mod objects;
+pub mod ocr;
+pub mod presence;
+pub mod screen;
+
+// Bigger images are refused rather than decoded
+const MAX_IMAGE_BYTES: usize = 25 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 85;
+// Faces don't need every frame; frames in between are skipped
+const DETECT_INTERVAL: Duration = Duration::from_millis(200);
+// Expressions change more slowly than faces come and go
+const EXPRESSION_INTERVAL: Duration = Duration::from_millis(500);
+// Object detection is the heaviest stage, so it runs least often
+const OBJECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The image `source` names, ready to send to a model: `source` is a file path, a data: URL or
+/// plain base64. It's shrunk to fit within `resolution` (width, height), keeping its proportions,
+/// and sent as JPEG. Blocks while decoding.
+pub fn prepare_image(source: &str, resolution: [u32; 2]) -> Result<ImageData> {
+    encode(&read_source(source)?, resolution)
+}
+
+/// The image `source` names, decoded; `source` is as for prepare_image. Blocks while decoding.
+pub fn load_image(source: &str) -> Result<DynamicImage> {
+    image::load_from_memory(&read_source(source)?).context("Failed to decode the image")
+}
+
+// The bytes of the image file, data: URL or base64 `source`
+fn read_source(source: &str) -> Result<Vec<u8>> {
+    let source = source.trim();
+    let bytes = if Path::new(source).is_file() {
+        let size = std::fs::metadata(source)?.len() as usize;
+        if size > MAX_IMAGE_BYTES {
+            return Err(anyhow::anyhow!("{} is too large to send ({} MB)", source, size / (1024 * 1024)));
+        }
+        std::fs::read(source).with_context(|| format!("Failed to read {}", source))?
+    } else {
+        let data = source.split_once(";base64,").map_or(source, |(_, data)| data);
+        // Base64 is a third bigger than what it encodes
+        if data.len() / 4 * 3 > MAX_IMAGE_BYTES {
+            return Err(anyhow::anyhow!("The image is too large to send"));
+        }
+        BASE64
+            .decode(data.trim())
+            .context("The image is neither a readable file nor base64")?
+    };
+    Ok(bytes)
+}
+
+/// `bytes`, an image in any common format, shrunk to fit `resolution` and encoded as JPEG.
+pub fn encode(bytes: &[u8], resolution: [u32; 2]) -> Result<ImageData> {
+    let image = image::load_from_memory(bytes).context("Failed to decode the image")?;
+    to_jpeg(&shrink(image, resolution))
+}
+
+/// `image` made to fit within `resolution` (width, height), keeping its proportions.
+pub fn shrink(image: DynamicImage, resolution: [u32; 2]) -> DynamicImage {
+    let [width, height] = resolution;
+    if width > 0 && height > 0 && (image.width() > width || image.height() > height) {
+        image.resize(width, height, FilterType::Triangle)
+    } else {
+        image
+    }
+}
+
+/// `image` encoded as JPEG at the size it is.
+pub fn to_jpeg(image: &DynamicImage) -> Result<ImageData> {
+    // JPEG has no alpha channel
+    let image = DynamicImage::ImageRgb8(image.to_rgb8());
+    let mut jpeg = Vec::new();
+    image
+        .write_with_encoder(image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY))
+        .context("Failed to encode the image")?;
+    Ok(ImageData {
+        mime_type: "image/jpeg".to_string(),
+        data: BASE64.encode(jpeg),
+    })
+}
+
+/// What watching the camera noticed.
+pub enum VisionEvent {
+    Presence(PresenceEvent),
+    Expression(Expression),
+    // Everything seen in the frame; empty once the last objects are gone
+    Objects(Vec<DetectedObject>),
+    // Where the user is and looks; None once their face is lost
+    Gaze(Option<Gaze>),
+}
+
+/// Watch `camera` on a thread of its own, calling `on_event` when someone arrives or leaves,
+/// when their expression changes and with the objects in view, as vision.face_detection,
+/// vision.emotion_recognition and vision.object_detection ask, and as the user moves or looks
+/// around when `track_gaze` is set. A stage whose model can't be loaded is left out. The
+/// thread ends with the camera.
+pub fn watch(
+    camera: &Camera,
+    config: &VisionConfig,
+    track_gaze: bool,
+    on_event: impl Fn(VisionEvent) + Send + 'static,
+) -> Result<()> {
+    let mut errors = Vec::new();
+    let mut detector = if config.face_detection || config.emotion_recognition || track_gaze {
+        FaceDetector::load(config.face_threshold).map_err(|e| errors.push(e)).ok()
+    } else {
+        None
+    };
+    let mut classifier = if config.emotion_recognition && detector.is_some() {
+        EmotionClassifier::load()
+            .map_err(|e| log::warn!("Facial expressions won't be read: {:#}", e))
+            .ok()
+    } else {
+        None
+    };
+    // Without it the avatar still follows where the user is, just not where they look
+    let mut head_poses = if track_gaze && detector.is_some() {
+        HeadPoseEstimator::load()
+            .map_err(|e| log::warn!("Head pose won't be read: {:#}", e))
+            .ok()
+    } else {
+        None
+    };
+    let mut object_detector = if config.object_detection {
+        ObjectDetector::load(&config.model, config.object_threshold)
+            .map_err(|e| errors.push(e))
+            .ok()
+    } else {
+        None
+    };
+    if detector.is_none() && object_detector.is_none() {
+        return Err(errors.into_iter().next().unwrap_or_else(|| anyhow::anyhow!("No vision stage is turned on")));
+    }
+    for e in errors {
+        log::warn!("{:#}", e);
+    }
+
+    let report_presence = config.face_detection;
+    let mut presence = Presence::new(Duration::from_secs(config.away_seconds.max(1) as u64));
+    let mut expressions = ExpressionTracker::default();
+    let mut gaze = GazeTracker::default();
+    let mut frames = camera.subscribe();
+    std::thread::spawn(move || {
+        let mut next_faces = Instant::now();
+        let mut next_expression = Instant::now();
+        let mut next_objects = Instant::now();
+        let mut objects_in_view = false;
+        loop {
+            let frame = match frames.blocking_recv() {
+                Ok(frame) => frame,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let at = frame.captured_at;
+
+            if let Some(detector) = detector.as_mut().filter(|_| at >= next_faces) {
+                next_faces = at + DETECT_INTERVAL;
+                match detector.detect(&frame.image) {
+                    Ok(faces) => {
+                        if let Some(change) = presence.update(faces.len(), at) {
+                            if !change.present {
+                                expressions.reset();
+                            }
+                            if report_presence {
+                                log::info!("User {}", if change.present { "arrived" } else { "left" });
+                                on_event(VisionEvent::Presence(change));
+                            }
+                        }
+
+                        // The nearest face, the biggest one, is taken to be the user's
+                        let nearest = faces.iter().max_by(|a, b| (a.width * a.height).total_cmp(&(b.width * b.height)));
+                        match nearest.filter(|_| track_gaze) {
+                            Some(face) => {
+                                let head = head_poses.as_mut().and_then(|head_poses| {
+                                    head_poses
+                                        .estimate(&frame.image, face)
+                                        .map_err(|e| log::warn!("Failed to read head pose: {:#}", e))
+                                        .ok()
+                                });
+                                if let Some(change) = gaze.update(face, head) {
+                                    on_event(VisionEvent::Gaze(Some(change)));
+                                }
+                            }
+                            None => {
+                                if gaze.reset() {
+                                    on_event(VisionEvent::Gaze(None));
+                                }
+                            }
+                        }
+                        if let (Some(classifier), Some(face)) = (classifier.as_mut(), nearest) {
+                            if at >= next_expression {
+                                next_expression = at + EXPRESSION_INTERVAL;
+                                match classifier.classify(&frame.image, face) {
+                                    Ok(probabilities) => {
+                                        if let Some(expression) = expressions.update(probabilities) {
+                                            on_event(VisionEvent::Expression(expression));
+                                        }
+                                    }
+                                    Err(e) => log::warn!("Failed to read a facial expression: {:#}", e),
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => log::error!("Face detection failed: {:#}", e),
+                }
+            }
+
+            if let Some(object_detector) = object_detector.as_mut().filter(|_| at >= next_objects) {
+                next_objects = at + OBJECT_INTERVAL;
+                match object_detector.detect(&frame.image) {
+                    // Nothing in view is sent once, not on every pass
+                    Ok(objects) if objects.is_empty() && !objects_in_view => {}
+                    Ok(objects) => {
+                        objects_in_view = !objects.is_empty();
+                        on_event(VisionEvent::Objects(objects));
+                    }
+                    Err(e) => log::error!("Object detection failed: {:#}", e),
+                }
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::{Rgb, RgbImage};
+use ort::session::Session;
+use ort::value::Tensor;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+// YOLOv8-style exports take a square, letterboxed frame
+const INPUT_SIZE: u32 = 640;
+// Boxes of the same kind overlapping more than this are taken to be the same object
+const NMS_IOU: f32 = 0.45;
+// A busy scene is cut to the most confident detections
+const MAX_OBJECTS: usize = 20;
+
+// The COCO classes YOLO models are trained on, in their order
+const COCO_LABELS: [&str; 80] = [
+    "person", "bicycle", "car", "motorcycle", "airplane", "bus", "train", "truck", "boat", "traffic light",
+    "fire hydrant", "stop sign", "parking meter", "bench", "bird", "cat", "dog", "horse", "sheep", "cow",
+    "elephant", "bear", "zebra", "giraffe", "backpack", "umbrella", "handbag", "tie", "suitcase", "frisbee",
+    "skis", "snowboard", "sports ball", "kite", "baseball bat", "baseball glove", "skateboard", "surfboard",
+    "tennis racket", "bottle", "wine glass", "cup", "fork", "knife", "spoon", "bowl", "banana", "apple",
+    "sandwich", "orange", "broccoli", "carrot", "hot dog", "pizza", "donut", "cake", "chair", "couch",
+    "potted plant", "bed", "dining table", "toilet", "tv", "laptop", "mouse", "remote", "keyboard",
+    "cell phone", "microwave", "oven", "toaster", "sink", "refrigerator", "book", "clock", "vase",
+    "scissors", "teddy bear", "hair drier", "toothbrush",
+];
+
+/// Something seen in a frame. The box is in fractions of the frame's width and height.
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectedObject {
+    pub label: &'static str,
+    pub confidence: f32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl DetectedObject {
+    fn iou(&self, other: &DetectedObject) -> f32 {
+        let overlap_width = (self.x + self.width).min(other.x + other.width) - self.x.max(other.x);
+        let overlap_height = (self.y + self.height).min(other.y + other.height) - self.y.max(other.y);
+        if overlap_width <= 0.0 || overlap_height <= 0.0 {
+            return 0.0;
+        }
+        let overlap = overlap_width * overlap_height;
+        overlap / (self.width * self.height + other.width * other.height - overlap)
+    }
+
+    /// Where in the frame it is, in words, e.g. "lower left".
+    pub fn position(&self) -> &'static str {
+        let (center_x, center_y) = (self.x + self.width / 2.0, self.y + self.height / 2.0);
+        match (center_y < 1.0 / 3.0, center_y > 2.0 / 3.0, center_x < 1.0 / 3.0, center_x > 2.0 / 3.0) {
+            (true, _, true, _) => "upper left",
+            (true, _, _, true) => "upper right",
+            (true, _, _, _) => "top",
+            (_, true, true, _) => "lower left",
+            (_, true, _, true) => "lower right",
+            (_, true, _, _) => "bottom",
+            (_, _, true, _) => "left",
+            (_, _, _, true) => "right",
+            _ => "center",
+        }
+    }
+}
+
+/// Finds everyday objects in camera frames with a YOLO model (YOLOv8 or later, exported to
+/// ONNX), run through ONNX Runtime.
+pub struct ObjectDetector {
+    session: Session,
+    threshold: f32,
+}
+
+impl ObjectDetector {
+    /// `model` is vision.model: a path to an .onnx file, or the name of one in the models
+    /// directory.
+    pub fn load(model: &str, threshold: f32) -> Result<Self> {
+        let path = model_path(model)?;
+        let session = Session::builder()
+            .and_then(|builder| builder.with_intra_threads(2))
+            .and_then(|builder| builder.commit_from_file(&path))
+            .with_context(|| format!("Failed to load the object detector from {}", path.display()))?;
+        log::info!("Object detector loaded from {}", path.display());
+        Ok(Self { session, threshold })
+    }
+
+    /// The objects in `frame`, most confident first.
+    pub fn detect(&mut self, frame: &RgbImage) -> Result<Vec<DetectedObject>> {
+        // Letterboxed: scaled to fit the square and padded with gray, so shapes aren't squashed
+        let scale = INPUT_SIZE as f32 / frame.width().max(frame.height()) as f32;
+        let (scaled_width, scaled_height) = (
+            ((frame.width() as f32 * scale) as u32).max(1),
+            ((frame.height() as f32 * scale) as u32).max(1),
+        );
+        let scaled = image::imageops::resize(frame, scaled_width, scaled_height, FilterType::Triangle);
+        let mut square = RgbImage::from_pixel(INPUT_SIZE, INPUT_SIZE, Rgb([114, 114, 114]));
+        image::imageops::overlay(&mut square, &scaled, 0, 0);
+
+        let plane = (INPUT_SIZE * INPUT_SIZE) as usize;
+        let mut input = vec![0.0f32; 3 * plane];
+        for (index, pixel) in square.pixels().enumerate() {
+            for channel in 0..3 {
+                input[channel * plane + index] = pixel[channel] as f32 / 255.0;
+            }
+        }
+
+        let outputs = self.session.run(ort::inputs![
+            "images" => Tensor::from_array(([1usize, 3, INPUT_SIZE as usize, INPUT_SIZE as usize], input))?,
+        ]?)?;
+        // [1, 4 + classes, candidates]: center x, center y, width, height, then a score per class
+        let (shape, output) = outputs["output0"].try_extract_raw_tensor::<f32>()?;
+        let (rows, candidates) = match shape {
+            [1, rows, candidates] if *rows > 4 => (*rows as usize, *candidates as usize),
+            _ => return Err(anyhow::anyhow!("The object detector's output has an unexpected shape {:?}", shape)),
+        };
+        let classes = (rows - 4).min(COCO_LABELS.len());
+
+        let (frame_width, frame_height) = (frame.width() as f32 * scale, frame.height() as f32 * scale);
+        let mut found: Vec<DetectedObject> = (0..candidates)
+            .filter_map(|candidate| {
+                let at = |row: usize| output[row * candidates + candidate];
+                let (class, confidence) = (0..classes)
+                    .map(|class| (class, at(4 + class)))
+                    .max_by(|a, b| a.1.total_cmp(&b.1))?;
+                if confidence < self.threshold {
+                    return None;
+                }
+                let (center_x, center_y, width, height) = (at(0), at(1), at(2), at(3));
+                let x = ((center_x - width / 2.0) / frame_width).clamp(0.0, 1.0);
+                let y = ((center_y - height / 2.0) / frame_height).clamp(0.0, 1.0);
+                Some(DetectedObject {
+                    label: COCO_LABELS[class],
+                    confidence,
+                    x,
+                    y,
+                    width: (width / frame_width).min(1.0 - x),
+                    height: (height / frame_height).min(1.0 - y),
+                })
+            })
+            .collect();
+        found.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        let mut objects: Vec<DetectedObject> = Vec::new();
+        for candidate in found {
+            if objects.len() == MAX_OBJECTS {
+                break;
+            }
+            if objects.iter().all(|object| object.label != candidate.label || object.iou(&candidate) < NMS_IOU) {
+                objects.push(candidate);
+            }
+        }
+        Ok(objects)
+    }
+}
+
+fn model_path(model: &str) -> Result<PathBuf> {
+    let model = model.trim();
+    if model.ends_with(".onnx") && Path::new(model).is_file() {
+        return Ok(PathBuf::from(model));
+    }
+    crate::models::resolve_asset(&format!("{}.onnx", model.trim_end_matches(".onnx")))
+}
+
+/// `objects` as a line per object, for the LLM.
+pub fn describe(objects: &[DetectedObject]) -> String {
+    objects
+        .iter()
+        .map(|object| format!("- {} ({:.0}% sure), {} of the view", object.label, object.confidence * 100.0, object.position()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
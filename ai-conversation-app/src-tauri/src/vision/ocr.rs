@@ -0,0 +1,56 @@
+use crate::config::OcrConfig;
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+// Tesseract misses small text; narrower images are scaled up to about this width first
+const MIN_WIDTH: u32 = 1600;
+const MAX_UPSCALE: u32 = 3;
+
+/// The text in `image`, read by Tesseract.
+pub async fn read_text(image: &DynamicImage, config: &OcrConfig) -> Result<String> {
+    let scale = (MIN_WIDTH / image.width().max(1)).clamp(1, MAX_UPSCALE);
+    let gray = if scale > 1 {
+        image.resize(image.width() * scale, image.height() * scale, FilterType::CatmullRom)
+    } else {
+        image.clone()
+    };
+    // Grayscale PNG keeps the letter edges Tesseract looks for
+    let mut png = Vec::new();
+    DynamicImage::ImageLuma8(gray.to_luma8())
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .context("Failed to encode the image for OCR")?;
+
+    let mut child = tokio::process::Command::new(&config.tesseract_binary)
+        .args(["stdin", "stdout", "-l", config.languages.trim()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {}; is Tesseract installed?", config.tesseract_binary))?;
+    let mut stdin = child.stdin.take().context("Tesseract has no stdin")?;
+    // Written alongside the wait, so a full output pipe can't stall the write
+    let writer = tokio::spawn(async move { stdin.write_all(&png).await });
+    let output = child.wait_with_output().await.context("Tesseract didn't finish")?;
+    writer.await?.context("Failed to hand the image to Tesseract")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // Tesseract pads lines and paragraphs with blank lines; one between paragraphs is enough
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    Ok(lines.join("\n").trim().to_string())
+}
@@ -0,0 +1,64 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+// A face has to stay this long to count, so someone walking past doesn't wake the assistant
+const ARRIVE_AFTER: Duration = Duration::from_secs(1);
+
+/// Sent as "presence-changed" when someone sits down in front of the camera or leaves.
+#[derive(Debug, Clone, Serialize)]
+pub struct PresenceEvent {
+    pub present: bool,
+    pub faces: usize,
+    // On arrival, how long the user was away; None the first time they're seen
+    pub away_seconds: Option<u64>,
+}
+
+/// Whether someone is in front of the camera, from the faces seen frame by frame.
+pub struct Presence {
+    present: bool,
+    away_after: Duration,
+    seen_since: Option<Instant>,
+    last_seen: Option<Instant>,
+    left_at: Option<Instant>,
+}
+
+impl Presence {
+    pub fn new(away_after: Duration) -> Self {
+        Self {
+            present: false,
+            away_after,
+            seen_since: None,
+            last_seen: None,
+            left_at: None,
+        }
+    }
+
+    /// Take in the faces seen in a frame, returning the change if presence changed.
+    pub fn update(&mut self, faces: usize, now: Instant) -> Option<PresenceEvent> {
+        if faces > 0 {
+            self.last_seen = Some(now);
+            let seen_since = *self.seen_since.get_or_insert(now);
+            if !self.present && now.duration_since(seen_since) >= ARRIVE_AFTER {
+                self.present = true;
+                return Some(PresenceEvent {
+                    present: true,
+                    faces,
+                    away_seconds: self.left_at.map(|left_at| now.duration_since(left_at).as_secs()),
+                });
+            }
+        } else {
+            self.seen_since = None;
+            let gone = self.last_seen.is_none_or(|last_seen| now.duration_since(last_seen) >= self.away_after);
+            if self.present && gone {
+                self.present = false;
+                self.left_at = self.last_seen;
+                return Some(PresenceEvent {
+                    present: false,
+                    faces: 0,
+                    away_seconds: None,
+                });
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use image::DynamicImage;
+use serde::Serialize;
+
+/// A display that can be captured.
+#[derive(Debug, Clone, Serialize)]
+pub struct Display {
+    // What describe_screen takes to pick it
+    pub index: u32,
+    pub name: String,
+    pub primary: bool,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[cfg(desktop)]
+pub fn list_displays() -> Result<Vec<Display>> {
+    xcap::Monitor::all()?
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| {
+            Ok(Display {
+                index: index as u32,
+                name: monitor.name()?,
+                primary: monitor.is_primary()?,
+                width: monitor.width()?,
+                height: monitor.height()?,
+            })
+        })
+        .collect()
+}
+
+/// What display `index` shows now, or the primary display's when None. Blocks while capturing.
+#[cfg(desktop)]
+pub fn capture(index: Option<u32>) -> Result<DynamicImage> {
+    use anyhow::Context;
+
+    let monitors = xcap::Monitor::all()?;
+    let monitor = match index {
+        Some(index) => monitors
+            .get(index as usize)
+            .with_context(|| format!("There's no display {}; {} are connected", index, monitors.len()))?,
+        None => monitors
+            .iter()
+            .find(|monitor| monitor.is_primary().unwrap_or(false))
+            .or_else(|| monitors.first())
+            .context("No display is connected")?,
+    };
+    let captured = monitor.capture_image().context("Failed to capture the screen")?;
+    let (width, height) = (captured.width(), captured.height());
+    // xcap may build against another version of the image crate; the raw pixels carry over
+    let image = image::RgbaImage::from_raw(width, height, captured.into_raw()).context("Screen capture has the wrong size")?;
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+#[cfg(mobile)]
+pub fn list_displays() -> Result<Vec<Display>> {
+    Ok(Vec::new())
+}
+
+#[cfg(mobile)]
+pub fn capture(_index: Option<u32>) -> Result<DynamicImage> {
+    Err(anyhow::anyhow!("Screen capture isn't available on this platform"))
+}
@@ -112,6 +112,7 @@ function App() {
       if (index < visemes.length && isSpeaking) {
         setVisemeData({
           phoneme: visemes[index],
+          viseme: visemes[index],
           timestamp: Date.now(),
           duration: 200,
           intensity: 0.8 + Math.random() * 0.2
@@ -1,5 +1,6 @@
 export interface VisemeData {
   phoneme: string;
+  viseme: string;
   timestamp: number;
   duration: number;
   intensity: number;